@@ -3,16 +3,33 @@ use std::fs::File;
 use std::io;
 use std::io::{Read, Seek, Write};
 use std::path::PathBuf;
+use std::process;
 
 use anyhow::Context;
 use clap::{Parser as _, ValueEnum};
+use mediasan_common::{detect_format, MediaFormat};
 use mp4san::SanitizedMetadata;
 
+/// The length of the file prefix read to detect its format.
+const PREFIX_LEN: u64 = 12;
+
+/// The exit code when the file was sanitized successfully.
+const EXIT_SUCCESS: i32 = 0;
+
+/// The exit code when an error occurred other than the file being invalid, e.g. the file couldn't be opened.
+const EXIT_ERROR: i32 = 1;
+
+/// The exit code when the file could not be parsed as valid media.
+const EXIT_INVALID: i32 = 2;
+
+/// Sanitize a media file, exiting with status 0 on success, 2 if the file is not valid media, and 1 on any other
+/// error, e.g. if the file could not be read.
 #[derive(clap::Parser)]
 struct Args {
     /// The format of the media file.
     ///
-    /// If not specified, a guess will be made based on the file extension.
+    /// If not specified, the format is detected from the start of the file, falling back to a guess based on the file
+    /// extension.
     #[clap(long, short = 't')]
     format: Option<Format>,
 
@@ -30,7 +47,18 @@ enum Format {
     Webp,
 }
 
-fn main() -> Result<(), anyhow::Error> {
+fn main() {
+    let exit_code = match run() {
+        Ok(()) => EXIT_SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {error:?}");
+            exit_code(&error)
+        }
+    };
+    process::exit(exit_code);
+}
+
+fn run() -> Result<(), anyhow::Error> {
     env_logger::builder()
         .filter_level(log::LevelFilter::Info)
         .parse_default_env()
@@ -39,20 +67,24 @@ fn main() -> Result<(), anyhow::Error> {
 
     let args = Args::try_parse().context("Error parsing command line arguments")?;
 
+    let mut infile = File::open(&args.file).context("Error opening file")?;
+
     let format = match args.format {
         Some(t) => t,
-        None => {
-            let extension = args.file.extension().unwrap_or_default();
-            ValueEnum::from_str(&extension.to_string_lossy(), true)
-                .map_err(|_| anyhow::anyhow!("can't guess media format (unrecognized extension {extension:?})"))?
-        }
+        None => match detect_format(&read_prefix(&mut infile).context("Error reading file")?) {
+            Some(MediaFormat::Mp4) => Format::Mp4,
+            Some(MediaFormat::Webp) => Format::Webp,
+            None => {
+                let extension = args.file.extension().unwrap_or_default();
+                ValueEnum::from_str(&extension.to_string_lossy(), true)
+                    .map_err(|_| anyhow::anyhow!("can't guess media format (unrecognized extension {extension:?})"))?
+            }
+        },
     };
 
-    let mut infile = File::open(&args.file).context("Error opening file")?;
-
     match format {
         Format::Mp4 => match mp4san::sanitize(&mut infile).context("Error parsing mp4 file")? {
-            SanitizedMetadata { metadata: Some(metadata), data } => {
+            SanitizedMetadata { metadata: Some(metadata), data, .. } => {
                 if let Some(output_path) = args.output {
                     let mut outfile = File::create(output_path).context("Error opening output file")?;
                     outfile.write(&metadata).context("Error writing output")?;
@@ -78,3 +110,19 @@ fn main() -> Result<(), anyhow::Error> {
 
     Ok(())
 }
+
+/// Returns the exit code for `error`, distinguishing media which failed to parse from any other error.
+fn exit_code(error: &anyhow::Error) -> i32 {
+    match (error.downcast_ref(), error.downcast_ref()) {
+        (Some(mp4san::Error::Parse(_)), _) | (_, Some(webpsan::Error::Parse(_))) => EXIT_INVALID,
+        _ => EXIT_ERROR,
+    }
+}
+
+/// Read the prefix of `file` needed to detect its format, leaving the file positioned at its start.
+fn read_prefix(file: &mut File) -> io::Result<Vec<u8>> {
+    let mut prefix = Vec::with_capacity(PREFIX_LEN as usize);
+    Read::take(&mut *file, PREFIX_LEN).read_to_end(&mut prefix)?;
+    file.rewind()?;
+    Ok(prefix)
+}
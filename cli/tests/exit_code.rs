@@ -0,0 +1,37 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const VALID_MP4: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../mp4san/fuzz/input/ffmpeg-black-1f.mp4");
+
+fn run_cli<P: AsRef<Path>>(file: P) -> Option<i32> {
+    let output = Command::new(env!("CARGO_BIN_EXE_mediasan-cli"))
+        .arg(file.as_ref())
+        .env("RUST_LOG", "off")
+        .output()
+        .unwrap();
+    output.status.code()
+}
+
+fn tmp_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_TARGET_TMPDIR")).join(name)
+}
+
+#[test]
+fn valid_file() {
+    assert_eq!(run_cli(VALID_MP4), Some(0));
+}
+
+#[test]
+fn invalid_file() {
+    let mut data = fs::read(VALID_MP4).unwrap();
+    data.truncate(data.len() / 2);
+    let path = tmp_path("exit-code-invalid.mp4");
+    fs::write(&path, data).unwrap();
+    assert_eq!(run_cli(&path), Some(2));
+}
+
+#[test]
+fn missing_file() {
+    assert_eq!(run_cli(tmp_path("exit-code-missing.mp4")), Some(1));
+}
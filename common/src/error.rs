@@ -21,7 +21,10 @@ pub enum Error<E: ReportableError> {
     Io(#[from] io::Error),
 
     /// The input could not be parsed as a media file.
-    #[error("Parse error: {0}")]
+    ///
+    /// The [`Display`] implementation includes the messages attached to the [`Report`], while the [`Debug`]
+    /// implementation additionally includes the source locations of each.
+    #[error("Parse error: {}", ReportWithMessages(.0))]
     Parse(#[from] Report<E>),
 }
 
@@ -88,12 +91,20 @@ pub trait ReportableErrorStack: Display {
     #[track_caller]
     /// Attach a [`Display`]-able type to the error [`Report`]'s stack trace.
     fn attach_printable<P: Display + Send + Sync + 'static>(self, printable: P) -> Self;
+
+    /// Write the messages attached to this stack to the formatter, each prefixed by `": "`, without their source
+    /// locations.
+    fn fmt_messages(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Ok(())
+    }
 }
 
 //
 // private types
 //
 
+struct ReportWithMessages<'a, E: ReportableError>(&'a Report<E>);
+
 #[derive(derive_more::Display)]
 #[display(fmt = "{message} at {location}")]
 struct ReportEntry {
@@ -139,9 +150,17 @@ impl<E: ReportableError> Debug for Report<E> {
 }
 
 //
-// ReportErrorStack impls
+// ReportWithMessages impls
 //
 
+impl<E: ReportableError> Display for ReportWithMessages<'_, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Report { error, stack } = self.0;
+        write!(f, "{error}")?;
+        stack.fmt_messages(f)
+    }
+}
+
 //
 // WhileParsingType impls
 //
@@ -182,6 +201,13 @@ impl ReportableErrorStack for ReportStack {
         self.entries.push(entry);
         self
     }
+
+    fn fmt_messages(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            write!(f, ": {}", entry.message)?;
+        }
+        Ok(())
+    }
 }
 
 //
@@ -263,4 +289,20 @@ mod test {
         assert!(report_debug.starts_with(TEST_ERROR_DISPLAY));
         assert!(report_debug.contains(TEST_ATTACHMENT));
     }
+
+    #[test]
+    fn test_error_display() {
+        let error = Error::Parse(test_report().attach_printable(WhileParsingType::new::<u32>()));
+        assert_eq!(
+            error.to_string(),
+            format!("Parse error: {TEST_ERROR_DISPLAY}: {TEST_ATTACHMENT}: while parsing value of type `u32`"),
+        );
+    }
+
+    #[test]
+    fn test_error_debug() {
+        let error_debug = format!("{error:?}", error = Error::Parse(test_report()));
+        assert!(error_debug.contains(TEST_ATTACHMENT));
+        assert!(error_debug.contains(file!()));
+    }
 }
@@ -0,0 +1,76 @@
+//! Detection of the format of media inputs.
+
+//
+// public types
+//
+
+/// A media format supported by a mediasan sanitizer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MediaFormat {
+    /// An ISO Base Media File Format input, such as an MP4, sanitized by `mp4san`.
+    Mp4,
+
+    /// A WebP input, sanitized by `webpsan`.
+    Webp,
+}
+
+//
+// public functions
+//
+
+/// Detect the format of a media input from a prefix of its data.
+///
+/// An MP4 is detected by an `ftyp` box at the start of the input, which needs the first 8 bytes, and a WebP by its
+/// `RIFF` header with form type `WEBP`, which needs the first 12 bytes. Returns `None` if the prefix does not match
+/// either format, including when it is too short to tell.
+///
+/// Detection only inspects the signature, so a detected input may still fail to sanitize.
+///
+/// # Examples
+///
+/// ```
+/// # use mediasan_common::{detect_format, MediaFormat};
+/// #
+/// assert_eq!(detect_format(b"\0\0\0\x14ftypisom"), Some(MediaFormat::Mp4));
+/// assert_eq!(detect_format(b"RIFF\x1a\0\0\0WEBPVP8L"), Some(MediaFormat::Webp));
+/// assert_eq!(detect_format(b"GIF89a"), None);
+/// ```
+pub fn detect_format(prefix: &[u8]) -> Option<MediaFormat> {
+    match prefix {
+        [_, _, _, _, b'f', b't', b'y', b'p', ..] => Some(MediaFormat::Mp4),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some(MediaFormat::Webp),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn mp4() {
+        assert_eq!(detect_format(b"\0\0\0\x18ftypmp42\0\0\0\0"), Some(MediaFormat::Mp4));
+    }
+
+    #[test]
+    fn webp() {
+        assert_eq!(detect_format(b"RIFF\x24\0\0\0WEBPVP8 "), Some(MediaFormat::Webp));
+    }
+
+    #[test]
+    fn riff_not_webp() {
+        assert_eq!(detect_format(b"RIFF\x24\0\0\0WAVEfmt "), None);
+    }
+
+    #[test]
+    fn not_media() {
+        assert_eq!(detect_format(b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR"), None);
+    }
+
+    #[test]
+    fn truncated() {
+        assert_eq!(detect_format(b""), None);
+        assert_eq!(detect_format(b"\0\0\0\x18fty"), None);
+        assert_eq!(detect_format(b"RIFF\x24\0\0\0WEB"), None);
+    }
+}
@@ -7,9 +7,12 @@ pub mod macros;
 
 pub mod async_skip;
 pub mod error;
+mod format;
 pub mod parse;
 mod skip;
 pub mod sync;
+#[cfg(feature = "tokio")]
+mod tokio_file;
 pub mod util;
 
 use std::io;
@@ -23,9 +26,11 @@ use derive_more::{Deref, DerefMut};
 //
 
 pub use error::{Error, Report, Result, ResultExt};
+pub use format::{detect_format, MediaFormat};
 
 /// A pointer to a span in the given input.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InputSpan {
     /// The offset from the beginning of the input where the span begins.
     pub offset: u64,
@@ -67,3 +72,6 @@ pub trait AsyncSkip {
 pub struct SeekSkipAdapter<T: ?Sized>(pub T);
 
 pub use async_skip::AsyncSkipExt;
+
+#[cfg(feature = "tokio")]
+pub use tokio_file::TokioFileAdapter;
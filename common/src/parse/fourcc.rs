@@ -6,7 +6,7 @@ use bytes::BufMut;
 use futures_util::{pin_mut, AsyncRead, AsyncReadExt};
 
 /// A four-byte character code.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct FourCC {
     /// The character code, as an array of four bytes.
     pub value: [u8; 4],
@@ -54,6 +54,15 @@ impl FourCC {
         Self { value }
     }
 
+    /// Return the character code as a string, if it consists only of printable ASCII characters, including spaces.
+    pub fn try_as_str(&self) -> Option<&str> {
+        if self.value.iter().all(|&byte| byte == b' ' || byte.is_ascii_graphic()) {
+            std::str::from_utf8(&self.value).ok()
+        } else {
+            None
+        }
+    }
+
     /// Writes `self` to the [`BufMut`] `out`.
     pub fn put_buf<B: BufMut>(&self, mut out: B) {
         out.put(&self.value[..])
@@ -61,12 +70,96 @@ impl FourCC {
 }
 
 impl fmt::Display for FourCC {
+    /// Formats the character code as a string with surrounding spaces trimmed, escaping any non-printable bytes as
+    /// `\xNN`.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if let Ok(string) = std::str::from_utf8(&self.value) {
-            let string = string.trim();
-            write!(f, "{string}")
-        } else {
-            write!(f, "0x{:08x}", u32::from_be_bytes(self.value))
+        if let Some(string) = self.try_as_str() {
+            return write!(f, "{}", string.trim());
+        }
+        for &byte in &self.value {
+            if byte == b' ' || byte.is_ascii_graphic() {
+                write!(f, "{}", byte as char)?;
+            } else {
+                write!(f, "\\x{byte:02x}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for FourCC {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.try_as_str() {
+            Some(string) => serializer.serialize_str(string),
+            None => serializer.collect_str(&format_args!("0x{:08x}", u32::from_be_bytes(self.value))),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for FourCC {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let string = String::deserialize(deserializer)?;
+        if let Ok(value) = <[u8; 4]>::try_from(string.as_bytes()) {
+            return Ok(Self { value });
+        }
+        match string.strip_prefix("0x") {
+            Some(hex) if hex.len() == 8 && hex.bytes().all(|byte| byte.is_ascii_hexdigit()) => {
+                u32::from_str_radix(hex, 16)
+                    .map(|value| Self { value: value.to_be_bytes() })
+                    .map_err(D::Error::custom)
+            }
+            _ => Err(D::Error::invalid_value(
+                serde::de::Unexpected::Str(&string),
+                &"a four-character code or a 32-bit hex value",
+            )),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn printable() {
+        let fourcc = FourCC::from_str("url");
+        assert_eq!(fourcc.try_as_str(), Some("url "));
+        assert_eq!(fourcc.to_string(), "url");
+    }
+
+    #[test]
+    fn non_printable() {
+        let fourcc = FourCC { value: [0xa9, b'n', 0, b'\n'] };
+        assert_eq!(fourcc.try_as_str(), None);
+        assert_eq!(fourcc.to_string(), r"\xa9n\x00\x0a");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_printable() {
+        let fourcc = FourCC::from_str("url");
+        let json = serde_json::to_string(&fourcc).unwrap();
+        assert_eq!(json, r#""url ""#);
+        assert_eq!(serde_json::from_str::<FourCC>(&json).unwrap(), fourcc);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_non_printable() {
+        let fourcc = FourCC { value: [0xa9, b'n', b'a', b'm'] };
+        let json = serde_json::to_string(&fourcc).unwrap();
+        assert_eq!(json, r#""0xa96e616d""#);
+        assert_eq!(serde_json::from_str::<FourCC>(&json).unwrap(), fourcc);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_invalid() {
+        serde_json::from_str::<FourCC>(r#""moov2""#).unwrap_err();
+        serde_json::from_str::<FourCC>(r#""0xa96e61""#).unwrap_err();
+    }
+}
@@ -0,0 +1,152 @@
+//! An [`AsyncRead`] + [`AsyncSkip`] adapter for [`tokio::fs::File`].
+
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use futures_util::AsyncRead;
+use tokio::fs::File;
+use tokio::io::{AsyncRead as TokioAsyncRead, AsyncSeek as TokioAsyncSeek, ReadBuf};
+
+use crate::AsyncSkip;
+
+//
+// public types
+//
+
+/// An adapter implementing [`AsyncRead`] + [`AsyncSkip`] for a [`tokio::fs::File`].
+///
+/// Skips are performed by seeking the file.
+///
+/// # Examples
+///
+/// ```
+/// # use mediasan_common::{AsyncSkipExt, TokioFileAdapter};
+/// #
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// # let path = tempfile::NamedTempFile::new()?.into_temp_path();
+/// # std::fs::write(&path, b"example")?;
+/// let mut input = TokioFileAdapter::new(tokio::fs::File::open(&path).await?);
+/// input.skip(2).await?;
+/// assert_eq!(input.stream_position().await?, 2);
+/// assert_eq!(input.stream_len().await?, 7);
+/// # Ok::<(), std::io::Error>(())
+/// # }).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct TokioFileAdapter {
+    file: File,
+    seek: Option<io::SeekFrom>,
+    stream_len: Option<StreamLenState>,
+}
+
+//
+// private types
+//
+
+#[derive(Clone, Copy, Debug)]
+enum StreamLenState {
+    SeekingEnd { stream_pos: u64 },
+    Restoring { stream_pos: u64, len: u64 },
+}
+
+//
+// TokioFileAdapter impls
+//
+
+impl TokioFileAdapter {
+    /// Construct a new [`TokioFileAdapter`] reading from the given file.
+    pub fn new(file: File) -> Self {
+        Self { file, seek: None, stream_len: None }
+    }
+
+    /// Return a reference to the underlying file.
+    pub fn get_ref(&self) -> &File {
+        &self.file
+    }
+
+    /// Unwrap the underlying file.
+    pub fn into_inner(self) -> File {
+        self.file
+    }
+
+    /// Poll seeking the file, continuing the seek to `pos` if it was already started.
+    fn poll_seek(&mut self, cx: &mut Context<'_>, pos: io::SeekFrom) -> Poll<io::Result<u64>> {
+        if self.seek != Some(pos) {
+            // Complete any other operation in progress on the file before starting the seek.
+            ready!(Pin::new(&mut self.file).poll_complete(cx))?;
+            Pin::new(&mut self.file).start_seek(pos)?;
+            self.seek = Some(pos);
+        }
+        let result = ready!(Pin::new(&mut self.file).poll_complete(cx));
+        self.seek = None;
+        Poll::Ready(result)
+    }
+
+    /// Poll the steps of finding the stream length, resuming from the last step which was pending.
+    fn poll_stream_len_steps(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        loop {
+            match self.stream_len {
+                None => {
+                    let stream_pos = ready!(self.poll_seek(cx, io::SeekFrom::Current(0)))?;
+                    self.stream_len = Some(StreamLenState::SeekingEnd { stream_pos });
+                }
+                Some(StreamLenState::SeekingEnd { stream_pos }) => {
+                    let len = ready!(self.poll_seek(cx, io::SeekFrom::End(0)))?;
+                    if len == stream_pos {
+                        return Poll::Ready(Ok(len));
+                    }
+                    self.stream_len = Some(StreamLenState::Restoring { stream_pos, len });
+                }
+                Some(StreamLenState::Restoring { stream_pos, len }) => {
+                    ready!(self.poll_seek(cx, io::SeekFrom::Start(stream_pos)))?;
+                    return Poll::Ready(Ok(len));
+                }
+            }
+        }
+    }
+}
+
+impl From<File> for TokioFileAdapter {
+    fn from(file: File) -> Self {
+        Self::new(file)
+    }
+}
+
+impl AsyncRead for TokioFileAdapter {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        let mut read_buf = ReadBuf::new(buf);
+        ready!(Pin::new(&mut self.file).poll_read(cx, &mut read_buf))?;
+        Poll::Ready(Ok(read_buf.filled().len()))
+    }
+}
+
+impl AsyncSkip for TokioFileAdapter {
+    fn poll_skip(self: Pin<&mut Self>, cx: &mut Context<'_>, amount: u64) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let seek_pos = match amount.try_into() {
+            Ok(0) => return Poll::Ready(Ok(())),
+            Ok(amount) => io::SeekFrom::Current(amount),
+            Err(_) => {
+                let stream_pos = ready!(this.poll_seek(cx, io::SeekFrom::Current(0)))?;
+                let seek_pos = stream_pos
+                    .checked_add(amount)
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "seek past u64::MAX"))?;
+                io::SeekFrom::Start(seek_pos)
+            }
+        };
+        ready!(this.poll_seek(cx, seek_pos))?;
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_stream_position(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        self.get_mut().poll_seek(cx, io::SeekFrom::Current(0))
+    }
+
+    fn poll_stream_len(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        let this = self.get_mut();
+        let result = ready!(this.poll_stream_len_steps(cx));
+        this.stream_len = None;
+        Poll::Ready(result)
+    }
+}
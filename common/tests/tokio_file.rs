@@ -0,0 +1,54 @@
+#![cfg(feature = "tokio")]
+
+use std::io::Write;
+
+use futures_util::AsyncReadExt;
+use mediasan_common::{AsyncSkipExt, TokioFileAdapter};
+use tempfile::NamedTempFile;
+
+async fn test_file(data: &[u8]) -> TokioFileAdapter {
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(data).unwrap();
+    let file = tokio::fs::File::open(file.path()).await.unwrap();
+    TokioFileAdapter::new(file)
+}
+
+#[tokio::test]
+async fn read_and_skip() {
+    let mut input = test_file(b"0123456789").await;
+
+    let mut buf = [0; 2];
+    input.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"01");
+
+    input.skip(3).await.unwrap();
+    assert_eq!(input.stream_position().await.unwrap(), 5);
+
+    input.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"56");
+}
+
+#[tokio::test]
+async fn stream_len() {
+    let mut input = test_file(b"0123456789").await;
+    input.skip(4).await.unwrap();
+
+    assert_eq!(input.stream_len().await.unwrap(), 10);
+    assert_eq!(input.stream_position().await.unwrap(), 4);
+
+    let mut rest = Vec::new();
+    input.read_to_end(&mut rest).await.unwrap();
+    assert_eq!(rest, b"456789");
+    assert_eq!(input.stream_len().await.unwrap(), 10);
+}
+
+#[tokio::test]
+async fn skip_past_end() {
+    let mut input = test_file(b"0123456789").await;
+    input.skip(20).await.unwrap();
+    assert_eq!(input.stream_position().await.unwrap(), 20);
+
+    let mut rest = Vec::new();
+    input.read_to_end(&mut rest).await.unwrap();
+    assert!(rest.is_empty());
+}
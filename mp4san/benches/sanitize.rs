@@ -0,0 +1,113 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use bytes::BytesMut;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use mp4san::{sanitize, sanitize_with_buffer, sanitize_with_config, Config, SanitizerHandle};
+use mp4san_test::{example_ftyp, example_mdat, example_moov};
+
+/// A global allocator counting allocations, to report the allocations saved by reusing a buffer.
+struct CountingAlloc;
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+static ALLOC_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// The number of `trak` boxes in the large `moov` fixture.
+const TRAK_COUNT: u32 = 64;
+
+/// The number of small inputs sanitized per iteration of the buffer reuse benchmark.
+const SMALL_FILE_COUNT: usize = 1000;
+
+/// The number of chunk offsets in each `trak`'s `stco` box in the large `moov` fixture.
+const CHUNK_COUNT: u32 = 16 * 1024;
+
+fn mp4_box(name: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let size = u32::try_from(8 + data.len()).unwrap();
+    [&size.to_be_bytes()[..], name, data].concat()
+}
+
+/// A `moov` box with many tracks with many chunks each, which must be fully parsed to be displaced.
+fn large_moov() -> Vec<u8> {
+    let mut stco_data = [&[0; 4][..], &CHUNK_COUNT.to_be_bytes()].concat();
+    for chunk_idx in 0..CHUNK_COUNT {
+        stco_data.extend_from_slice(&(chunk_idx * 16).to_be_bytes());
+    }
+    let stbl = mp4_box(b"stbl", &mp4_box(b"stco", &stco_data));
+    let trak = mp4_box(b"trak", &mp4_box(b"mdia", &mp4_box(b"minf", &stbl)));
+    mp4_box(b"moov", &trak.repeat(TRAK_COUNT as usize))
+}
+
+fn sanitize_large_moov(c: &mut Criterion) {
+    // Placing the moov after the mdat requires its chunk offsets to be displaced, in addition to being logged.
+    let input = [example_ftyp(), example_mdat(), large_moov()].concat();
+
+    let mut group = c.benchmark_group("sanitize");
+    group.throughput(Throughput::Bytes(input.len() as u64));
+    group.bench_function("large_moov", |b| {
+        b.iter_batched(
+            || io::Cursor::new(&input[..]),
+            |input| sanitize(input).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+fn sanitize_small_files(c: &mut Criterion) {
+    let input = [example_ftyp(), example_mdat(), example_moov()].concat();
+    let sanitize_allocating = || {
+        for _ in 0..SMALL_FILE_COUNT {
+            sanitize_with_config(io::Cursor::new(&input[..]), Config::default()).unwrap();
+        }
+    };
+    let mut buf = BytesMut::new();
+    let mut sanitize_reusing = || {
+        for _ in 0..SMALL_FILE_COUNT {
+            sanitize_with_buffer(io::Cursor::new(&input[..]), Config::default(), &mut buf).unwrap();
+        }
+    };
+    let mut sanitizer = SanitizerHandle::new(Config::default());
+    let mut sanitize_with_sanitizer = || {
+        for _ in 0..SMALL_FILE_COUNT {
+            sanitizer.sanitize(io::Cursor::new(&input[..])).unwrap();
+        }
+    };
+
+    eprintln!("allocations sanitizing {SMALL_FILE_COUNT} small files:");
+    eprintln!("  allocating: {}", count_allocs(sanitize_allocating));
+    eprintln!("  reusing a buffer: {}", count_allocs(&mut sanitize_reusing));
+    eprintln!(
+        "  reusing a sanitizer handle: {}",
+        count_allocs(&mut sanitize_with_sanitizer)
+    );
+
+    let mut group = c.benchmark_group("sanitize_small_files");
+    group.throughput(Throughput::Elements(SMALL_FILE_COUNT as u64));
+    group.bench_function("allocating", |b| b.iter(sanitize_allocating));
+    group.bench_function("reusing_buffer", |b| b.iter(&mut sanitize_reusing));
+    group.bench_function("reusing_sanitizer_handle", |b| b.iter(&mut sanitize_with_sanitizer));
+    group.finish();
+}
+
+fn count_allocs(fun: impl FnOnce()) -> usize {
+    let start_count = ALLOC_COUNT.load(Ordering::Relaxed);
+    fun();
+    ALLOC_COUNT.load(Ordering::Relaxed) - start_count
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOC_COUNT.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+criterion_group!(benches, sanitize_large_moov, sanitize_small_files);
+criterion_main!(benches);
@@ -1,12 +1,19 @@
 use std::io;
 
+use arbitrary::{Arbitrary, Unstructured};
+
 fn main() {
     afl::fuzz!(|data: &[u8]| {
         std::panic::set_hook(Box::new(|panic| {
             eprintln!("{panic}");
             std::process::abort();
         }));
-        match mp4san::sanitize(io::Cursor::new(data)) {
+        let mut data = Unstructured::new(data);
+        let Ok(config) = mp4san::Config::arbitrary(&mut data) else {
+            return;
+        };
+        let data = data.take_rest();
+        match mp4san::sanitize_with_config(io::Cursor::new(data), config) {
             Ok(sanitized) => {
                 eprintln!(
                     "mp4san returned ok: metadata len {metadata_len:?} data offset {data_offset} len {data_len}",
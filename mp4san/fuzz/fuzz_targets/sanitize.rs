@@ -4,9 +4,10 @@ use std::io;
 
 use libfuzzer_sys::fuzz_target;
 
-fuzz_target!(|data: &[u8]| {
+fuzz_target!(|input: (mp4san::Config, &[u8])| {
+    let (config, data) = input;
     #[cfg_attr(not(fuzzing_repro), allow(unused))]
-    match mp4san::sanitize(io::Cursor::new(data)) {
+    match mp4san::sanitize_with_config(io::Cursor::new(data), config) {
         Ok(sanitized) => {
             #[cfg(fuzzing_repro)]
             eprintln!(
@@ -0,0 +1,257 @@
+//! Extraction of a single track into a standalone MP4.
+
+use bytes::BytesMut;
+use derive_more::Display;
+
+use crate::parse::error::WhileParsingBox;
+use crate::parse::{BoxHeader, BoxType, FtypBox, MoovBox, Mp4Box, Mp4Value, ParseError};
+use crate::{sanitize_to_vec, Config, Error};
+
+//
+// private types
+//
+
+#[derive(Clone, Copy, Debug, Display)]
+#[display(fmt = "no track with ID {}", _0)]
+struct TrackNotFound(u32);
+
+//
+// public functions
+//
+
+/// Extract the track with the given `track_id` from an MP4 input held in memory, returning a new standalone MP4 file
+/// containing only that track.
+///
+/// The input is first sanitized with the given [`Config`]. The output's `moov` contains only the selected `trak`, and
+/// its `mdat` contains only that track's chunks, copied contiguously in order, with the track's chunk offsets rewritten
+/// to point into it. Media data belonging only to other tracks is dropped.
+///
+/// ```no_run
+/// let input = std::fs::read("input.mp4")?;
+///
+/// let audio_only = mp4san::extract_track(&input, 2, mp4san::Config::default())?;
+/// std::fs::write("audio.mp4", audio_only)?;
+/// #
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// # Errors
+///
+/// If the input cannot be parsed, or it contains no track with ID `track_id`, an [`Error`] is returned.
+pub fn extract_track(input: &[u8], track_id: u32, config: Config) -> Result<Vec<u8>, Error> {
+    let sanitized = sanitize_to_vec(input, config)?;
+    let (ftyp, mut moov) = parse_metadata(&sanitized)?;
+
+    let moov_data = moov.data.parse()?;
+    ensure_attach!(
+        moov_data.retain_track(track_id)?,
+        ParseError::MissingRequiredBox(BoxType::TRAK),
+        TrackNotFound(track_id),
+        WhileParsingBox(BoxType::MOOV),
+    );
+    relayout_media(&sanitized, ftyp, moov)
+}
+
+//
+// crate functions
+//
+
+/// Rewrite a sanitized MP4 file held in memory so that its `mdat` contains only the chunks of its tracks.
+pub(crate) fn compact_media(sanitized: &[u8]) -> Result<Vec<u8>, Error> {
+    let (ftyp, moov) = parse_metadata(sanitized)?;
+    relayout_media(sanitized, ftyp, moov)
+}
+
+//
+// private functions
+//
+
+/// Assemble a new MP4 file from `ftyp`, `moov`, and an `mdat` containing only the chunks of each track in `moov`,
+/// copied from `sanitized` contiguously in order of their original offsets, with the chunk offsets rewritten to match.
+fn relayout_media(sanitized: &[u8], mut ftyp: Mp4Box<FtypBox>, mut moov: Mp4Box<MoovBox>) -> Result<Vec<u8>, Error> {
+    let mut chunks = Vec::new();
+    let mut relative_offsets = Vec::new();
+    for (trak_idx, trak) in moov.data.parse()?.traks().enumerate() {
+        let trak = trak?;
+        let chunk_sizes = trak.chunk_sizes()?;
+        let chunk_offsets = trak.usize_chunk_offsets()?.collect::<Result<Vec<_>, _>>()?;
+        for (chunk_idx, (&offset, &size)) in chunk_offsets.iter().zip(&chunk_sizes).enumerate() {
+            chunks.push((offset, size, trak_idx, chunk_idx));
+        }
+        relative_offsets.push(vec![0; chunk_offsets.len()]);
+    }
+    chunks.sort_unstable();
+
+    let mut mdat_data = Vec::new();
+    let mut last_chunk = None;
+    for (offset, size, trak_idx, chunk_idx) in chunks {
+        // A chunk shared by several tracks is only copied once.
+        let relative_offset = match last_chunk {
+            Some((last_offset, last_size, last_relative_offset)) if (last_offset, last_size) == (offset, size) => {
+                last_relative_offset
+            }
+            _ => {
+                let chunk = usize::try_from(size)
+                    .ok()
+                    .and_then(|size| sanitized.get(offset..)?.get(..size))
+                    .ok_or_else(|| report_attach!(ParseError::TruncatedBox, WhileParsingBox(BoxType::MDAT)))?;
+                let relative_offset = mdat_data.len() as u64;
+                mdat_data.extend_from_slice(chunk);
+                relative_offset
+            }
+        };
+        last_chunk = Some((offset, size, relative_offset));
+        relative_offsets[trak_idx][chunk_idx] = relative_offset;
+    }
+    let mdat_header = BoxHeader::with_data_size(BoxType::MDAT, mdat_data.len() as u64)?;
+
+    // Rewriting the chunk offsets can change the size of the `moov` (if `stco` is upgraded to `co64`), so repeat until
+    // the offsets are consistent with the metadata length.
+    let mut metadata_len = ftyp.encoded_len() + moov.encoded_len();
+    loop {
+        let mdat_data_offset = metadata_len + mdat_header.encoded_len();
+        for (trak, trak_offsets) in moov.data.parse()?.traks().zip(&relative_offsets) {
+            let offsets = trak_offsets
+                .iter()
+                .map(|&offset| mdat_data_offset + offset)
+                .collect::<Vec<_>>();
+            trak?.set_chunk_offsets(&offsets)?;
+        }
+        let new_metadata_len = ftyp.encoded_len() + moov.encoded_len();
+        if new_metadata_len == metadata_len {
+            break;
+        }
+        metadata_len = new_metadata_len;
+    }
+
+    let mut output = Vec::with_capacity((metadata_len + mdat_header.encoded_len()) as usize + mdat_data.len());
+    ftyp.data.parse()?;
+    ftyp.put_buf(&mut output);
+    moov.put_buf(&mut output);
+    mdat_header.put_buf(&mut output);
+    output.extend_from_slice(&mdat_data);
+    Ok(output)
+}
+
+/// Parse the `ftyp` and `moov` boxes from a sanitized MP4 file.
+fn parse_metadata(sanitized: &[u8]) -> Result<(Mp4Box<FtypBox>, Mp4Box<MoovBox>), Error> {
+    let mut ftyp = None;
+    let mut moov = None;
+    let mut remaining = sanitized;
+    while !remaining.is_empty() && (ftyp.is_none() || moov.is_none()) {
+        let header = BoxHeader::parse(remaining)?;
+        let box_len = match header.box_size() {
+            Some(box_size) => usize::try_from(box_size).unwrap_or(usize::MAX).min(remaining.len()),
+            None => remaining.len(),
+        };
+        let (box_bytes, rest) = remaining.split_at(box_len);
+        match header.box_type() {
+            BoxType::FTYP if ftyp.is_none() => ftyp = Some(Mp4Box::parse(&mut BytesMut::from(box_bytes))?),
+            BoxType::MOOV if moov.is_none() => moov = Some(Mp4Box::parse(&mut BytesMut::from(box_bytes))?),
+            _ => {}
+        }
+        remaining = rest;
+    }
+    let ftyp = ftyp.ok_or_else(|| report_attach!(ParseError::MissingRequiredBox(BoxType::FTYP)))?;
+    let moov = moov.ok_or_else(|| report_attach!(ParseError::MissingRequiredBox(BoxType::MOOV)))?;
+    Ok((ftyp, moov))
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use mp4san_test::{ffmpeg_assert_eq, gpac_assert_eq};
+
+    use crate::parse::box_type::{FREE, FTYP, MDAT, MOOV};
+    use crate::sanitize_slice;
+    use crate::util::test::{sanitized_data, test_moov, test_mp4};
+
+    use super::*;
+
+    fn extract_track_ok(input: &[u8], track_id: u32, mdat_data: &[u8]) -> Vec<u8> {
+        let extracted = extract_track(input, track_id, Config::default()).unwrap();
+        let sanitized = sanitize_slice(&extracted, Config::default()).unwrap();
+        assert_eq!(sanitized_data(sanitized, &extracted), extracted);
+        ffmpeg_assert_eq(&extracted, mdat_data);
+        gpac_assert_eq(&extracted, mdat_data);
+        extracted
+    }
+
+    #[test]
+    fn extract() {
+        let test = test_mp4().build();
+        extract_track_ok(&test.data, 1, &test.mdat_data);
+    }
+
+    #[test]
+    fn extract_moov_first() {
+        let test = test_mp4().boxes(&[FTYP, MOOV, MDAT][..]).build();
+        extract_track_ok(&test.data, 1, &test.mdat_data);
+    }
+
+    #[test]
+    fn extract_drops_unreferenced_data() {
+        let test = test_mp4().boxes(&[FTYP, MDAT, FREE, MDAT, MOOV][..]).build();
+        let extracted = extract_track_ok(&test.data, 1, &test.mdat_data);
+        let sanitized = sanitize_to_vec(&test.data, Config::default()).unwrap();
+        assert!(
+            extracted.len() < sanitized.len(),
+            "{} >= {}",
+            extracted.len(),
+            sanitized.len()
+        );
+    }
+
+    #[test]
+    fn extract_non_first_track() {
+        let test = test_mp4()
+            .boxes(&[FTYP, MOOV, MDAT][..])
+            .moov(test_moov().trak_count(3).clone())
+            .mdat_data(&b"abcdefghijkl"[..])
+            .build();
+
+        // Point each track's chunks at a separate third of the media data, e.g. `efghefghefgh` for the second track.
+        let mut data = test.data.to_vec();
+        let (ftyp, mut moov) = parse_metadata(&data).unwrap();
+        for (trak_idx, trak) in moov.data.parse().unwrap().traks().enumerate() {
+            let trak = trak.unwrap();
+            let offsets = trak.chunk_offsets().unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+            let offsets = (0..offsets.len() as u64)
+                .map(|chunk_idx| offsets[0] + 4 * trak_idx as u64 + chunk_idx % 4)
+                .collect::<Vec<_>>();
+            trak.set_chunk_offsets(&offsets).unwrap();
+        }
+        moov.put_buf(&mut data[ftyp.encoded_len() as usize..]);
+
+        let extracted = extract_track_ok(&data, 2, b"efghefghefgh");
+        let (_, mut extracted_moov) = parse_metadata(&extracted).unwrap();
+        let mut traks = extracted_moov.data.parse().unwrap().traks();
+        let trak = traks.next().unwrap().unwrap();
+        assert_eq!(trak.tkhd_mut().unwrap().unwrap().track_id(), 2);
+        let offsets = trak
+            .usize_chunk_offsets()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let chunks = offsets.iter().map(|&offset| extracted[offset]).collect::<Vec<_>>();
+        assert_eq!(chunks, b"efghefghefgh");
+        assert!(traks.next().is_none());
+
+        // Only the extracted track's chunks are kept in the media data.
+        assert!(extracted.ends_with(b"\0\0\0\x0cmdatefgh"));
+        for other_chunks in [&b"abcd"[..], b"ijkl"] {
+            assert!(!extracted.windows(4).any(|window| window == other_chunks));
+        }
+    }
+
+    #[test]
+    fn extract_missing_track() {
+        let test = test_mp4().build();
+        assert_matches!(
+            extract_track(&test.data, 2, Config::default()).unwrap_err(),
+            Error::Parse(err) => {
+                assert_matches!(err.into_inner(), ParseError::MissingRequiredBox(BoxType::TRAK));
+            }
+        );
+    }
+}
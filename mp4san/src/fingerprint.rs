@@ -0,0 +1,141 @@
+//! Fingerprinting of the structure of an MP4 file's metadata.
+
+use std::io;
+use std::io::Read;
+
+use bytes::BytesMut;
+use futures_util::io::BufReader;
+use futures_util::pin_mut;
+use mediasan_common::sync;
+use sha2::{Digest, Sha256};
+
+use crate::parse::{displace_chunk_offsets, BoxHeader, BoxType, MoovBox, Mp4Value, ParseError};
+use crate::{read_boxes, Config, Error, ReadBoxes, Skip};
+
+//
+// public functions
+//
+
+/// Compute a fingerprint of the structure of an MP4 input's metadata, with the given [`Config`].
+///
+/// This function is only available with the `fingerprint` feature, which adds a dependency on `sha2`.
+///
+/// The `input` must implement [`Read`] + [`Skip`], where [`Skip`] represents a subset of the [`Seek`] trait; an input
+/// stream which can be skipped forward, but not necessarily seeked to arbitrary positions.
+///
+/// The fingerprint is a SHA-256 hash of a canonicalized serialization of the `moov` box, which ignores the contents of
+/// the media data. Inputs with the same box tree and sample tables have the same fingerprint, even if their media data
+/// differs. The canonicalization:
+///
+/// - removes `free` and `skip` padding boxes;
+/// - zeroes the creation and modification times, as with [`Config::zero_timestamps`];
+/// - makes chunk offsets relative to the first chunk, so that they don't depend on the position of the media data;
+/// - uses the minimal encoding of each box size, as with [`Config::canonicalize_box_sizes`]; and
+/// - ignores the order of the `moov` box's children other than its `trak` boxes, whose order is significant.
+///
+/// ```
+/// # use mp4san_test::{example_ftyp, example_mdat, example_moov};
+/// #
+/// let example_input = [example_ftyp(), example_mdat(), example_moov()].concat();
+/// let faststart_input = [example_ftyp(), example_moov(), example_mdat()].concat();
+///
+/// let config = mp4san::Config::default();
+/// let fingerprint = mp4san::moov_fingerprint(std::io::Cursor::new(example_input), config.clone())?;
+/// let faststart_fingerprint = mp4san::moov_fingerprint(std::io::Cursor::new(faststart_input), config)?;
+/// assert_eq!(fingerprint, faststart_fingerprint);
+/// #
+/// # Ok::<(), mp4san::Error>(())
+/// ```
+///
+/// # Errors
+///
+/// If the input cannot be parsed, or an IO error occurs, an [`Error`] is returned.
+///
+/// [`Seek`]: std::io::Seek
+pub fn moov_fingerprint<R: Read + Skip + Unpin>(input: R, config: Config) -> Result<[u8; 32], Error> {
+    sync::sanitize(input, |input| async move {
+        let reader = BufReader::with_capacity(BoxHeader::MAX_SIZE as usize, input);
+        pin_mut!(reader);
+        let ReadBoxes { mut moov, .. } =
+            read_boxes(reader, &config, None, None, None::<&mut io::Sink>, &mut BytesMut::new()).await?;
+        fingerprint(moov.data.parse()?)
+    })
+}
+
+//
+// private functions
+//
+
+/// Canonicalize `moov` as described by [`moov_fingerprint`], and hash its serialization.
+fn fingerprint(moov: &mut MoovBox) -> Result<[u8; 32], Error> {
+    moov.remove_padding()?;
+    moov.zero_timestamps()?;
+
+    let mut first_chunk_offset: Option<u64> = None;
+    for trak in moov.traks() {
+        for chunk_offset in trak?.chunk_offsets()? {
+            let chunk_offset = chunk_offset?;
+            first_chunk_offset = Some(first_chunk_offset.map_or(chunk_offset, |offset| offset.min(chunk_offset)));
+        }
+    }
+    if let Some(first_chunk_offset) = first_chunk_offset {
+        let delta = i64::try_from(first_chunk_offset)
+            .map_err(|_| report_attach!(ParseError::InvalidInput, "chunk offset too large"))?;
+        displace_chunk_offsets(moov, -delta)?;
+    }
+    moov.canonicalize_box_sizes()?;
+
+    let mut traks = Vec::new();
+    let mut other_children = Vec::new();
+    for child in moov.children() {
+        let mut encoded = Vec::with_capacity(child.encoded_len() as usize);
+        child.put_buf(&mut encoded);
+        match child.calculated_header().box_type() {
+            BoxType::TRAK => traks.push(encoded),
+            _ => other_children.push(encoded),
+        }
+    }
+    other_children.sort_unstable();
+
+    let mut hasher = Sha256::new();
+    for encoded in other_children.iter().chain(&traks) {
+        hasher.update((encoded.len() as u64).to_be_bytes());
+        hasher.update(encoded);
+    }
+    Ok(hasher.finalize().into())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parse::box_type::{FTYP, MDAT, MOOV};
+    use crate::util::test::mp4::TestMp4;
+    use crate::util::test::{test_moov, test_mp4};
+
+    use super::*;
+
+    fn fingerprint_of(test: &TestMp4) -> [u8; 32] {
+        moov_fingerprint(test.clone(), Config::default()).unwrap()
+    }
+
+    #[test]
+    fn same_structure_different_mdat() {
+        let test = test_mp4().mdat_data(&b"abcdefg"[..]).build();
+        let other_test = test_mp4().mdat_data(&b"gfedcba"[..]).build();
+        assert_ne!(test.data, other_test.data);
+        assert_eq!(fingerprint_of(&test), fingerprint_of(&other_test));
+    }
+
+    #[test]
+    fn same_structure_different_layout() {
+        let test = test_mp4().boxes(&[FTYP, MDAT, MOOV][..]).build();
+        let faststart_test = test_mp4().boxes(&[FTYP, MOOV, MDAT][..]).build();
+        assert_eq!(fingerprint_of(&test), fingerprint_of(&faststart_test));
+    }
+
+    #[test]
+    fn different_structure() {
+        let test = test_mp4().build();
+        let other_test = test_mp4().moov(test_moov().trak_count(2).clone()).build();
+        assert_ne!(fingerprint_of(&test), fingerprint_of(&other_test));
+    }
+}
@@ -55,29 +55,46 @@ extern crate self as mp4san;
 extern crate mediasan_common;
 
 pub mod error;
+mod extract;
+#[cfg(feature = "fingerprint")]
+mod fingerprint;
 pub mod parse;
+mod push;
+mod reader;
 mod util;
 
-use std::io::Read;
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::io::{Read, Write};
 use std::pin::Pin;
+use std::time::Instant;
 
+use bytes::BytesMut;
 use derive_builder::Builder;
 use derive_more::Display;
 use futures_util::io::BufReader;
-use futures_util::{pin_mut, AsyncBufReadExt, AsyncRead};
+use futures_util::{pin_mut, AsyncBufReadExt, AsyncRead, AsyncReadExt};
 use mediasan_common::sync;
-use mediasan_common::util::{checked_add_signed, IoResultExt};
+use mediasan_common::util::IoResultExt;
 use mediasan_common::AsyncSkipExt;
 
 use crate::error::Report;
-use crate::parse::error::{MultipleBoxes, WhileParsingBox};
-use crate::parse::{BoxHeader, BoxType, FourCC, FtypBox, MoovBox, Mp4Box, Mp4Value, ParseError, StblCoMut};
+use crate::parse::error::{MultipleBoxes, WhileParsingBox, WhileParsingChild};
+use crate::parse::{
+    displace_chunk_offsets, BoxHeader, BoxType, BoxUuid, FourCC, FtypBox, MetaBox, MoovBox, Mp4Box, Mp4Value, ParseBox,
+    ParseError, ParsedBox,
+};
+use crate::reader::{BoxDataReader, OffsetReader};
 
 //
 // public types
 //
 
 pub use crate::error::Error;
+pub use crate::extract::extract_track;
+#[cfg(feature = "fingerprint")]
+pub use crate::fingerprint::moov_fingerprint;
+pub use crate::push::Sanitizer;
 
 #[derive(Builder, Clone)]
 #[builder(build_fn(name = "try_build"))]
@@ -85,11 +102,259 @@ pub use crate::error::Error;
 pub struct Config {
     /// The maximum size of metadata to support.
     ///
-    /// This is useful to set an upper bound on memory consumption in the parser.
+    /// This is useful to set an upper bound on memory consumption in the parser. A larger `moov` box, or a larger
+    /// top-level `meta` box when [validated](Self::validate_meta), results in a [`ParseError::MetadataTooLarge`] error
+    /// giving its actual size.
     ///
     /// The default is 1 GiB.
     #[builder(default = "1024 * 1024 * 1024")]
     pub max_metadata_size: u64,
+
+    /// The maximum total length of the input to accept, if any.
+    ///
+    /// When set, an input longer than this is rejected with [`ParseError::InvalidInput`] before any box is parsed. The
+    /// check is skipped for inputs whose length is unknown, i.e. whose [`stream_len`](Skip::stream_len) is
+    /// unsupported. A [`Sanitizer`] instead checks the length of the input pushed so far on each
+    /// [`push`](Sanitizer::push).
+    ///
+    /// The default is [`None`].
+    #[builder(default, setter(strip_option))]
+    pub max_input_len: Option<u64>,
+
+    /// The maximum declared size of a top-level box to accept, if any.
+    ///
+    /// A top-level box declaring a larger size, such as a corrupt 64-bit extended size, is rejected with
+    /// [`ParseError::InvalidInput`] as soon as its header is read, rather than failing only once reading or skipping it
+    /// reaches the end of the input.
+    ///
+    /// Setting this to the length of the input, when it is known up front, rejects any box extending past the end of
+    /// the input. It isn't derived from the input by default, since the length of an incrementally pushed input is only
+    /// known once all of it has been received.
+    ///
+    /// The default is [`None`].
+    #[builder(default, setter(strip_option))]
+    pub max_box_size: Option<u64>,
+
+    /// The maximum number of `trak` boxes to accept in the `moov` box, if any.
+    ///
+    /// A `moov` box with more tracks, which usually indicates corruption, is rejected with [`ParseError::InvalidInput`]
+    /// before any track is validated. Tracks removed by [`keep_track_ids`](Self::keep_track_ids) are counted.
+    ///
+    /// The default is [`None`].
+    #[builder(default, setter(strip_option))]
+    pub max_track_count: Option<usize>,
+
+    /// The maximum number of entries to accept in each track's `stsc` box, if any.
+    ///
+    /// Each entry describes a run of chunks, and the entries are iterated over e.g. when
+    /// [validating the chunk count](Self::validate_chunk_count). A track with more entries is rejected with
+    /// [`ParseError::InvalidInput`] before they are iterated over.
+    ///
+    /// The default is [`None`].
+    #[builder(default, setter(strip_option))]
+    pub max_stsc_entries: Option<u32>,
+
+    /// Whether to attempt to recover from corrupted regions between top-level boxes.
+    ///
+    /// When enabled, an invalid or unrecognized top-level box header causes the sanitizer to scan forward, byte by
+    /// byte, for the next plausible header of a box type it supports, instead of returning an error. The skipped region
+    /// is left out of the sanitized output, so recovery always produces rewritten
+    /// [metadata](SanitizedMetadata::metadata). Corruption inside a box, or between `mdat` boxes, is not recoverable,
+    /// and an `mdat` extending past the end of the input is treated as truncated rather than corrupt.
+    ///
+    /// The default is `false`.
+    #[builder(default)]
+    pub recover: bool,
+
+    /// Whether to remove `free` and `skip` padding boxes from within `moov` when rewriting it.
+    ///
+    /// Padding is removed from `moov` and its `trak`, `mdia`, `minf`, and `stbl` descendants. This only takes effect
+    /// when the sanitizer rewrites the [metadata](SanitizedMetadata::metadata), e.g. to move `moov` before `mdat`; an
+    /// input which is already streamable is returned unmodified.
+    ///
+    /// The default is `false`.
+    #[builder(default)]
+    pub compact_moov: bool,
+
+    /// Whether to check that the number of chunk offsets in each track's `stco` or `co64` box is consistent with the
+    /// chunks referenced by its `stsc` box.
+    ///
+    /// A mismatch indicates a corrupted sample table, and results in a [`ParseError::InvalidInput`] error.
+    ///
+    /// The default is `false`.
+    #[builder(default)]
+    pub validate_chunk_count: bool,
+
+    /// Whether to check that every chunk offset in each track's `stco` or `co64` box lies within the media data span
+    /// returned in [`SanitizedMetadata::data`].
+    ///
+    /// A chunk offset outside the media data indicates a corrupted sample table, or one referencing data outside the
+    /// returned span, and results in a [`ParseError::InvalidInput`] error.
+    ///
+    /// The default is `false`.
+    #[builder(default)]
+    pub validate_sample_bounds: bool,
+
+    /// Whether to reject top-level `skip` boxes, which may be used to hide data, while still ignoring `free` boxes.
+    ///
+    /// When enabled, a top-level `skip` box results in a [`ParseError::UnsupportedBox`] error.
+    ///
+    /// The default is `false`.
+    #[builder(default)]
+    pub reject_skip_boxes: bool,
+
+    /// Whether to ignore trailing bytes after the last box which are too few to form a complete box header.
+    ///
+    /// When disabled, such trailing bytes result in a [`ParseError::TruncatedBox`] error. When enabled, they are logged
+    /// and left out of the sanitized output, so the metadata is always rewritten. Trailing bytes which do form a box
+    /// header are always parsed as a box.
+    ///
+    /// The default is `false`.
+    #[builder(default)]
+    pub allow_trailing_garbage: bool,
+
+    /// Whether to parse and validate top-level `meta` boxes, rather than skipping them.
+    ///
+    /// When enabled, each top-level `meta` box is read, limited in size by
+    /// [`max_metadata_size`](Self::max_metadata_size), and must be a well-formed full box containing a single `hdlr`
+    /// box, followed by well-formed child boxes such as an `ilst` item list. As when skipped, the box is left out of
+    /// the sanitized metadata.
+    ///
+    /// The default is `false`.
+    #[builder(default)]
+    pub validate_meta: bool,
+
+    /// Whether to require at least one video track, i.e. a `trak` box whose `hdlr` box declares the
+    /// [`vide` handler type](VIDEO_HANDLER_TYPE).
+    ///
+    /// When enabled, inputs without any video track, such as audio-only `.m4a` files, result in a
+    /// [`ParseError::MissingRequiredBox`] error.
+    ///
+    /// The default is `false`.
+    #[builder(default)]
+    pub require_video_track: bool,
+
+    /// Whether to reject tracks with a non-trivial edit list, for callers which need the media to be presented
+    /// unmodified.
+    ///
+    /// When enabled, a track whose `elst` box contains anything other than a single edit presenting its media from the
+    /// start at normal rate, such as an empty edit delaying the start of the track, or an edit trimming its media,
+    /// results in a [`ParseError::UnsupportedBox`] error.
+    ///
+    /// The default is `false`.
+    #[builder(default)]
+    pub reject_edit_lists: bool,
+
+    /// Whether to set the creation and modification times in the `mvhd` box, and in each track's `tkhd` and `mdhd`
+    /// boxes, to zero, so that the sanitized metadata doesn't reveal when the media was created.
+    ///
+    /// When enabled, the metadata is always rewritten, even if the input was already "faststart", so
+    /// [`SanitizedMetadata::metadata`] is always present.
+    ///
+    /// The default is `false`.
+    #[builder(default)]
+    pub zero_timestamps: bool,
+
+    /// Whether to remove the iTunes-style metadata items, such as the title or location of the media, i.e. the `ilst`
+    /// box and the `keys` box, if any, from each `meta` box in the `moov` box or in one of its `trak` boxes, whether
+    /// directly or within a `udta` box.
+    ///
+    /// A top-level `meta` box outside the `moov` box is not part of the sanitized metadata, and is left as it is: if it
+    /// follows the media data, it is kept in the [`data`](SanitizedMetadata::data) span along with it.
+    ///
+    /// When enabled, the metadata is always rewritten, even if the input was already "faststart", so
+    /// [`SanitizedMetadata::metadata`] is always present.
+    ///
+    /// The default is `false`.
+    #[builder(default)]
+    pub strip_metadata_items: bool,
+
+    /// Whether to rewrite the headers of the boxes in the sanitized metadata to their minimal encoding.
+    ///
+    /// A box whose size fits in 32 bits may still be encoded with an extended 64-bit size, wasting 8 bytes. When
+    /// enabled, such headers are replaced with their canonical 32-bit form within the `moov` box and its `mvex`,
+    /// `trak`, `edts`, `mdia`, `minf`, `stbl`, and `stsd` descendants, including the child boxes of audio and visual
+    /// sample entries, and chunk offsets are adjusted for the resulting change in metadata size. Boxes nested within
+    /// other boxes, e.g. the `meta` box within `udta` or the `dref` box within `dinf`, are left as they are. The
+    /// metadata is always rewritten, even if the input was already "faststart", so [`SanitizedMetadata::metadata`] is
+    /// always present.
+    ///
+    /// The default is `false`.
+    #[builder(default)]
+    pub canonicalize_box_sizes: bool,
+
+    /// Whether to accept an input whose `moov` box is cut short by the end of the input.
+    ///
+    /// When enabled, a `moov` box declaring a size larger than the remainder of the input is cut back to the end of its
+    /// last complete child box, and sanitized as if it had declared that size, instead of being rejected with
+    /// [`ParseError::TruncatedBox`]. The truncated `moov` must still contain every box required of it, so this is only
+    /// useful when the input was truncated after the essential metadata, e.g. during a trailing `udta` box. The
+    /// metadata is always rewritten for such an input.
+    ///
+    /// The default is `false`.
+    #[builder(default)]
+    pub truncated_moov_ok: bool,
+
+    /// How to handle top-level `uuid` boxes with known user types.
+    ///
+    /// A top-level `uuid` box is rejected with [`ParseError::UnsupportedBox`] unless its user type maps to
+    /// [`UuidPolicy::Skip`], in which case it is skipped like a `free` box. This allows benign vendor boxes, such as
+    /// the [XMP metadata box](BoxUuid::XMP), to be accepted without accepting all `uuid` boxes.
+    ///
+    /// The default is empty.
+    #[builder(default, setter(into, each(name = "known_uuid_box")))]
+    pub known_uuid_boxes: HashMap<BoxUuid, UuidPolicy>,
+
+    /// Whether to verify that the sanitized metadata re-parses before returning it.
+    ///
+    /// When enabled, the assembled `ftyp` and `moov` boxes are parsed again and must re-encode to exactly the same
+    /// bytes, followed by at most a `free` padding box. A failure indicates a bug in the sanitizer rather than in the
+    /// input, and results in a [`ParseError::InvalidBoxLayout`] error instead of invalid metadata being returned. This
+    /// costs an extra parse of the metadata.
+    ///
+    /// The default is `false`.
+    #[builder(default)]
+    pub verify_output: bool,
+
+    /// Which boxes an input is required to contain.
+    ///
+    /// See [`RequiredBoxes`] for the boxes which are always required.
+    ///
+    /// The default is [`RequiredBoxes::default`], requiring an `mdat` box and no additional boxes.
+    #[builder(default)]
+    pub required_boxes: RequiredBoxes,
+
+    /// A box type to give the box containing the media data in place of `mdat`, e.g. [`BoxType::FREE`] so that players
+    /// skip the media until it is renamed back to `mdat` for staged delivery.
+    ///
+    /// When set and the metadata is rewritten, the header of the first `mdat` box is moved from the start of
+    /// [`SanitizedMetadata::data`] to the end of [`SanitizedMetadata::metadata`], renamed, and resized to span all of
+    /// the media data. The renamed header is encoded with the same length as the original, so no chunk offsets change,
+    /// and renaming it back to `mdat` produces a valid file. When the metadata isn't rewritten, or the media data
+    /// wasn't read from an `mdat` box in the input, this has no effect. A box type whose header can't be encoded with
+    /// the same length as the original results in a [`ParseError::UnsupportedBoxLayout`] error. With
+    /// [`sanitize_with_media_sink`], the moved header isn't written to the media sink.
+    ///
+    /// The default is `None`.
+    #[builder(default, setter(strip_option))]
+    pub rename_mdat_to: Option<BoxType>,
+
+    /// The IDs of the tracks to keep, if only some of the input's tracks are wanted, e.g. to drop unwanted audio or
+    /// subtitle tracks.
+    ///
+    /// When set, each `trak` box whose `tkhd` box's track ID is not in the set is removed from the sanitized `moov`,
+    /// along with any references to it from the remaining tracks' `tref` boxes, and the media data is rewritten to
+    /// contain only the kept tracks' chunks. An input with none of the given tracks is rejected with
+    /// [`ParseError::MissingRequiredBox`].
+    ///
+    /// Since the media data must be rewritten, this is only supported by [`sanitize_to_vec`], [`validate`], and
+    /// [`estimate_faststart_overhead`]. The functions returning [`SanitizedMetadata`], whose
+    /// [`data`](SanitizedMetadata::data) is a span of the unmodified input, return an [`io::ErrorKind::InvalidInput`]
+    /// error when it is set.
+    ///
+    /// The default is `None`.
+    #[builder(default, setter(strip_option))]
+    pub keep_track_ids: Option<HashSet<u32>>,
 }
 
 /// Sanitized metadata returned by the sanitizer.
@@ -105,6 +370,111 @@ pub struct SanitizedMetadata {
 
     /// A pointer to the span in the input containing the (contiguous) media data.
     pub data: InputSpan,
+
+    /// Whether the input was already "faststart", i.e. its `moov` box preceded its media data, so that it did not
+    /// need to be modified.
+    ///
+    /// When this is `true`, [`metadata`](Self::metadata) is [`None`] and the input can be used as-is.
+    pub was_already_faststart: bool,
+
+    /// The file type information from the input's `ftyp` box.
+    pub ftyp: FtypInfo,
+
+    /// Whether the input's media data is encrypted with Common Encryption (CENC), as indicated by a `pssh` box in its
+    /// `moov`, or by a track with `saiz`, `saio`, or `senc` boxes or with protected sample entries.
+    ///
+    /// Encrypted media data is passed through unmodified like any other media data.
+    pub is_encrypted: bool,
+
+    /// The total number of chunks across all of the input's tracks, as given by their `stco` or `co64` boxes.
+    pub chunk_count: u64,
+
+    /// The span in the input containing the first sync sample, e.g. the first key frame, of the first video track.
+    ///
+    /// Like [`data`](Self::data), the span refers to the input rather than to the sanitized output. This is [`None`] if
+    /// the input has no video track, or if its first video track has no `stss` box listing its sync samples.
+    pub first_keyframe_span: Option<InputSpan>,
+
+    /// Whether the input's `moov` contains an `mvex` box, indicating that the input is fragmented and its `moov` is an
+    /// initialization segment for media in subsequent movie fragments.
+    pub is_fragmented_init: bool,
+}
+
+/// File type information parsed from an input's `ftyp` box.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FtypInfo {
+    /// The brand of the specification the input best conforms to.
+    pub major_brand: FourCC,
+
+    /// The version of the major brand.
+    pub minor_version: u32,
+
+    /// The brands of the specifications the input is compatible with, in their order in the input.
+    pub compatible_brands: Vec<FourCC>,
+}
+
+/// The outcome of successfully [validating](validate) an MP4 input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValidationOutcome {
+    /// The input is valid and already "faststart", i.e. its `moov` box precedes its media data, so sanitizing it would
+    /// leave it unmodified.
+    Faststart,
+
+    /// The input is valid, but sanitizing it would rewrite its metadata, e.g. to move its `moov` box before its media
+    /// data.
+    NeedsRewrite,
+}
+
+/// How to handle a top-level `uuid` box with a known user type, as configured by [`Config::known_uuid_boxes`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum UuidPolicy {
+    /// Skip the box, treating it like a `free` box.
+    Skip,
+
+    /// Reject the input with [`ParseError::UnsupportedBox`], as for unknown user types.
+    #[default]
+    Reject,
+}
+
+/// The boxes an input is required to contain, as configured by [`Config::required_boxes`].
+///
+/// The `ftyp` and `moov` boxes, and the boxes within `moov` locating each track's media data, are always required,
+/// since the sanitizer cannot rewrite an input without them. A missing required box results in a
+/// [`ParseError::MissingRequiredBox`] error.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequiredBoxes {
+    /// Whether an `mdat` box is required.
+    ///
+    /// When not required, an input without any `mdat` box, such as a "metadata-only" file whose tracks have no samples,
+    /// is sanitized as if it ended with an empty `mdat` box.
+    ///
+    /// The default is `true`.
+    pub mdat: bool,
+
+    /// Additional top-level box types which must be present, e.g. [`BoxType::META`].
+    ///
+    /// The default is empty.
+    pub additional: HashSet<BoxType>,
+}
+
+/// A reusable handle for sanitizing many MP4 inputs with the same [`Config`].
+///
+/// Unlike the push-style [`Sanitizer`], which sanitizes a single input fed to it incrementally, a `SanitizerHandle`
+/// sanitizes any number of [`Read`] + [`Skip`] inputs in turn.
+///
+/// Each call to [`sanitize`](Self::sanitize) reads the input's metadata into a scratch buffer owned by the handle, as
+/// with [`sanitize_with_buffer`], so that once the buffer has grown to fit the largest input's metadata, no further
+/// memory is allocated for it.
+///
+/// # Thread safety
+///
+/// A `SanitizerHandle` is [`Send`] and [`Sync`], but [`sanitize`](Self::sanitize) takes `&mut self`, so it sanitizes
+/// one input at a time. To sanitize inputs concurrently, use one handle per thread, e.g. by [cloning](Clone) one.
+/// Cloning copies the [`Config`] but not the contents of the scratch buffer.
+#[derive(Default)]
+pub struct SanitizerHandle {
+    config: Config,
+    scratch: BytesMut,
 }
 
 pub use mediasan_common::{AsyncSkip, InputSpan, SeekSkipAdapter, Skip};
@@ -115,16 +485,91 @@ pub use mediasan_common::{AsyncSkip, InputSpan, SeekSkipAdapter, Skip};
 /// sanitizer.
 pub const COMPATIBLE_BRAND: FourCC = FourCC { value: *b"isom" };
 
+/// The handler type of video tracks, as declared in their `hdlr` box.
+pub const VIDEO_HANDLER_TYPE: FourCC = FourCC { value: *b"vide" };
+
 //
 // private types
 //
 
+/// The boxes read from an input by [`read_boxes`], before its sanitized metadata is assembled.
+struct ReadBoxes {
+    ftyp: Mp4Box<FtypBox>,
+    moov: Mp4Box<MoovBox>,
+    ftyp_info: FtypInfo,
+    data: InputSpan,
+    /// The header of the first `mdat` box in `data`, if it was read from the input.
+    data_header: Option<BoxHeader>,
+    needs_rewrite: bool,
+    is_encrypted: bool,
+    chunk_count: u64,
+    first_keyframe_span: Option<InputSpan>,
+    is_fragmented_init: bool,
+}
+
+/// The headers of the top-level boxes of an input, read in order by [`read_boxes`] and
+/// [`estimate_faststart_overhead`], recovering from corrupt headers and stopping at trailing garbage as configured.
+#[derive(Default)]
+struct BoxHeaders {
+    /// The header found past a corrupt region of the input, to be returned next.
+    recovered_header: Option<(u64, BoxHeader)>,
+    /// Whether a corrupt region of the input was skipped.
+    recovered: bool,
+    /// Whether the input ended with trailing garbage too short to be a box header.
+    trailing_garbage: bool,
+}
+
 #[derive(Clone, Copy, Debug, Display)]
 #[display(fmt = "box data too large: {} > {}", _0, _1)]
 struct BoxDataTooLarge(u64, u64);
 
+#[derive(Clone, Copy, Debug, Display)]
+#[display(fmt = "box data truncated: expected {} bytes, read {}", _0, _1)]
+struct TruncatedBoxData(u64, u64);
+
+#[derive(Clone, Copy, Debug, Display)]
+#[display(fmt = "first box @ 0x{:08x}, second box @ 0x{:08x}", _0, _1)]
+struct DuplicateBoxOffsets(u64, u64);
+
+#[derive(Clone, Copy, Debug, Display)]
+#[display(fmt = "input too large: {} > {}", _0, _1)]
+struct InputTooLarge(u64, u64);
+
+#[derive(Clone, Copy, Debug, Display)]
+#[display(fmt = "box too large: {} > {}", _0, _1)]
+struct BoxTooLarge(u64, u64);
+
+#[derive(Clone, Copy, Debug, Display)]
+#[display(fmt = "too many tracks: {} > {}", _0, _1)]
+struct TooManyTracks(usize, usize);
+
+#[derive(Clone, Copy, Debug, Display)]
+#[display(fmt = "too many stsc entries: {} > {}", _0, _1)]
+struct TooManyStscEntries(u32, u32);
+
+#[derive(Clone, Copy, Debug, Display)]
+#[display(fmt = "chunk offset 0x{:08x} outside media data @ 0x{:08x}: {} bytes", _0, _1, _2)]
+struct ChunkOffsetOutOfBounds(u64, u64, u64);
+
 const MAX_FTYP_SIZE: u64 = 1024;
 
+/// The size of the chunks in which media data is copied to a media sink.
+const MEDIA_SINK_CHUNK_SIZE: usize = 8 * 1024;
+
+/// The maximum number of bytes scanned for a box header when recovering from a corrupted region.
+const MAX_RECOVERY_SCAN_LEN: u64 = 1024 * 1024;
+
+/// The top-level box types which may be found when recovering from a corrupted region.
+const RECOVERABLE_BOX_TYPES: [BoxType; 7] = [
+    BoxType::FREE,
+    BoxType::SKIP,
+    BoxType::FTYP,
+    BoxType::MDAT,
+    BoxType::MOOV,
+    BoxType::META,
+    BoxType::MECO,
+];
+
 //
 // public functions
 //
@@ -174,524 +619,3463 @@ pub fn sanitize_with_config<R: Read + Skip + Unpin>(input: R, config: Config) ->
     sync::sanitize(input, |input| sanitize_async_with_config(input, config))
 }
 
-/// Sanitize an MP4 input asynchronously, with the default [`Config`].
+/// Sanitize an MP4 input, with the given [`Config`], reusing the memory of `buf` to hold its metadata.
 ///
-/// The `input` must implement [`AsyncRead`] + [`AsyncSkip`], where [`AsyncSkip`] represents a subset of the
-/// [`AsyncSeek`] trait; an input stream which can be skipped forward, but not necessarily seeked to arbitrary
-/// positions.
+/// This is equivalent to [`sanitize_with_config`], except that the `moov` box is read into `buf`, which is cleared
+/// first. Reusing the same `buf` when sanitizing many inputs in a loop avoids allocating memory for each input's
+/// metadata, once `buf` has grown to fit the largest one.
 ///
-/// # Examples
+/// ```
+/// # use bytes::BytesMut;
+/// # use mp4san_test::{example_ftyp, example_mdat, example_moov};
+/// #
+/// let example_input = [example_ftyp(), example_mdat(), example_moov()].concat();
+///
+/// let mut buf = BytesMut::new();
+/// for _ in 0..3 {
+///     let input = std::io::Cursor::new(&example_input);
+///     let sanitized = mp4san::sanitize_with_buffer(input, mp4san::Config::default(), &mut buf)?;
+///     assert_eq!(sanitized.metadata, Some([example_ftyp(), example_moov()].concat()));
+/// }
+/// # Ok::<(), mp4san::Error>(())
+/// ```
+///
+/// # Errors
+///
+/// If the input cannot be parsed, or an IO error occurs, an [`Error`] is returned.
+pub fn sanitize_with_buffer<R: Read + Skip + Unpin>(
+    input: R,
+    config: Config,
+    buf: &mut BytesMut,
+) -> Result<SanitizedMetadata, Error> {
+    sync::sanitize(input, |input| sanitize_async_inner(input, config, None, None, buf))
+}
+
+/// Sanitize an MP4 input which begins at the offset `start` within `input`, with the given [`Config`].
+///
+/// This is useful when some of `input` was already read, e.g. to detect its content type, and `input` can't be rewound.
+/// The `input` is first skipped forward to `start`, and the MP4 is sanitized as though its first box began the input.
+/// The returned [`data`](SanitizedMetadata::data) and [`first_keyframe_span`](SanitizedMetadata::first_keyframe_span)
+/// spans are relative to the whole of `input`, like those returned by [`sanitize_with_config`], while the returned
+/// [`metadata`](SanitizedMetadata::metadata) is relative to the start of the MP4. When the metadata is [`None`], the
+/// MP4 beginning at `start` can be used as-is.
 ///
 /// ```
-/// # use mp4san::sanitize_async;
 /// # use mp4san_test::{example_ftyp, example_mdat, example_moov};
 /// #
-/// # fn main() -> Result<(), mp4san::Error> {
-/// #     futures_util::FutureExt::now_or_never(run()).unwrap()
-/// # }
+/// let prefix = b"already read";
+/// let example_input = [&prefix[..], &example_ftyp(), &example_mdat(), &example_moov()].concat();
+///
+/// let input = std::io::Cursor::new(example_input);
+/// let sanitized = mp4san::sanitize_from_position(input, prefix.len() as u64, mp4san::Config::default())?;
+///
+/// assert_eq!(sanitized.metadata, Some([example_ftyp(), example_moov()].concat()));
+/// assert_eq!(sanitized.data.offset, (prefix.len() + example_ftyp().len()) as u64);
+/// #
+/// # Ok::<(), mp4san::Error>(())
+/// ```
+///
+/// # Errors
+///
+/// If `input` has already been read past `start`, the MP4 cannot be parsed, or an IO error occurs, an [`Error`] is
+/// returned.
+pub fn sanitize_from_position<R: Read + Skip + Unpin>(
+    input: R,
+    start: u64,
+    config: Config,
+) -> Result<SanitizedMetadata, Error> {
+    let input = OffsetReader::new(input, start)?;
+    let mut sanitized = sanitize_with_config(input, config)?;
+    sanitized.data.offset += start;
+    if let Some(span) = &mut sanitized.first_keyframe_span {
+        span.offset += start;
+    }
+    Ok(sanitized)
+}
+
+/// Sanitize an MP4 input held in memory, such as a memory-mapped file, with the given [`Config`].
+///
+/// This is equivalent to calling [`sanitize_with_config`] with an [`io::Cursor`] over `input`. Since the whole input is
+/// addressable, the returned [`data`](SanitizedMetadata::data) span, and the entire input when
+/// [`metadata`](SanitizedMetadata::metadata) is [`None`], can be used directly from `input` without copying.
+///
+/// ```
+/// # use mp4san_test::{example_ftyp, example_mdat, example_moov};
 /// #
-/// # async fn run() -> Result<(), mp4san::Error> {
 /// let example_input = [example_ftyp(), example_mdat(), example_moov()].concat();
 ///
-/// let sanitized = sanitize_async(futures_util::io::Cursor::new(example_input)).await?;
+/// let sanitized = mp4san::sanitize_slice(&example_input, mp4san::Config::default())?;
 ///
 /// assert_eq!(sanitized.metadata, Some([example_ftyp(), example_moov()].concat()));
-/// assert_eq!(sanitized.data.offset, example_ftyp().len() as u64);
-/// assert_eq!(sanitized.data.len, example_mdat().len() as u64);
-/// # Ok(())
-/// # }
+/// let mdat = &example_input[sanitized.data.offset as usize..][..sanitized.data.len as usize];
+/// assert_eq!(mdat, example_mdat());
+/// #
+/// # Ok::<(), mp4san::Error>(())
 /// ```
 ///
 /// # Errors
 ///
-/// If the input cannot be parsed, or an IO error occurs, an [`Error`] is returned.
-///
-/// [`AsyncSeek`]: futures_util::io::AsyncSeek
-pub async fn sanitize_async<R: AsyncRead + AsyncSkip>(input: R) -> Result<SanitizedMetadata, Error> {
-    sanitize_async_with_config(input, Config::default()).await
+/// If the input cannot be parsed, an [`Error`] is returned.
+pub fn sanitize_slice(input: &[u8], config: Config) -> Result<SanitizedMetadata, Error> {
+    sanitize_with_config(io::Cursor::new(input), config)
 }
 
-/// Sanitize an MP4 input asynchronously, with the given [`Config`].
+/// Sanitize an MP4 input held in memory, with the given [`Config`], returning the entire sanitized file.
 ///
-/// The `input` must implement [`AsyncRead`] + [`AsyncSkip`], where [`AsyncSkip`] represents a subset of the
-/// [`AsyncSeek`] trait; an input stream which can be skipped forward, but not necessarily seeked to arbitrary
-/// positions.
+/// This is a convenience wrapper around [`sanitize_with_config`] which concatenates the sanitized
+/// [`metadata`](SanitizedMetadata::metadata) with the media [`data`](SanitizedMetadata::data) copied from `input`. If
+/// the metadata did not need to be modified, a copy of `input` is returned.
 ///
-/// # Examples
+/// If [`Config::keep_track_ids`] is set, the media data is instead rewritten to contain only the chunks of the kept
+/// tracks, with their chunk offsets updated to match.
 ///
 /// ```
-/// # use mp4san::{sanitize_async_with_config, Config};
 /// # use mp4san_test::{example_ftyp, example_mdat, example_moov};
 /// #
-/// # fn main() -> Result<(), mp4san::Error> {
-/// #     futures_util::FutureExt::now_or_never(run()).unwrap()
-/// # }
-/// #
-/// # async fn run() -> Result<(), mp4san::Error> {
 /// let example_input = [example_ftyp(), example_mdat(), example_moov()].concat();
 ///
-/// let config = Config::builder().max_metadata_size(example_moov().len() as u64).build();
-/// let sanitized = sanitize_async_with_config(futures_util::io::Cursor::new(example_input), config).await?;
+/// let sanitized = mp4san::sanitize_to_vec(&example_input, mp4san::Config::default())?;
 ///
-/// assert_eq!(sanitized.metadata, Some([example_ftyp(), example_moov()].concat()));
-/// assert_eq!(sanitized.data.offset, example_ftyp().len() as u64);
-/// assert_eq!(sanitized.data.len, example_mdat().len() as u64);
-/// # Ok(())
-/// # }
+/// assert_eq!(sanitized, [example_ftyp(), example_moov(), example_mdat()].concat());
+/// #
+/// # Ok::<(), mp4san::Error>(())
 /// ```
 ///
 /// # Errors
 ///
-/// If the input cannot be parsed, or an IO error occurs, an [`Error`] is returned.
+/// If the input cannot be parsed, an [`Error`] is returned.
+pub fn sanitize_to_vec(input: &[u8], config: Config) -> Result<Vec<u8>, Error> {
+    let compact = config.keep_track_ids.is_some();
+    let sanitized = sync::sanitize(io::Cursor::new(input), |input| async move {
+        let reader = BufReader::with_capacity(BoxHeader::MAX_SIZE as usize, input);
+        pin_mut!(reader);
+        let buf = &mut BytesMut::new();
+        sanitize_buf_reader(reader, config, None, None, None::<&mut io::Sink>, compact, buf).await
+    })?;
+    let Some(mut metadata) = sanitized.metadata else {
+        return match compact {
+            true => extract::compact_media(input),
+            false => Ok(input.to_vec()),
+        };
+    };
+    let InputSpan { offset, len } = sanitized.data;
+    let data = usize::try_from(offset)
+        .ok()
+        .zip(usize::try_from(len).ok())
+        .and_then(|(offset, len)| input.get(offset..)?.get(..len))
+        .ok_or_else(|| report_attach!(ParseError::TruncatedBox, WhileParsingBox(BoxType::MDAT)))?;
+    metadata.extend_from_slice(data);
+    match compact {
+        true => extract::compact_media(&metadata),
+        false => Ok(metadata),
+    }
+}
+
+/// Sanitize the metadata of an MP4 whose media data is stored separately, with the given [`Config`].
 ///
-/// [`AsyncSeek`]: futures_util::io::AsyncSeek
-pub async fn sanitize_async_with_config<R: AsyncRead + AsyncSkip>(
+/// The `input` must implement [`Read`] + [`Skip`], where [`Skip`] represents a subset of the [`Seek`] trait; an input
+/// stream which can be skipped forward, but not necessarily seeked to arbitrary positions.
+///
+/// The `input` must contain the `ftyp` and `moov` boxes but no `mdat` box. It is sanitized as though it were
+/// immediately followed by an `mdat` box `media_len` bytes long, including its header, so the returned
+/// [`data`](SanitizedMetadata::data) span starts at the end of the input. The returned
+/// [`metadata`](SanitizedMetadata::metadata) is always present, and can be concatenated with the separately stored
+/// `mdat` box to form a valid MP4 file.
+///
+/// ```
+/// # use mp4san_test::{example_ftyp, example_mdat, example_moov};
+/// #
+/// let example_input = [example_ftyp(), example_moov()].concat();
+/// let example_input_len = example_input.len() as u64;
+///
+/// let media_len = example_mdat().len() as u64;
+/// let config = mp4san::Config::default();
+/// let sanitized = mp4san::sanitize_metadata_only(std::io::Cursor::new(example_input), media_len, config)?;
+///
+/// assert_eq!(sanitized.data.offset, example_input_len);
+/// assert_eq!(sanitized.data.len, media_len);
+/// #
+/// # Ok::<(), mp4san::Error>(())
+/// ```
+///
+/// # Errors
+///
+/// If the input cannot be parsed, contains an `mdat` box, or an IO error occurs, an [`Error`] is returned.
+///
+/// [`Seek`]: std::io::Seek
+pub fn sanitize_metadata_only<R: Read + Skip + Unpin>(
     input: R,
+    media_len: u64,
     config: Config,
 ) -> Result<SanitizedMetadata, Error> {
-    let reader = BufReader::with_capacity(BoxHeader::MAX_SIZE as usize, input);
-    pin_mut!(reader);
-
-    let mut ftyp: Option<Mp4Box<FtypBox>> = None;
-    let mut moov: Option<Mp4Box<MoovBox>> = None;
-    let mut data: Option<InputSpan> = None;
-    let mut moov_offset = None;
-
-    while !reader.as_mut().fill_buf().await?.is_empty() {
-        let start_pos = reader.as_mut().stream_position().await?;
-
-        let header = BoxHeader::read(&mut reader)
-            .await
-            .map_eof(|_| Error::Parse(report_attach!(ParseError::TruncatedBox, "while parsing box header")))?;
-
-        match header.box_type() {
-            name @ (BoxType::FREE | BoxType::SKIP) => {
-                let box_size = skip_box(reader.as_mut(), &header).await? + header.encoded_len();
-                log::info!("{name} @ 0x{start_pos:08x}: {box_size} bytes");
-
-                // Try to extend any already accumulated data in case there's more mdat boxes to come.
-                if let Some(data) = &mut data {
-                    if data.offset + data.len == start_pos {
-                        data.len += box_size;
-                    }
-                }
-            }
+    let mut buf = BytesMut::new();
+    sync::sanitize(input, |input| {
+        sanitize_async_inner(input, config, None, Some(media_len), &mut buf)
+    })
+}
 
-            BoxType::FTYP => {
-                ensure_attach!(
-                    ftyp.is_none(),
-                    ParseError::InvalidBoxLayout,
-                    MultipleBoxes(BoxType::FTYP)
-                );
+/// Validate an MP4 input, with the given [`Config`], without assembling its sanitized metadata.
+///
+/// The `input` must implement [`Read`] + [`Skip`], where [`Skip`] represents a subset of the [`Seek`] trait; an input
+/// stream which can be skipped forward, but not necessarily seeked to arbitrary positions.
+///
+/// The input is read and parsed exactly as by [`sanitize_with_config`], but rather than assembling the sanitized
+/// metadata, only whether it would need to be rewritten is returned. This avoids copying the metadata for callers which
+/// only need to know whether an input is valid and already "faststart".
+///
+/// An input which would need to be rewritten is not checked for errors which only occur while rewriting it, such as its
+/// media data being displaced too far, so [`sanitize_with_config`] may still reject an input which returns
+/// [`ValidationOutcome::NeedsRewrite`].
+///
+/// ```
+/// # use mp4san::ValidationOutcome;
+/// # use mp4san_test::{example_ftyp, example_mdat, example_moov};
+/// #
+/// let example_input = [example_ftyp(), example_mdat(), example_moov()].concat();
+/// let outcome = mp4san::validate(std::io::Cursor::new(example_input), mp4san::Config::default())?;
+/// assert_eq!(outcome, ValidationOutcome::NeedsRewrite);
+///
+/// let example_input = [example_ftyp(), example_moov(), example_mdat()].concat();
+/// let outcome = mp4san::validate(std::io::Cursor::new(example_input), mp4san::Config::default())?;
+/// assert_eq!(outcome, ValidationOutcome::Faststart);
+/// #
+/// # Ok::<(), mp4san::Error>(())
+/// ```
+///
+/// # Errors
+///
+/// If the input cannot be parsed, or an IO error occurs, an [`Error`] is returned.
+///
+/// [`Seek`]: std::io::Seek
+pub fn validate<R: Read + Skip + Unpin>(input: R, config: Config) -> Result<ValidationOutcome, Error> {
+    sync::sanitize(input, |input| async move {
+        let reader = BufReader::with_capacity(BoxHeader::MAX_SIZE as usize, input);
+        pin_mut!(reader);
+        let ReadBoxes { needs_rewrite, .. } =
+            read_boxes(reader, &config, None, None, None::<&mut io::Sink>, &mut BytesMut::new()).await?;
+        match needs_rewrite {
+            true => Ok(ValidationOutcome::NeedsRewrite),
+            false => Ok(ValidationOutcome::Faststart),
+        }
+    })
+}
+
+/// Estimate the length of the metadata a faststart rewrite of an MP4 input would produce, with the given [`Config`],
+/// without sanitizing it.
+///
+/// The `input` must implement [`Read`] + [`Skip`], where [`Skip`] represents a subset of the [`Seek`] trait; an input
+/// stream which can be skipped forward, but not necessarily seeked to arbitrary positions.
+///
+/// Only the box headers of the input are read; the `moov` box is skipped rather than parsed. The returned length is an
+/// upper bound on the length of the [`metadata`](SanitizedMetadata::metadata) returned by [`sanitize_with_config`],
+/// i.e. the bytes which must be written ahead of the media data, or `0` if the input is already faststart and would not
+/// be rewritten. The bound is conservative where chunk offsets may no longer fit in 32 bits, in which case every `stco`
+/// box could be upgraded to a larger `co64` box, and where [`keep_track_ids`](Config::keep_track_ids) is set, in which
+/// case the input is assumed to be rewritten.
+///
+/// ```
+/// # use mp4san_test::{example_ftyp, example_mdat, example_moov};
+/// #
+/// let example_input = [example_ftyp(), example_mdat(), example_moov()].concat();
+/// let overhead = mp4san::estimate_faststart_overhead(std::io::Cursor::new(example_input), mp4san::Config::default())?;
+/// assert_eq!(overhead, (example_ftyp().len() + example_moov().len()) as u64);
+///
+/// let example_input = [example_ftyp(), example_moov(), example_mdat()].concat();
+/// let overhead = mp4san::estimate_faststart_overhead(std::io::Cursor::new(example_input), mp4san::Config::default())?;
+/// assert_eq!(overhead, 0);
+/// #
+/// # Ok::<(), mp4san::Error>(())
+/// ```
+///
+/// # Errors
+///
+/// If the input's box headers cannot be parsed, it contains an unsupported box, it is missing a required box, or an IO
+/// error occurs, an [`Error`] is returned. Since the boxes themselves are not parsed, an input for which an estimate is
+/// returned may still fail to sanitize.
+///
+/// [`Seek`]: std::io::Seek
+pub fn estimate_faststart_overhead<R: Read + Skip + Unpin>(input: R, config: Config) -> Result<u64, Error> {
+    sync::sanitize(input, |input| async move {
+        let reader = BufReader::with_capacity(BoxHeader::MAX_SIZE as usize, input);
+        pin_mut!(reader);
+
+        let mut ftyp_len = None;
+        let mut moov: Option<InputSpan> = None;
+        let mut data: Option<InputSpan> = None;
+        let mut data_header = None;
+        let mut box_headers = BoxHeaders::default();
+        // Whether the boxes would be altered while being read, other than by recovering from a corrupt region of the
+        // input or dropping trailing garbage. Since the moov is not parsed, whether any of its tracks would be removed
+        // is unknown, so it is assumed they would be.
+        let mut altered = config.keep_track_ids.is_some();
+        while let Some((start_pos, header)) = box_headers.next(reader.as_mut(), &config, None).await? {
+            let box_data_size = match skip_box(reader.as_mut(), &header).await {
+                // A truncated moov is rewritten to keep only its complete children, so its declared size is an upper
+                // bound on its rewritten size.
+                Err(Error::Parse(err))
+                    if header.box_type() == BoxType::MOOV
+                        && config.truncated_moov_ok
+                        && matches!(err.get_ref(), ParseError::TruncatedBox) =>
+                {
+                    altered = true;
+                    header.box_data_size()?.unwrap_or_default()
+                }
+                box_data_size => box_data_size?,
+            };
+            let box_size = box_data_size + header.encoded_len();
+            if header.box_type() == BoxType::MOOV && config.truncated_moov_ok && !altered {
+                // Skipping past the end of a seekable input succeeds, so compare the end of the moov to its length.
+                match reader.as_mut().stream_len().await {
+                    Ok(stream_len) => altered = start_pos.saturating_add(box_size) > stream_len,
+                    Err(err) if err.kind() == io::ErrorKind::Unsupported => altered = true,
+                    Err(err) => return Err(err.into()),
+                }
+            }
+            match header.box_type() {
+                name @ (BoxType::FREE | BoxType::SKIP) => {
+                    ensure_attach!(
+                        !(name == BoxType::SKIP && config.reject_skip_boxes),
+                        ParseError::UnsupportedBox(name),
+                    );
+                    if let Some(data) = &mut data {
+                        extend_data(data, start_pos, box_size)?;
+                    }
+                }
+                BoxType::FTYP => ftyp_len = Some(box_size),
+                _ if ftyp_len.is_none() => {
+                    bail_attach!(ParseError::InvalidBoxLayout, "ftyp is not the first significant box");
+                }
+                BoxType::MOOV => moov = Some(InputSpan { offset: start_pos, len: box_size }),
+                BoxType::MDAT => match &mut data {
+                    Some(data) => {
+                        ensure_attach!(
+                            extend_data(data, start_pos, box_size)?,
+                            ParseError::UnsupportedBoxLayout,
+                            "discontiguous mdat boxes",
+                        );
+                    }
+                    None => {
+                        data = Some(InputSpan { offset: start_pos, len: box_size });
+                        data_header = Some(header);
+                    }
+                },
+                BoxType::META | BoxType::MECO => {
+                    if let Some(data) = &mut data {
+                        extend_data(data, start_pos, box_size)?;
+                    }
+                }
+                BoxType::Uuid(uuid) if config.known_uuid_boxes.get(&uuid) == Some(&UuidPolicy::Skip) => {
+                    if let Some(data) = &mut data {
+                        extend_data(data, start_pos, box_size)?;
+                    }
+                }
+                name => bail_attach!(ParseError::UnsupportedBox(name)),
+            }
+        }
+
+        let Some(ftyp_len) = ftyp_len else {
+            bail_attach!(ParseError::MissingRequiredBox(BoxType::FTYP));
+        };
+        let Some(moov) = moov else {
+            bail_attach!(ParseError::MissingRequiredBox(BoxType::MOOV));
+        };
+        let data = match data {
+            Some(data) => data,
+            None if !config.required_boxes.mdat => {
+                let offset = reader.as_mut().stream_position().await?;
+                InputSpan { offset, len: 0 }
+            }
+            None => bail_attach!(ParseError::MissingRequiredBox(BoxType::MDAT)),
+        };
+
+        let altered = altered || box_headers.recovered || box_headers.trailing_garbage;
+        if !needs_rewrite(&config, moov.offset < data.offset, altered) {
+            return Ok(0);
+        }
+
+        // Displacing the chunk offsets past 32 bits upgrades `stco` boxes to `co64`, doubling the size of their
+        // entries. Since the `stco` boxes are only part of the `moov`, the `moov` at most doubles in size.
+        let mut metadata_len = ftyp_len.saturating_add(moov.len);
+        if data.offset.saturating_add(data.len).saturating_add(metadata_len) > u32::MAX.into() {
+            metadata_len = metadata_len.saturating_add(moov.len);
+        }
+        // If the mdat would move backward, the metadata is padded to its original offset.
+        metadata_len = metadata_len.max(data.offset);
+        // A renamed mdat header is moved to the end of the metadata.
+        if let (Some(_), Some(data_header)) = (config.rename_mdat_to, data_header) {
+            metadata_len = metadata_len.saturating_add(data_header.encoded_len());
+        }
+        Ok(metadata_len)
+    })
+}
+
+/// Sanitize an MP4 input, with the given [`Config`], copying its media data to `media_sink` while it is read.
+///
+/// The `input` must implement [`Read`] + [`Skip`], where [`Skip`] represents a subset of the [`Seek`] trait; an input
+/// stream which can be skipped forward, but not necessarily seeked to arbitrary positions.
+///
+/// This is equivalent to [`sanitize_with_config`], except that rather than being skipped, the bytes of the returned
+/// [`data`](SanitizedMetadata::data) span are read and written to `media_sink`, in order, as they are encountered. This
+/// allows a sanitized copy of an input which can only be read once, such as a network stream, to be produced in a
+/// single pass: the sanitized [`metadata`](SanitizedMetadata::metadata) followed by the bytes written to `media_sink`
+/// form the sanitized output. The metadata is therefore always rewritten, even if the input is already faststart, so
+/// the returned [`metadata`](SanitizedMetadata::metadata) is never [`None`]. Any transformation of the media data, such
+/// as hashing or encryption, can be performed by the [`Write`] implementation of `media_sink`.
+///
+/// The media data is written to `media_sink` before the input has been fully validated, so if an [`Error`] is returned,
+/// anything written to it should be discarded.
+///
+/// ```
+/// # use mp4san_test::{example_ftyp, example_mdat, example_moov};
+/// #
+/// let example_input = [example_ftyp(), example_mdat(), example_moov()].concat();
+///
+/// let mut media_data = Vec::new();
+/// let input = std::io::Cursor::new(example_input);
+/// let sanitized = mp4san::sanitize_with_media_sink(input, mp4san::Config::default(), &mut media_data)?;
+///
+/// assert_eq!(sanitized.metadata, Some([example_ftyp(), example_moov()].concat()));
+/// assert_eq!(media_data, example_mdat());
+/// #
+/// # Ok::<(), mp4san::Error>(())
+/// ```
+///
+/// # Errors
+///
+/// If the input cannot be parsed, or an IO error occurs while reading the input or writing to `media_sink`, an
+/// [`Error`] is returned.
+///
+/// [`Seek`]: std::io::Seek
+pub fn sanitize_with_media_sink<R: Read + Skip + Unpin, S: Write>(
+    input: R,
+    config: Config,
+    mut media_sink: S,
+) -> Result<SanitizedMetadata, Error> {
+    sync::sanitize(input, |input| async move {
+        let reader = BufReader::with_capacity(BoxHeader::MAX_SIZE as usize, input);
+        pin_mut!(reader);
+        sanitize_buf_reader(
+            reader,
+            config,
+            None,
+            None,
+            Some(&mut media_sink),
+            false,
+            &mut BytesMut::new(),
+        )
+        .await
+    })
+}
+
+/// Sanitize an MP4 input asynchronously, with the default [`Config`].
+///
+/// The `input` must implement [`AsyncRead`] + [`AsyncSkip`], where [`AsyncSkip`] represents a subset of the
+/// [`AsyncSeek`] trait; an input stream which can be skipped forward, but not necessarily seeked to arbitrary
+/// positions.
+///
+/// # Examples
+///
+/// ```
+/// # use mp4san::sanitize_async;
+/// # use mp4san_test::{example_ftyp, example_mdat, example_moov};
+/// #
+/// # fn main() -> Result<(), mp4san::Error> {
+/// #     futures_util::FutureExt::now_or_never(run()).unwrap()
+/// # }
+/// #
+/// # async fn run() -> Result<(), mp4san::Error> {
+/// let example_input = [example_ftyp(), example_mdat(), example_moov()].concat();
+///
+/// let sanitized = sanitize_async(futures_util::io::Cursor::new(example_input)).await?;
+///
+/// assert_eq!(sanitized.metadata, Some([example_ftyp(), example_moov()].concat()));
+/// assert_eq!(sanitized.data.offset, example_ftyp().len() as u64);
+/// assert_eq!(sanitized.data.len, example_mdat().len() as u64);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// If the input cannot be parsed, or an IO error occurs, an [`Error`] is returned.
+///
+/// [`AsyncSeek`]: futures_util::io::AsyncSeek
+pub async fn sanitize_async<R: AsyncRead + AsyncSkip>(input: R) -> Result<SanitizedMetadata, Error> {
+    sanitize_async_with_config(input, Config::default()).await
+}
+
+/// Sanitize an MP4 input asynchronously, with the given [`Config`].
+///
+/// The `input` must implement [`AsyncRead`] + [`AsyncSkip`], where [`AsyncSkip`] represents a subset of the
+/// [`AsyncSeek`] trait; an input stream which can be skipped forward, but not necessarily seeked to arbitrary
+/// positions.
+///
+/// # Examples
+///
+/// ```
+/// # use mp4san::{sanitize_async_with_config, Config};
+/// # use mp4san_test::{example_ftyp, example_mdat, example_moov};
+/// #
+/// # fn main() -> Result<(), mp4san::Error> {
+/// #     futures_util::FutureExt::now_or_never(run()).unwrap()
+/// # }
+/// #
+/// # async fn run() -> Result<(), mp4san::Error> {
+/// let example_input = [example_ftyp(), example_mdat(), example_moov()].concat();
+///
+/// let config = Config::builder().max_metadata_size(example_moov().len() as u64).build();
+/// let sanitized = sanitize_async_with_config(futures_util::io::Cursor::new(example_input), config).await?;
+///
+/// assert_eq!(sanitized.metadata, Some([example_ftyp(), example_moov()].concat()));
+/// assert_eq!(sanitized.data.offset, example_ftyp().len() as u64);
+/// assert_eq!(sanitized.data.len, example_mdat().len() as u64);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// If the input cannot be parsed, or an IO error occurs, an [`Error`] is returned.
+///
+/// [`AsyncSeek`]: futures_util::io::AsyncSeek
+pub async fn sanitize_async_with_config<R: AsyncRead + AsyncSkip>(
+    input: R,
+    config: Config,
+) -> Result<SanitizedMetadata, Error> {
+    sanitize_async_inner(input, config, None, None, &mut BytesMut::new()).await
+}
+
+/// Sanitize an MP4 input asynchronously, with the given [`Config`], giving up once `deadline` has passed.
+///
+/// The `input` must implement [`AsyncRead`] + [`AsyncSkip`], where [`AsyncSkip`] represents a subset of the
+/// [`AsyncSeek`] trait; an input stream which can be skipped forward, but not necessarily seeked to arbitrary
+/// positions.
+///
+/// The deadline is checked cooperatively, before each top-level box is read and before each `trak` box in the `moov` is
+/// processed, so no async runtime or timer is required. An IO operation which is already in progress, such as a
+/// blocking skip over a large `mdat`, will not be interrupted; the deadline will only be noticed once it completes.
+///
+/// # Examples
+///
+/// ```
+/// # use std::time::{Duration, Instant};
+/// #
+/// # use mp4san::{sanitize_async_with_deadline, Config};
+/// # use mp4san_test::{example_ftyp, example_mdat, example_moov};
+/// #
+/// # fn main() -> Result<(), mp4san::Error> {
+/// #     futures_util::FutureExt::now_or_never(run()).unwrap()
+/// # }
+/// #
+/// # async fn run() -> Result<(), mp4san::Error> {
+/// let example_input = [example_ftyp(), example_mdat(), example_moov()].concat();
+///
+/// let deadline = Instant::now() + Duration::from_secs(10);
+/// let sanitized =
+///     sanitize_async_with_deadline(futures_util::io::Cursor::new(example_input), Config::default(), deadline).await?;
+///
+/// assert_eq!(sanitized.metadata, Some([example_ftyp(), example_moov()].concat()));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// If `deadline` passes before sanitization completes, an [`Error::Parse`] wrapping [`ParseError::DeadlineExceeded`] is
+/// returned. If the input cannot be parsed, or an IO error occurs, an [`Error`] is returned.
+///
+/// [`AsyncSeek`]: futures_util::io::AsyncSeek
+pub async fn sanitize_async_with_deadline<R: AsyncRead + AsyncSkip>(
+    input: R,
+    config: Config,
+    deadline: Instant,
+) -> Result<SanitizedMetadata, Error> {
+    sanitize_async_inner(input, config, Some(deadline), None, &mut BytesMut::new()).await
+}
+
+/// Sanitize an MP4 input asynchronously, with the given [`Config`], returning the input along with the sanitized
+/// metadata.
+///
+/// The `input` must implement [`AsyncRead`] + [`AsyncSkip`], where [`AsyncSkip`] represents a subset of the
+/// [`AsyncSeek`] trait; an input stream which can be skipped forward, but not necessarily seeked to arbitrary
+/// positions.
+///
+/// Unlike [`sanitize_async_with_config`], the input is returned to the caller once sanitization succeeds, for example
+/// to continue reading the media data from it. The input is positioned at its end.
+///
+/// # Examples
+///
+/// ```
+/// # use mp4san::{sanitize_async_into, Config};
+/// # use mp4san_test::{example_ftyp, example_mdat, example_moov};
+/// #
+/// # fn main() -> Result<(), mp4san::Error> {
+/// #     futures_util::FutureExt::now_or_never(run()).unwrap()
+/// # }
+/// #
+/// # async fn run() -> Result<(), mp4san::Error> {
+/// let example_input = [example_ftyp(), example_mdat(), example_moov()].concat();
+///
+/// let input = futures_util::io::Cursor::new(example_input);
+/// let (sanitized, input) = sanitize_async_into(input, Config::default()).await?;
+///
+/// let mdat = &input.get_ref()[sanitized.data.offset as usize..][..sanitized.data.len as usize];
+/// assert_eq!(mdat, example_mdat());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// If the input cannot be parsed, or an IO error occurs, an [`Error`] is returned.
+///
+/// [`AsyncSeek`]: futures_util::io::AsyncSeek
+pub async fn sanitize_async_into<R: AsyncRead + AsyncSkip + Unpin>(
+    input: R,
+    config: Config,
+) -> Result<(SanitizedMetadata, R), Error> {
+    let mut reader = BufReader::with_capacity(BoxHeader::MAX_SIZE as usize, input);
+    let sanitized = sanitize_buf_reader(
+        Pin::new(&mut reader),
+        config,
+        None,
+        None,
+        None::<&mut io::Sink>,
+        false,
+        &mut BytesMut::new(),
+    )
+    .await?;
+    Ok((sanitized, reader.into_inner()))
+}
+
+async fn sanitize_async_inner<R: AsyncRead + AsyncSkip>(
+    input: R,
+    config: Config,
+    deadline: Option<Instant>,
+    media_len: Option<u64>,
+    buf: &mut BytesMut,
+) -> Result<SanitizedMetadata, Error> {
+    let reader = BufReader::with_capacity(BoxHeader::MAX_SIZE as usize, input);
+    pin_mut!(reader);
+    sanitize_buf_reader(reader, config, deadline, media_len, None::<&mut io::Sink>, false, buf).await
+}
+
+/// Sanitize an MP4 input, where `compact_media` is whether the caller will rewrite the media data to contain only the
+/// chunks of the kept tracks, as required by [`Config::keep_track_ids`].
+async fn sanitize_buf_reader<R: AsyncRead + AsyncSkip, W: Write>(
+    reader: Pin<&mut BufReader<R>>,
+    config: Config,
+    deadline: Option<Instant>,
+    media_len: Option<u64>,
+    media_sink: Option<&mut W>,
+    compact_media: bool,
+    buf: &mut BytesMut,
+) -> Result<SanitizedMetadata, Error> {
+    if config.keep_track_ids.is_some() && !compact_media {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "keep_track_ids is only supported by sanitize_to_vec, validate, and estimate_faststart_overhead",
+        )
+        .into());
+    }
+
+    let ReadBoxes {
+        ftyp,
+        moov,
+        ftyp_info,
+        mut data,
+        data_header,
+        needs_rewrite,
+        is_encrypted,
+        chunk_count,
+        first_keyframe_span,
+        is_fragmented_init,
+    } = read_boxes(reader, &config, deadline, media_len, media_sink, buf).await?;
+
+    // Return early if there's nothing to sanitize. Since the only thing the sanitizer does currently is move the moov
+    // to before the mdat to make the mp4 streamable, return if we don't need to do that.
+    if !needs_rewrite {
+        log::info!("metadata: nothing to sanitize");
+        return Ok(SanitizedMetadata {
+            metadata: None,
+            data,
+            was_already_faststart: true,
+            ftyp: ftyp_info,
+            is_encrypted,
+            chunk_count,
+            first_keyframe_span,
+            is_fragmented_init,
+        });
+    }
+
+    // Make sure none of the metadata boxes use BoxSize::UntilEof, as we want the caller to be able to concatenate movie
+    // data to the end of the metadata.
+    let ftyp = Mp4Box::with_data(ftyp.data)?;
+    let mut moov = Mp4Box::with_data(moov.data)?;
+
+    // The moov was already parsed while counting its tracks and chunks, and its data is kept in parsed form, so the
+    // `parse` calls below, as well as those on its descendants, return the already-parsed boxes rather than parsing
+    // them again.
+    if config.compact_moov {
+        moov.data.parse()?.remove_padding()?;
+    }
+    if config.zero_timestamps {
+        moov.data.parse()?.zero_timestamps()?;
+    }
+    if config.strip_metadata_items {
+        moov.data.parse()?.strip_metadata_items()?;
+    }
+    if config.canonicalize_box_sizes {
+        moov.data.parse()?.canonicalize_box_sizes()?;
+    }
+
+    // Add a free box to pad, if one will fit, if the mdat box would move backward. If one won't fit, or if the mdat box
+    // would move forward, adjust mdat offsets in stco/co64 the amount it was displaced.
+    let mut metadata_len = ftyp.encoded_len() + moov.encoded_len();
+    let mut pad_size = 0;
+    const PAD_HEADER_SIZE: u64 = BoxHeader::with_u32_data_size(BoxType::FREE, 0).encoded_len();
+    const MAX_PAD_SIZE: u64 = u32::MAX as u64 - PAD_HEADER_SIZE;
+    match data.offset.checked_sub(metadata_len) {
+        Some(0) => {
+            log::info!("metadata: 0x{metadata_len:08x} bytes");
+        }
+        Some(size @ PAD_HEADER_SIZE..=MAX_PAD_SIZE) => {
+            pad_size = size;
+            log::info!("metadata: 0x{metadata_len:08x} bytes; adding padding of 0x{pad_size:08x} bytes");
+        }
+        mdat_backward_displacement => {
+            let mdat_displacement = match mdat_backward_displacement {
+                Some(mdat_backward_displacement) => {
+                    mdat_backward_displacement.try_into().ok().and_then(i64::checked_neg)
+                }
+                None => metadata_len.checked_sub(data.offset).unwrap().try_into().ok(),
+            };
+            let mut mdat_displacement: i64 = mdat_displacement
+                .ok_or_else(|| report_attach!(ParseError::UnsupportedBoxLayout, "mdat displaced too far"))?;
+
+            // Upgrading an stco box to co64 grows the metadata, displacing the mdat further, so repeat until stable.
+            loop {
+                log::info!(
+                    "metadata: 0x{metadata_len:08x} bytes; displacing chunk offsets by 0x{mdat_displacement:08x}"
+                );
+
+                check_deadline(deadline)?;
+                displace_chunk_offsets(moov.data.parse()?, mdat_displacement)?;
+
+                let displaced_metadata_len = ftyp.encoded_len() + moov.encoded_len();
+                if displaced_metadata_len == metadata_len {
+                    break;
+                }
+                mdat_displacement = (displaced_metadata_len - metadata_len) as i64;
+                metadata_len = displaced_metadata_len;
+            }
+        }
+    }
+
+    let mut metadata = Vec::with_capacity((metadata_len + pad_size) as usize);
+    ftyp.put_buf(&mut metadata);
+    moov.put_buf(&mut metadata);
+    if pad_size != 0 {
+        let pad_header = BoxHeader::with_u32_data_size(BoxType::FREE, (pad_size - PAD_HEADER_SIZE) as u32);
+        pad_header.put_buf(&mut metadata);
+        metadata.resize((metadata_len + pad_size) as usize, 0);
+    }
+    if config.verify_output {
+        verify_metadata(&metadata)?;
+    }
+    if let (Some(box_type), Some(data_header)) = (config.rename_mdat_to, data_header) {
+        rename_data_box(&mut metadata, &mut data, data_header, box_type)?;
+    }
+
+    Ok(SanitizedMetadata {
+        metadata: Some(metadata),
+        data,
+        was_already_faststart: false,
+        ftyp: ftyp_info,
+        is_encrypted,
+        chunk_count,
+        first_keyframe_span,
+        is_fragmented_init,
+    })
+}
+
+/// Read and validate the boxes of an input, up to the point of assembling its sanitized metadata.
+async fn read_boxes<R: AsyncRead + AsyncSkip, W: Write>(
+    mut reader: Pin<&mut BufReader<R>>,
+    config: &Config,
+    deadline: Option<Instant>,
+    media_len: Option<u64>,
+    mut media_sink: Option<&mut W>,
+    buf: &mut BytesMut,
+) -> Result<ReadBoxes, Error> {
+    let mut ftyp: Option<Mp4Box<FtypBox>> = None;
+    let mut moov: Option<Mp4Box<MoovBox>> = None;
+    let mut data: Option<InputSpan> = None;
+    let mut moov_offset = None;
+    let mut is_encrypted = false;
+    let mut is_fragmented_init = false;
+    let mut chunk_count = 0u64;
+    let mut first_keyframe_span = None;
+    let mut box_headers = BoxHeaders::default();
+    let mut tracks_removed = false;
+    let mut box_types = HashSet::new();
+    let mut data_header = None;
+
+    if let Some(max_input_len) = config.max_input_len {
+        match reader.as_mut().stream_len().await {
+            Ok(input_len) => {
+                ensure_attach!(
+                    input_len <= max_input_len,
+                    ParseError::InvalidInput,
+                    InputTooLarge(input_len, max_input_len),
+                );
+            }
+            Err(err) if err.kind() == io::ErrorKind::Unsupported => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    while let Some((start_pos, header)) = box_headers.next(reader.as_mut(), config, deadline).await? {
+        if let (Some(box_size), Some(max_box_size)) = (header.box_size(), config.max_box_size) {
+            ensure_attach!(
+                box_size <= max_box_size,
+                ParseError::InvalidInput,
+                BoxTooLarge(box_size, max_box_size),
+                WhileParsingBox(header.box_type()),
+            );
+        }
+
+        box_types.insert(header.box_type());
+        match header.box_type() {
+            name @ (BoxType::FREE | BoxType::SKIP) => {
+                ensure_attach!(
+                    !(name == BoxType::SKIP && config.reject_skip_boxes),
+                    ParseError::UnsupportedBox(name),
+                );
+                let media_sink = media_sink.as_deref_mut().filter(|_| continues_data(data, start_pos));
+                let box_size =
+                    skip_or_copy_box(reader.as_mut(), &header, media_sink, true).await? + header.encoded_len();
+                log::info!("{name} @ 0x{start_pos:08x}: {box_size} bytes");
+
+                // Try to extend any already accumulated data in case there's more mdat boxes to come.
+                if let Some(data) = &mut data {
+                    extend_data(data, start_pos, box_size)?;
+                }
+            }
+
+            BoxType::FTYP => {
+                ensure_attach!(
+                    ftyp.is_none(),
+                    ParseError::InvalidBoxLayout,
+                    MultipleBoxes(BoxType::FTYP)
+                );
                 let mut read_ftyp = Mp4Box::read_data(reader.as_mut(), header, MAX_FTYP_SIZE).await?;
                 let ftyp_data: &mut FtypBox = read_ftyp.data.parse()?;
                 let compatible_brand_count = ftyp_data.compatible_brands().len();
                 let FtypBox { major_brand, minor_version, .. } = ftyp_data;
                 log::info!("ftyp @ 0x{start_pos:08x}: {major_brand} version {minor_version}, {compatible_brand_count} compatible brands");
 
-                ensure_attach!(
-                    ftyp_data.compatible_brands().any(|b| b == COMPATIBLE_BRAND),
-                    ParseError::UnsupportedFormat(ftyp_data.major_brand)
-                );
+                ensure_attach!(
+                    ftyp_data.compatible_brands().any(|b| b == COMPATIBLE_BRAND),
+                    ParseError::UnsupportedFormat(ftyp_data.major_brand)
+                );
+
+                ftyp = Some(read_ftyp);
+            }
+
+            // NB: ISO 14496-12-2012 specifies a default ftyp, but we don't currently use it. The spec says that it
+            // contains a single compatible brand, "mp41", and notably not "isom" which is the ISO spec we follow for
+            // parsing now. This implies that there's additional stuff in "mp41" which is not in "isom". "mp41" is also
+            // very old at this point, so it'll require additional research/work to be able to parse/remux it.
+            _ if ftyp.is_none() => {
+                bail_attach!(ParseError::InvalidBoxLayout, "ftyp is not the first significant box");
+            }
+
+            BoxType::MDAT => {
+                ensure_attach!(
+                    media_len.is_none(),
+                    ParseError::InvalidBoxLayout,
+                    "mdat in metadata-only input",
+                );
+
+                // The header of the first mdat is moved to the end of the metadata when renaming it, so it isn't part
+                // of the media data copied to the sink.
+                let copy_header = data.is_some() || config.rename_mdat_to.is_none();
+                let box_size = skip_or_copy_box(reader.as_mut(), &header, media_sink.as_deref_mut(), copy_header)
+                    .await?
+                    + header.encoded_len();
+                log::info!("mdat @ 0x{start_pos:08x}: {box_size} bytes");
+
+                if let Some(data) = &mut data {
+                    // Try to extend already accumulated data.
+                    ensure_attach!(
+                        extend_data(data, start_pos, box_size)?,
+                        ParseError::UnsupportedBoxLayout,
+                        "discontiguous mdat boxes",
+                    );
+                } else {
+                    data = Some(InputSpan { offset: start_pos, len: box_size });
+                    data_header = Some(header);
+                }
+            }
+
+            BoxType::MOOV => {
+                if let Some(moov_offset) = moov_offset {
+                    bail_attach!(
+                        ParseError::InvalidBoxLayout,
+                        MultipleBoxes(BoxType::MOOV),
+                        DuplicateBoxOffsets(moov_offset, start_pos),
+                    );
+                }
+                let mut read_moov = if config.truncated_moov_ok {
+                    let (read_moov, truncated) =
+                        Mp4Box::read_truncated_data_into(reader.as_mut(), header, config.max_metadata_size, buf)
+                            .await?;
+                    // The rewritten header no longer matches the input, so the metadata must be rewritten.
+                    box_headers.recovered |= truncated;
+                    read_moov
+                } else {
+                    Mp4Box::read_data_into(reader.as_mut(), header, config.max_metadata_size, buf).await?
+                };
+
+                let moov_data: &mut MoovBox = read_moov.data.parse()?;
+                if let Some(max_track_count) = config.max_track_count {
+                    let trak_count = moov_data.trak_count();
+                    ensure_attach!(
+                        trak_count <= max_track_count,
+                        ParseError::InvalidInput,
+                        TooManyTracks(trak_count, max_track_count),
+                        WhileParsingBox(BoxType::MOOV),
+                    );
+                }
+                moov_data.validate_track_references()?;
+                if let Some(keep_track_ids) = &config.keep_track_ids {
+                    tracks_removed = moov_data.retain_tracks(keep_track_ids)? != 0;
+                    ensure_attach!(
+                        moov_data.traks().next().is_some(),
+                        ParseError::MissingRequiredBox(BoxType::TRAK),
+                        "no track to keep",
+                        WhileParsingBox(BoxType::MOOV),
+                    );
+                }
+                let mut trak_count = 0;
+                let mut video_trak_count = 0;
+                let mut seen_video_trak = false;
+                for trak in moov_data.traks() {
+                    check_deadline(deadline)?;
+                    let trak = trak?;
+                    trak_count += 1;
+                    if let Some(max_stsc_entries) = config.max_stsc_entries {
+                        let stsc_entry_count = trak.stsc_entry_count()?;
+                        ensure_attach!(
+                            stsc_entry_count <= max_stsc_entries,
+                            ParseError::InvalidInput,
+                            TooManyStscEntries(stsc_entry_count, max_stsc_entries),
+                            WhileParsingChild(BoxType::STBL, BoxType::STSC),
+                        );
+                    }
+                    if config.validate_chunk_count {
+                        trak.validate_chunk_count()?;
+                    }
+                    if config.reject_edit_lists {
+                        trak.validate_no_edits()?;
+                    }
+                    trak.validate_media_header()?;
+                    trak.validate_sync_samples()?;
+                    trak.ctts_mut()?;
+                    if !seen_video_trak && trak.is_video()? {
+                        seen_video_trak = true;
+                        first_keyframe_span = trak.first_sync_sample_span()?;
+                    }
+                    trak.colr_boxes()?;
+                    trak.esds_boxes()?;
+                    trak.pasp_boxes()?;
+                    trak.validate_hevc_parameter_sets()?;
+                    if config.require_video_track && trak.handler_type()? == VIDEO_HANDLER_TYPE {
+                        video_trak_count += 1;
+                    }
+                    chunk_count = chunk_count
+                        .checked_add(trak.co_mut()?.entry_count().into())
+                        .ok_or_else(|| {
+                            report_attach!(
+                                ParseError::InvalidInput,
+                                "chunk count overflow",
+                                WhileParsingBox(BoxType::MOOV)
+                            )
+                        })?;
+                }
+                ensure_attach!(
+                    !config.require_video_track || video_trak_count != 0,
+                    ParseError::MissingRequiredBox(BoxType::TRAK),
+                    "no video track",
+                    WhileParsingBox(BoxType::MOOV),
+                );
+
+                is_encrypted = moov_data.is_encrypted()?;
+                is_fragmented_init = moov_data.is_fragmented_init()?;
+                log::info!(
+                    "moov @ 0x{start_pos:08x}: {trak_count} traks {chunk_count} chunks, encrypted {is_encrypted}"
+                );
+                moov = Some(read_moov);
+                moov_offset = Some(start_pos);
+            }
+
+            BoxType::Uuid(uuid) if config.known_uuid_boxes.get(&uuid) == Some(&UuidPolicy::Skip) => {
+                let media_sink = media_sink.as_deref_mut().filter(|_| continues_data(data, start_pos));
+                let box_size =
+                    skip_or_copy_box(reader.as_mut(), &header, media_sink, true).await? + header.encoded_len();
+                log::info!("uuid {uuid} @ 0x{start_pos:08x}: {box_size} bytes");
+
+                // Try to extend any already accumulated data in case there's more mdat boxes to come.
+                if let Some(data) = &mut data {
+                    extend_data(data, start_pos, box_size)?;
+                }
+            }
+
+            name @ (BoxType::META | BoxType::MECO) => {
+                let media_sink = media_sink.as_deref_mut().filter(|_| continues_data(data, start_pos));
+                let box_size = match name {
+                    BoxType::META if config.validate_meta => {
+                        read_meta(reader.as_mut(), &header, config.max_metadata_size, media_sink).await?
+                            + header.encoded_len()
+                    }
+                    _ => skip_or_copy_box(reader.as_mut(), &header, media_sink, true).await? + header.encoded_len(),
+                };
+                log::info!("{name} @ 0x{start_pos:08x}: {box_size} bytes");
+
+                // Try to extend any already accumulated data in case there's more mdat boxes to come.
+                if let Some(data) = &mut data {
+                    extend_data(data, start_pos, box_size)?;
+                }
+            }
+
+            name => {
+                let box_size = skip_box(reader.as_mut(), &header).await? + header.encoded_len();
+                log::info!("{name} @ 0x{start_pos:08x}: {box_size} bytes");
+                bail_attach!(ParseError::UnsupportedBox(name));
+            }
+        }
+
+        // Guarantee termination on crafted input: every box must advance the input by at least its header.
+        let end_pos = reader.as_mut().stream_position().await?;
+        ensure_attach!(
+            end_pos >= start_pos.saturating_add(header.encoded_len()),
+            ParseError::InvalidBoxLayout,
+            "box made no progress",
+            WhileParsingBox(header.box_type()),
+        );
+    }
+
+    let Some(mut ftyp) = ftyp else {
+        bail_attach!(ParseError::MissingRequiredBox(BoxType::FTYP));
+    };
+    let ftyp_info = FtypInfo::new(ftyp.data.parse()?);
+    let (Some(mut moov), Some(moov_offset)) = (moov, moov_offset) else {
+        bail_attach!(ParseError::MissingRequiredBox(BoxType::MOOV));
+    };
+    // Media supplied separately is treated as an mdat box immediately following the end of the input.
+    if let Some(media_len) = media_len {
+        let offset = reader.as_mut().stream_position().await?;
+        ensure_attach!(
+            offset.checked_add(media_len).is_some(),
+            ParseError::InvalidInput,
+            "media length overflows input length",
+        );
+        data = Some(InputSpan { offset, len: media_len });
+    }
+    let data = match data {
+        Some(data) => data,
+        None if !config.required_boxes.mdat => {
+            let offset = reader.as_mut().stream_position().await?;
+            InputSpan { offset, len: 0 }
+        }
+        None => bail_attach!(ParseError::MissingRequiredBox(BoxType::MDAT)),
+    };
+    config.required_boxes.check_additional(&box_types)?;
+    if config.validate_sample_bounds {
+        validate_sample_bounds(moov.data.parse()?, data)?;
+    }
+
+    let needs_rewrite = needs_rewrite(
+        config,
+        moov_offset < data.offset && media_len.is_none(),
+        // The media data copied to a sink is only usable after rewritten metadata, even if the input was faststart.
+        box_headers.recovered || box_headers.trailing_garbage || tracks_removed || media_sink.is_some(),
+    );
+    Ok(ReadBoxes {
+        ftyp,
+        moov,
+        ftyp_info,
+        data,
+        data_header,
+        needs_rewrite,
+        is_encrypted,
+        chunk_count,
+        first_keyframe_span,
+        is_fragmented_init,
+    })
+}
+
+//
+// Config impls
+//
+
+impl Config {
+    /// Construct a builder for `Config`.
+    ///
+    /// See the documentation for [`ConfigBuilder`].
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+#[cfg(feature = "fuzz")]
+impl<'a> arbitrary::Arbitrary<'a> for Config {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let rename_mdat_to = u
+            .arbitrary::<Option<_>>()?
+            .map(|value| BoxType::FourCC(FourCC { value }));
+        Ok(Self {
+            max_metadata_size: u.arbitrary()?,
+            max_input_len: u.arbitrary()?,
+            max_box_size: u.arbitrary()?,
+            max_track_count: u.arbitrary()?,
+            max_stsc_entries: u.arbitrary()?,
+            recover: u.arbitrary()?,
+            compact_moov: u.arbitrary()?,
+            validate_chunk_count: u.arbitrary()?,
+            validate_sample_bounds: u.arbitrary()?,
+            reject_skip_boxes: u.arbitrary()?,
+            allow_trailing_garbage: u.arbitrary()?,
+            validate_meta: u.arbitrary()?,
+            require_video_track: u.arbitrary()?,
+            reject_edit_lists: u.arbitrary()?,
+            zero_timestamps: u.arbitrary()?,
+            strip_metadata_items: u.arbitrary()?,
+            canonicalize_box_sizes: u.arbitrary()?,
+            truncated_moov_ok: u.arbitrary()?,
+            known_uuid_boxes: Default::default(),
+            verify_output: u.arbitrary()?,
+            required_boxes: RequiredBoxes { mdat: u.arbitrary()?, ..Default::default() },
+            rename_mdat_to,
+            keep_track_ids: u.arbitrary()?,
+        })
+    }
+}
+
+//
+// ConfigBuilder impls
+//
+
+impl ConfigBuilder {
+    /// Build a new [`Config`].
+    pub fn build(&self) -> Config {
+        self.try_build().unwrap()
+    }
+}
+
+//
+// SanitizerHandle impls
+//
+
+impl SanitizerHandle {
+    /// Construct a new `SanitizerHandle` using the given [`Config`].
+    pub fn new(config: Config) -> Self {
+        Self { config, scratch: BytesMut::new() }
+    }
+
+    /// The [`Config`] used by this `SanitizerHandle`.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Sanitize an MP4 input, reusing this `SanitizerHandle`'s scratch buffer.
+    ///
+    /// This is equivalent to [`sanitize_with_config`] with this `SanitizerHandle`'s [`Config`].
+    ///
+    /// ```
+    /// # use mp4san_test::{example_ftyp, example_mdat, example_moov};
+    /// #
+    /// let example_input = [example_ftyp(), example_mdat(), example_moov()].concat();
+    ///
+    /// let mut sanitizer = mp4san::SanitizerHandle::new(mp4san::Config::default());
+    /// for _ in 0..3 {
+    ///     let sanitized = sanitizer.sanitize(std::io::Cursor::new(&example_input))?;
+    ///     assert_eq!(sanitized.metadata, Some([example_ftyp(), example_moov()].concat()));
+    /// }
+    /// # Ok::<(), mp4san::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If the input cannot be parsed, or an IO error occurs, an [`Error`] is returned.
+    pub fn sanitize<R: Read + Skip + Unpin>(&mut self, input: R) -> Result<SanitizedMetadata, Error> {
+        sanitize_with_buffer(input, self.config.clone(), &mut self.scratch)
+    }
+}
+
+impl Clone for SanitizerHandle {
+    fn clone(&self) -> Self {
+        Self::new(self.config.clone())
+    }
+}
+
+//
+// RequiredBoxes impls
+//
+
+impl RequiredBoxes {
+    /// Check that each of the [`additional`](Self::additional) required box types is among the top-level `box_types`
+    /// present in an input.
+    fn check_additional(&self, box_types: &HashSet<BoxType>) -> Result<(), Error> {
+        if let Some(&missing) = self.additional.iter().find(|box_type| !box_types.contains(box_type)) {
+            bail_attach!(ParseError::MissingRequiredBox(missing));
+        }
+        Ok(())
+    }
+}
+
+impl Default for RequiredBoxes {
+    fn default() -> Self {
+        Self { mdat: true, additional: HashSet::new() }
+    }
+}
+
+//
+// FtypInfo impls
+//
+
+impl FtypInfo {
+    fn new(ftyp: &FtypBox) -> Self {
+        Self {
+            major_brand: ftyp.major_brand,
+            minor_version: ftyp.minor_version,
+            compatible_brands: ftyp.compatible_brands().collect(),
+        }
+    }
+}
+
+//
+// private functions
+//
+
+/// Returns whether the metadata of an input must be rewritten with the given [`Config`], given whether its `moov`
+/// already precedes its media data, and whether its boxes were altered while being read, e.g. by recovering from a
+/// corrupt region of the input, keeping a truncated `moov`, or removing tracks.
+fn needs_rewrite(config: &Config, moov_precedes_data: bool, altered: bool) -> bool {
+    !moov_precedes_data
+        || altered
+        || config.zero_timestamps
+        || config.strip_metadata_items
+        || config.canonicalize_box_sizes
+}
+
+/// Return [`ParseError::DeadlineExceeded`] if `deadline` has passed.
+fn check_deadline(deadline: Option<Instant>) -> Result<(), Error> {
+    match deadline {
+        Some(deadline) if Instant::now() >= deadline => Err(report_attach!(ParseError::DeadlineExceeded).into()),
+        _ => Ok(()),
+    }
+}
+
+/// Return whether `header`, read at `start_pos`, is a supported top-level box which fits within the input.
+async fn is_plausible_header<R: AsyncRead + AsyncSkip>(
+    mut reader: Pin<&mut BufReader<R>>,
+    start_pos: u64,
+    header: &BoxHeader,
+) -> Result<bool, Error> {
+    if !RECOVERABLE_BOX_TYPES.contains(&header.box_type()) {
+        return Ok(false);
+    }
+    match header.box_data_size() {
+        Ok(Some(box_data_size)) => {
+            let stream_len = match reader.as_mut().stream_len().await {
+                Ok(stream_len) => stream_len,
+                // Whether the box fits can't be checked against an input of unknown length.
+                Err(err) if err.kind() == io::ErrorKind::Unsupported => return Ok(true),
+                Err(err) => return Err(err.into()),
+            };
+            let box_end = start_pos.checked_add(header.encoded_len() + box_data_size);
+            Ok(box_end.is_some_and(|box_end| box_end <= stream_len))
+        }
+        Ok(None) => Ok(true),
+        Err(_) => Ok(false),
+    }
+}
+
+/// Return whether `header`, read at `start_pos`, starts a corrupted region of the input to recover from.
+///
+/// An `mdat` header is never treated as corrupt, even if its box extends past the end of the input: it is a truncated
+/// box, and scanning its media data for a header could find one in the media itself.
+impl BoxHeaders {
+    /// Read the header of the next box, returning its offset and header, or `None` at the end of the input.
+    async fn next<R: AsyncRead + AsyncSkip>(
+        &mut self,
+        mut reader: Pin<&mut BufReader<R>>,
+        config: &Config,
+        deadline: Option<Instant>,
+    ) -> Result<Option<(u64, BoxHeader)>, Error> {
+        loop {
+            check_deadline(deadline)?;
+
+            let (start_pos, header) = match self.recovered_header.take() {
+                Some(recovered_header) => recovered_header,
+                None if reader.as_mut().fill_buf().await?.is_empty() => return Ok(None),
+                None => {
+                    let start_pos = reader.as_mut().stream_position().await?;
+                    let header = match BoxHeader::read(&mut reader).await {
+                        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof && config.allow_trailing_garbage => {
+                            let end_pos = reader.as_mut().stream_position().await?;
+                            log::info!("trailing garbage @ 0x{start_pos:08x}: {} bytes", end_pos - start_pos);
+                            self.trailing_garbage = true;
+                            return Ok(None);
+                        }
+                        header => header.map_eof(|_| {
+                            Error::Parse(report_attach!(ParseError::TruncatedBox, "while parsing box header"))
+                        })?,
+                    };
+                    (start_pos, header)
+                }
+            };
+
+            if config.recover && is_corrupt_header(reader.as_mut(), start_pos, &header).await? {
+                self.recovered = true;
+                self.recovered_header = recover_box_header(reader.as_mut(), start_pos, &header).await?;
+                continue;
+            }
+            return Ok(Some((start_pos, header)));
+        }
+    }
+}
+
+async fn is_corrupt_header<R: AsyncRead + AsyncSkip>(
+    reader: Pin<&mut BufReader<R>>,
+    start_pos: u64,
+    header: &BoxHeader,
+) -> Result<bool, Error> {
+    if header.box_type() == BoxType::MDAT {
+        return Ok(false);
+    }
+    Ok(!is_plausible_header(reader, start_pos, header).await?)
+}
+
+/// Scan forward from an implausible `header` read at `start_pos` for the next plausible box header.
+///
+/// Returns the position and header of the box found, or [`None`] if the end of the input was reached first.
+async fn recover_box_header<R: AsyncRead + AsyncSkip>(
+    mut reader: Pin<&mut BufReader<R>>,
+    start_pos: u64,
+    header: &BoxHeader,
+) -> Result<Option<(u64, BoxHeader)>, Error> {
+    // The bytes of the input starting at `pos`, which have already been read.
+    let mut window = Vec::with_capacity(BoxHeader::MAX_SIZE as usize);
+    header.put_buf(&mut window);
+
+    let mut pos = start_pos;
+    loop {
+        window.remove(0);
+        pos += 1;
+
+        ensure_attach!(
+            pos - start_pos <= MAX_RECOVERY_SCAN_LEN,
+            ParseError::InvalidInput,
+            "no box header found while recovering",
+        );
+
+        if !fill_window(reader.as_mut(), &mut window, 8).await? {
+            break;
+        }
+        let size = u32::from_be_bytes([window[0], window[1], window[2], window[3]]);
+        if size == 1 && !fill_window(reader.as_mut(), &mut window, 16).await? {
+            break;
+        }
+
+        // Bytes read past the end of a header would have to be skipped as part of its box data, so such a header is
+        // passed over. This can only happen shortly after an extended-size header which turned out to be implausible.
+        let Ok(header) = BoxHeader::parse(&window[..]) else {
+            continue;
+        };
+        if header.encoded_len() == window.len() as u64 && is_plausible_header(reader.as_mut(), pos, &header).await? {
+            log::info!(
+                "recovered from 0x{gap:08x} corrupted bytes @ 0x{start_pos:08x}",
+                gap = pos - start_pos
+            );
+            return Ok(Some((pos, header)));
+        }
+    }
+
+    let gap = pos + window.len() as u64 - start_pos;
+    log::info!("skipped 0x{gap:08x} trailing corrupted bytes @ 0x{start_pos:08x}");
+    Ok(None)
+}
+
+/// Read from `reader` until `window` is at least `len` bytes long.
+///
+/// Returns `false` if the end of the input was reached first.
+async fn fill_window<R: AsyncRead>(
+    mut reader: Pin<&mut BufReader<R>>,
+    window: &mut Vec<u8>,
+    len: usize,
+) -> io::Result<bool> {
+    while window.len() < len {
+        let mut byte = [0];
+        if reader.read(&mut byte).await? == 0 {
+            return Ok(false);
+        }
+        window.push(byte[0]);
+    }
+    Ok(true)
+}
+
+/// Extend the accumulated media `data` by a box of `box_size` bytes at `start_pos`, if it immediately follows `data`.
+///
+/// Returns whether `data` was extended.
+fn extend_data(data: &mut InputSpan, start_pos: u64, box_size: u64) -> Result<bool, Error> {
+    let data_end = data.offset.checked_add(data.len);
+    ensure_attach!(
+        data_end.is_some(),
+        ParseError::InvalidInput,
+        "media data length overflow"
+    );
+    if data_end != Some(start_pos) {
+        return Ok(false);
+    }
+    data.len = data
+        .len
+        .checked_add(box_size)
+        .ok_or_else(|| report_attach!(ParseError::InvalidInput, "media data length overflow"))?;
+    Ok(true)
+}
+
+/// Whether a box starting at `start_pos` directly follows any already accumulated media data.
+fn continues_data(data: Option<InputSpan>, start_pos: u64) -> bool {
+    data.is_some_and(|data| data.offset.checked_add(data.len) == Some(start_pos))
+}
+
+/// Move the header `data_header` of the first box in the media `data` to the end of `metadata`, renamed to `box_type`
+/// and resized to span all of the media data.
+fn rename_data_box(
+    metadata: &mut Vec<u8>,
+    data: &mut InputSpan,
+    data_header: BoxHeader,
+    box_type: BoxType,
+) -> Result<(), Error> {
+    let header_len = data_header.encoded_len();
+    let data_size = data.len - header_len;
+    let mut header = BoxHeader::with_data_size(box_type, data_size)?;
+    if header.encoded_len() < header_len {
+        header = BoxHeader::with_ext_data_size(box_type, data_size);
+    }
+    ensure_attach!(
+        header.encoded_len() == header_len,
+        ParseError::UnsupportedBoxLayout,
+        "renamed mdat header length differs",
+        WhileParsingBox(BoxType::MDAT),
+    );
+    header.put_buf(&mut *metadata);
+    data.offset += header_len;
+    data.len -= header_len;
+    Ok(())
+}
+
+/// Check that every chunk offset in `moov` lies within the media `data`.
+fn validate_sample_bounds(moov: &mut MoovBox, data: InputSpan) -> Result<(), Error> {
+    let data_end = data.offset.saturating_add(data.len);
+    for trak in moov.traks() {
+        for offset in trak?.chunk_offsets()? {
+            let offset = offset?;
+            ensure_attach!(
+                (data.offset..data_end).contains(&offset),
+                ParseError::InvalidInput,
+                ChunkOffsetOutOfBounds(offset, data.offset, data.len),
+                WhileParsingBox(BoxType::MOOV),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Check that sanitized `metadata` parses as an `ftyp` box and a `moov` box which re-encode to the same bytes, followed
+/// by at most a `free` padding box.
+fn verify_metadata(metadata: &[u8]) -> Result<(), Error> {
+    let rest = verify_metadata_box::<FtypBox>(metadata)?;
+    let rest = verify_metadata_box::<MoovBox>(rest)?;
+    if !rest.is_empty() {
+        let header = BoxHeader::parse(rest)?;
+        ensure_attach!(
+            header.box_type() == BoxType::FREE && header.box_size() == Some(rest.len() as u64),
+            ParseError::InvalidBoxLayout,
+            "sanitized metadata has trailing data",
+        );
+    }
+    Ok(())
+}
+
+/// Parse a box of type `T` from the start of sanitized `metadata`, check that it re-encodes to the same bytes, and
+/// return the remaining metadata following it.
+fn verify_metadata_box<T: ParseBox + ParsedBox>(metadata: &[u8]) -> Result<&[u8], Error> {
+    let header = BoxHeader::parse(metadata)?;
+    ensure_attach!(
+        header.box_type() == T::box_type(),
+        ParseError::InvalidBoxLayout,
+        "unexpected box in sanitized metadata",
+        WhileParsingBox(T::box_type()),
+    );
+    let mut mp4box: Mp4Box<T> = Mp4Box::parse(&mut BytesMut::from(metadata))?;
+    mp4box.data.parse()?;
+    let mut encoded = Vec::with_capacity(mp4box.encoded_len() as usize);
+    mp4box.put_buf(&mut encoded);
+    ensure_attach!(
+        metadata.starts_with(&encoded),
+        ParseError::InvalidBoxLayout,
+        "sanitized metadata does not round-trip",
+        WhileParsingBox(T::box_type()),
+    );
+    Ok(&metadata[encoded.len()..])
+}
+
+/// Read, parse, and validate a `meta` box's data assuming its header has already been read, returning its size.
+///
+/// The box, including its header, is written unmodified to `media_sink`, if any.
+async fn read_meta<R: AsyncRead + AsyncSkip, W: Write>(
+    reader: Pin<&mut BufReader<R>>,
+    header: &BoxHeader,
+    max_size: u64,
+    media_sink: Option<&mut W>,
+) -> Result<u64, Error> {
+    let mut meta: Mp4Box<MetaBox> = Mp4Box::read_data(reader, *header, max_size).await?;
+    let box_data_size = meta.data.encoded_len();
+    if let Some(media_sink) = media_sink {
+        let mut box_buf = BytesMut::new();
+        meta.put_buf(&mut box_buf);
+        media_sink.write_all(&box_buf)?;
+    }
+
+    let meta_data = meta.data.parse()?;
+    let handler_type = meta_data.hdlr_mut()?.handler_type();
+    let child_types = meta_data
+        .child_types()
+        .map(|box_type| box_type.to_string())
+        .collect::<Vec<_>>();
+    log::info!("meta: {handler_type} handler, children [{}]", child_types.join(", "));
+    Ok(box_data_size)
+}
+
+/// Skip a box's data assuming its header has already been read.
+///
+/// Returns the amount of data that was skipped.
+async fn skip_box<R: AsyncRead + AsyncSkip>(
+    mut reader: Pin<&mut BufReader<R>>,
+    header: &BoxHeader,
+) -> Result<u64, Error> {
+    let box_data_size = match header.box_data_size()? {
+        Some(box_size) => box_size,
+        None => match remaining_input_len(reader.as_mut()).await? {
+            Some(remaining_len) => remaining_len,
+            // The input's length is unknown, so the box's data is read to find its end.
+            None => return Ok(futures_util::io::copy(reader, &mut futures_util::io::sink()).await?),
+        },
+    };
+    reader.skip(box_data_size).await.map_eof(|_| {
+        Error::Parse(report_attach!(
+            ParseError::TruncatedBox,
+            WhileParsingBox(header.box_type())
+        ))
+    })?;
+    Ok(box_data_size)
+}
+
+/// Skip a box's data assuming its header has already been read, or, if `media_sink` is given, copy the box to it.
+///
+/// The box is copied including its header, so that the copied boxes can be concatenated to reproduce the input, unless
+/// `copy_header` is false.
+///
+/// Returns the size of the box's data.
+async fn skip_or_copy_box<R: AsyncRead + AsyncSkip, W: Write>(
+    mut reader: Pin<&mut BufReader<R>>,
+    header: &BoxHeader,
+    media_sink: Option<&mut W>,
+    copy_header: bool,
+) -> Result<u64, Error> {
+    let Some(media_sink) = media_sink else {
+        return skip_box(reader, header).await;
+    };
+    let box_data_size = match header.box_data_size()? {
+        Some(box_size) => Some(box_size),
+        None => remaining_input_len(reader.as_mut()).await?,
+    };
+
+    if copy_header {
+        let mut header_buf = BytesMut::new();
+        header.put_buf(&mut header_buf);
+        media_sink.write_all(&header_buf)?;
+    }
+
+    // If the input's length is unknown, a box extending to its end is copied until EOF.
+    let mut data_reader = BoxDataReader::new(reader, header.box_type(), box_data_size.unwrap_or(u64::MAX));
+    let mut chunk = [0; MEDIA_SINK_CHUNK_SIZE];
+    let mut copied_len = 0;
+    loop {
+        let read_len = data_reader.read(&mut chunk).await?;
+        if read_len == 0 {
+            break;
+        }
+        media_sink.write_all(&chunk[..read_len])?;
+        copied_len += read_len as u64;
+    }
+    ensure_attach!(
+        box_data_size.is_none() || data_reader.remaining() == 0,
+        ParseError::TruncatedBox,
+        WhileParsingBox(data_reader.box_type()),
+    );
+    Ok(copied_len)
+}
+
+/// Return the length of the input remaining after the current position, or [`None`] if the input's length is unknown,
+/// as for a [`Sanitizer`] which hasn't yet been [`finish`](Sanitizer::finish)ed.
+pub(crate) async fn remaining_input_len<R: AsyncRead + AsyncSkip>(
+    mut reader: Pin<&mut BufReader<R>>,
+) -> Result<Option<u64>, Error> {
+    match reader.as_mut().stream_len().await {
+        Ok(stream_len) => Ok(Some(stream_len - reader.as_mut().stream_position().await?)),
+        Err(err) if err.kind() == io::ErrorKind::Unsupported => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[cfg(doctest)]
+#[doc = include_str!("../README.md")]
+pub mod readme {}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+    use std::time::Duration;
+
+    use assert_matches::assert_matches;
+    use bytes::BytesMut;
+    use futures_util::FutureExt;
+    use mp4san_test::{ffmpeg_assert_eq, gpac_assert_eq};
+
+    use crate::parse::box_type::{
+        CO64, FREE, FTYP, GMHD, MDAT, MDIA, MECO, MEHD, META, MINF, MOOV, NMHD, PSSH, SAIO, SAIZ, SENC, SKIP, SMHD,
+        STBL, STCO, TRAK, VMHD,
+    };
+    use crate::parse::{fourcc, AnyMp4Box, BoxData, CttsBox, ElstBox, ElstEntry, MvexBox, StblCoMut, StssBox};
+    use crate::util::test::mp4::TestMp4;
+    use crate::util::test::{
+        init_logger, sanitized_data, test_free, test_ftyp, test_meta, test_moov, test_mp4, test_tref, test_udta,
+        write_test_ilst_meta_data, write_test_mdat, HDR10_NCLX_COLR, ISOM, MP41, MP42, SOUN, TEST_CREATION_TIME,
+        TEST_ESDS, TEST_JUNK, TEST_UUID,
+    };
+
+    use super::*;
+
+    #[test]
+    fn until_eof_sized_moov() {
+        init_logger();
+
+        let mut data = vec![];
+        let mut metadata = vec![];
+        test_ftyp().build().put_buf(&mut data);
+        test_ftyp().build().put_buf(&mut metadata);
+        let mdat = write_test_mdat(&mut data, b"abcdefg");
+
+        let moov_pos = data.len();
+        test_moov().build().put_buf(&mut data);
+        test_moov().build().put_buf(&mut metadata);
+        BoxHeader::until_eof(MOOV).put_buf(&mut &mut data[moov_pos..]);
+
+        let sanitized = sanitize(io::Cursor::new(&data)).unwrap();
+        assert_eq!(sanitized.data, mdat);
+        assert_eq!(sanitized.metadata, Some(metadata));
+        sanitize(io::Cursor::new(sanitized_data(sanitized, &data))).unwrap();
+    }
+
+    #[test]
+    fn until_eof_sized_mdat() {
+        let test = test_mp4()
+            .boxes(&[FTYP, MOOV, MDAT][..])
+            .mdat_data(&b"abcdefg"[..])
+            .mdat_data_until_eof()
+            .build();
+        test.sanitize_ok_noop();
+    }
+
+    #[test]
+    fn empty_mdat() {
+        let test = test_mp4().mdat_data(vec![]).build();
+        let sanitized = sanitize(test.clone()).unwrap();
+        assert_eq!(sanitized.data, test.mdat);
+        assert_eq!(
+            sanitized.data.len,
+            BoxHeader::with_u32_data_size(BoxType::MDAT, 0).encoded_len()
+        );
+        sanitize(io::Cursor::new(sanitized_data(sanitized, &test.data))).unwrap();
+    }
+
+    #[test]
+    fn empty_mdat_before_mdat() {
+        init_logger();
+
+        let mdat_data = b"abcdefg";
+        let mut data = vec![];
+        test_ftyp().build().put_buf(&mut data);
+        let empty_mdat = write_test_mdat(&mut data, b"");
+        let mdat = write_test_mdat(&mut data, mdat_data);
+        let mdat_data_offset = mdat.offset + mdat.len - mdat_data.len() as u64;
+        let co_entries: Vec<_> = (mdat_data_offset..).take(mdat_data.len()).collect();
+        test_moov().co_entries(co_entries.clone()).build().put_buf(&mut data);
+
+        let sanitized = sanitize(io::Cursor::new(&data)).unwrap();
+        assert_eq!(
+            sanitized.data,
+            InputSpan { offset: empty_mdat.offset, len: empty_mdat.len + mdat.len }
+        );
+
+        let mut metadata = vec![];
+        test_ftyp().build().put_buf(&mut metadata);
+        let moov_len = test_moov().co_entries(co_entries.clone()).build().encoded_len();
+        let displacement = metadata.len() as u64 + moov_len - empty_mdat.offset;
+        let co_entries: Vec<_> = co_entries.iter().map(|co_entry| co_entry + displacement).collect();
+        test_moov().co_entries(co_entries).build().put_buf(&mut metadata);
+        assert_eq!(sanitized.metadata.as_ref(), Some(&metadata));
+
+        let sanitized_data = sanitized_data(sanitized, &data);
+        sanitize(io::Cursor::new(&sanitized_data)).unwrap();
+        ffmpeg_assert_eq(&sanitized_data, mdat_data);
+        gpac_assert_eq(&sanitized_data, mdat_data);
+    }
+
+    #[test]
+    fn empty_mdat_after_mdat() {
+        init_logger();
+
+        let mdat_data = b"abcdefg";
+        let mut data = vec![];
+        test_ftyp().build().put_buf(&mut data);
+        let mdat = write_test_mdat(&mut data, mdat_data);
+        let empty_mdat = write_test_mdat(&mut data, b"");
+        let mdat_data_offset = mdat.offset + mdat.len - mdat_data.len() as u64;
+        let co_entries: Vec<_> = (mdat_data_offset..).take(mdat_data.len()).collect();
+        test_moov().co_entries(co_entries).build().put_buf(&mut data);
+
+        let sanitized = sanitize(io::Cursor::new(&data)).unwrap();
+        assert_eq!(
+            sanitized.data,
+            InputSpan { offset: mdat.offset, len: mdat.len + empty_mdat.len }
+        );
+
+        let sanitized_data = sanitized_data(sanitized, &data);
+        sanitize(io::Cursor::new(&sanitized_data)).unwrap();
+        ffmpeg_assert_eq(&sanitized_data, mdat_data);
+        gpac_assert_eq(&sanitized_data, mdat_data);
+    }
+
+    #[test]
+    fn compact_moov() {
+        let config = Config::builder().compact_moov(true).build();
+        let test = test_mp4().moov(test_moov().padding(true).clone()).build();
+        let compacted = test_mp4().build();
+        assert!(compacted.expected_metadata.len() < test.expected_metadata.len());
+
+        let sanitized = sanitize_with_config(test.clone(), config).unwrap();
+        assert_eq!(sanitized.data, test.mdat);
+        assert_eq!(sanitized.metadata.as_deref(), Some(&compacted.expected_metadata[..]));
+
+        let sanitized_data = sanitized_data(sanitized, &test.data);
+        sanitize(io::Cursor::new(&sanitized_data)).unwrap();
+        ffmpeg_assert_eq(&sanitized_data, &test.mdat_data);
+        gpac_assert_eq(&sanitized_data, &test.mdat_data);
+    }
+
+    #[test]
+    fn canonicalize_box_sizes() {
+        let config = Config::builder().canonicalize_box_sizes(true).build();
+        let test = test_mp4().moov(test_moov().ext_tkhd_size(true).clone()).build();
+        let canonical = test_mp4().build();
+        assert_eq!(test.expected_metadata.len(), canonical.expected_metadata.len() + 8);
+
+        let sanitized = sanitize_with_config(test.clone(), config.clone()).unwrap();
+        assert_eq!(sanitized.data, test.mdat);
+        assert_eq!(sanitized.metadata.as_deref(), Some(&canonical.expected_metadata[..]));
+
+        let sanitized_data = sanitized_data(sanitized, &test.data);
+        let sanitized = sanitize_with_config(io::Cursor::new(&sanitized_data), config).unwrap();
+        assert_eq!(sanitized.metadata.as_deref(), Some(&canonical.expected_metadata[..]));
+        ffmpeg_assert_eq(&sanitized_data, &test.mdat_data);
+        gpac_assert_eq(&sanitized_data, &test.mdat_data);
+    }
+
+    #[test]
+    fn canonicalize_box_sizes_disabled() {
+        let test = test_mp4().moov(test_moov().ext_tkhd_size(true).clone()).build();
+        let sanitized = sanitize(test.clone()).unwrap();
+        assert_eq!(sanitized.metadata.as_deref(), Some(&test.expected_metadata[..]));
+    }
+
+    /// Append a `free` box of `free_len` bytes to the `moov` box at the end of `test`, so that the input can be
+    /// truncated within a trailing child of `moov` without cutting into any of its required children.
+    fn with_trailing_moov_free(test: &TestMp4, free_len: u32) -> Vec<u8> {
+        let mut data = test.data.to_vec();
+        let moov_offset = (test.mdat.offset + test.mdat.len) as usize;
+        let moov_len = u32::from_be_bytes(data[moov_offset..][..4].try_into().unwrap());
+        data[moov_offset..][..4].copy_from_slice(&(moov_len + free_len).to_be_bytes());
+        test_free(FREE, free_len).put_buf(&mut data);
+        data
+    }
+
+    #[test]
+    fn truncated_moov() {
+        let test = test_mp4().boxes(&[FTYP, MDAT, MOOV][..]).build();
+        let data = with_trailing_moov_free(&test, 16);
+        for truncated_len in [1, 8, 15, 16, 17, 64] {
+            let data = &data[..data.len() - truncated_len];
+            assert_matches!(sanitize(io::Cursor::new(data)).unwrap_err(), Error::Parse(err) => {
+                assert!(format!("{err:?}").contains("box data truncated"), "{err:?}");
+                assert_matches!(err.into_inner(), ParseError::TruncatedBox);
+            });
+        }
+    }
+
+    #[test]
+    fn truncated_moov_ok() {
+        let config = Config::builder().truncated_moov_ok(true).build();
+        let test = test_mp4().boxes(&[FTYP, MDAT, MOOV][..]).build();
+        let data = with_trailing_moov_free(&test, 16);
+        for truncated_len in [1, 8, 15, 16] {
+            let data = &data[..data.len() - truncated_len];
+            let sanitized = sanitize_with_config(io::Cursor::new(data), config.clone()).unwrap();
+            assert_eq!(sanitized.data, test.mdat);
+            assert_eq!(sanitized.metadata.as_deref(), Some(&test.expected_metadata[..]));
+        }
+
+        // Truncating the required children of the moov is still an error.
+        for truncated_len in [17, 64] {
+            let data = &data[..data.len() - truncated_len];
+            sanitize_with_config(io::Cursor::new(data), config.clone()).unwrap_err();
+        }
+    }
+
+    #[test]
+    fn truncated_moov_ok_untruncated() {
+        let config = Config::builder().truncated_moov_ok(true).build();
+        let test = test_mp4().boxes(&[FTYP, MDAT, MOOV][..]).build();
+        let data = with_trailing_moov_free(&test, 16);
+
+        // A moov which isn't truncated is kept whole, including its trailing free box.
+        let sanitized = sanitize_with_config(io::Cursor::new(&data), config).unwrap();
+        let metadata = sanitized.metadata.unwrap();
+        assert_eq!(metadata.len(), test.expected_metadata.len() + 16);
+    }
+
+    #[test]
+    fn compact_moov_disabled() {
+        let test = test_mp4().moov(test_moov().padding(true).clone()).build();
+        test.sanitize_ok();
+    }
+
+    #[test]
+    fn validate_chunk_count() {
+        let config = Config::builder().validate_chunk_count(true).build();
+        let test = test_mp4()
+            .moov(
+                test_moov()
+                    .co_entries(vec![0, 1, 2])
+                    .stsc_first_chunks(vec![1, 3])
+                    .clone(),
+            )
+            .build();
+        test.sanitize_ok_with_config(config);
+    }
+
+    #[test]
+    fn validate_sample_bounds() {
+        let config = Config::builder().validate_sample_bounds(true).build();
+        let test = test_mp4().build();
+        test.sanitize_ok_with_config(config);
+    }
+
+    #[test]
+    fn validate_sample_bounds_before_data() {
+        let config = Config::builder().validate_sample_bounds(true).build();
+        let mut data = vec![];
+        test_ftyp().build().put_buf(&mut data);
+        let mdat = write_test_mdat(&mut data, b"abcdefg");
+        test_moov().co_entries(vec![mdat.offset - 1]).build().put_buf(&mut data);
+        assert_matches!(sanitize_with_config(io::Cursor::new(&data), config).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidInput);
+        });
+        sanitize(io::Cursor::new(&data)).unwrap();
+    }
+
+    #[test]
+    fn validate_sample_bounds_past_data() {
+        let config = Config::builder().validate_sample_bounds(true).build();
+        let mdat_data = b"abcdefg".to_vec();
+        let moov = test_moov().co_entries(vec![mdat_data.len() as u64]).clone();
+        let test = test_mp4().mdat_data(mdat_data).moov(moov).build();
+        assert_matches!(sanitize_with_config(test, config).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidInput);
+        });
+    }
+
+    #[test]
+    fn max_stsc_entries() {
+        let moov = test_moov()
+            .co_entries(vec![0, 1, 2])
+            .stsc_first_chunks(vec![1, 3])
+            .clone();
+        let test = test_mp4().moov(moov).build();
+        test.sanitize_ok_with_config(Config::builder().max_stsc_entries(2).build());
+        let config = Config::builder().max_stsc_entries(1).build();
+        assert_matches!(sanitize_with_config(test, config).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidInput);
+        });
+    }
+
+    #[test]
+    fn max_stsc_entries_inflated_entry_count() {
+        let test = test_mp4().build();
+        let mut data = test.data.to_vec();
+        let stsc_pos = data.windows(4).position(|window| window == b"stsc").unwrap();
+        // Declare far more entries than the box contains.
+        data[stsc_pos + 8..][..4].copy_from_slice(&0x1000_0000_u32.to_be_bytes());
+
+        let config = Config::builder().max_stsc_entries(16).build();
+        assert_matches!(sanitize_with_config(io::Cursor::new(&data), config).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidInput);
+        });
+    }
+
+    #[test]
+    fn validate_chunk_count_truncated_stco() {
+        let config = Config::builder().validate_chunk_count(true).build();
+        let moov = test_moov().stsc_first_chunks(vec![1, 7]).clone();
+        let test = test_mp4().moov(moov).build();
+        assert_matches!(sanitize_with_config(test.clone(), config).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidInput);
+        });
+        test.sanitize_ok();
+    }
+
+    #[test]
+    fn validate_chunk_count_truncated_co64() {
+        let config = Config::builder().validate_chunk_count(true).build();
+        let moov = test_moov().stco(false).co64(true).stsc_first_chunks(vec![1, 7]).clone();
+        let test = test_mp4().moov(moov).build();
+        assert_matches!(sanitize_with_config(test, config).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidInput);
+        });
+    }
+
+    #[test]
+    fn validate_chunk_count_unordered_stsc() {
+        let config = Config::builder().validate_chunk_count(true).build();
+        let moov = test_moov()
+            .co_entries(vec![0, 1, 2])
+            .stsc_first_chunks(vec![1, 3, 2])
+            .clone();
+        let test = test_mp4().moov(moov).build();
+        assert_matches!(sanitize_with_config(test, config).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidInput);
+        });
+    }
+
+    #[test]
+    fn colr_nclx() {
+        let moov = test_moov().visual_sample_entry_children(HDR10_NCLX_COLR).clone();
+        let test = test_mp4().moov(moov).build();
+        test.sanitize_ok();
+    }
+
+    #[test]
+    fn colr_icc_profile_size_mismatch() {
+        let mut colr = b"\0\0\0\x8ccolrprof".to_vec();
+        colr.extend_from_slice(&129_u32.to_be_bytes());
+        colr.resize(0x8c, 0);
+        let moov = test_moov().visual_sample_entry_children(colr).clone();
+        let test = test_mp4().moov(moov).build();
+        assert_matches!(sanitize(test).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidInput);
+        });
+    }
+
+    #[test]
+    fn esds() {
+        let moov = test_moov()
+            .handler_type(SOUN)
+            .audio_sample_entry_children(TEST_ESDS)
+            .clone();
+        let test = test_mp4().moov(moov).build();
+        test.sanitize_ok();
+    }
+
+    #[test]
+    fn esds_truncated_descriptor() {
+        let mut esds = TEST_ESDS.to_vec();
+        esds.pop();
+        esds[3] -= 1;
+        let moov = test_moov().handler_type(SOUN).audio_sample_entry_children(esds).clone();
+        let test = test_mp4().moov(moov).build();
+        assert_matches!(sanitize(test).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::TruncatedBox);
+        });
+    }
+
+    #[test]
+    fn not_encrypted() {
+        let test = test_mp4().build();
+        assert!(!sanitize(test).unwrap().is_encrypted);
+    }
+
+    #[test]
+    fn encrypted_senc() {
+        let moov = test_moov().add_stbl_box(SENC).clone();
+        let test = test_mp4().moov(moov).build();
+        assert!(sanitize(test).unwrap().is_encrypted);
+    }
+
+    #[test]
+    fn encrypted_saiz_saio() {
+        let moov = test_moov().extra_stbl_boxes([SAIZ, SAIO]).clone();
+        let test = test_mp4().moov(moov).build();
+        assert!(sanitize(test).unwrap().is_encrypted);
+    }
+
+    #[test]
+    fn encrypted_pssh() {
+        let moov = test_moov().add_moov_box(PSSH).clone();
+        let test = test_mp4().boxes(&[FTYP, MOOV, MDAT][..]).moov(moov).build();
+        let sanitized = sanitize(test).unwrap();
+        assert!(sanitized.was_already_faststart);
+        assert!(sanitized.is_encrypted);
+    }
+
+    #[test]
+    fn to_vec() {
+        let test = test_mp4().build();
+        let sanitized = sanitize_to_vec(&test.data, Config::default()).unwrap();
+        assert_eq!(sanitized, sanitized_data(test.sanitize_ok(), &test.data));
+        sanitize(io::Cursor::new(&sanitized)).unwrap();
+    }
+
+    #[test]
+    fn to_vec_noop() {
+        let test = test_mp4().boxes(&[FTYP, MOOV, MDAT][..]).build();
+        let sanitized = sanitize_to_vec(&test.data, Config::default()).unwrap();
+        assert_eq!(sanitized, test.data);
+    }
+
+    #[test]
+    fn stco_upgraded_to_co64() {
+        init_logger();
+
+        let mdat_data = b"abcdefg";
+        let mut data = vec![];
+        test_ftyp().build().put_buf(&mut data);
+        let mdat = write_test_mdat(&mut data, mdat_data);
+        let mdat_data_offset = mdat.offset + mdat.len - mdat_data.len() as u64;
+        let mut co_entries: Vec<_> = (mdat_data_offset..).take(mdat_data.len()).collect();
+        co_entries.push(u32::MAX.into());
+        test_moov().co_entries(co_entries.clone()).build().put_buf(&mut data);
+
+        let sanitized = sanitize(io::Cursor::new(&data)).unwrap();
+        assert_eq!(sanitized.data, mdat);
+
+        let mut metadata = BytesMut::from(&sanitized.metadata.unwrap()[..]);
+        let ftyp_len = test_ftyp().build().encoded_len() as usize;
+        let mut moov = Mp4Box::<MoovBox>::parse(&mut metadata.split_off(ftyp_len)).unwrap();
+        let displacement = (ftyp_len as u64 + moov.encoded_len()) - mdat.offset;
+        let trak = moov.data.parse().unwrap().traks().next().unwrap().unwrap();
+        assert_matches!(trak.co_mut().unwrap(), StblCoMut::Co64(co64) => {
+            let displaced_co_entries: Vec<_> = co64.entries_mut().map(|entry| entry.get().unwrap()).collect();
+            let expected_co_entries: Vec<_> = co_entries.iter().map(|co_entry| co_entry + displacement).collect();
+            assert_eq!(displaced_co_entries, expected_co_entries);
+        });
+    }
+
+    #[test]
+    fn metadata_only() {
+        init_logger();
+
+        let mdat_data = b"abcdefg";
+        let mut mdat = vec![];
+        write_test_mdat(&mut mdat, mdat_data);
+
+        let mut data = vec![];
+        test_ftyp().build().put_buf(&mut data);
+        test_free(FREE, 16).put_buf(&mut data);
+        let moov_len = test_moov().co_entries(vec![0; mdat_data.len()]).build().encoded_len();
+        let mdat_data_offset = data.len() as u64 + moov_len + (mdat.len() - mdat_data.len()) as u64;
+        let co_entries: Vec<_> = (mdat_data_offset..).take(mdat_data.len()).collect();
+        test_moov().co_entries(co_entries).build().put_buf(&mut data);
+
+        let sanitized = sanitize_metadata_only(io::Cursor::new(&data), mdat.len() as u64, Config::default()).unwrap();
+        assert_eq!(
+            sanitized.data,
+            InputSpan { offset: data.len() as u64, len: mdat.len() as u64 }
+        );
+        let metadata = sanitized.metadata.unwrap();
+        assert_eq!(metadata.len(), data.len());
+
+        let sanitized_data = [&metadata[..], &mdat[..]].concat();
+        sanitize(io::Cursor::new(&sanitized_data)).unwrap();
+        ffmpeg_assert_eq(&sanitized_data, mdat_data);
+        gpac_assert_eq(&sanitized_data, mdat_data);
+    }
+
+    #[test]
+    fn metadata_only_mdat() {
+        let test = test_mp4().build();
+        let err = sanitize_metadata_only(test, 16, Config::default()).unwrap_err();
+        assert_matches!(err, Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidBoxLayout);
+        });
+    }
+
+    #[test]
+    fn skip() {
+        test_mp4().mdat_data(&b"abcdefg"[..]).build().sanitize_ok();
+    }
+
+    #[test]
+    fn max_input_length() {
+        let mut test = test_mp4().boxes(&[FTYP, MOOV, MDAT][..]).mdat_data(vec![]).clone();
+        let test_data_len = test.mdat_data_len(u64::MAX - 16).build().data.len() as u64;
+        let test = test.mdat_data_len(u64::MAX - test_data_len).build();
+        let sanitized = sanitize(test.clone()).unwrap();
+        assert_eq!(sanitized.data, test.mdat);
+        assert_eq!(sanitized.data.offset + sanitized.data.len, u64::MAX);
+        assert_eq!(sanitized.metadata, None);
+    }
+
+    #[test]
+    fn input_length_overflow() {
+        let mut test = test_mp4().mdat_data(vec![]).clone();
+        let test_data_len = test.mdat_data_len(u64::MAX - 16).build().data.len() as u64;
+        let test = test.mdat_data_len(u64::MAX - test_data_len + 1).build();
+        sanitize(test).unwrap_err();
+    }
+
+    #[test]
+    fn data_len_overflow() {
+        let mut data = InputSpan { offset: 16, len: u64::MAX - 32 };
+        let err = extend_data(&mut data, u64::MAX - 16, 33).unwrap_err();
+        assert_matches!(err, Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidInput);
+        });
+
+        let mut data = InputSpan { offset: u64::MAX, len: 1 };
+        let err = extend_data(&mut data, 0, 1).unwrap_err();
+        assert_matches!(err, Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidInput);
+        });
+    }
+
+    #[test]
+    fn data_len_extended() {
+        let mut data = InputSpan { offset: 16, len: 32 };
+        assert!(extend_data(&mut data, 48, 8).unwrap());
+        assert_eq!(data, InputSpan { offset: 16, len: 40 });
+        assert!(!extend_data(&mut data, 64, 8).unwrap());
+        assert_eq!(data, InputSpan { offset: 16, len: 40 });
+    }
+
+    #[test]
+    fn box_size_overflow() {
+        let test = test_mp4().mdat_data_len(u64::MAX - 16).build();
+        sanitize(test).unwrap_err();
+    }
+
+    #[test]
+    fn ftyp_too_large() {
+        let mut compatible_brands = vec![];
+        while compatible_brands.len() * COMPATIBLE_BRAND.value.len() < MAX_FTYP_SIZE as usize {
+            compatible_brands.push(COMPATIBLE_BRAND);
+        }
+
+        let test = test_mp4()
+            .ftyp(test_ftyp().compatible_brands(compatible_brands).clone())
+            .build();
+        assert_matches!(sanitize(test).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidInput);
+        });
+    }
+
+    #[test]
+    fn max_moov_size() {
+        let test_spec = test_mp4().build_spec().unwrap();
+        let config = Config::builder()
+            .max_metadata_size(test_spec.moov().build().encoded_len())
+            .build();
+        test_spec.build().sanitize_ok_with_config(config);
+    }
+
+    #[test]
+    fn moov_too_large() {
+        let test_spec = test_mp4().build_spec().unwrap();
+        let config = Config::builder()
+            .max_metadata_size(test_spec.moov().build().data.encoded_len() - 1)
+            .build();
+        let test = test_spec.build();
+        test.sanitize_ok();
+        assert_matches!(sanitize_with_config(test, config).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(
+                err.into_inner(),
+                ParseError::MetadataTooLarge { size, limit } if size == limit + 1
+            );
+        });
+    }
+
+    #[test]
+    fn metadata_too_large_retry() {
+        let test = test_mp4().boxes(&[FTYP, META, MDAT, MOOV][..]).build();
+        let mut max_metadata_size = 0;
+        let mut sizes = Vec::new();
+        loop {
+            let config = Config::builder()
+                .validate_meta(true)
+                .max_metadata_size(max_metadata_size)
+                .build();
+            let err = match sanitize_with_config(test.clone(), config) {
+                Ok(_) => break,
+                Err(Error::Parse(err)) => err.into_inner(),
+                Err(err) => panic!("{err:?}"),
+            };
+            assert_matches!(err, ParseError::MetadataTooLarge { size, limit } => {
+                assert_eq!(limit, max_metadata_size);
+                assert!(size > limit);
+                sizes.push(size);
+                max_metadata_size = size;
+            });
+        }
+        assert_eq!(sizes[0], test_meta().data.encoded_len());
+        assert!(sizes.len() <= 2);
+    }
+
+    #[test]
+    fn verify_output() {
+        let config = Config::builder().verify_output(true).build();
+        let test = test_mp4().build();
+        test.sanitize_ok_with_config(config);
+
+        let config = Config::builder().verify_output(true).build();
+        let test = test_mp4().boxes(&[FTYP, FREE, MDAT, MOOV][..]).build();
+        test.sanitize_ok_with_config(config);
+    }
+
+    #[test]
+    fn verify_output_corrupted() {
+        let test = test_mp4().build();
+        let metadata = sanitize(test).unwrap().metadata.unwrap();
+        verify_metadata(&metadata).unwrap();
+        let ftyp_len = test_ftyp().build().encoded_len() as usize;
+
+        // A moov box of the wrong type.
+        let mut corrupted = metadata.clone();
+        corrupted[ftyp_len + 4..][..4].copy_from_slice(b"moof");
+        assert_matches!(verify_metadata(&corrupted).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidBoxLayout);
+        });
+
+        // A moov box which is truncated.
+        let corrupted = &metadata[..metadata.len() - 1];
+        assert_matches!(verify_metadata(corrupted).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::TruncatedBox);
+        });
+
+        // Trailing data which is not a free box.
+        let mut corrupted = metadata.clone();
+        corrupted.extend_from_slice(b"\0\0\0\x08junk");
+        assert_matches!(verify_metadata(&corrupted).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidBoxLayout);
+        });
+    }
+
+    #[test]
+    fn reuse_buffer() {
+        let test = test_mp4().build();
+        let expected = sanitize(test.clone()).unwrap();
+
+        let mut buf = BytesMut::new();
+        let sanitized = sanitize_with_buffer(test.clone(), Config::default(), &mut buf).unwrap();
+        assert_eq!(sanitized, expected);
+        let buf_end = buf.as_ptr();
+
+        // The memory of the first read is reclaimed, so the box data is read into the same location.
+        let sanitized = sanitize_with_buffer(test.clone(), Config::default(), &mut buf).unwrap();
+        assert_eq!(sanitized, expected);
+        assert_eq!(buf.as_ptr(), buf_end);
+    }
+
+    #[test]
+    fn sanitizer_handle() {
+        let test = test_mp4().build();
+        let expected = sanitize(test.clone()).unwrap();
+
+        let mut sanitizer = SanitizerHandle::new(Config::default());
+        assert_eq!(sanitizer.sanitize(test.clone()).unwrap(), expected);
+        let scratch_end = sanitizer.scratch.as_ptr();
+        assert_eq!(sanitizer.sanitize(test.clone()).unwrap(), expected);
+        assert_eq!(sanitizer.scratch.as_ptr(), scratch_end);
+
+        let mut cloned = sanitizer.clone();
+        assert!(cloned.scratch.is_empty());
+        assert_eq!(cloned.sanitize(test).unwrap(), expected);
+    }
+
+    #[test]
+    fn slice() {
+        let test = test_mp4().build();
+        let sanitized = sanitize_slice(&test.data, Config::default()).unwrap();
+        assert_eq!(sanitized, sanitize(test.clone()).unwrap());
+
+        let mut data = sanitized.metadata.unwrap();
+        data.extend_from_slice(&test.data[sanitized.data.offset as usize..][..sanitized.data.len as usize]);
+        let sanitized = sanitize_slice(&data, Config::default()).unwrap();
+        assert_eq!(sanitized.metadata, None);
+        assert!(sanitized.was_already_faststart);
+    }
+
+    #[test]
+    fn validate_needs_rewrite() {
+        let test = test_mp4().boxes(&[FTYP, MDAT, MOOV][..]).build();
+        assert_eq!(
+            validate(test, Config::default()).unwrap(),
+            ValidationOutcome::NeedsRewrite
+        );
+    }
+
+    #[test]
+    fn validate_faststart() {
+        let test = test_mp4().boxes(&[FTYP, MOOV, MDAT][..]).build();
+        assert_eq!(validate(test, Config::default()).unwrap(), ValidationOutcome::Faststart);
+    }
+
+    #[test]
+    fn validate_no_moov() {
+        let test = test_mp4().boxes(&[FTYP, MDAT][..]).build();
+        assert_matches!(validate(test, Config::default()).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::MissingRequiredBox(MOOV));
+        });
+    }
+
+    fn estimate_overhead(test: &TestMp4, config: Config) -> (u64, Option<Vec<u8>>) {
+        let estimate = estimate_faststart_overhead(io::Cursor::new(&test.data), config.clone()).unwrap();
+        let metadata = sanitize_with_config(io::Cursor::new(&test.data), config)
+            .unwrap()
+            .metadata;
+        (estimate, metadata)
+    }
+
+    #[test]
+    fn estimate_faststart_overhead_needs_rewrite() {
+        let test = test_mp4().boxes(&[FTYP, MDAT, MOOV][..]).build();
+        let (estimate, metadata) = estimate_overhead(&test, Config::default());
+        assert_eq!(estimate, metadata.unwrap().len() as u64);
+    }
+
+    #[test]
+    fn estimate_faststart_overhead_padded() {
+        let test = test_mp4().boxes(&[FTYP, FREE, MDAT, MOOV][..]).build();
+        let (estimate, metadata) = estimate_overhead(&test, Config::default());
+        assert!(estimate >= metadata.unwrap().len() as u64);
+
+        let test = test_mp4().boxes(&[FTYP, MOOV, FREE, MDAT][..]).build();
+        let config = Config::builder().zero_timestamps(true).build();
+        let (estimate, metadata) = estimate_overhead(&test, config);
+        assert_eq!(estimate, metadata.unwrap().len() as u64);
+    }
+
+    #[test]
+    fn estimate_faststart_overhead_faststart() {
+        let test = test_mp4().boxes(&[FTYP, MOOV, MDAT][..]).build();
+        let (estimate, metadata) = estimate_overhead(&test, Config::default());
+        assert_eq!(estimate, 0);
+        assert_eq!(metadata, None);
+    }
+
+    #[test]
+    fn estimate_faststart_overhead_rename_mdat() {
+        let config = Config::builder().rename_mdat_to(FREE).build();
+        let test = test_mp4().boxes(&[FTYP, MDAT, MOOV][..]).build();
+        let (estimate, metadata) = estimate_overhead(&test, config.clone());
+        assert_eq!(estimate, metadata.unwrap().len() as u64);
+
+        let test = test_mp4().boxes(&[FTYP, FREE, MDAT, MOOV][..]).build();
+        let (estimate, metadata) = estimate_overhead(&test, config);
+        assert!(estimate >= metadata.unwrap().len() as u64);
+    }
+
+    #[test]
+    fn estimate_faststart_overhead_keep_track_ids() {
+        let config = Config::builder().keep_track_ids(HashSet::from([1])).build();
+        let test = test_mp4()
+            .boxes(&[FTYP, MOOV, MDAT][..])
+            .moov(test_moov().trak_count(2).clone())
+            .build();
+        let estimate = estimate_faststart_overhead(io::Cursor::new(&test.data), config.clone()).unwrap();
+        let output = sanitize_to_vec(&test.data, config).unwrap();
+        let metadata_len = sanitize_slice(&output, Config::default()).unwrap().data.offset;
+        assert!(estimate >= metadata_len);
+    }
+
+    #[test]
+    fn estimate_faststart_overhead_recovered() {
+        let config = Config::builder().recover(true).build();
+        let test = test_mp4().boxes(&[FTYP, TEST_JUNK, MOOV, MDAT][..]).build();
+        let (estimate, metadata) = estimate_overhead(&test, config);
+        assert!(estimate >= metadata.unwrap().len() as u64);
+    }
+
+    #[test]
+    fn estimate_faststart_overhead_truncated_moov() {
+        let config = Config::builder()
+            .truncated_moov_ok(true)
+            .required_boxes(RequiredBoxes { mdat: false, ..Default::default() })
+            .build();
+        let test = test_mp4().boxes(&[FTYP, MOOV][..]).build();
+        let mut data = test.data.to_vec();
+        let moov_offset = test_ftyp().build().encoded_len() as usize;
+        let moov_len = u32::from_be_bytes(data[moov_offset..][..4].try_into().unwrap());
+        data[moov_offset..][..4].copy_from_slice(&(moov_len + 16).to_be_bytes());
+        test_free(FREE, 8).put_buf(&mut data);
+
+        let estimate = estimate_faststart_overhead(io::Cursor::new(&data), config.clone()).unwrap();
+        let metadata = sanitize_with_config(io::Cursor::new(&data), config).unwrap().metadata;
+        assert!(estimate >= metadata.unwrap().len() as u64);
+    }
+
+    #[test]
+    fn estimate_faststart_overhead_unsupported_box() {
+        let test = test_mp4().boxes(&[FTYP, MOOV, TEST_UUID, MDAT][..]).build();
+        assert_matches!(
+            estimate_faststart_overhead(io::Cursor::new(&test.data), Config::default()).unwrap_err(),
+            Error::Parse(err) => {
+                assert_matches!(err.into_inner(), ParseError::UnsupportedBox(TEST_UUID));
+            }
+        );
+    }
+
+    #[test]
+    fn estimate_faststart_overhead_no_moov() {
+        let test = test_mp4().boxes(&[FTYP, MDAT][..]).build();
+        assert_matches!(
+            estimate_faststart_overhead(io::Cursor::new(&test.data), Config::default()).unwrap_err(),
+            Error::Parse(err) => {
+                assert_matches!(err.into_inner(), ParseError::MissingRequiredBox(MOOV));
+            }
+        );
+    }
+
+    fn sanitize_media_sink_ok(test: TestMp4) -> Vec<u8> {
+        let mut media_data = Vec::new();
+        let sanitized =
+            sanitize_with_media_sink(io::Cursor::new(&test.data), Config::default(), &mut media_data).unwrap();
+        let InputSpan { offset, len } = sanitized.data;
+        assert_eq!(media_data, test.data[offset as usize..][..len as usize]);
+        assert_eq!(sanitized.metadata.as_deref(), Some(&test.expected_metadata[..]));
+        media_data
+    }
+
+    #[test]
+    fn media_sink() {
+        let test = test_mp4().boxes(&[FTYP, MDAT, MOOV][..]).build();
+        let media_data = sanitize_media_sink_ok(test.clone());
+        assert_eq!(
+            media_data,
+            test.data[test.mdat.offset as usize..][..test.mdat.len as usize]
+        );
+    }
+
+    #[test]
+    fn media_sink_faststart() {
+        let test = test_mp4().boxes(&[FTYP, MOOV, MDAT][..]).build();
+        let mut media_data = Vec::new();
+        let sanitized =
+            sanitize_with_media_sink(io::Cursor::new(&test.data), Config::default(), &mut media_data).unwrap();
+        let InputSpan { offset, len } = sanitized.data;
+        assert_eq!(media_data, test.data[offset as usize..][..len as usize]);
+
+        let output = [sanitized.metadata.unwrap(), media_data].concat();
+        let sanitized_output = sanitize(io::Cursor::new(&output)).unwrap();
+        assert_eq!(sanitized_output.metadata, None);
+        assert_eq!(output, test.data);
+    }
+
+    #[test]
+    fn media_sink_multiple_mdat() {
+        let test = test_mp4()
+            .boxes(&[FTYP, FREE, MDAT, FREE, MDAT, MDAT, FREE, MOOV][..])
+            .build();
+        sanitize_media_sink_ok(test);
+    }
+
+    #[test]
+    fn media_sink_truncated_mdat() {
+        let test = test_mp4().boxes(&[FTYP, MOOV, MDAT][..]).build();
+        let truncated = &test.data[..test.data.len() - 1];
+        assert_matches!(
+            sanitize_with_media_sink(io::Cursor::new(truncated), Config::default(), io::sink()).unwrap_err(),
+            Error::Parse(err) => {
+                assert_matches!(err.into_inner(), ParseError::TruncatedBox);
+            }
+        );
+    }
+
+    #[test]
+    fn header_only_boxes() {
+        let mut data = vec![];
+        test_ftyp().build().put_buf(&mut data);
+        test_free(FREE, 8).put_buf(&mut data);
+        test_free(SKIP, 8).put_buf(&mut data);
+        test_moov().build().put_buf(&mut data);
+        write_test_mdat(&mut data, &[]);
+        let sanitized = sanitize(io::Cursor::new(&data)).unwrap();
+        assert_eq!(sanitized.metadata, None);
+    }
+
+    #[test]
+    fn box_made_no_progress() {
+        /// An input which never reports advancing, as a malfunctioning or malicious [`Skip`] implementation might.
+        struct StuckInput(io::Cursor<Vec<u8>>);
+
+        impl io::Read for StuckInput {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.0.read(buf)
+            }
+        }
+
+        impl Skip for StuckInput {
+            fn skip(&mut self, amount: u64) -> io::Result<()> {
+                self.0.skip(amount)
+            }
+
+            fn stream_position(&mut self) -> io::Result<u64> {
+                Ok(0)
+            }
+
+            fn stream_len(&mut self) -> io::Result<u64> {
+                self.0.stream_len()
+            }
+        }
+
+        let test = test_mp4().build();
+        let input = StuckInput(io::Cursor::new(test.data.to_vec()));
+        assert_matches!(sanitize(input).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidBoxLayout);
+        });
+    }
+
+    #[test]
+    fn max_box_size() {
+        let test = test_mp4().build();
+        let config = Config::builder().max_box_size(test.data_len).build();
+        test.sanitize_ok_with_config(config);
+    }
+
+    #[test]
+    fn max_box_size_ext_size_too_large() {
+        let mut data = vec![];
+        test_ftyp().build().put_buf(&mut data);
+        data.extend_from_slice(b"\0\0\0\x01mdat");
+        data.extend_from_slice(&u64::MAX.to_be_bytes());
+        data.extend_from_slice(b"abcdefg");
+        let config = Config::builder().max_box_size(data.len() as u64).build();
+        assert_matches!(sanitize_with_config(io::Cursor::new(&data), config).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidInput);
+        });
+    }
+
+    #[test]
+    fn max_input_len() {
+        let test = test_mp4().build();
+        let config = Config::builder().max_input_len(test.data_len).build();
+        test.sanitize_ok_with_config(config);
+    }
+
+    #[test]
+    fn input_too_large() {
+        let test = test_mp4().build();
+        let config = Config::builder().max_input_len(test.data_len - 1).build();
+        assert_matches!(sanitize_with_config(test, config).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidInput);
+        });
+    }
+
+    #[test]
+    fn deadline_not_exceeded() {
+        let test = test_mp4().build();
+        let deadline = Instant::now() + Duration::from_secs(3600);
+        sync::sanitize(test, |input| {
+            sanitize_async_with_deadline(input, Config::default(), deadline)
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn deadline_exceeded() {
+        let test = test_mp4().build();
+        let deadline = Instant::now();
+        let err = sync::sanitize(test, |input| {
+            sanitize_async_with_deadline(input, Config::default(), deadline)
+        });
+        assert_matches!(err.unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::DeadlineExceeded);
+        });
+    }
+
+    #[test]
+    fn sanitize_into() {
+        let test = test_mp4().build();
+        let input = futures_util::io::Cursor::new(test.data.clone());
+        let (sanitized, input) = sanitize_async_into(input, Config::default())
+            .now_or_never()
+            .unwrap()
+            .unwrap();
+        assert_eq!(sanitized.data, test.mdat);
+        assert_eq!(input.position(), test.data_len);
+        assert_eq!(input.into_inner(), test.data);
+    }
+
+    #[test]
+    fn mdat_after_moov() {
+        test_mp4().boxes(&[FTYP, MOOV, MDAT][..]).build().sanitize_ok_noop();
+    }
+
+    #[test]
+    fn no_ftyp() {
+        let test = test_mp4().boxes(&[MOOV, MDAT][..]).build();
+        assert_matches!(sanitize(test).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidBoxLayout);
+        });
+    }
+
+    #[test]
+    fn multiple_ftyp() {
+        let test = test_mp4().boxes(&[FTYP, FTYP, MOOV, MDAT][..]).build();
+        assert_matches!(sanitize(test).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidBoxLayout);
+        });
+    }
+
+    #[test]
+    fn multiple_moov() {
+        let test = test_mp4().boxes(&[FTYP, MOOV, MOOV, MDAT][..]).build();
+        assert_matches!(sanitize(test).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidBoxLayout);
+        });
+
+        let test = test_mp4().boxes(&[FTYP, MOOV, MDAT, MOOV][..]).build();
+        assert_matches!(sanitize(test).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidBoxLayout);
+        });
+    }
+
+    #[test]
+    fn trailing_garbage() {
+        let test = test_mp4().build();
+        for garbage_len in 1..=7 {
+            let data = [&test.data[..], &[0xff; 7][..garbage_len]].concat();
+
+            assert_matches!(sanitize(io::Cursor::new(&data)).unwrap_err(), Error::Parse(err) => {
+                assert_matches!(err.into_inner(), ParseError::TruncatedBox);
+            });
+
+            let config = Config::builder().allow_trailing_garbage(true).build();
+            let sanitized = sanitize_with_config(io::Cursor::new(&data), config).unwrap();
+            assert_eq!(sanitized, sanitize(test.clone()).unwrap());
+        }
+    }
+
+    #[test]
+    fn trailing_garbage_faststart() {
+        let test = test_mp4().boxes(&[FTYP, MOOV, MDAT][..]).build();
+        let data = [&test.data[..], &[0xff; 7][..]].concat();
+        let config = Config::builder().allow_trailing_garbage(true).build();
+        let sanitized = sanitize_with_config(io::Cursor::new(&data), config.clone()).unwrap();
+        assert!(sanitized.metadata.is_some());
+        assert_eq!(sanitized_data(sanitized, &data), test.data);
+
+        let estimate = estimate_faststart_overhead(io::Cursor::new(&data), config).unwrap();
+        assert_eq!(estimate, test.expected_metadata.len() as u64);
+    }
+
+    #[test]
+    fn ftyp_not_first_box() {
+        let test = test_mp4().boxes(&[FREE, FREE, FTYP, MDAT, MOOV][..]).build();
+        test.sanitize_ok();
+    }
+
+    #[test]
+    fn ftyp_not_first_significant_box() {
+        let test = test_mp4().boxes(&[MOOV, FTYP, MDAT][..]).build();
+        assert_matches!(sanitize(test).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidBoxLayout);
+        });
+    }
+
+    #[test]
+    fn no_moov() {
+        let test = test_mp4().boxes(&[FTYP, MDAT][..]).build();
+        assert_matches!(sanitize(test).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::MissingRequiredBox(MOOV));
+        });
+    }
 
-                ftyp = Some(read_ftyp);
-            }
+    #[test]
+    fn no_mdat() {
+        let test = test_mp4().boxes(&[FTYP, MOOV][..]).build();
+        assert_matches!(sanitize(test).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::MissingRequiredBox(MDAT));
+        });
+    }
 
-            // NB: ISO 14496-12-2012 specifies a default ftyp, but we don't currently use it. The spec says that it
-            // contains a single compatible brand, "mp41", and notably not "isom" which is the ISO spec we follow for
-            // parsing now. This implies that there's additional stuff in "mp41" which is not in "isom". "mp41" is also
-            // very old at this point, so it'll require additional research/work to be able to parse/remux it.
-            _ if ftyp.is_none() => {
-                bail_attach!(ParseError::InvalidBoxLayout, "ftyp is not the first significant box");
-            }
+    #[test]
+    fn no_mdat_not_required() {
+        let test = test_mp4().boxes(&[FTYP, MOOV][..]).build();
+        let required_boxes = RequiredBoxes { mdat: false, ..Default::default() };
+        let config = Config::builder().required_boxes(required_boxes).build();
+        let sanitized = sanitize_with_config(test.clone(), config).unwrap();
+        assert_eq!(sanitized.metadata, None);
+        assert_eq!(sanitized.data, InputSpan { offset: test.data.len() as u64, len: 0 });
+    }
 
-            BoxType::MDAT => {
-                let box_size = skip_box(reader.as_mut(), &header).await? + header.encoded_len();
-                log::info!("mdat @ 0x{start_pos:08x}: {box_size} bytes");
+    #[test]
+    fn additional_required_box() {
+        let required_boxes = RequiredBoxes { additional: HashSet::from([FREE]), ..Default::default() };
+        let config = Config::builder().required_boxes(required_boxes).build();
 
-                if let Some(data) = &mut data {
-                    // Try to extend already accumulated data.
-                    ensure_attach!(
-                        data.offset + data.len == start_pos,
-                        ParseError::UnsupportedBoxLayout,
-                        "discontiguous mdat boxes",
-                    );
-                    data.len += box_size;
-                } else {
-                    data = Some(InputSpan { offset: start_pos, len: box_size });
-                }
-            }
+        let test = test_mp4().boxes(&[FTYP, MOOV, MDAT][..]).build();
+        assert_matches!(sanitize_with_config(test, config.clone()).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::MissingRequiredBox(FREE));
+        });
 
-            BoxType::MOOV => {
-                let mut read_moov = Mp4Box::read_data(reader.as_mut(), header, config.max_metadata_size).await?;
+        let test = test_mp4().boxes(&[FTYP, FREE, MOOV, MDAT][..]).build();
+        sanitize_with_config(test, config).unwrap();
+    }
 
-                let moov_data: &mut MoovBox = read_moov.data.parse()?;
-                let trak_chunk_counts = moov_data
-                    .traks()
-                    .map(|trak| Ok::<_, Report<_>>(trak?.co_mut()?.entry_count()));
-                let chunk_count = trak_chunk_counts.reduce(|a, b| Ok(a? + b?)).unwrap_or(Ok(0))?;
-                let trak_count = moov_data.traks().count();
+    #[test]
+    fn sanitize_from_position() {
+        let prefix = b"sniffed prefix";
+        for boxes in [&[FTYP, MDAT, MOOV][..], &[FTYP, MOOV, MDAT]] {
+            let test = test_mp4().boxes(boxes).build();
+            let expected = sanitize(test.clone()).unwrap();
 
-                log::info!("moov @ 0x{start_pos:08x}: {trak_count} traks {chunk_count} chunks");
-                moov = Some(read_moov);
-                moov_offset = Some(start_pos);
-            }
+            let input = [&prefix[..], &test.data].concat();
+            let sanitized =
+                super::sanitize_from_position(io::Cursor::new(&input), prefix.len() as u64, Config::default());
+            let sanitized = sanitized.unwrap();
+            assert_eq!(sanitized.metadata, expected.metadata);
+            assert_eq!(sanitized.data.offset, prefix.len() as u64 + expected.data.offset);
+            assert_eq!(sanitized.data.len, expected.data.len);
+        }
+    }
 
-            name @ (BoxType::META | BoxType::MECO) => {
-                let box_size = skip_box(reader.as_mut(), &header).await? + header.encoded_len();
-                log::info!("{name} @ 0x{start_pos:08x}: {box_size} bytes");
+    #[test]
+    fn sanitize_from_position_already_read() {
+        let test = test_mp4().build();
+        let mut input = io::Cursor::new(&test.data[..]);
+        input.set_position(8);
+        let err = super::sanitize_from_position(input, 4, Config::default()).unwrap_err();
+        assert_matches!(err, Error::Io(err) => {
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        });
+    }
 
-                // Try to extend any already accumulated data in case there's more mdat boxes to come.
-                if let Some(data) = &mut data {
-                    if data.offset + data.len == start_pos {
-                        data.len += box_size;
-                    }
-                }
-            }
+    #[test]
+    fn rename_mdat() {
+        let test = test_mp4().boxes(&[FTYP, MDAT, MOOV][..]).build();
+        let config = Config::builder().rename_mdat_to(FREE).build();
+        let sanitized = sanitize_with_config(test.clone(), config).unwrap();
+        let metadata_len = sanitized.metadata.as_ref().unwrap().len();
+        assert_eq!(&sanitized.metadata.as_ref().unwrap()[metadata_len - 4..], b"free");
 
-            name => {
-                let box_size = skip_box(reader.as_mut(), &header).await? + header.encoded_len();
-                log::info!("{name} @ 0x{start_pos:08x}: {box_size} bytes");
-                bail_attach!(ParseError::UnsupportedBox(name));
-            }
-        }
+        let mut renamed = sanitized_data(sanitized, &test.data);
+        renamed[metadata_len - 4..metadata_len].copy_from_slice(b"mdat");
+        assert_eq!(renamed, sanitized_data(sanitize(test.clone()).unwrap(), &test.data));
+        sanitize(io::Cursor::new(&renamed)).unwrap();
     }
 
-    let Some(ftyp) = ftyp else {
-        bail_attach!(ParseError::MissingRequiredBox(BoxType::FTYP));
-    };
-    let (Some(moov), Some(moov_offset)) = (moov, moov_offset) else {
-        bail_attach!(ParseError::MissingRequiredBox(BoxType::MOOV));
-    };
-    let Some(data) = data else {
-        bail_attach!(ParseError::MissingRequiredBox(BoxType::MDAT));
-    };
+    #[test]
+    fn rename_mdat_media_sink() {
+        let test = test_mp4().boxes(&[FTYP, MDAT, MOOV][..]).build();
+        let config = Config::builder().rename_mdat_to(FREE).build();
+        let mut media_data = Vec::new();
+        let sanitized = sanitize_with_media_sink(io::Cursor::new(&test.data), config.clone(), &mut media_data).unwrap();
+        let InputSpan { offset, len } = sanitized.data;
+        assert_eq!(media_data, test.data[offset as usize..][..len as usize]);
 
-    // Return early if there's nothing to sanitize. Since the only thing the sanitizer does currently is move the moov
-    // to before the mdat to make the mp4 streamable, return if we don't need to do that.
-    if moov_offset < data.offset {
-        log::info!("metadata: nothing to sanitize");
-        return Ok(SanitizedMetadata { metadata: None, data });
+        let output = [sanitized.metadata.clone().unwrap(), media_data].concat();
+        assert_eq!(output, sanitized_data(sanitized, &test.data));
     }
 
-    // Make sure none of the metadata boxes use BoxSize::UntilEof, as we want the caller to be able to concatenate movie
-    // data to the end of the metadata.
-    let ftyp = Mp4Box::with_data(ftyp.data)?;
-    let mut moov = Mp4Box::with_data(moov.data)?;
+    #[test]
+    fn free_boxes_in_metadata() {
+        let test = test_mp4().boxes(&[FTYP, FREE, SKIP, MDAT, MOOV, FREE][..]).build();
+        test.sanitize_ok();
+    }
 
-    // Add a free box to pad, if one will fit, if the mdat box would move backward. If one won't fit, or if the mdat box
-    // would move forward, adjust mdat offsets in stco/co64 the amount it was displaced.
-    let metadata_len = ftyp.encoded_len() + moov.encoded_len();
-    let mut pad_size = 0;
-    const PAD_HEADER_SIZE: u64 = BoxHeader::with_u32_data_size(BoxType::FREE, 0).encoded_len();
-    const MAX_PAD_SIZE: u64 = u32::MAX as u64 - PAD_HEADER_SIZE;
-    match data.offset.checked_sub(metadata_len) {
-        Some(0) => {
-            log::info!("metadata: 0x{metadata_len:08x} bytes");
-        }
-        Some(size @ PAD_HEADER_SIZE..=MAX_PAD_SIZE) => {
-            pad_size = size;
-            log::info!("metadata: 0x{metadata_len:08x} bytes; adding padding of 0x{pad_size:08x} bytes");
-        }
-        mdat_backward_displacement => {
-            let mdat_displacement = match mdat_backward_displacement {
-                Some(mdat_backward_displacement) => {
-                    mdat_backward_displacement.try_into().ok().and_then(i32::checked_neg)
-                }
-                None => metadata_len.checked_sub(data.offset).unwrap().try_into().ok(),
-            };
-            let mdat_displacement: i32 = mdat_displacement
-                .ok_or_else(|| report_attach!(ParseError::UnsupportedBoxLayout, "mdat displaced too far"))?;
+    #[test]
+    fn reject_skip_boxes() {
+        let config = Config::builder().reject_skip_boxes(true).build();
+        let test = test_mp4().boxes(&[FTYP, FREE, SKIP, MDAT, MOOV][..]).build();
+        test.sanitize_ok();
+        assert_matches!(sanitize_with_config(test, config.clone()).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::UnsupportedBox(SKIP));
+        });
 
-            log::info!("metadata: 0x{metadata_len:08x} bytes; displacing chunk offsets by 0x{mdat_displacement:08x}");
-
-            for trak in &mut moov.data.parse()?.traks() {
-                let co = trak?.co_mut()?;
-                if let StblCoMut::Stco(stco) = co {
-                    for mut entry in &mut stco.entries_mut() {
-                        let value = entry.get().unwrap_or_else(|_| unreachable!());
-                        entry.set(
-                            checked_add_signed(value, mdat_displacement).ok_or_else(|| {
-                                report_attach!(ParseError::InvalidInput, "chunk offset not within mdat")
-                            })?,
-                        );
-                    }
-                } else if let StblCoMut::Co64(co64) = co {
-                    for mut entry in &mut co64.entries_mut() {
-                        let value = entry.get().unwrap_or_else(|_| unreachable!());
-                        entry.set(
-                            checked_add_signed(value, mdat_displacement.into()).ok_or_else(|| {
-                                report_attach!(ParseError::InvalidInput, "chunk offset not within mdat")
-                            })?,
-                        );
-                    }
-                }
-            }
-        }
+        let test = test_mp4().boxes(&[FTYP, FREE, MDAT, MOOV, FREE][..]).build();
+        test.sanitize_ok_with_config(config);
     }
 
-    let mut metadata = Vec::with_capacity((metadata_len + pad_size) as usize);
-    ftyp.put_buf(&mut metadata);
-    moov.put_buf(&mut metadata);
-    if pad_size != 0 {
-        let pad_header = BoxHeader::with_u32_data_size(BoxType::FREE, (pad_size - PAD_HEADER_SIZE) as u32);
-        pad_header.put_buf(&mut metadata);
-        metadata.resize((metadata_len + pad_size) as usize, 0);
+    #[test]
+    fn free_boxes_after_mdat() {
+        let test = test_mp4().boxes(&[FTYP, MDAT, SKIP, FREE, MOOV][..]).build();
+        test.sanitize_ok();
     }
 
-    Ok(SanitizedMetadata { metadata: Some(metadata), data })
-}
+    #[test]
+    fn meta_boxes_in_metadata() {
+        let test = test_mp4().boxes(&[FTYP, MDAT, MOOV, META, MECO][..]).build();
+        test.sanitize_ok();
+    }
 
-//
-// Config impls
-//
+    #[test]
+    fn meta_boxes_after_mdat() {
+        let test = test_mp4().boxes(&[FTYP, MDAT, META, MDAT, MECO, MOOV][..]).build();
+        test.sanitize_ok();
+    }
 
-impl Config {
-    /// Construct a builder for `Config`.
-    ///
-    /// See the documentation for [`ConfigBuilder`].
-    pub fn builder() -> ConfigBuilder {
-        ConfigBuilder::default()
+    #[test]
+    fn validate_meta() {
+        let config = Config::builder().validate_meta(true).build();
+        let test = test_mp4().boxes(&[FTYP, MDAT, META, MDAT, MECO, MOOV][..]).build();
+        test.sanitize_ok_with_config(config);
     }
-}
 
-impl Default for Config {
-    fn default() -> Self {
-        Self::builder().build()
+    #[test]
+    fn validate_meta_ilst() {
+        let test = test_mp4().build();
+        let mut meta = BytesMut::new();
+        write_test_ilst_meta_data(&mut meta);
+        let mut data = test.data.to_vec();
+        Mp4Box::<MetaBox>::with_data(BoxData::Bytes(meta))
+            .unwrap()
+            .put_buf(&mut data);
+
+        let config = Config::builder().validate_meta(true).build();
+        let sanitized = sanitize_with_config(io::Cursor::new(&data), config).unwrap();
+        assert_eq!(sanitized, sanitize(test.clone()).unwrap());
     }
-}
 
-//
-// ConfigBuilder impls
-//
+    #[test]
+    fn require_video_track() {
+        let config = Config::builder().require_video_track(true).build();
+        let test = test_mp4()
+            .moov(test_moov().handler_type(VIDEO_HANDLER_TYPE).clone())
+            .build();
+        test.sanitize_ok_with_config(config);
+    }
 
-impl ConfigBuilder {
-    /// Build a new [`Config`].
-    pub fn build(&self) -> Config {
-        self.try_build().unwrap()
+    #[test]
+    fn first_keyframe_span() {
+        let moov = test_moov()
+            .handler_type(VIDEO_HANDLER_TYPE)
+            .stss(StssBox::from_iter([3, 5]))
+            .clone();
+        let test = test_mp4().moov(moov).build();
+        let span = test.sanitize_ok().first_keyframe_span.unwrap();
+        // The test builder writes one chunk containing a single one-byte sample per byte of media data.
+        assert_eq!(span, InputSpan { offset: test.mdat.offset + 8 + 2, len: 1 });
+        assert_eq!(test.data[span.offset as usize], test.mdat_data[2]);
     }
-}
 
-//
-// private functions
-//
+    #[test]
+    fn first_keyframe_span_none() {
+        let test = test_mp4()
+            .moov(test_moov().handler_type(VIDEO_HANDLER_TYPE).clone())
+            .build();
+        assert_eq!(test.sanitize_ok().first_keyframe_span, None);
 
-/// Skip a box's data assuming its header has already been read.
-///
-/// Returns the amount of data that was skipped.
-async fn skip_box<R: AsyncRead + AsyncSkip>(
-    mut reader: Pin<&mut BufReader<R>>,
-    header: &BoxHeader,
-) -> Result<u64, Error> {
-    let box_data_size = match header.box_data_size()? {
-        Some(box_size) => box_size,
-        None => reader.as_mut().stream_len().await? - reader.as_mut().stream_position().await?,
-    };
-    reader.skip(box_data_size).await.map_eof(|_| {
-        Error::Parse(report_attach!(
-            ParseError::TruncatedBox,
-            WhileParsingBox(header.box_type())
-        ))
-    })?;
-    Ok(box_data_size)
-}
+        let moov = test_moov().handler_type(SOUN).stss(StssBox::from_iter([1])).clone();
+        let test = test_mp4().moov(moov).build();
+        assert_eq!(test.sanitize_ok().first_keyframe_span, None);
+    }
 
-#[cfg(doctest)]
-#[doc = include_str!("../README.md")]
-pub mod readme {}
+    #[test]
+    fn require_video_track_audio_only() {
+        let test = test_mp4().moov(test_moov().handler_type(SOUN).clone()).build();
+        test.sanitize_ok();
 
-#[cfg(test)]
-mod test {
-    use std::io;
+        let config = Config::builder().require_video_track(true).build();
+        assert_matches!(sanitize_with_config(test, config).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::MissingRequiredBox(BoxType::TRAK));
+        });
+    }
 
-    use assert_matches::assert_matches;
+    #[test]
+    fn media_header_audio() {
+        test_mp4()
+            .moov(test_moov().handler_type(SOUN).clone())
+            .build()
+            .sanitize_ok();
+    }
+
+    #[test]
+    fn media_header_mismatch() {
+        let moov = test_moov()
+            .handler_type(VIDEO_HANDLER_TYPE)
+            .media_header(Some(SMHD))
+            .clone();
+        let test = test_mp4().moov(moov).build();
+        assert_matches!(sanitize(test).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidBoxLayout);
+        });
+    }
+
+    #[test]
+    fn media_header_missing() {
+        let moov = test_moov().handler_type(VIDEO_HANDLER_TYPE).media_header(None).clone();
+        let test = test_mp4().moov(moov).build();
+        assert_matches!(sanitize(test).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidBoxLayout);
+        });
+    }
+
+    #[test]
+    fn media_header_metadata() {
+        for media_header in [None, Some(NMHD), Some(GMHD)] {
+            let moov = test_moov()
+                .handler_type(fourcc::META)
+                .media_header(media_header)
+                .clone();
+            test_mp4().moov(moov).build().sanitize_ok();
+        }
+    }
+
+    #[test]
+    fn media_header_metadata_mismatch() {
+        let moov = test_moov().handler_type(fourcc::META).media_header(Some(VMHD)).clone();
+        let test = test_mp4().moov(moov).build();
+        assert_matches!(sanitize(test).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidBoxLayout);
+        });
+    }
+
+    #[test]
+    fn media_header_video_nmhd() {
+        let moov = test_moov()
+            .handler_type(VIDEO_HANDLER_TYPE)
+            .media_header(Some(NMHD))
+            .clone();
+        let test = test_mp4().moov(moov).build();
+        assert_matches!(sanitize(test).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidBoxLayout);
+        });
+    }
+
+    #[test]
+    fn zero_timestamps() {
+        let config = Config::builder().zero_timestamps(true).build();
+        let test = test_mp4().build();
+        let sanitized = sanitize_with_config(test.clone(), config.clone()).unwrap();
+        let metadata = sanitized.metadata.clone().unwrap();
+        let data = sanitized_data(sanitized, &test.data);
+
+        let mut moov_data = BytesMut::from(&metadata[test_ftyp().build().encoded_len() as usize..]);
+        let mut moov = Mp4Box::<MoovBox>::parse(&mut moov_data).unwrap();
+        let moov = moov.data.parse().unwrap();
+        assert_eq!(moov.mvhd_mut().unwrap().unwrap().times().creation_time(), 0);
+        assert_eq!(moov.mvhd_mut().unwrap().unwrap().times().modification_time(), 0);
+        let trak = moov.traks().next().unwrap().unwrap();
+        let tkhd = trak.tkhd_mut().unwrap().unwrap();
+        assert_eq!((tkhd.creation_time(), tkhd.modification_time()), (0, 0));
+        let mdhd = trak.mdia_mut().unwrap().mdhd_mut().unwrap().unwrap();
+        assert_eq!((mdhd.times().creation_time(), mdhd.times().modification_time()), (0, 0));
+
+        // The output is still valid, and is already faststart, but its metadata is still rewritten.
+        let sanitized = sanitize_with_config(io::Cursor::new(&data), config).unwrap();
+        assert!(!sanitized.was_already_faststart);
+        assert_eq!(sanitized.metadata.as_deref(), Some(&data[..metadata.len()]));
+        assert_eq!(sanitize(io::Cursor::new(&data)).unwrap().metadata, None);
+    }
+
+    #[test]
+    fn zero_timestamps_disabled() {
+        let test = test_mp4().build();
+        let metadata = sanitize(test).unwrap().metadata.unwrap();
+        let mut moov_data = BytesMut::from(&metadata[test_ftyp().build().encoded_len() as usize..]);
+        let mut moov = Mp4Box::<MoovBox>::parse(&mut moov_data).unwrap();
+        let mvhd = moov.data.parse().unwrap().mvhd_mut().unwrap().unwrap();
+        assert_eq!(mvhd.times().creation_time(), u64::from(TEST_CREATION_TIME));
+    }
 
-    use crate::parse::box_type::{CO64, FREE, FTYP, MDAT, MDIA, MECO, META, MINF, MOOV, SKIP, STBL, STCO, TRAK};
-    use crate::util::test::{
-        init_logger, sanitized_data, test_ftyp, test_moov, test_mp4, write_test_mdat, ISOM, MP41, MP42, TEST_UUID,
-    };
+    #[test]
+    fn reject_edit_lists_trivial() {
+        let config = Config::builder().reject_edit_lists(true).build();
+        test_mp4().build().sanitize_ok_with_config(config.clone());
 
-    use super::*;
+        let elst = ElstBox::from_entries_v0([ElstEntry {
+            segment_duration: 1000,
+            media_time: 0,
+            media_rate_integer: 1,
+            media_rate_fraction: 0,
+        }]);
+        let test = test_mp4().moov(test_moov().elst(elst).clone()).build();
+        test.sanitize_ok_with_config(config);
+    }
 
     #[test]
-    fn until_eof_sized_moov() {
-        init_logger();
+    fn metadata_items() {
+        let test = test_mp4().moov(test_moov().udta(test_udta()).clone()).build();
+        let metadata = test.sanitize_ok().metadata.unwrap();
+        let mut moov_data = BytesMut::from(&metadata[test_ftyp().build().encoded_len() as usize..]);
+        let mut moov = Mp4Box::<MoovBox>::parse(&mut moov_data).unwrap();
+        let meta = moov
+            .data
+            .parse()
+            .unwrap()
+            .udta_mut()
+            .unwrap()
+            .unwrap()
+            .meta_mut()
+            .unwrap()
+            .unwrap();
+        let items = meta.items().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].key, b"\xa9nam");
+        assert_eq!(items[0].values.len(), 1);
+        assert_eq!(items[0].values[0].value(), b"test");
+    }
 
-        let mut data = vec![];
-        let mut metadata = vec![];
-        test_ftyp().build().put_buf(&mut data);
-        test_ftyp().build().put_buf(&mut metadata);
-        let mdat = write_test_mdat(&mut data, b"abcdefg");
+    #[test]
+    fn strip_metadata_items() {
+        let config = Config::builder().strip_metadata_items(true).build();
+        let test = test_mp4().moov(test_moov().udta(test_udta()).clone()).build();
+        let sanitized = sanitize_with_config(test.clone(), config.clone()).unwrap();
+        let metadata = sanitized.metadata.clone().unwrap();
+        let data = sanitized_data(sanitized, &test.data);
 
-        let moov_pos = data.len();
-        test_moov().build().put_buf(&mut data);
-        test_moov().build().put_buf(&mut metadata);
-        BoxHeader::until_eof(MOOV).put_buf(&mut &mut data[moov_pos..]);
+        let mut moov_data = BytesMut::from(&metadata[test_ftyp().build().encoded_len() as usize..]);
+        let mut moov = Mp4Box::<MoovBox>::parse(&mut moov_data).unwrap();
+        let meta = moov
+            .data
+            .parse()
+            .unwrap()
+            .udta_mut()
+            .unwrap()
+            .unwrap()
+            .meta_mut()
+            .unwrap()
+            .unwrap();
+        assert_eq!(meta.child_types().collect::<Vec<_>>(), [BoxType::HDLR]);
+        assert_eq!(meta.items().unwrap(), []);
 
-        let sanitized = sanitize(io::Cursor::new(&data)).unwrap();
-        assert_eq!(sanitized.data, mdat);
-        assert_eq!(sanitized.metadata, Some(metadata));
-        sanitize(io::Cursor::new(sanitized_data(sanitized, &data))).unwrap();
+        // The output is still valid, and is already faststart, but its metadata is still rewritten.
+        let sanitized = sanitize_with_config(io::Cursor::new(&data), config).unwrap();
+        assert_eq!(sanitized.metadata.as_deref(), Some(&data[..metadata.len()]));
+        assert_eq!(sanitize(io::Cursor::new(&data)).unwrap().metadata, None);
     }
 
     #[test]
-    fn until_eof_sized_mdat() {
+    fn strip_trak_metadata_items() {
+        let config = Config::builder().strip_metadata_items(true).build();
         let test = test_mp4()
-            .boxes(&[FTYP, MOOV, MDAT][..])
-            .mdat_data(&b"abcdefg"[..])
-            .mdat_data_until_eof()
+            .moov(test_moov().udta(test_udta()).trak_udta(test_udta()).clone())
             .build();
-        test.sanitize_ok_noop();
+        let sanitized = sanitize_with_config(test.clone(), config).unwrap();
+        let metadata = sanitized.metadata.clone().unwrap();
+        let unstripped_metadata = sanitize(test.clone()).unwrap().metadata.unwrap();
+
+        // Both the `moov` box's `ilst` box and the track's are removed.
+        const ILST_LEN: usize = 36;
+        assert_eq!(metadata.len(), unstripped_metadata.len() - 2 * ILST_LEN);
+        let mut moov_data = BytesMut::from(&metadata[test_ftyp().build().encoded_len() as usize..]);
+        let mut moov = Mp4Box::<MoovBox>::parse(&mut moov_data).unwrap();
+        assert!(!moov.data.parse().unwrap().strip_metadata_items().unwrap());
+        ffmpeg_assert_eq(&sanitized_data(sanitized, &test.data), &test.mdat_data);
     }
 
     #[test]
-    fn skip() {
-        test_mp4().mdat_data(&b"abcdefg"[..]).build().sanitize_ok();
+    fn strip_metadata_items_without_udta() {
+        let config = Config::builder().strip_metadata_items(true).build();
+        let sanitized = test_mp4().build().sanitize_ok_with_config(config);
+        assert!(sanitized.metadata.is_some());
     }
 
     #[test]
-    fn max_input_length() {
-        let mut test = test_mp4().boxes(&[FTYP, MOOV, MDAT][..]).mdat_data(vec![]).clone();
-        let test_data_len = test.mdat_data_len(u64::MAX - 16).build().data.len() as u64;
-        let test = test.mdat_data_len(u64::MAX - test_data_len).build();
-        let sanitized = sanitize(test.clone()).unwrap();
-        assert_eq!(sanitized.data, test.mdat);
-        assert_eq!(sanitized.data.offset + sanitized.data.len, u64::MAX);
-        assert_eq!(sanitized.metadata, None);
+    fn tref_dangling_track_id() {
+        let chap = BoxType::FourCC(FourCC::from_str("chap"));
+        let test = test_mp4().moov(test_moov().tref(test_tref(chap, &[1])).clone()).build();
+        test.sanitize_ok();
+
+        let test = test_mp4().moov(test_moov().tref(test_tref(chap, &[2])).clone()).build();
+        assert_matches!(sanitize(test).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidInput);
+        });
     }
 
-    #[test]
-    fn input_length_overflow() {
-        let mut test = test_mp4().mdat_data(vec![]).clone();
-        let test_data_len = test.mdat_data_len(u64::MAX - 16).build().data.len() as u64;
-        let test = test.mdat_data_len(u64::MAX - test_data_len + 1).build();
-        sanitize(test).unwrap_err();
+    fn sanitized_track_ids(metadata: &[u8]) -> Vec<u32> {
+        let mut moov_data = BytesMut::from(&metadata[test_ftyp().build().encoded_len() as usize..]);
+        let mut moov = Mp4Box::<MoovBox>::parse(&mut moov_data).unwrap();
+        let moov = moov.data.parse().unwrap();
+        let track_ids = moov
+            .traks()
+            .map(|trak| trak.unwrap().tkhd_mut().unwrap().unwrap().track_id());
+        track_ids.collect()
     }
 
     #[test]
-    fn box_size_overflow() {
-        let test = test_mp4().mdat_data_len(u64::MAX - 16).build();
-        sanitize(test).unwrap_err();
+    fn keep_track_ids() {
+        let test = test_mp4().moov(test_moov().trak_count(3).clone()).build();
+        let config = Config::builder().keep_track_ids(HashSet::from([1, 3])).build();
+        let output = sanitize_to_vec(&test.data, config).unwrap();
+        assert_eq!(sanitized_track_ids(&output), [1, 3]);
+        let sanitized = sanitize_slice(&output, Config::default()).unwrap();
+        assert_eq!(sanitized.metadata, None);
+        assert_eq!(sanitized.chunk_count, 2 * test.mdat_data.len() as u64);
+        ffmpeg_assert_eq(&output, &test.mdat_data);
+        gpac_assert_eq(&output, &test.mdat_data);
     }
 
     #[test]
-    fn ftyp_too_large() {
-        let mut compatible_brands = vec![];
-        while compatible_brands.len() * COMPATIBLE_BRAND.value.len() < MAX_FTYP_SIZE as usize {
-            compatible_brands.push(COMPATIBLE_BRAND);
-        }
+    fn keep_track_ids_drops_unreferenced_data() {
+        let test = test_mp4()
+            .boxes(&[FTYP, MDAT, FREE, MDAT, MOOV][..])
+            .moov(test_moov().trak_count(2).clone())
+            .build();
+        let config = Config::builder().keep_track_ids(HashSet::from([2])).build();
+        let output = sanitize_to_vec(&test.data, config).unwrap();
+        assert_eq!(sanitized_track_ids(&output), [2]);
+        let sanitized = sanitize_to_vec(&test.data, Config::default()).unwrap();
+        assert!(
+            output.len() < sanitized.len(),
+            "{} >= {}",
+            output.len(),
+            sanitized.len()
+        );
+        ffmpeg_assert_eq(&output, &test.mdat_data);
+        gpac_assert_eq(&output, &test.mdat_data);
+    }
 
+    #[test]
+    fn keep_track_ids_removes_track_references() {
+        let chap = BoxType::FourCC(FourCC::from_str("chap"));
         let test = test_mp4()
-            .ftyp(test_ftyp().compatible_brands(compatible_brands).clone())
+            .moov(test_moov().trak_count(2).tref(test_tref(chap, &[1, 2])).clone())
             .build();
-        assert_matches!(sanitize(test).unwrap_err(), Error::Parse(err) => {
-            assert_matches!(err.into_inner(), ParseError::InvalidInput);
+        let config = Config::builder().keep_track_ids(HashSet::from([1])).build();
+        let output = sanitize_to_vec(&test.data, config).unwrap();
+        assert_eq!(sanitized_track_ids(&output), [1]);
+        sanitize_slice(&output, Config::default()).unwrap();
+    }
+
+    #[test]
+    fn keep_track_ids_missing() {
+        let test = test_mp4().build();
+        let config = Config::builder().keep_track_ids(HashSet::from([2])).build();
+        assert_matches!(sanitize_to_vec(&test.data, config).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::MissingRequiredBox(BoxType::TRAK));
         });
     }
 
     #[test]
-    fn max_moov_size() {
-        let test_spec = test_mp4().build_spec().unwrap();
-        let config = Config::builder()
-            .max_metadata_size(test_spec.moov().build().encoded_len())
-            .build();
-        test_spec.build().sanitize_ok_with_config(config);
+    fn keep_track_ids_span_output() {
+        let test = test_mp4().moov(test_moov().trak_count(2).clone()).build();
+        let config = Config::builder().keep_track_ids(HashSet::from([1])).build();
+        assert_matches!(sanitize_with_config(test.clone(), config.clone()).unwrap_err(), Error::Io(err) => {
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        });
+        let err = sanitize_with_media_sink(io::Cursor::new(&test.data), config.clone(), io::sink()).unwrap_err();
+        assert_matches!(err, Error::Io(err) => {
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        });
+        assert_eq!(validate(test, config).unwrap(), ValidationOutcome::NeedsRewrite);
     }
 
     #[test]
-    fn moov_too_large() {
-        let test_spec = test_mp4().build_spec().unwrap();
+    fn max_track_count_before_keep_track_ids() {
+        let test = test_mp4().moov(test_moov().trak_count(3).clone()).build();
         let config = Config::builder()
-            .max_metadata_size(test_spec.moov().build().data.encoded_len() - 1)
+            .max_track_count(2)
+            .keep_track_ids(HashSet::from([1]))
             .build();
-        let test = test_spec.build();
-        test.sanitize_ok();
-        assert_matches!(sanitize_with_config(test, config).unwrap_err(), Error::Parse(err) => {
+        assert_matches!(sanitize_to_vec(&test.data, config).unwrap_err(), Error::Parse(err) => {
             assert_matches!(err.into_inner(), ParseError::InvalidInput);
         });
     }
 
     #[test]
-    fn mdat_after_moov() {
-        test_mp4().boxes(&[FTYP, MOOV, MDAT][..]).build().sanitize_ok_noop();
+    fn is_fragmented_init() {
+        let test = test_mp4().build();
+        assert!(!test.sanitize_ok().is_fragmented_init);
+
+        let test = test_mp4()
+            .moov(test_moov().mvex(MvexBox::with_children(vec![])).clone())
+            .build();
+        assert!(test.sanitize_ok().is_fragmented_init);
+
+        let mehd = AnyMp4Box::with_bytes(MEHD, BytesMut::from(&[0, 0, 0, 0, 0, 0, 0x12, 0x34][..]));
+        let test = test_mp4()
+            .moov(test_moov().mvex(MvexBox::with_children(vec![mehd])).clone())
+            .build();
+        assert!(test.sanitize_ok().is_fragmented_init);
     }
 
     #[test]
-    fn no_ftyp() {
-        let test = test_mp4().boxes(&[MOOV, MDAT][..]).build();
+    fn is_fragmented_init_invalid_mehd() {
+        let mehd = AnyMp4Box::with_bytes(MEHD, BytesMut::from(&[2, 0, 0, 0, 0, 0, 0, 0][..]));
+        let test = test_mp4()
+            .moov(test_moov().mvex(MvexBox::with_children(vec![mehd])).clone())
+            .build();
         assert_matches!(sanitize(test).unwrap_err(), Error::Parse(err) => {
-            assert_matches!(err.into_inner(), ParseError::InvalidBoxLayout);
+            assert_matches!(err.into_inner(), ParseError::InvalidInput);
         });
     }
 
     #[test]
-    fn multiple_ftyp() {
-        let test = test_mp4().boxes(&[FTYP, FTYP, MOOV, MDAT][..]).build();
+    fn ctts_sample_count_mismatch() {
+        let sample_count = test_mp4().build().mdat_data.len() as u32;
+        let ctts = CttsBox::from_entries_v0([(sample_count, 1)]);
+        let test = test_mp4().moov(test_moov().ctts(Some(ctts)).clone()).build();
+        test.sanitize_ok();
+
+        let ctts = CttsBox::from_entries_v0([(sample_count - 1, 1)]);
+        let test = test_mp4().moov(test_moov().ctts(Some(ctts)).clone()).build();
         assert_matches!(sanitize(test).unwrap_err(), Error::Parse(err) => {
-            assert_matches!(err.into_inner(), ParseError::InvalidBoxLayout);
+            assert_matches!(err.into_inner(), ParseError::InvalidInput);
         });
     }
 
     #[test]
-    fn ftyp_not_first_box() {
-        let test = test_mp4().boxes(&[FREE, FREE, FTYP, MDAT, MOOV][..]).build();
+    fn stss_sample_number_out_of_range() {
+        let sample_count = test_mp4().build().mdat_data.len() as u32;
+        let test = test_mp4()
+            .moov(test_moov().stss(StssBox::from_iter([1, sample_count])).clone())
+            .build();
         test.sanitize_ok();
+
+        let test = test_mp4()
+            .moov(test_moov().stss(StssBox::from_iter([sample_count + 1])).clone())
+            .build();
+        assert_matches!(sanitize(test).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidInput);
+        });
     }
 
     #[test]
-    fn ftyp_not_first_significant_box() {
-        let test = test_mp4().boxes(&[MOOV, FTYP, MDAT][..]).build();
-        assert_matches!(sanitize(test).unwrap_err(), Error::Parse(err) => {
-            assert_matches!(err.into_inner(), ParseError::InvalidBoxLayout);
+    fn reject_edit_lists_empty_edit() {
+        let elst = ElstBox::from_entries_v1([
+            ElstEntry { segment_duration: 1000, media_time: -1, media_rate_integer: 1, media_rate_fraction: 0 },
+            ElstEntry { segment_duration: 1000, media_time: 0, media_rate_integer: 1, media_rate_fraction: 0 },
+        ]);
+        let test = test_mp4().moov(test_moov().elst(elst).clone()).build();
+        test.sanitize_ok();
+
+        let config = Config::builder().reject_edit_lists(true).build();
+        assert_matches!(sanitize_with_config(test, config).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::UnsupportedBox(BoxType::ELST));
         });
     }
 
     #[test]
-    fn no_moov() {
-        let test = test_mp4().boxes(&[FTYP, MDAT][..]).build();
-        assert_matches!(sanitize(test).unwrap_err(), Error::Parse(err) => {
-            assert_matches!(err.into_inner(), ParseError::MissingRequiredBox(MOOV));
+    fn reject_edit_lists_trimming_edit() {
+        let elst = ElstBox::from_entries_v0([ElstEntry {
+            segment_duration: 1000,
+            media_time: 512,
+            media_rate_integer: 1,
+            media_rate_fraction: 0,
+        }]);
+        let test = test_mp4().moov(test_moov().elst(elst).clone()).build();
+        test.sanitize_ok();
+
+        let config = Config::builder().reject_edit_lists(true).build();
+        assert_matches!(sanitize_with_config(test, config).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::UnsupportedBox(BoxType::ELST));
         });
     }
 
     #[test]
-    fn no_mdat() {
-        let test = test_mp4().boxes(&[FTYP, MOOV][..]).build();
-        assert_matches!(sanitize(test).unwrap_err(), Error::Parse(err) => {
-            assert_matches!(err.into_inner(), ParseError::MissingRequiredBox(MDAT));
+    fn validate_meta_no_hdlr() {
+        let test = test_mp4().build();
+        let mut data = test.data.to_vec();
+        let meta_data = BytesMut::from(&[0; 4][..]);
+        Mp4Box::<MetaBox>::with_data(BoxData::Bytes(meta_data))
+            .unwrap()
+            .put_buf(&mut data);
+        sanitize(io::Cursor::new(&data)).unwrap();
+
+        let config = Config::builder().validate_meta(true).build();
+        assert_matches!(sanitize_with_config(io::Cursor::new(&data), config).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::MissingRequiredBox(BoxType::HDLR));
         });
     }
 
     #[test]
-    fn free_boxes_in_metadata() {
-        let test = test_mp4().boxes(&[FTYP, FREE, SKIP, MDAT, MOOV, FREE][..]).build();
-        test.sanitize_ok();
+    fn multiple_mdat() {
+        test_mp4()
+            .boxes(&[FTYP, MDAT, FREE, MDAT, MDAT, FREE, MOOV][..])
+            .build()
+            .sanitize_ok();
+    }
+
+    /// Returns the types of the top-level boxes making up `span` of `data`, asserting that `span` ends on a box
+    /// boundary.
+    fn span_box_types(data: &[u8], span: InputSpan) -> Vec<BoxType> {
+        let mut span_data = &data[span.offset as usize..][..span.len as usize];
+        let mut box_types = vec![];
+        while !span_data.is_empty() {
+            let header = BoxHeader::parse(span_data).unwrap();
+            box_types.push(header.box_type());
+            span_data = &span_data[header.box_size().unwrap() as usize..];
+        }
+        box_types
+    }
+
+    fn assert_coalesced_span(test: &TestMp4, config: Config, expected_offset: u64, expected_box_types: &[BoxType]) {
+        let sanitized = sanitize_with_config(test.clone(), config).unwrap();
+        assert_eq!(sanitized.data, test.mdat);
+        assert_eq!(sanitized.data.offset, expected_offset);
+        assert_eq!(span_box_types(&test.data, sanitized.data), expected_box_types);
     }
 
     #[test]
-    fn free_boxes_after_mdat() {
-        let test = test_mp4().boxes(&[FTYP, MDAT, SKIP, FREE, MOOV][..]).build();
-        test.sanitize_ok();
+    fn coalesced_span_mdat_meta_mdat() {
+        let ftyp_len = test_ftyp().build().encoded_len();
+        let test = test_mp4().boxes(&[FTYP, MDAT, META, MDAT, MOOV][..]).build();
+        assert_coalesced_span(&test, Config::default(), ftyp_len, &[MDAT, META, MDAT]);
+
+        let config = Config::builder().validate_meta(true).build();
+        assert_coalesced_span(&test, config, ftyp_len, &[MDAT, META, MDAT]);
     }
 
     #[test]
-    fn meta_boxes_in_metadata() {
-        let test = test_mp4().boxes(&[FTYP, MDAT, MOOV, META, MECO][..]).build();
-        test.sanitize_ok();
+    fn coalesced_span_mdat_free_skip() {
+        let ftyp_len = test_ftyp().build().encoded_len();
+        let test = test_mp4()
+            .boxes(&[FTYP, MDAT, FREE, MDAT, SKIP, MDAT, MOOV][..])
+            .build();
+        assert_coalesced_span(&test, Config::default(), ftyp_len, &[MDAT, FREE, MDAT, SKIP, MDAT]);
     }
 
     #[test]
-    fn meta_boxes_after_mdat() {
-        let test = test_mp4().boxes(&[FTYP, MDAT, META, MDAT, MECO, MOOV][..]).build();
+    fn coalesced_span_excludes_non_contiguous_boxes() {
+        let ftyp_len = test_ftyp().build().encoded_len();
+        let free_len = test_free(FREE, 13).encoded_len();
+        let test = test_mp4()
+            .boxes(&[FTYP, FREE, MDAT, MECO, MOOV, FREE, META][..])
+            .build();
+        assert_coalesced_span(&test, Config::default(), ftyp_len + free_len, &[MDAT, MECO]);
+
+        let test = test_mp4().boxes(&[FTYP, MOOV, SKIP, MDAT, FREE][..]).build();
+        let moov_len = test.data.len() as u64 - ftyp_len - test_free(SKIP, 13).encoded_len() - test.mdat.len;
+        let sanitized = sanitize(test.clone()).unwrap();
+        assert_eq!(sanitized.data.offset, ftyp_len + moov_len + free_len);
+        assert_eq!(span_box_types(&test.data, sanitized.data), [MDAT, FREE]);
+    }
+
+    #[test]
+    fn coalesced_span_excludes_leading_free() {
+        let ftyp_len = test_ftyp().build().encoded_len();
+        let free_len = test_free(FREE, 13).encoded_len();
+
+        let test = test_mp4().boxes(&[FTYP, FREE, MDAT, MOOV][..]).build();
+        assert_coalesced_span(&test, Config::default(), ftyp_len + free_len, &[MDAT]);
         test.sanitize_ok();
+
+        let test = test_mp4().boxes(&[FTYP, FREE, MOOV, MDAT][..]).build();
+        let moov_len = test.data.len() as u64 - ftyp_len - free_len - test.mdat.len;
+        assert_coalesced_span(&test, Config::default(), ftyp_len + free_len + moov_len, &[MDAT]);
+        test.sanitize_ok_noop();
     }
 
     #[test]
-    fn multiple_mdat() {
-        test_mp4()
-            .boxes(&[FTYP, MDAT, FREE, MDAT, MDAT, FREE, MOOV][..])
-            .build()
-            .sanitize_ok();
+    fn recover_junk_before_mdat() {
+        let config = Config::builder().recover(true).build();
+        let test = test_mp4()
+            .boxes(&[FTYP, TEST_JUNK, MDAT, MOOV][..])
+            .mdat_data(&b"\0\0\0\x08free"[..])
+            .build();
+        test.sanitize_ok_with_config(config);
+    }
+
+    #[test]
+    fn recover_junk_before_moov() {
+        let config = Config::builder().recover(true).build();
+        let test = test_mp4().boxes(&[FTYP, TEST_JUNK, MOOV, MDAT][..]).build();
+
+        // The junk is replaced with a free box in the metadata, leaving the mdat in place.
+        let sanitized = sanitize_with_config(test.clone(), config).unwrap();
+        assert_eq!(sanitized.data, test.mdat);
+        let sanitized_data = sanitized_data(sanitized, &test.data);
+        assert_eq!(sanitized_data.len(), test.data.len());
+        sanitize(io::Cursor::new(&sanitized_data)).unwrap();
+    }
+
+    #[test]
+    fn recover_trailing_junk() {
+        let config = Config::builder().recover(true).build();
+        let test = test_mp4().boxes(&[FTYP, MDAT, MOOV, TEST_JUNK][..]).build();
+        test.sanitize_ok_with_config(config);
+    }
+
+    #[test]
+    fn recover_junk_between_mdat() {
+        let config = Config::builder().recover(true).build();
+        let test = test_mp4().boxes(&[FTYP, MDAT, TEST_JUNK, MDAT, MOOV][..]).build();
+        assert_matches!(sanitize_with_config(test, config).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::UnsupportedBoxLayout);
+        });
+    }
+
+    #[test]
+    fn recover_truncated_mdat() {
+        let config = Config::builder().recover(true).build();
+        let test = test_mp4()
+            .boxes(&[FTYP, MOOV, MDAT][..])
+            .mdat_data(&b"\0\0\0\x08free\0\0\0\x08free"[..])
+            .build();
+        let truncated = &test.data[..test.data.len() - 1];
+
+        // The truncated mdat is handled as without recovery, rather than scanned for box headers.
+        assert_eq!(
+            sanitize_with_config(io::Cursor::new(truncated), config.clone()).unwrap(),
+            sanitize(io::Cursor::new(truncated)).unwrap(),
+        );
+        assert_matches!(
+            sanitize_with_media_sink(io::Cursor::new(truncated), config, io::sink()).unwrap_err(),
+            Error::Parse(err) => {
+                assert_matches!(err.into_inner(), ParseError::TruncatedBox);
+            }
+        );
+    }
+
+    #[test]
+    fn recover_disabled() {
+        let test = test_mp4().boxes(&[FTYP, TEST_JUNK, MDAT, MOOV][..]).build();
+        assert_matches!(sanitize(test).unwrap_err(), Error::Parse(_));
+    }
+
+    #[test]
+    fn recover_scan_len_exceeded() {
+        init_logger();
+
+        let mut data = vec![];
+        test_ftyp().build().put_buf(&mut data);
+        data.resize(data.len() + MAX_RECOVERY_SCAN_LEN as usize + 16, 0xFF);
+
+        let config = Config::builder().recover(true).build();
+        assert_matches!(sanitize_with_config(io::Cursor::new(data), config).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidInput);
+        });
     }
 
     #[test]
@@ -702,6 +4086,82 @@ mod test {
         });
     }
 
+    #[test]
+    fn chunk_count() {
+        // The test builder adds one chunk per byte of media data to each track.
+        let test = test_mp4().moov(test_moov().trak_count(3).clone()).build();
+        assert_eq!(test.sanitize_ok().chunk_count, 3 * test.mdat_data.len() as u64);
+
+        let test = test_mp4().boxes(&[FTYP, MOOV, MDAT][..]).build();
+        assert_eq!(test.sanitize_ok_noop().chunk_count, test.mdat_data.len() as u64);
+    }
+
+    #[test]
+    fn max_track_count() {
+        let test = test_mp4().moov(test_moov().trak_count(3).clone()).build();
+        let config = Config::builder().max_track_count(3).build();
+        sanitize_with_config(test.clone(), config).unwrap();
+
+        let config = Config::builder().max_track_count(2).build();
+        assert_matches!(sanitize_with_config(test, config).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidInput);
+        });
+    }
+
+    #[test]
+    fn raw_uuid() {
+        let uuid = BoxType::Uuid(BoxUuid { value: *b"customuuidforbox" });
+        let test = test_mp4()
+            .boxes(&[FTYP, MOOV][..])
+            .raw_box(uuid, b"custom uuid box data")
+            .add_box(MDAT)
+            .build();
+        let uuid_box = [
+            &44_u32.to_be_bytes()[..],
+            b"uuid",
+            b"customuuidforbox",
+            b"custom uuid box data",
+        ]
+        .concat();
+        assert!(test.data.windows(uuid_box.len()).any(|window| window == uuid_box));
+        assert_matches!(sanitize(test).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::UnsupportedBox(name) if name == uuid);
+        });
+    }
+
+    #[test]
+    fn known_uuid_skip() {
+        let config = Config::builder()
+            .known_uuid_box((BoxUuid::XMP, UuidPolicy::Skip))
+            .build();
+        let test = test_mp4()
+            .boxes(&[FTYP][..])
+            .raw_box(BoxType::Uuid(BoxUuid::XMP), &b"<x:xmpmeta/>"[..])
+            .add_box(MDAT)
+            .add_box(MOOV)
+            .build();
+        test.sanitize_ok_with_config(config.clone());
+
+        let test = test_mp4().boxes(&[FTYP, MOOV, TEST_UUID, MDAT][..]).build();
+        assert_matches!(sanitize_with_config(test, config).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::UnsupportedBox(TEST_UUID));
+        });
+    }
+
+    #[test]
+    fn known_uuid_reject() {
+        let BoxType::Uuid(test_uuid) = TEST_UUID else {
+            unreachable!()
+        };
+        let config = Config::builder()
+            .known_uuid_box((test_uuid, UuidPolicy::Reject))
+            .build();
+        let test = test_mp4().boxes(&[FTYP, MOOV, TEST_UUID, MDAT][..]).build();
+        assert_matches!(sanitize_with_config(test, config).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::UnsupportedBox(TEST_UUID));
+        });
+    }
+
     #[test]
     fn mp41() {
         let test = test_mp4()
@@ -722,6 +4182,26 @@ mod test {
         test.sanitize_ok();
     }
 
+    #[test]
+    fn ftyp_info() {
+        let ftyp = test_ftyp()
+            .major_brand(MP42)
+            .minor_version(1)
+            .compatible_brands(vec![MP42, ISOM, MP41])
+            .clone();
+        let expected_ftyp = FtypInfo { major_brand: MP42, minor_version: 1, compatible_brands: vec![MP42, ISOM, MP41] };
+
+        let sanitized = test_mp4().ftyp(ftyp.clone()).build().sanitize_ok();
+        assert_eq!(sanitized.ftyp, expected_ftyp);
+
+        let sanitized = test_mp4()
+            .ftyp(ftyp)
+            .boxes(&[FTYP, MOOV, MDAT][..])
+            .build()
+            .sanitize_ok_noop();
+        assert_eq!(sanitized.ftyp, expected_ftyp);
+    }
+
     #[test]
     fn no_compatible_brands() {
         let test = test_mp4()
@@ -2,40 +2,98 @@
 
 mod array;
 mod co64;
+mod colr;
+mod ctts;
+mod edts;
+mod elst;
 pub mod error;
+mod esds;
 mod ftyp;
+mod hdlr;
 mod header;
+mod hvcc;
+mod ilst;
 mod integers;
+mod keys;
+mod mdhd;
 mod mdia;
+mod mehd;
+mod meta;
 mod minf;
 mod moov;
 mod mp4box;
+mod mvex;
+mod mvhd;
+mod nmhd;
+mod pasp;
+mod smhd;
 mod stbl;
 mod stco;
+mod stsc;
+mod stsd;
+mod stss;
+mod stsz;
+mod stz2;
+mod times;
+mod tkhd;
 mod trak;
+mod tref;
+mod udta;
 mod value;
+mod vmhd;
 
 pub use array::{ArrayEntry, ArrayEntryMut, BoundedArray, UnboundedArray};
 pub use co64::Co64Box;
-pub use error::ParseError;
+pub use colr::{ColourInformation, ColrBox};
+pub use ctts::{CttsBox, CttsEntry};
+pub use edts::EdtsBox;
+pub use elst::{ElstBox, ElstEntry};
+pub use error::{ParseError, ParseErrorCode};
+pub use esds::{EsDescriptor, EsdsBox};
 pub use ftyp::FtypBox;
-pub use header::{box_type, fourcc, BoxHeader, BoxSize, BoxType, BoxUuid, ConstFullBoxHeader, FullBoxHeader};
+pub use hdlr::HdlrBox;
+pub use header::{
+    box_type, fourcc, BoxHeader, BoxSize, BoxType, BoxUuid, ConstFullBoxHeader, FullBoxHeader, ParseBoxTypeError,
+};
+pub use hvcc::{HevcDecoderConfigurationRecord, HvccBox};
+pub use ilst::{DataBox, IlstBox};
 pub use integers::Mp4Prim;
+pub use keys::KeysBox;
+pub use mdhd::MdhdBox;
 pub use mdia::MdiaBox;
+pub use mehd::MehdBox;
+pub use meta::{MetaBox, MetadataItem};
 pub use minf::MinfBox;
-pub use moov::MoovBox;
+pub use moov::{displace_chunk_offsets, MoovBox};
 pub use mp4box::{AnyMp4Box, BoxData, Boxes, BoxesValidator, Mp4Box, ParseBox, ParsedBox};
+pub use mvex::MvexBox;
+pub use mvhd::MvhdBox;
+pub use nmhd::NmhdBox;
+pub use pasp::PaspBox;
+pub use smhd::SmhdBox;
 pub use stbl::{StblBox, StblCoMut};
 pub use stco::StcoBox;
+pub use stsc::{StscBox, StscEntry};
+pub use stsd::{AudioSampleEntry, StsdBox, VisualSampleEntry};
+pub use stss::StssBox;
+pub use stsz::StszBox;
+pub use stz2::Stz2Box;
+pub use times::MediaTimes;
+pub use tkhd::TkhdBox;
 pub use trak::TrakBox;
+pub use tref::TrefBox;
+pub use udta::UdtaBox;
 pub use value::{Mp4Value, Mp4ValueReaderExt, Mp4ValueWriterExt};
+pub use vmhd::VmhdBox;
 
 pub use mediasan_common::parse::FourCC;
 pub use mp4san_derive::{ParseBox, ParsedBox};
 
 #[cfg(test)]
 mod test {
-    use bytes::BytesMut;
+    use bytes::{BufMut, BytesMut};
+    use proptest::collection::vec;
+    use proptest::prelude::*;
 
     use super::*;
 
@@ -58,6 +116,7 @@ mod test {
     #[box_type = "xa04"]
     pub struct Fifth;
 
+    #[allow(dead_code)]
     #[derive(Clone, Debug, ParseBox, ParsedBox)]
     #[box_type = "test"]
     pub struct ArrayBox {
@@ -71,7 +130,7 @@ mod test {
         let not_a_real = NotARealBox { bar_ax: u64::MAX, foo_by: u32::MAX };
         assert_eq!(
             not_a_real.encoded_len(),
-            (<u64 as Mp4Prim>::encoded_len() + <u32 as Mp4Prim>::encoded_len()) as u64
+            <u64 as Mp4Prim>::encoded_len() + <u32 as Mp4Prim>::encoded_len()
         );
     }
 
@@ -132,4 +191,60 @@ mod test {
             NotARealBox { bar_ax: 0x0102030405060708, foo_by: 0x090a0b0c }
         );
     }
+
+    /// Encode `parsed_box`, then check that it parses back from its encoding, and that it reports its encoded length
+    /// correctly, returning the parsed box.
+    fn roundtrip<T: ParseBox + ParsedBox>(parsed_box: &T) -> T {
+        let mut buf = BytesMut::new();
+        parsed_box.put_buf(&mut buf);
+        assert_eq!(parsed_box.encoded_len(), buf.len() as u64);
+
+        let mut data = buf.clone();
+        let parsed = T::parse(&mut data).unwrap();
+        assert!(data.is_empty(), "{} trailing bytes after parsing", data.len());
+
+        let mut reencoded = BytesMut::new();
+        parsed.put_buf(&mut reencoded);
+        assert_eq!(reencoded, buf);
+        parsed
+    }
+
+    proptest! {
+        #[test]
+        fn roundtrip_simple(bar_ax: u64, foo_by: u32) {
+            let not_a_real = NotARealBox { bar_ax, foo_by };
+            prop_assert_eq!(roundtrip(&not_a_real), not_a_real);
+        }
+
+        #[test]
+        fn roundtrip_arrays(
+            array_32 in vec(any::<i32>(), 0..64),
+            array_16 in vec(any::<i16>(), 0..64),
+            unbounded_array in vec(any::<u8>(), 0..64),
+        ) {
+            // The 16-bit bounded array can't be collected into, so parse the box from its expected encoding instead.
+            let mut data = BytesMut::new();
+            data.put_u32(array_32.len() as u32);
+            array_32.iter().for_each(|&entry| data.put_i32(entry));
+            data.put_u16(array_16.len() as u16);
+            array_16.iter().for_each(|&entry| data.put_i16(entry));
+            data.put_slice(&unbounded_array);
+            let array_box = ArrayBox::parse(&mut data).unwrap();
+
+            let parsed = roundtrip(&array_box);
+            prop_assert_eq!(parsed.array_32.entries().map(|entry| entry.get().unwrap()).collect::<Vec<_>>(), array_32);
+            prop_assert_eq!(parsed.array_16.entries().map(|entry| entry.get().unwrap()).collect::<Vec<_>>(), array_16);
+            prop_assert_eq!(
+                parsed.unbounded_array.entries().map(|entry| entry.get().unwrap()).collect::<Vec<_>>(),
+                unbounded_array
+            );
+        }
+
+        #[test]
+        fn roundtrip_stco(entries in vec(any::<u32>(), 0..256)) {
+            let mut parsed = roundtrip(&entries.iter().copied().collect::<StcoBox>());
+            prop_assert_eq!(parsed.entry_count() as usize, entries.len());
+            prop_assert_eq!(parsed.entries_mut().map(|entry| entry.get().unwrap()).collect::<Vec<_>>(), entries);
+        }
+    }
 }
@@ -0,0 +1,217 @@
+#![allow(missing_docs)]
+
+use bytes::{Buf, BufMut, BytesMut};
+use derive_more::Display;
+
+use crate::error::Result;
+
+use super::error::WhileParsingBox;
+use super::{BoxType, FourCC, Mp4Prim, Mp4Value, ParseBox, ParseError, ParsedBox};
+
+/// A colour information box, found in a visual sample entry.
+#[derive(Clone, Debug, PartialEq, Eq, ParseBox, ParsedBox)]
+#[box_type = "colr"]
+pub struct ColrBox {
+    pub info: ColourInformation,
+}
+
+/// The colour information in a [`ColrBox`], depending on its colour type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ColourInformation {
+    /// An `nclx` on-screen colour type, with coded colour properties as defined in ISO/IEC 23091-2.
+    Nclx {
+        colour_primaries: u16,
+        transfer_characteristics: u16,
+        matrix_coefficients: u16,
+        /// The full range flag in the most significant bit, followed by 7 reserved bits.
+        full_range_flags: u8,
+    },
+
+    /// A `prof` unrestricted or `rICC` restricted ICC profile.
+    IccProfile { colour_type: FourCC, profile: BytesMut },
+
+    /// A colour type not known to the parser.
+    Unknown { colour_type: FourCC, data: BytesMut },
+}
+
+#[derive(Clone, Copy, Debug, Display)]
+#[display(fmt = "ICC profile size {} does not match colr box size {}", _0, _1)]
+struct IccProfileSizeMismatch(u32, usize);
+
+const NAME: BoxType = BoxType::COLR;
+
+/// The minimum size of an ICC profile, i.e. the size of its header.
+const ICC_PROFILE_HEADER_SIZE: usize = 128;
+
+impl ColrBox {
+    pub fn colour_type(&self) -> FourCC {
+        self.info.colour_type()
+    }
+}
+
+impl ColourInformation {
+    pub const NCLX: FourCC = FourCC::from_str("nclx");
+    pub const PROF: FourCC = FourCC::from_str("prof");
+    pub const RICC: FourCC = FourCC::from_str("rICC");
+
+    pub fn colour_type(&self) -> FourCC {
+        match self {
+            ColourInformation::Nclx { .. } => Self::NCLX,
+            ColourInformation::IccProfile { colour_type, .. } | ColourInformation::Unknown { colour_type, .. } => {
+                *colour_type
+            }
+        }
+    }
+
+    /// Whether an [`Nclx`](Self::Nclx) colour type uses the full range of sample values.
+    pub fn full_range(&self) -> Option<bool> {
+        match self {
+            ColourInformation::Nclx { full_range_flags, .. } => Some(full_range_flags & 0x80 != 0),
+            _ => None,
+        }
+    }
+}
+
+impl Mp4Value for ColourInformation {
+    fn parse(buf: &mut BytesMut) -> Result<Self, ParseError> {
+        let colour_type = <FourCC as Mp4Prim>::parse(&mut *buf)?;
+        match colour_type {
+            Self::NCLX => Ok(Self::Nclx {
+                colour_primaries: Mp4Prim::parse(&mut *buf)?,
+                transfer_characteristics: Mp4Prim::parse(&mut *buf)?,
+                matrix_coefficients: Mp4Prim::parse(&mut *buf)?,
+                full_range_flags: Mp4Prim::parse(&mut *buf)?,
+            }),
+            Self::PROF | Self::RICC => {
+                let profile = buf.split();
+                ensure_attach!(
+                    profile.len() >= ICC_PROFILE_HEADER_SIZE,
+                    ParseError::TruncatedBox,
+                    WhileParsingBox(NAME),
+                );
+                let profile_size = (&profile[..]).get_u32();
+                ensure_attach!(
+                    profile_size as usize == profile.len(),
+                    ParseError::InvalidInput,
+                    IccProfileSizeMismatch(profile_size, profile.len()),
+                    WhileParsingBox(NAME),
+                );
+                Ok(Self::IccProfile { colour_type, profile })
+            }
+            _ => Ok(Self::Unknown { colour_type, data: buf.split() }),
+        }
+    }
+
+    fn encoded_len(&self) -> u64 {
+        FourCC::size()
+            + match self {
+                ColourInformation::Nclx { .. } => 7,
+                ColourInformation::IccProfile { profile: data, .. } | ColourInformation::Unknown { data, .. } => {
+                    data.len() as u64
+                }
+            }
+    }
+
+    fn put_buf<B: BufMut>(&self, mut buf: B) {
+        self.colour_type().put_buf(&mut buf);
+        match self {
+            ColourInformation::Nclx {
+                colour_primaries,
+                transfer_characteristics,
+                matrix_coefficients,
+                full_range_flags,
+            } => {
+                buf.put_u16(*colour_primaries);
+                buf.put_u16(*transfer_characteristics);
+                buf.put_u16(*matrix_coefficients);
+                buf.put_u8(*full_range_flags);
+            }
+            ColourInformation::IccProfile { profile: data, .. } | ColourInformation::Unknown { data, .. } => {
+                buf.put_slice(data)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+
+    use crate::parse::Mp4Box;
+    use crate::util::test::HDR10_NCLX_COLR;
+
+    use super::*;
+
+    fn parse_colr(data: &[u8]) -> Result<ColrBox, ParseError> {
+        let mut mp4box = Mp4Box::<ColrBox>::parse(&mut BytesMut::from(data))?;
+        mp4box.data.parse().cloned()
+    }
+
+    fn test_icc_profile(len: usize) -> BytesMut {
+        let mut profile = BytesMut::zeroed(len);
+        profile[..4].copy_from_slice(&(len as u32).to_be_bytes());
+        profile
+    }
+
+    #[test]
+    fn nclx() {
+        let colr = parse_colr(HDR10_NCLX_COLR).unwrap();
+        assert_eq!(
+            colr.info,
+            ColourInformation::Nclx {
+                colour_primaries: 9,
+                transfer_characteristics: 16,
+                matrix_coefficients: 9,
+                full_range_flags: 0,
+            },
+        );
+        assert_eq!(colr.info.full_range(), Some(false));
+
+        let mut output = BytesMut::new();
+        Mp4Box::with_data(colr.into()).unwrap().put_buf(&mut output);
+        assert_eq!(&output[..], HDR10_NCLX_COLR);
+    }
+
+    #[test]
+    fn nclx_truncated() {
+        let mut data = HDR10_NCLX_COLR[..HDR10_NCLX_COLR.len() - 1].to_vec();
+        data[3] -= 1;
+        assert_matches!(parse_colr(&data).unwrap_err().into_inner(), ParseError::TruncatedBox);
+    }
+
+    #[test]
+    fn nclx_extra_data() {
+        let mut data = HDR10_NCLX_COLR.to_vec();
+        data.push(0);
+        data[3] += 1;
+        assert_matches!(parse_colr(&data).unwrap_err().into_inner(), ParseError::InvalidInput);
+    }
+
+    #[test]
+    fn prof() {
+        let profile = test_icc_profile(ICC_PROFILE_HEADER_SIZE + 4);
+        let colr = ColrBox { info: ColourInformation::IccProfile { colour_type: ColourInformation::PROF, profile } };
+        let mut data = BytesMut::new();
+        Mp4Box::with_data(colr.clone().into()).unwrap().put_buf(&mut data);
+        assert_eq!(parse_colr(&data).unwrap(), colr);
+    }
+
+    #[test]
+    fn prof_size_mismatch() {
+        let mut profile = test_icc_profile(ICC_PROFILE_HEADER_SIZE + 4);
+        profile[3] += 1;
+        let colr = ColrBox { info: ColourInformation::IccProfile { colour_type: ColourInformation::RICC, profile } };
+        let mut data = BytesMut::new();
+        Mp4Box::with_data(colr.into()).unwrap().put_buf(&mut data);
+        assert_matches!(parse_colr(&data).unwrap_err().into_inner(), ParseError::InvalidInput);
+    }
+
+    #[test]
+    fn prof_truncated() {
+        let profile = test_icc_profile(ICC_PROFILE_HEADER_SIZE - 1);
+        let colr = ColrBox { info: ColourInformation::IccProfile { colour_type: ColourInformation::PROF, profile } };
+        let mut data = BytesMut::new();
+        Mp4Box::with_data(colr.into()).unwrap().put_buf(&mut data);
+        assert_matches!(parse_colr(&data).unwrap_err().into_inner(), ParseError::TruncatedBox);
+    }
+}
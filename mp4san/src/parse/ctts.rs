@@ -0,0 +1,151 @@
+#![allow(missing_docs)]
+
+use crate::error::Result;
+
+use super::error::{UnsupportedVersion, WhileParsingBox};
+use super::{BoundedArray, BoxType, FullBoxHeader, ParseBox, ParseError, ParsedBox};
+
+#[derive(Clone, Debug, Default, ParseBox, ParsedBox)]
+#[box_type = "ctts"]
+pub struct CttsBox {
+    header: FullBoxHeader,
+    entries: BoundedArray<u32, [u32; 2]>,
+}
+
+/// An entry in a [`CttsBox`], giving the composition time offset of a run of consecutive samples.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CttsEntry {
+    pub sample_count: u32,
+
+    /// The composition time offset, which is unsigned in version 0 boxes and signed in version 1 boxes.
+    pub sample_offset: i64,
+}
+
+const NAME: BoxType = BoxType::CTTS;
+
+impl CttsBox {
+    /// Construct a version 0 [`CttsBox`] from a list of `(sample_count, sample_offset)` pairs.
+    pub fn from_entries_v0<I: IntoIterator<Item = (u32, u32)>>(entries: I) -> Self {
+        let entries = entries.into_iter().map(|(count, offset)| [count, offset]).collect();
+        Self { header: FullBoxHeader { version: 0, flags: 0 }, entries }
+    }
+
+    /// Construct a version 1 [`CttsBox`] from a list of `(sample_count, sample_offset)` pairs.
+    pub fn from_entries_v1<I: IntoIterator<Item = (u32, i32)>>(entries: I) -> Self {
+        let entries = entries
+            .into_iter()
+            .map(|(count, offset)| [count, offset as u32])
+            .collect();
+        Self { header: FullBoxHeader { version: 1, flags: 0 }, entries }
+    }
+
+    pub fn version(&self) -> u8 {
+        self.header.version
+    }
+
+    pub fn entry_count(&self) -> u32 {
+        self.entries.entry_count()
+    }
+
+    pub fn entries(&self) -> Result<impl ExactSizeIterator<Item = Result<CttsEntry, ParseError>> + '_, ParseError> {
+        let version = self.header.version;
+        ensure_attach!(
+            version <= 1,
+            ParseError::InvalidInput,
+            UnsupportedVersion(version),
+            WhileParsingBox(NAME),
+        );
+        Ok(self.entries.entries().map(move |entry| {
+            let [sample_count, sample_offset] = entry.get()?;
+            let sample_offset = match version {
+                0 => sample_offset.into(),
+                _ => (sample_offset as i32).into(),
+            };
+            Ok(CttsEntry { sample_count, sample_offset })
+        }))
+    }
+
+    /// The total number of samples described by this box.
+    pub fn sample_count(&self) -> Result<u64, ParseError> {
+        self.entries()?.map(|entry| Ok(u64::from(entry?.sample_count))).sum()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use bytes::BytesMut;
+
+    use crate::parse::{ParseBox, ParsedBox, TrakBox};
+    use crate::util::test::test_moov;
+
+    use super::*;
+
+    fn test_trak_ctts(sample_count: usize, ctts: Option<CttsBox>) -> Result<Option<Vec<CttsEntry>>, ParseError> {
+        let mut moov = test_moov().co_entries(vec![0; sample_count]).ctts(ctts).build();
+        let trak: &mut TrakBox = moov.data.parse()?.traks().next().unwrap()?;
+        Ok(trak
+            .ctts_mut()?
+            .map(|ctts| ctts.entries().unwrap().map(Result::unwrap).collect()))
+    }
+
+    #[test]
+    fn roundtrip() {
+        let ctts = CttsBox::from_entries_v1([(1, -1), (2, 3)]);
+        let mut buf = BytesMut::new();
+        ctts.put_buf(&mut buf);
+        let parsed = CttsBox::parse(&mut buf.clone()).unwrap();
+        assert_eq!(parsed.encoded_len(), buf.len() as u64);
+        assert_eq!(parsed.version(), 1);
+        assert_eq!(parsed.entry_count(), 2);
+    }
+
+    #[test]
+    fn no_ctts() {
+        assert_eq!(test_trak_ctts(3, None).unwrap(), None);
+    }
+
+    #[test]
+    fn v0() {
+        let entries = test_trak_ctts(3, Some(CttsBox::from_entries_v0([(2, 1), (1, u32::MAX)]))).unwrap();
+        assert_eq!(
+            entries.unwrap(),
+            [
+                CttsEntry { sample_count: 2, sample_offset: 1 },
+                CttsEntry { sample_count: 1, sample_offset: u32::MAX.into() },
+            ],
+        );
+    }
+
+    #[test]
+    fn v1_negative_offset() {
+        let entries = test_trak_ctts(3, Some(CttsBox::from_entries_v1([(1, 2), (2, -1)]))).unwrap();
+        assert_eq!(
+            entries.unwrap(),
+            [
+                CttsEntry { sample_count: 1, sample_offset: 2 },
+                CttsEntry { sample_count: 2, sample_offset: -1 }
+            ],
+        );
+    }
+
+    #[test]
+    fn sample_count_mismatch() {
+        let err = test_trak_ctts(3, Some(CttsBox::from_entries_v0([(2, 1)]))).unwrap_err();
+        assert_matches!(err.into_inner(), ParseError::InvalidInput);
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let mut ctts = CttsBox::from_entries_v0([(1, 1)]);
+        ctts.header.version = 2;
+        assert_matches!(
+            ctts.entries().map(|_| ()).unwrap_err().into_inner(),
+            ParseError::InvalidInput
+        );
+        assert_matches!(
+            test_trak_ctts(1, Some(ctts)).unwrap_err().into_inner(),
+            ParseError::InvalidInput
+        );
+    }
+}
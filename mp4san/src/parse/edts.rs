@@ -0,0 +1,31 @@
+#![allow(missing_docs)]
+
+use crate::error::Result;
+
+use super::error::ParseResultExt;
+use super::mp4box::Boxes;
+use super::{BoxType, ElstBox, ParseBox, ParseError, ParsedBox};
+
+#[derive(Clone, Debug, ParseBox, ParsedBox)]
+#[box_type = "edts"]
+pub struct EdtsBox {
+    children: Boxes,
+}
+
+const NAME: BoxType = BoxType::EDTS;
+
+impl EdtsBox {
+    #[cfg(test)]
+    pub(crate) fn with_children<C: Into<Boxes>>(children: C) -> Self {
+        Self { children: children.into() }
+    }
+
+    /// Replace the header of each child box with the minimal encoding of its size.
+    pub fn canonicalize_box_sizes(&mut self) -> Result<(), ParseError> {
+        self.children.canonicalize_headers()
+    }
+
+    pub fn elst_mut(&mut self) -> Result<Option<&mut ElstBox>, ParseError> {
+        self.children.get_opt_mut().while_parsing_child(NAME, BoxType::ELST)
+    }
+}
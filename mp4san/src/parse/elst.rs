@@ -0,0 +1,188 @@
+#![allow(missing_docs)]
+
+use crate::error::Result;
+
+use super::error::{UnsupportedVersion, WhereEq, WhileParsingBox};
+use super::{BoxType, FullBoxHeader, ParseBox, ParseError, ParsedBox, UnboundedArray};
+
+/// The edit list box, mapping the presentation timeline of a track to its media timeline.
+///
+/// Version 0 boxes encode the segment duration and media time of each entry as 32-bit values, and version 1 boxes as
+/// 64-bit values.
+#[derive(Clone, Debug, ParseBox, ParsedBox)]
+#[box_type = "elst"]
+pub struct ElstBox {
+    header: FullBoxHeader,
+    entry_count: u32,
+    entries: UnboundedArray<u32>,
+}
+
+/// An entry in an [`ElstBox`], mapping a segment of the presentation timeline to a range of media time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ElstEntry {
+    /// The duration of the segment, in units of the movie timescale.
+    pub segment_duration: u64,
+
+    /// The starting media time of the segment, in units of the media timescale, or `-1` for an empty edit.
+    pub media_time: i64,
+
+    pub media_rate_integer: i16,
+
+    pub media_rate_fraction: i16,
+}
+
+const NAME: BoxType = BoxType::ELST;
+
+impl ElstBox {
+    /// Construct a version 0 [`ElstBox`], truncating the segment durations and media times of `entries` to 32 bits.
+    pub fn from_entries_v0<I: IntoIterator<Item = ElstEntry>>(entries: I) -> Self {
+        let mut words = Vec::new();
+        for entry in entries {
+            words.extend([
+                entry.segment_duration as u32,
+                entry.media_time as u32,
+                entry.media_rate(),
+            ]);
+        }
+        Self::with_words(0, 3, words)
+    }
+
+    /// Construct a version 1 [`ElstBox`].
+    pub fn from_entries_v1<I: IntoIterator<Item = ElstEntry>>(entries: I) -> Self {
+        let mut words = Vec::new();
+        for entry in entries {
+            let ElstEntry { segment_duration, media_time, .. } = entry;
+            words.extend([(segment_duration >> 32) as u32, segment_duration as u32]);
+            words.extend([(media_time >> 32) as u32, media_time as u32, entry.media_rate()]);
+        }
+        Self::with_words(1, 5, words)
+    }
+
+    fn with_words(version: u8, entry_words: usize, words: Vec<u32>) -> Self {
+        let entry_count = (words.len() / entry_words) as u32;
+        Self { header: FullBoxHeader { version, flags: 0 }, entry_count, entries: words.into_iter().collect() }
+    }
+
+    pub fn version(&self) -> u8 {
+        self.header.version
+    }
+
+    pub fn entry_count(&self) -> u32 {
+        self.entry_count
+    }
+
+    pub fn entries(&self) -> Result<impl ExactSizeIterator<Item = Result<ElstEntry, ParseError>> + '_, ParseError> {
+        let version = self.header.version;
+        let entry_words = match version {
+            0 => 3,
+            1 => 5,
+            _ => bail_attach!(
+                ParseError::InvalidInput,
+                UnsupportedVersion(version),
+                WhileParsingBox(NAME)
+            ),
+        };
+        let entries_len = self.entries.entry_count() / entry_words;
+        ensure_attach!(
+            self.entries.entry_count() % entry_words == 0 && entries_len as u64 == u64::from(self.entry_count),
+            ParseError::InvalidInput,
+            "entry count does not match entries length",
+            WhereEq("entry count", self.entry_count),
+            WhileParsingBox(NAME),
+        );
+
+        let mut words = self.entries.entries();
+        Ok((0..entries_len).map(move |_| {
+            let mut next_word = || words.next().unwrap_or_else(|| unreachable!()).get();
+            let (segment_duration, media_time) = match version {
+                0 => (next_word()?.into(), (next_word()? as i32).into()),
+                _ => {
+                    let segment_duration = u64::from(next_word()?) << 32 | u64::from(next_word()?);
+                    let media_time = u64::from(next_word()?) << 32 | u64::from(next_word()?);
+                    (segment_duration, media_time as i64)
+                }
+            };
+            let media_rate = next_word()?;
+            Ok(ElstEntry {
+                segment_duration,
+                media_time,
+                media_rate_integer: (media_rate >> 16) as i16,
+                media_rate_fraction: media_rate as i16,
+            })
+        }))
+    }
+}
+
+impl ElstEntry {
+    /// Whether this entry is an empty edit, which inserts a delay without presenting any media.
+    pub fn is_empty_edit(&self) -> bool {
+        self.media_time == -1
+    }
+
+    fn media_rate(&self) -> u32 {
+        u32::from(self.media_rate_integer as u16) << 16 | u32::from(self.media_rate_fraction as u16)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use bytes::BytesMut;
+
+    use super::*;
+
+    const ENTRIES: [ElstEntry; 2] = [
+        ElstEntry { segment_duration: 1000, media_time: -1, media_rate_integer: 1, media_rate_fraction: 0 },
+        ElstEntry { segment_duration: 2000, media_time: 512, media_rate_integer: -1, media_rate_fraction: 0x4000 },
+    ];
+
+    fn roundtrip(elst: ElstBox) -> ElstBox {
+        let mut buf = BytesMut::new();
+        elst.put_buf(&mut buf);
+        let parsed = ElstBox::parse(&mut buf.clone()).unwrap();
+        assert_eq!(parsed.encoded_len(), buf.len() as u64);
+        parsed
+    }
+
+    #[test]
+    fn v0() {
+        let elst = roundtrip(ElstBox::from_entries_v0(ENTRIES));
+        assert_eq!(elst.version(), 0);
+        assert_eq!(elst.encoded_len(), 4 + 4 + 2 * 12);
+        let entries: Vec<_> = elst.entries().unwrap().map(Result::unwrap).collect();
+        assert_eq!(entries, ENTRIES);
+        assert!(entries[0].is_empty_edit());
+        assert!(!entries[1].is_empty_edit());
+    }
+
+    #[test]
+    fn v1() {
+        let mut entries = ENTRIES;
+        entries[1].segment_duration = u64::MAX - 1;
+        entries[1].media_time = i64::MAX;
+        let elst = roundtrip(ElstBox::from_entries_v1(entries));
+        assert_eq!(elst.version(), 1);
+        assert_eq!(elst.encoded_len(), 4 + 4 + 2 * 20);
+        assert_eq!(elst.entries().unwrap().map(Result::unwrap).collect::<Vec<_>>(), entries);
+    }
+
+    #[test]
+    fn entry_count_mismatch() {
+        let mut elst = ElstBox::from_entries_v0(ENTRIES);
+        elst.entry_count = 3;
+        assert_matches!(
+            elst.entries().map(|_| ()).unwrap_err().into_inner(),
+            ParseError::InvalidInput
+        );
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let mut elst = ElstBox::from_entries_v0(ENTRIES);
+        elst.header.version = 2;
+        assert_matches!(
+            elst.entries().map(|_| ()).unwrap_err().into_inner(),
+            ParseError::InvalidInput
+        );
+    }
+}
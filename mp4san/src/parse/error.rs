@@ -16,6 +16,14 @@ use super::{BoxType, FourCC};
 #[allow(missing_docs)]
 #[derive(Clone, Debug, thiserror::Error)]
 pub enum ParseError {
+    /// The deadline given to [`sanitize_async_with_deadline`](crate::sanitize_async_with_deadline) passed before
+    /// sanitization completed.
+    ///
+    /// Unlike [`InvalidInput`](Self::InvalidInput), the input may be valid, and could be sanitized with a later
+    /// deadline.
+    #[error("Deadline exceeded")]
+    DeadlineExceeded,
+
     /// The input is invalid because its boxes are in a ordering or configuration disallowed by the ISO specification.
     #[error("Invalid box layout")]
     InvalidBoxLayout,
@@ -24,6 +32,20 @@ pub enum ParseError {
     #[error("Invalid input")]
     InvalidInput,
 
+    /// The input's metadata (`moov`, or a top-level `meta` when [validated](crate::Config::validate_meta)) is larger
+    /// than the configured [`max_metadata_size`](crate::Config::max_metadata_size).
+    ///
+    /// Unlike [`InvalidInput`](Self::InvalidInput), the input may be valid, and could be sanitized with a higher limit.
+    /// Setting [`max_metadata_size`](crate::Config::max_metadata_size) to at least `size` allows the box to be read on
+    /// a retry, though a later box may still exceed it.
+    #[error("Metadata too large: {size} > {limit}")]
+    MetadataTooLarge {
+        /// The size of the box's data, excluding its header.
+        size: u64,
+        /// The configured [`max_metadata_size`](crate::Config::max_metadata_size).
+        limit: u64,
+    },
+
     /// The input is invalid because it is missing a box required by the ISO specification.
     #[error("Missing required `{_0}` box")]
     MissingRequiredBox(BoxType),
@@ -48,6 +70,40 @@ pub enum ParseError {
     UnsupportedFormat(FourCC),
 }
 
+/// A machine-readable code identifying the kind of a [`ParseError`], as returned by [`ParseError::code`].
+///
+/// Unlike [`ParseError`], the codes carry no context, and are suitable e.g. as labels for metrics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ParseErrorCode {
+    /// See [`ParseError::DeadlineExceeded`].
+    DeadlineExceeded,
+
+    /// See [`ParseError::InvalidBoxLayout`].
+    InvalidBoxLayout,
+
+    /// See [`ParseError::InvalidInput`].
+    InvalidInput,
+
+    /// See [`ParseError::MetadataTooLarge`].
+    MetadataTooLarge,
+
+    /// See [`ParseError::MissingRequiredBox`].
+    MissingRequiredBox,
+
+    /// See [`ParseError::TruncatedBox`].
+    TruncatedBox,
+
+    /// See [`ParseError::UnsupportedBox`].
+    UnsupportedBox,
+
+    /// See [`ParseError::UnsupportedBoxLayout`].
+    UnsupportedBoxLayout,
+
+    /// See [`ParseError::UnsupportedFormat`].
+    UnsupportedFormat,
+}
+
 #[doc(hidden)]
 /// Used by the derive macros' generated code.
 pub trait __ParseResultExt: ResultExt + Sized {
@@ -92,12 +148,57 @@ pub(crate) struct WhileParsingField<T>(pub(crate) BoxType, pub(crate) T);
 #[display(fmt = "while parsing `{}` box child `{}`", _0, _1)]
 pub(crate) struct WhileParsingChild(pub(crate) BoxType, pub(crate) BoxType);
 
+#[derive(Clone, Copy, Debug, Display)]
+#[display(fmt = "unsupported box version `{}`", _0)]
+pub(crate) struct UnsupportedVersion(pub(crate) u8);
+
 #[derive(Clone, Copy, Debug, Display)]
 #[display(fmt = "where `{} = {}`", _0, _1)]
 pub(crate) struct WhereEq<T, U>(pub(crate) T, pub(crate) U);
 
+impl ParseError {
+    /// Returns the [`ParseErrorCode`] identifying the kind of this error.
+    pub fn code(&self) -> ParseErrorCode {
+        match self {
+            Self::DeadlineExceeded => ParseErrorCode::DeadlineExceeded,
+            Self::InvalidBoxLayout => ParseErrorCode::InvalidBoxLayout,
+            Self::InvalidInput => ParseErrorCode::InvalidInput,
+            Self::MetadataTooLarge { .. } => ParseErrorCode::MetadataTooLarge,
+            Self::MissingRequiredBox(_) => ParseErrorCode::MissingRequiredBox,
+            Self::TruncatedBox => ParseErrorCode::TruncatedBox,
+            Self::UnsupportedBox(_) => ParseErrorCode::UnsupportedBox,
+            Self::UnsupportedBoxLayout => ParseErrorCode::UnsupportedBoxLayout,
+            Self::UnsupportedFormat(_) => ParseErrorCode::UnsupportedFormat,
+        }
+    }
+}
+
 impl ReportableError for ParseError {
     type Stack = ReportStack;
 }
 
 impl<T> ParseResultExt for Result<T, ParseError> {}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn distinct_codes() {
+        let errors = [
+            ParseError::DeadlineExceeded,
+            ParseError::InvalidBoxLayout,
+            ParseError::InvalidInput,
+            ParseError::MetadataTooLarge { size: 2, limit: 1 },
+            ParseError::MissingRequiredBox(BoxType::MOOV),
+            ParseError::TruncatedBox,
+            ParseError::UnsupportedBox(BoxType::MOOV),
+            ParseError::UnsupportedBoxLayout,
+            ParseError::UnsupportedFormat(FourCC { value: *b"isom" }),
+        ];
+        let codes = errors.iter().map(ParseError::code).collect::<HashSet<_>>();
+        assert_eq!(codes.len(), errors.len());
+    }
+}
@@ -0,0 +1,275 @@
+#![allow(missing_docs)]
+
+use bytes::{Buf, BufMut, BytesMut};
+use derive_more::Display;
+
+use crate::error::Result;
+
+use super::error::WhileParsingBox;
+use super::{BoxType, ConstFullBoxHeader, Mp4Value, ParseBox, ParseError, ParsedBox};
+
+/// An elementary stream descriptor box, found in an MPEG-4 audio sample entry, e.g. `mp4a`.
+#[derive(Clone, Debug, ParseBox, ParsedBox)]
+#[box_type = "esds"]
+pub struct EsdsBox {
+    header: ConstFullBoxHeader,
+    pub descriptor: EsDescriptor,
+}
+
+/// The `ES_Descriptor` in an [`EsdsBox`], as defined in ISO/IEC 14496-1.
+///
+/// The lengths of the descriptor and each of its nested descriptors are validated to be consistent when parsed. The
+/// descriptor is re-encoded verbatim, preserving any non-minimal encodings of its lengths.
+#[derive(Clone, Debug)]
+pub struct EsDescriptor {
+    es_id: u16,
+    object_type_indication: u8,
+    stream_type: u8,
+    decoder_specific_info: Option<BytesMut>,
+    data: BytesMut,
+}
+
+#[derive(Clone, Copy, Debug, Display)]
+#[display(fmt = "descriptor tag 0x{:02x} length {} exceeds {} available bytes", _0, _1, _2)]
+struct DescriptorLengthTooLarge(u8, usize, usize);
+
+#[derive(Clone, Copy, Debug, Display)]
+#[display(fmt = "expected descriptor tag 0x{:02x}, found 0x{:02x}", _0, _1)]
+struct UnexpectedDescriptorTag(u8, u8);
+
+const NAME: BoxType = BoxType::ESDS;
+
+/// The tag of an `ES_Descriptor`.
+const ES_DESCRIPTOR_TAG: u8 = 0x03;
+
+/// The tag of a `DecoderConfigDescriptor`.
+const DECODER_CONFIG_DESCRIPTOR_TAG: u8 = 0x04;
+
+/// The tag of a `DecoderSpecificInfo` descriptor.
+const DECODER_SPECIFIC_INFO_TAG: u8 = 0x05;
+
+/// The length of the fields of a `DecoderConfigDescriptor` preceding its nested descriptors.
+const DECODER_CONFIG_FIELDS_LEN: usize = 13;
+
+/// The maximum number of bytes used to encode a descriptor's length.
+const MAX_DESCRIPTOR_LENGTH_BYTES: usize = 4;
+
+impl EsDescriptor {
+    pub fn es_id(&self) -> u16 {
+        self.es_id
+    }
+
+    /// The object type of the stream, e.g. `0x40` for MPEG-4 audio.
+    pub fn object_type_indication(&self) -> u8 {
+        self.object_type_indication
+    }
+
+    /// The type of the stream, e.g. `0x05` for an audio stream.
+    pub fn stream_type(&self) -> u8 {
+        self.stream_type
+    }
+
+    /// The decoder-specific configuration, e.g. an `AudioSpecificConfig` for AAC, if present.
+    pub fn decoder_specific_info(&self) -> Option<&[u8]> {
+        self.decoder_specific_info.as_deref()
+    }
+}
+
+impl Mp4Value for EsDescriptor {
+    fn parse(buf: &mut BytesMut) -> Result<Self, ParseError> {
+        let data = buf.split();
+        let mut input = &data[..];
+
+        let mut es_descriptor = parse_descriptor(&mut input)?;
+        ensure_attach!(
+            es_descriptor.tag == ES_DESCRIPTOR_TAG,
+            ParseError::InvalidInput,
+            UnexpectedDescriptorTag(ES_DESCRIPTOR_TAG, es_descriptor.tag),
+            WhileParsingBox(NAME),
+        );
+        let body = &mut es_descriptor.body;
+        ensure_attach!(body.remaining() >= 3, ParseError::TruncatedBox, WhileParsingBox(NAME));
+        let es_id = body.get_u16();
+        let flags = body.get_u8();
+        let stream_dependence_len = if flags & 0x80 != 0 { 2 } else { 0 };
+        let ocr_stream_len = if flags & 0x20 != 0 { 2 } else { 0 };
+        ensure_attach!(
+            body.remaining() >= stream_dependence_len,
+            ParseError::TruncatedBox,
+            WhileParsingBox(NAME),
+        );
+        body.advance(stream_dependence_len);
+        if flags & 0x40 != 0 {
+            ensure_attach!(body.has_remaining(), ParseError::TruncatedBox, WhileParsingBox(NAME));
+            let url_len = body.get_u8() as usize;
+            ensure_attach!(
+                body.remaining() >= url_len,
+                ParseError::TruncatedBox,
+                WhileParsingBox(NAME)
+            );
+            body.advance(url_len);
+        }
+        ensure_attach!(
+            body.remaining() >= ocr_stream_len,
+            ParseError::TruncatedBox,
+            WhileParsingBox(NAME)
+        );
+        body.advance(ocr_stream_len);
+
+        let mut decoder_config = None;
+        while body.has_remaining() {
+            let descriptor = parse_descriptor(body)?;
+            if descriptor.tag == DECODER_CONFIG_DESCRIPTOR_TAG && decoder_config.is_none() {
+                decoder_config = Some(descriptor.body);
+            }
+        }
+        let Some(mut decoder_config) = decoder_config else {
+            bail_attach!(
+                ParseError::InvalidInput,
+                "missing decoder config descriptor",
+                WhileParsingBox(NAME),
+            );
+        };
+        ensure_attach!(
+            decoder_config.remaining() >= DECODER_CONFIG_FIELDS_LEN,
+            ParseError::TruncatedBox,
+            "decoder config descriptor too short",
+            WhileParsingBox(NAME),
+        );
+        let object_type_indication = decoder_config.get_u8();
+        let stream_type = decoder_config.get_u8() >> 2;
+        decoder_config.advance(DECODER_CONFIG_FIELDS_LEN - 2);
+
+        let mut decoder_specific_info = None;
+        while decoder_config.has_remaining() {
+            let descriptor = parse_descriptor(&mut decoder_config)?;
+            if descriptor.tag == DECODER_SPECIFIC_INFO_TAG && decoder_specific_info.is_none() {
+                decoder_specific_info = Some(BytesMut::from(descriptor.body));
+            }
+        }
+
+        Ok(Self { es_id, object_type_indication, stream_type, decoder_specific_info, data })
+    }
+
+    fn encoded_len(&self) -> u64 {
+        self.data.len() as u64
+    }
+
+    fn put_buf<B: BufMut>(&self, mut buf: B) {
+        buf.put_slice(&self.data);
+    }
+}
+
+/// A descriptor's tag and body, as parsed by [`parse_descriptor`].
+struct Descriptor<'a> {
+    tag: u8,
+    body: &'a [u8],
+}
+
+/// Parse a descriptor's tag and variable-length size from `input`, returning its tag and body and advancing `input`
+/// past it.
+fn parse_descriptor<'a>(input: &mut &'a [u8]) -> Result<Descriptor<'a>, ParseError> {
+    ensure_attach!(input.has_remaining(), ParseError::TruncatedBox, WhileParsingBox(NAME));
+    let tag = input.get_u8();
+
+    let mut len = 0;
+    let mut length_bytes = 0;
+    loop {
+        ensure_attach!(input.has_remaining(), ParseError::TruncatedBox, WhileParsingBox(NAME));
+        ensure_attach!(
+            length_bytes < MAX_DESCRIPTOR_LENGTH_BYTES,
+            ParseError::InvalidInput,
+            "descriptor length too long",
+            WhileParsingBox(NAME),
+        );
+        let byte = input.get_u8();
+        len = (len << 7) | usize::from(byte & 0x7f);
+        length_bytes += 1;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+
+    ensure_attach!(
+        len <= input.len(),
+        ParseError::TruncatedBox,
+        DescriptorLengthTooLarge(tag, len, input.len()),
+        WhileParsingBox(NAME),
+    );
+    let (body, rest) = input.split_at(len);
+    *input = rest;
+    Ok(Descriptor { tag, body })
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+
+    use crate::parse::Mp4Box;
+    use crate::util::test::TEST_ESDS;
+
+    use super::*;
+
+    fn parse_esds(data: &[u8]) -> Result<EsdsBox, ParseError> {
+        let mut mp4box = Mp4Box::<EsdsBox>::parse(&mut BytesMut::from(data))?;
+        mp4box.data.parse().cloned()
+    }
+
+    #[test]
+    fn esds() {
+        let esds = parse_esds(TEST_ESDS).unwrap();
+        assert_eq!(esds.descriptor.es_id(), 1);
+        assert_eq!(esds.descriptor.object_type_indication(), 0x40);
+        assert_eq!(esds.descriptor.stream_type(), 0x05);
+        assert_eq!(esds.descriptor.decoder_specific_info(), Some(&[0x12, 0x10][..]));
+
+        let mut encoded = BytesMut::new();
+        Mp4Box::with_data(esds.into()).unwrap().put_buf(&mut encoded);
+        assert_eq!(&encoded[..], TEST_ESDS);
+    }
+
+    #[test]
+    fn non_minimal_lengths() {
+        let mut data = b"\0\0\0\0esds\0\0\0\0".to_vec();
+        data.extend_from_slice(b"\x03\x80\x80\x80\x1c\0\x01\0");
+        data.extend_from_slice(b"\x04\x80\x80\x80\x14\x40\x15\0\0\0\0\0\0\0\0\0\0\0");
+        data.extend_from_slice(b"\x05\x80\x80\x80\x02\x12\x10");
+        data[3] = data.len() as u8;
+        let esds = parse_esds(&data).unwrap();
+        assert_eq!(esds.descriptor.decoder_specific_info(), Some(&[0x12, 0x10][..]));
+    }
+
+    #[test]
+    fn truncated_decoder_config() {
+        let mut data = TEST_ESDS.to_vec();
+        // Drop the last byte, leaving the descriptor lengths pointing past the end of the box.
+        data.pop();
+        data[3] -= 1;
+        assert_matches!(parse_esds(&data).unwrap_err().into_inner(), ParseError::TruncatedBox);
+    }
+
+    #[test]
+    fn short_decoder_config() {
+        let mut data = b"\0\0\0\0esds\0\0\0\0".to_vec();
+        data.extend_from_slice(b"\x03\x0a\0\x01\0");
+        data.extend_from_slice(b"\x04\x05\x40\x15\0\0\0");
+        data[3] = data.len() as u8;
+        assert_matches!(parse_esds(&data).unwrap_err().into_inner(), ParseError::TruncatedBox);
+    }
+
+    #[test]
+    fn missing_decoder_config() {
+        let mut data = b"\0\0\0\0esds\0\0\0\0".to_vec();
+        data.extend_from_slice(b"\x03\x06\0\x01\0\x06\x01\x02");
+        data[3] = data.len() as u8;
+        assert_matches!(parse_esds(&data).unwrap_err().into_inner(), ParseError::InvalidInput);
+    }
+
+    #[test]
+    fn overlong_length() {
+        let mut data = b"\0\0\0\0esds\0\0\0\0".to_vec();
+        data.extend_from_slice(b"\x03\x80\x80\x80\x80\x01");
+        data[3] = data.len() as u8;
+        assert_matches!(parse_esds(&data).unwrap_err().into_inner(), ParseError::InvalidInput);
+    }
+}
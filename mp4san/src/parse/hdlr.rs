@@ -0,0 +1,51 @@
+#![allow(missing_docs)]
+
+use super::{ConstFullBoxHeader, FourCC, ParseBox, ParsedBox, UnboundedArray};
+
+/// The handler reference box, declaring the type of the media in a track or of the metadata in a [`MetaBox`].
+///
+/// [`MetaBox`]: super::MetaBox
+#[derive(Clone, Debug, ParseBox, ParsedBox)]
+#[box_type = "hdlr"]
+pub struct HdlrBox {
+    header: ConstFullBoxHeader,
+    pre_defined: u32,
+    handler_type: FourCC,
+    reserved: [u32; 3],
+    name: UnboundedArray<u8>,
+}
+
+impl HdlrBox {
+    pub fn handler_type(&self) -> FourCC {
+        self.handler_type
+    }
+
+    /// The human-readable name of the handler, as null-terminated UTF-8, though this is not validated.
+    pub fn name(&self) -> &[u8] {
+        self.name.as_bytes()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::BytesMut;
+
+    use crate::parse::{fourcc, ParseBox, ParsedBox};
+    use crate::util::test::write_hdlr_data;
+
+    use super::HdlrBox;
+
+    #[test]
+    fn roundtrip() {
+        let mut buf = BytesMut::new();
+        write_hdlr_data(&mut buf, fourcc::META);
+        let hdlr = HdlrBox::parse(&mut buf.clone()).unwrap();
+        assert_eq!(hdlr.handler_type(), fourcc::META);
+        assert_eq!(hdlr.name(), b"\0");
+        assert_eq!(hdlr.encoded_len(), buf.len() as u64);
+
+        let mut output = BytesMut::new();
+        hdlr.put_buf(&mut output);
+        assert_eq!(output, buf);
+    }
+}
@@ -1,6 +1,7 @@
 use std::fmt;
 use std::io;
 use std::mem::size_of;
+use std::str::FromStr;
 
 use bytes::{Buf, BufMut};
 use derive_more::{Display, From};
@@ -28,7 +29,8 @@ pub enum BoxSize {
 }
 
 /// An MP4 box type.
-#[derive(Clone, Copy, Debug, Display, From, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Display, From, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(untagged))]
 pub enum BoxType {
     /// A box type in four-byte character code form.
     FourCC(FourCC),
@@ -37,8 +39,13 @@ pub enum BoxType {
     Uuid(BoxUuid),
 }
 
+/// An error returned when parsing a [`BoxType`] from a string fails.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("invalid box type `{_0}`: expected a four-character code or hyphenated UUID")]
+pub struct ParseBoxTypeError(String);
+
 /// An MP4 box type as a UUID.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 #[repr(transparent)]
 pub struct BoxUuid {
     /// The UUID, as an array of 16 bytes.
@@ -86,6 +93,13 @@ impl BoxHeader {
         Ok(Self { box_type, box_size: BoxSize::Ext(box_size) })
     }
 
+    /// A header encoding a box of `data_size` bytes of data with an extended 64-bit size, even if it would fit in 32
+    /// bits.
+    pub const fn with_ext_data_size(box_type: BoxType, data_size: u64) -> Self {
+        let header_len = Self { box_type, box_size: BoxSize::Ext(0) }.encoded_len();
+        Self { box_type, box_size: BoxSize::Ext(data_size + header_len) }
+    }
+
     #[cfg(test)]
     pub const fn until_eof(box_type: BoxType) -> Self {
         Self { box_type, box_size: BoxSize::UntilEof }
@@ -231,32 +245,90 @@ macro_rules! box_type {
 }
 
 box_type! {
+    AVC1,
     CO64,
+    COLR,
+    CTTS,
+    DATA,
     DINF,
     DREF,
+    EDTS,
+    ELST,
+    ENCA,
+    ENCV,
+    ESDS,
     FREE,
     FTYP,
+    GMHD,
     HDLR,
+    HEV1,
+    HVC1,
+    ILST,
+    KEYS,
     MDAT,
     MDHD,
     MDIA,
     MECO,
+    MEHD,
     META,
     METT,
     MINF,
     MOOV,
+    MP4A,
+    MVEX,
     MVHD,
+    NMHD,
+    PASP,
+    PSSH,
+    SAIO,
+    SAIZ,
+    SENC,
     SKIP,
+    SMHD,
     STBL,
     STCO,
     STSC,
     STSD,
+    STSS,
     STSZ,
+    STZ2,
     STTS,
     TKHD,
     TRAK,
+    TREF,
+    UDTA,
     URL,
     UUID,
+    VMHD,
+}
+
+impl FromStr for BoxType {
+    type Err = ParseBoxTypeError;
+
+    /// Parse a [`BoxType`] from either a four-character code, e.g. `"moov"`, or a hyphenated UUID, e.g.
+    /// `"b7d87c37-3a9b-4f6e-8653-8d340a455b0f"`.
+    ///
+    /// Each character of a four-character code is taken as a single byte, so characters up to `U+00FF` can be used, as
+    /// in the iTunes-style metadata item type `"©nam"`, whose first byte is `0xa9`.
+    fn from_str(string: &str) -> std::result::Result<Self, Self::Err> {
+        let bytes = string
+            .chars()
+            .map(u8::try_from)
+            .collect::<std::result::Result<Vec<_>, _>>();
+        if let Some(value) = bytes.ok().and_then(|bytes| bytes.try_into().ok()) {
+            return Ok(Self::FourCC(FourCC { value }));
+        }
+        string.parse().map(Self::Uuid)
+    }
+}
+
+impl BoxUuid {
+    /// The user type of the `uuid` box carrying XMP metadata, `be7acfcb-97a9-42e8-9c71-999491e3afac`.
+    pub const XMP: Self = Self {
+        value: [
+            0xbe, 0x7a, 0xcf, 0xcb, 0x97, 0xa9, 0x42, 0xe8, 0x9c, 0x71, 0x99, 0x94, 0x91, 0xe3, 0xaf, 0xac,
+        ],
+    };
 }
 
 impl fmt::Display for BoxUuid {
@@ -269,6 +341,45 @@ impl fmt::Display for BoxUuid {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for BoxUuid {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for BoxUuid {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        use serde::de::{Error, Unexpected};
+
+        let string = String::deserialize(deserializer)?;
+        string
+            .parse()
+            .map_err(|_| D::Error::invalid_value(Unexpected::Str(&string), &"a hyphenated UUID"))
+    }
+}
+
+impl FromStr for BoxUuid {
+    type Err = ParseBoxTypeError;
+
+    /// Parse a [`BoxUuid`] from its hyphenated form, e.g. `"b7d87c37-3a9b-4f6e-8653-8d340a455b0f"`.
+    fn from_str(string: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || ParseBoxTypeError(string.to_string());
+        let group_lens = string.split('-').map(str::len);
+        let hex = string.bytes().filter(|&byte| byte != b'-').collect::<Vec<_>>();
+        if !group_lens.eq([8, 4, 4, 4, 12]) || !hex.iter().all(u8::is_ascii_hexdigit) {
+            return Err(invalid());
+        }
+        let mut value = [0; 16];
+        for (byte, hex_byte) in value.iter_mut().zip(hex.chunks_exact(2)) {
+            let hex_byte = std::str::from_utf8(hex_byte).map_err(|_| invalid())?;
+            *byte = u8::from_str_radix(hex_byte, 16).map_err(|_| invalid())?;
+        }
+        Ok(Self { value })
+    }
+}
+
 #[allow(missing_docs)]
 impl FullBoxHeader {
     pub const fn default() -> Self {
@@ -329,3 +440,86 @@ impl<const VERSION: u8, const FLAGS: u32> Mp4Prim for ConstFullBoxHeader<VERSION
         out.put_uint(FLAGS.into(), 3);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn box_type_from_str_fourcc() {
+        assert_eq!("moov".parse(), Ok(BoxType::MOOV));
+        assert_eq!("url ".parse(), Ok(BoxType::URL));
+        assert_eq!(
+            "\u{a9}nam".parse(),
+            Ok(BoxType::FourCC(FourCC { value: [0xa9, b'n', b'a', b'm'] }))
+        );
+    }
+
+    #[test]
+    fn box_type_from_str_uuid() {
+        let value = [
+            0xb7, 0xd8, 0x7c, 0x37, 0x3a, 0x9b, 0x4f, 0x6e, 0x86, 0x53, 0x8d, 0x34, 0x0a, 0x45, 0x5b, 0x0f,
+        ];
+        let expected = BoxType::Uuid(BoxUuid { value });
+        assert_eq!("b7d87c37-3a9b-4f6e-8653-8d340a455b0f".parse(), Ok(expected));
+        assert_eq!("B7D87C37-3A9B-4F6E-8653-8D340A455B0F".parse(), Ok(expected));
+    }
+
+    #[test]
+    fn box_type_from_str_invalid() {
+        for string in [
+            "",
+            "url",
+            "moov2",
+            "\u{a9}na",
+            "\u{20ac}nam",
+            "b7d87c373a9b4f6e86538d340a455b0f",
+            "b7d87c37-3a9b-4f6e-8653-8d340a455b0",
+            "b7d87c37-3a9b-4f6e-8653-8d340a455b0g",
+            "+7d87c37-3a9b-4f6e-8653-8d340a455b0f",
+        ] {
+            assert_eq!(
+                string.parse::<BoxType>(),
+                Err(ParseBoxTypeError(string.to_string())),
+                "{string}"
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_box_type_fourcc() {
+        let json = serde_json::to_string(&BoxType::MOOV).unwrap();
+        assert_eq!(json, r#""moov""#);
+        assert_eq!(serde_json::from_str::<BoxType>(&json).unwrap(), BoxType::MOOV);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_box_type_non_printable() {
+        let box_type = BoxType::FourCC(FourCC { value: [0xa9, b'n', b'a', b'm'] });
+        let json = serde_json::to_string(&box_type).unwrap();
+        assert_eq!(json, r#""0xa96e616d""#);
+        assert_eq!(serde_json::from_str::<BoxType>(&json).unwrap(), box_type);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_box_type_uuid() {
+        let value = [
+            0xb7, 0xd8, 0x7c, 0x37, 0x3a, 0x9b, 0x4f, 0x6e, 0x86, 0x53, 0x8d, 0x34, 0x0a, 0x45, 0x5b, 0x0f,
+        ];
+        let box_type = BoxType::Uuid(BoxUuid { value });
+        let json = serde_json::to_string(&box_type).unwrap();
+        assert_eq!(json, r#""b7d87c37-3a9b-4f6e-8653-8d340a455b0f""#);
+        assert_eq!(serde_json::from_str::<BoxType>(&json).unwrap(), box_type);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_box_uuid_invalid() {
+        serde_json::from_str::<BoxUuid>(r#""b7d87c373a9b4f6e86538d340a455b0f""#).unwrap_err();
+        serde_json::from_str::<BoxUuid>(r#""b7d87c37-3a9b-4f6e-8653-8d340a455b0g""#).unwrap_err();
+        serde_json::from_str::<BoxUuid>(r#""+7d87c37-3a9b-4f6e-8653-8d340a455b0f""#).unwrap_err();
+    }
+}
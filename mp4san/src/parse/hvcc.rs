@@ -0,0 +1,152 @@
+#![allow(missing_docs)]
+
+use bytes::{Buf, BufMut, BytesMut};
+use derive_more::Display;
+
+use crate::error::Result;
+
+use super::error::WhileParsingBox;
+use super::{BoxType, FourCC, Mp4Value, ParseBox, ParseError, ParsedBox};
+
+/// An HEVC configuration box, found in an HEVC visual sample entry, e.g. `hvc1` or `hev1`.
+#[derive(Clone, Debug, ParseBox, ParsedBox)]
+#[box_type = "hvcC"]
+pub struct HvccBox {
+    pub record: HevcDecoderConfigurationRecord,
+}
+
+/// The `HEVCDecoderConfigurationRecord` in an [`HvccBox`], as defined in ISO/IEC 14496-15.
+///
+/// The lengths of the record's arrays of NAL units are validated to be consistent when parsed. The record is re-encoded
+/// verbatim.
+#[derive(Clone, Debug)]
+pub struct HevcDecoderConfigurationRecord {
+    configuration_version: u8,
+    /// The NAL unit type of each non-empty array of NAL units, in order.
+    nal_unit_types: Vec<u8>,
+    data: BytesMut,
+}
+
+#[derive(Clone, Copy, Debug, Display)]
+#[display(fmt = "NAL unit length {} exceeds {} available bytes", _0, _1)]
+struct NalUnitLengthTooLarge(usize, usize);
+
+const NAME: BoxType = BoxType::FourCC(FourCC::from_str("hvcC"));
+
+/// The length of the fields of a record preceding its arrays of NAL units, up to but excluding their count.
+const RECORD_FIELDS_LEN: usize = 22;
+
+/// The mask of the NAL unit type in the first byte of each array of NAL units.
+const NAL_UNIT_TYPE_MASK: u8 = 0x3f;
+
+impl HevcDecoderConfigurationRecord {
+    /// The NAL unit type of a video parameter set.
+    pub const VPS_NAL_UNIT_TYPE: u8 = 32;
+
+    /// The NAL unit type of a sequence parameter set.
+    pub const SPS_NAL_UNIT_TYPE: u8 = 33;
+
+    /// The NAL unit type of a picture parameter set.
+    pub const PPS_NAL_UNIT_TYPE: u8 = 34;
+
+    pub fn configuration_version(&self) -> u8 {
+        self.configuration_version
+    }
+
+    /// Whether the record contains at least one NAL unit of type `nal_unit_type`.
+    pub fn contains_nal_unit_type(&self, nal_unit_type: u8) -> bool {
+        self.nal_unit_types.contains(&nal_unit_type)
+    }
+
+    /// Whether the record contains at least one each of a video, sequence, and picture parameter set.
+    pub fn has_parameter_sets(&self) -> bool {
+        [
+            Self::VPS_NAL_UNIT_TYPE,
+            Self::SPS_NAL_UNIT_TYPE,
+            Self::PPS_NAL_UNIT_TYPE,
+        ]
+        .into_iter()
+        .all(|nal_unit_type| self.contains_nal_unit_type(nal_unit_type))
+    }
+}
+
+impl Mp4Value for HevcDecoderConfigurationRecord {
+    fn parse(buf: &mut BytesMut) -> Result<Self, ParseError> {
+        let data = buf.split();
+        let mut input = &data[..];
+
+        ensure_attach!(
+            input.remaining() > RECORD_FIELDS_LEN,
+            ParseError::TruncatedBox,
+            WhileParsingBox(NAME),
+        );
+        let configuration_version = input.get_u8();
+        input.advance(RECORD_FIELDS_LEN - 1);
+
+        let array_count = input.get_u8();
+        let mut nal_unit_types = Vec::new();
+        for _ in 0..array_count {
+            ensure_attach!(input.remaining() >= 3, ParseError::TruncatedBox, WhileParsingBox(NAME));
+            let nal_unit_type = input.get_u8() & NAL_UNIT_TYPE_MASK;
+            let nal_unit_count = input.get_u16();
+            for _ in 0..nal_unit_count {
+                ensure_attach!(input.remaining() >= 2, ParseError::TruncatedBox, WhileParsingBox(NAME));
+                let nal_unit_len = usize::from(input.get_u16());
+                ensure_attach!(
+                    nal_unit_len <= input.remaining(),
+                    ParseError::TruncatedBox,
+                    NalUnitLengthTooLarge(nal_unit_len, input.remaining()),
+                    WhileParsingBox(NAME),
+                );
+                input.advance(nal_unit_len);
+            }
+            if nal_unit_count != 0 {
+                nal_unit_types.push(nal_unit_type);
+            }
+        }
+
+        Ok(Self { configuration_version, nal_unit_types, data })
+    }
+
+    fn encoded_len(&self) -> u64 {
+        self.data.len() as u64
+    }
+
+    fn put_buf<B: BufMut>(&self, mut buf: B) {
+        buf.put_slice(&self.data);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+
+    use crate::parse::Mp4Box;
+    use crate::util::test::TEST_HVCC;
+
+    use super::*;
+
+    fn parse_hvcc(data: &[u8]) -> Result<HvccBox, ParseError> {
+        let mut mp4box = Mp4Box::<HvccBox>::parse(&mut BytesMut::from(data))?;
+        mp4box.data.parse().cloned()
+    }
+
+    #[test]
+    fn hvcc() {
+        let hvcc = parse_hvcc(TEST_HVCC).unwrap();
+        assert_eq!(hvcc.record.configuration_version(), 1);
+        assert!(hvcc.record.has_parameter_sets());
+
+        let mut encoded = BytesMut::new();
+        Mp4Box::with_data(hvcc.into()).unwrap().put_buf(&mut encoded);
+        assert_eq!(&encoded[..], TEST_HVCC);
+    }
+
+    #[test]
+    fn truncated_nal_unit() {
+        let mut data = TEST_HVCC.to_vec();
+        data.pop();
+        data[3] -= 1;
+        assert_matches!(parse_hvcc(&data).unwrap_err().into_inner(), ParseError::TruncatedBox);
+    }
+}
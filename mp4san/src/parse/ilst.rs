@@ -0,0 +1,96 @@
+#![allow(missing_docs)]
+
+use crate::error::Result;
+
+use super::error::ParseResultExt;
+use super::mp4box::{BoxData, Boxes};
+use super::{BoxType, Mp4Value, ParseBox, ParseError, ParsedBox, UnboundedArray};
+
+/// An iTunes-style item list box, found in a [`MetaBox`](super::MetaBox), containing a box for each metadata item, e.g.
+/// `©nam` for the title, each holding the item's values in `data` boxes.
+///
+/// If the `meta` box also has a [`KeysBox`](super::KeysBox), the type of each item box is instead the 1-based index of
+/// its key in the `keys` box.
+#[derive(Clone, Debug, ParseBox, ParsedBox)]
+#[box_type = "ilst"]
+pub struct IlstBox {
+    children: Boxes,
+}
+
+/// A value of a metadata item in an [`IlstBox`].
+#[derive(Clone, Debug, PartialEq, Eq, ParseBox, ParsedBox)]
+#[box_type = "data"]
+pub struct DataBox {
+    type_indicator: u32,
+    locale: u32,
+    value: UnboundedArray<u8>,
+}
+
+const NAME: BoxType = BoxType::ILST;
+
+impl IlstBox {
+    /// Returns an iterator over the type of each item's box and the values of the item.
+    pub fn items(&self) -> impl Iterator<Item = Result<(BoxType, Vec<DataBox>), ParseError>> + '_ {
+        self.children.iter().filter_map(|item| {
+            let box_type = item.calculated_header().box_type();
+            let BoxData::Bytes(data) = &item.data else {
+                return None;
+            };
+            let values = Boxes::<()>::parse(&mut data.clone())
+                .and_then(|mut children| children.get_mut().map(|value| value.cloned()).collect())
+                .while_parsing_child(NAME, box_type)
+                .map(|values| (box_type, values));
+            Some(values)
+        })
+    }
+}
+
+impl DataBox {
+    /// The well-known type of the value, e.g. `1` for UTF-8 text.
+    pub fn type_indicator(&self) -> u32 {
+        self.type_indicator
+    }
+
+    pub fn locale(&self) -> u32 {
+        self.locale
+    }
+
+    pub fn value(&self) -> &[u8] {
+        self.value.as_bytes()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use bytes::BytesMut;
+
+    use crate::parse::{AnyMp4Box, FourCC, MetaBox};
+    use crate::util::test::write_test_ilst_meta_data;
+
+    use super::*;
+
+    #[test]
+    fn items() {
+        let mut buf = BytesMut::new();
+        write_test_ilst_meta_data(&mut buf);
+        let mut meta = MetaBox::parse(&mut buf).unwrap();
+        let ilst = meta.ilst_mut().unwrap().unwrap();
+        let items = ilst.items().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(items.len(), 1);
+        let (box_type, values) = &items[0];
+        assert_eq!(*box_type, BoxType::FourCC(FourCC { value: *b"\xa9nam" }));
+        assert_eq!(values.len(), 1);
+        assert_eq!((values[0].type_indicator(), values[0].locale()), (1, 0));
+        assert_eq!(values[0].value(), b"test");
+    }
+
+    #[test]
+    fn item_truncated_data() {
+        let item_type = BoxType::FourCC(FourCC { value: *b"\xa9nam" });
+        let item = AnyMp4Box::with_bytes(item_type, BytesMut::from(&b"\0\0\0\x10data\0\0\0\x01"[..]));
+        let ilst = IlstBox { children: vec![item].into() };
+        let err = ilst.items().next().unwrap().unwrap_err();
+        assert_matches!(err.into_inner(), ParseError::TruncatedBox);
+    }
+}
@@ -0,0 +1,111 @@
+#![allow(missing_docs)]
+
+use bytes::Buf;
+use derive_more::Display;
+
+use crate::error::Result;
+
+use super::error::WhileParsingBox;
+use super::{BoxType, ConstFullBoxHeader, FourCC, ParseBox, ParseError, ParsedBox, UnboundedArray};
+
+/// A metadata item keys box, found in a [`MetaBox`](super::MetaBox) alongside an [`IlstBox`](super::IlstBox), naming
+/// the items of the item list by index.
+#[derive(Clone, Debug, ParseBox, ParsedBox)]
+#[box_type = "keys"]
+pub struct KeysBox {
+    header: ConstFullBoxHeader,
+    entry_count: u32,
+    entries: UnboundedArray<u8>,
+}
+
+#[derive(Clone, Copy, Debug, Display)]
+#[display(fmt = "invalid key size {}", _0)]
+struct InvalidKeySize(u32);
+
+const NAME: BoxType = BoxType::KEYS;
+
+/// The length of the size and namespace fields preceding each key's value.
+const KEY_HEADER_LEN: u32 = 8;
+
+impl KeysBox {
+    pub fn entry_count(&self) -> u32 {
+        self.entry_count
+    }
+
+    /// Parse the namespace and value of each key, in order of their 1-based indices.
+    pub fn keys(&self) -> Result<Vec<(FourCC, &[u8])>, ParseError> {
+        let mut entries = self.entries.as_bytes();
+        let mut keys = Vec::new();
+        while entries.has_remaining() {
+            ensure_attach!(
+                entries.remaining() >= KEY_HEADER_LEN as usize,
+                ParseError::TruncatedBox,
+                WhileParsingBox(NAME),
+            );
+            let key_size = entries.get_u32();
+            let value_len = key_size.checked_sub(KEY_HEADER_LEN).ok_or_else(|| {
+                report_attach!(
+                    ParseError::InvalidInput,
+                    InvalidKeySize(key_size),
+                    WhileParsingBox(NAME)
+                )
+            })?;
+            let namespace = FourCC { value: entries.get_u32().to_be_bytes() };
+            ensure_attach!(
+                entries.remaining() >= value_len as usize,
+                ParseError::TruncatedBox,
+                WhileParsingBox(NAME),
+            );
+            let (value, rest) = entries.split_at(value_len as usize);
+            keys.push((namespace, value));
+            entries = rest;
+        }
+        ensure_attach!(
+            keys.len() == self.entry_count as usize,
+            ParseError::InvalidInput,
+            "key count does not match entry count",
+            WhileParsingBox(NAME),
+        );
+        Ok(keys)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use bytes::BytesMut;
+
+    use super::*;
+
+    fn parse_keys(entry_count: u32, entries: &[u8]) -> KeysBox {
+        let mut buf = BytesMut::from(&[0, 0, 0, 0][..]);
+        buf.extend_from_slice(&entry_count.to_be_bytes());
+        buf.extend_from_slice(entries);
+        KeysBox::parse(&mut buf).unwrap()
+    }
+
+    #[test]
+    fn keys() {
+        let keys = parse_keys(2, b"\0\0\0\x0dmdtatitle\0\0\0\x0amdtaab");
+        let mdta = FourCC { value: *b"mdta" };
+        assert_eq!(keys.keys().unwrap(), [(mdta, &b"title"[..]), (mdta, &b"ab"[..])]);
+    }
+
+    #[test]
+    fn entry_count_mismatch() {
+        let keys = parse_keys(2, b"\0\0\0\x0dmdtatitle");
+        assert_matches!(keys.keys().unwrap_err().into_inner(), ParseError::InvalidInput);
+    }
+
+    #[test]
+    fn invalid_key_size() {
+        let keys = parse_keys(1, b"\0\0\0\x04mdta");
+        assert_matches!(keys.keys().unwrap_err().into_inner(), ParseError::InvalidInput);
+    }
+
+    #[test]
+    fn truncated_key() {
+        let keys = parse_keys(1, b"\0\0\0\x0dmdtatit");
+        assert_matches!(keys.keys().unwrap_err().into_inner(), ParseError::TruncatedBox);
+    }
+}
@@ -0,0 +1,89 @@
+#![allow(missing_docs)]
+
+use super::{MediaTimes, ParseBox, ParsedBox};
+
+#[derive(Clone, Debug, ParseBox, ParsedBox)]
+#[box_type = "mdhd"]
+pub struct MdhdBox {
+    times: MediaTimes,
+    language: u16,
+    pre_defined: u16,
+}
+
+impl MdhdBox {
+    pub fn times(&self) -> &MediaTimes {
+        &self.times
+    }
+
+    pub fn times_mut(&mut self) -> &mut MediaTimes {
+        &mut self.times
+    }
+
+    /// The duration of the media in seconds, or [`None`] if it is unknown or the timescale is zero.
+    pub fn duration_secs(&self) -> Option<f64> {
+        self.times.duration_secs()
+    }
+
+    /// The ISO 639-2/T language code of the media, e.g. `*b"eng"`, or `*b"und"` if it is undetermined.
+    ///
+    /// The code is stored packed as three 5-bit characters, each offset from `0x60`.
+    pub fn language(&self) -> [u8; 3] {
+        let char_at = |shift: u16| 0x60 + ((self.language >> shift) & 0x1f) as u8;
+        [char_at(10), char_at(5), char_at(0)]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::BytesMut;
+
+    use crate::parse::{ParseBox, ParsedBox};
+    use crate::util::test::write_test_mdhd_data;
+
+    use super::MdhdBox;
+
+    #[test]
+    fn roundtrip() {
+        let mut buf = BytesMut::new();
+        write_test_mdhd_data(&mut buf);
+        let mdhd = MdhdBox::parse(&mut buf.clone()).unwrap();
+        assert_eq!(mdhd.encoded_len(), buf.len() as u64);
+
+        let mut output = BytesMut::new();
+        mdhd.put_buf(&mut output);
+        assert_eq!(output, buf);
+    }
+
+    #[test]
+    fn version_1() {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(&[1, 0, 0, 0]); // version, flags
+        buf.extend_from_slice(&u64::MAX.to_be_bytes()); // creation time
+        buf.extend_from_slice(&u64::MAX.to_be_bytes()); // modification time
+        buf.extend_from_slice(&48000u32.to_be_bytes()); // timescale
+        buf.extend_from_slice(&(48000u64 << 32).to_be_bytes()); // duration
+        buf.extend_from_slice(&[0x55, 0xc4, 0, 0]); // language, pre-defined
+        let mdhd = MdhdBox::parse(&mut buf).unwrap();
+        assert_eq!(mdhd.times().creation_time(), u64::MAX);
+        assert_eq!(mdhd.duration_secs(), Some((1u64 << 32) as f64));
+    }
+
+    #[test]
+    fn language() {
+        let mut buf = BytesMut::new();
+        write_test_mdhd_data(&mut buf);
+        buf[20..22].copy_from_slice(&[0x55, 0xc4]);
+        assert_eq!(&MdhdBox::parse(&mut buf.clone()).unwrap().language(), b"und");
+
+        buf[20..22].copy_from_slice(&[0x15, 0xc7]);
+        assert_eq!(&MdhdBox::parse(&mut buf).unwrap().language(), b"eng");
+    }
+
+    #[test]
+    fn duration_secs_zero_timescale() {
+        let mut buf = BytesMut::new();
+        write_test_mdhd_data(&mut buf);
+        buf[12..16].copy_from_slice(&[0, 0, 0, 0]); // timescale
+        assert_eq!(MdhdBox::parse(&mut buf).unwrap().duration_secs(), None);
+    }
+}
@@ -4,7 +4,7 @@ use crate::error::Result;
 
 use super::error::ParseResultExt;
 use super::mp4box::Boxes;
-use super::{BoxType, MinfBox, ParseBox, ParseError, ParsedBox};
+use super::{BoxType, FourCC, HdlrBox, MdhdBox, MinfBox, ParseBox, ParseError, ParsedBox};
 
 #[derive(Clone, Debug, ParseBox, ParsedBox)]
 #[box_type = "mdia"]
@@ -20,6 +20,32 @@ impl MdiaBox {
         Self { children: children.into() }
     }
 
+    pub fn remove_padding(&mut self) -> Result<(), ParseError> {
+        self.children.remove_padding();
+        self.minf_mut()?.remove_padding()
+    }
+
+    /// Replace the header of each box within this box and its `minf`, `stbl`, and `stsd` descendants, including the
+    /// sample entries, with the minimal encoding of its size.
+    pub fn canonicalize_box_sizes(&mut self) -> Result<(), ParseError> {
+        self.children.canonicalize_headers()?;
+        self.minf_mut()?.canonicalize_box_sizes()
+    }
+
+    /// The `hdlr` box declaring the type of the media in the track, e.g. `vide` or `soun`.
+    pub fn hdlr_mut(&mut self) -> Result<&mut HdlrBox, ParseError> {
+        self.children.get_one_mut().while_parsing_child(NAME, BoxType::HDLR)
+    }
+
+    /// Returns the handler type declaring the type of the media in the track, e.g. `vide` or `soun`.
+    pub fn handler_type(&mut self) -> Result<FourCC, ParseError> {
+        Ok(self.hdlr_mut()?.handler_type())
+    }
+
+    pub fn mdhd_mut(&mut self) -> Result<Option<&mut MdhdBox>, ParseError> {
+        self.children.get_opt_mut().while_parsing_child(NAME, BoxType::MDHD)
+    }
+
     pub fn minf_mut(&mut self) -> Result<&mut MinfBox, ParseError> {
         self.children.get_one_mut().while_parsing_child(NAME, BoxType::MINF)
     }
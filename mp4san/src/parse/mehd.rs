@@ -0,0 +1,104 @@
+#![allow(missing_docs)]
+
+use bytes::{BufMut, BytesMut};
+
+use crate::error::Result;
+
+use super::error::UnsupportedVersion;
+use super::{FullBoxHeader, Mp4Value, ParseBox, ParseError, ParsedBox};
+
+/// A movie extends header box, found in an `mvex` box, giving the duration of a fragmented movie including all of its
+/// fragments.
+#[derive(Clone, Debug, ParseBox, ParsedBox)]
+#[box_type = "mehd"]
+pub struct MehdBox {
+    duration: FragmentDuration,
+}
+
+/// The version-dependent full box header and duration field of the `mehd` box.
+///
+/// Version 0 boxes encode the duration as a 32-bit value, and version 1 boxes as a 64-bit value.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct FragmentDuration {
+    header: FullBoxHeader,
+    fragment_duration: u64,
+}
+
+impl MehdBox {
+    pub fn version(&self) -> u8 {
+        self.duration.header.version
+    }
+
+    /// The duration of the whole movie including its fragments, in the timescale of the `mvhd` box.
+    pub fn fragment_duration(&self) -> u64 {
+        self.duration.fragment_duration
+    }
+}
+
+impl Mp4Value for FragmentDuration {
+    fn parse(buf: &mut BytesMut) -> Result<Self, ParseError> {
+        let header = FullBoxHeader::parse(&mut *buf)?;
+        let fragment_duration = match header.version {
+            0 => u32::parse(&mut *buf)?.into(),
+            1 => u64::parse(&mut *buf)?,
+            version => bail_attach!(ParseError::InvalidInput, UnsupportedVersion(version)),
+        };
+        Ok(Self { header, fragment_duration })
+    }
+
+    fn encoded_len(&self) -> u64 {
+        match self.header.version {
+            0 => 4 + 4,
+            _ => 4 + 8,
+        }
+    }
+
+    fn put_buf<B: BufMut>(&self, mut buf: B) {
+        self.header.put_buf(&mut buf);
+        match self.header.version {
+            0 => buf.put_u32(self.fragment_duration as u32),
+            _ => buf.put_u64(self.fragment_duration),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use bytes::BytesMut;
+
+    use crate::parse::{ParseBox, ParsedBox};
+
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        for data in [
+            &[0, 0, 0, 0, 0, 0, 0x12, 0x34][..],
+            &[1, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0],
+        ] {
+            let mut buf = BytesMut::from(data);
+            let mehd = MehdBox::parse(&mut buf.clone()).unwrap();
+            assert_eq!(mehd.encoded_len(), buf.len() as u64);
+
+            let mut output = BytesMut::new();
+            mehd.put_buf(&mut output);
+            assert_eq!(output, buf.split());
+        }
+    }
+
+    #[test]
+    fn fragment_duration() {
+        let mehd = MehdBox::parse(&mut BytesMut::from(&[0, 0, 0, 0, 0, 0, 0x12, 0x34][..])).unwrap();
+        assert_eq!((mehd.version(), mehd.fragment_duration()), (0, 0x1234));
+
+        let mehd = MehdBox::parse(&mut BytesMut::from(&[1, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0][..])).unwrap();
+        assert_eq!((mehd.version(), mehd.fragment_duration()), (1, 1 << 32));
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let err = MehdBox::parse(&mut BytesMut::from(&[2, 0, 0, 0, 0, 0, 0, 0][..])).unwrap_err();
+        assert_matches!(err.into_inner(), ParseError::InvalidInput);
+    }
+}
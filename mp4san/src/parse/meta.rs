@@ -0,0 +1,214 @@
+#![allow(missing_docs)]
+
+use derive_more::Display;
+
+use crate::error::Result;
+
+use super::error::{ParseResultExt, WhileParsingChild};
+use super::{
+    BoxType, Boxes, ConstFullBoxHeader, DataBox, HdlrBox, IlstBox, KeysBox, ParseBox, ParseError, ParsedBox, UdtaBox,
+};
+
+/// The metadata box, holding untimed metadata such as an `ilst` item list, described by its `hdlr` box.
+///
+/// Unlike plain container boxes such as [`MoovBox`](super::MoovBox), `meta` is a full box, so its child boxes follow a
+/// version and flags header.
+#[derive(Clone, Debug, ParseBox, ParsedBox)]
+#[box_type = "meta"]
+pub struct MetaBox {
+    header: ConstFullBoxHeader,
+    pub children: Boxes,
+}
+
+/// A metadata item from the `ilst` box of a [`MetaBox`], with its key resolved.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MetadataItem {
+    /// The key of the item: the type of its box in the `ilst` box, e.g. `©nam`, or the value of its key in the `keys`
+    /// box, e.g. `com.apple.quicktime.title`, if there is one.
+    pub key: Vec<u8>,
+
+    /// The values of the item, from its `data` boxes.
+    pub values: Vec<DataBox>,
+}
+
+#[derive(Clone, Copy, Debug, Display)]
+#[display(fmt = "no key with index {}", _0)]
+struct MissingKey(u32);
+
+const NAME: BoxType = BoxType::META;
+
+impl MetaBox {
+    pub fn child_types(&self) -> impl ExactSizeIterator<Item = BoxType> + '_ {
+        self.children.box_types()
+    }
+
+    /// The `hdlr` box declaring the format of the metadata, which is required.
+    pub fn hdlr_mut(&mut self) -> Result<&mut HdlrBox, ParseError> {
+        self.children.get_one_mut().while_parsing_child(NAME, BoxType::HDLR)
+    }
+
+    pub fn ilst_mut(&mut self) -> Result<Option<&mut IlstBox>, ParseError> {
+        self.children.get_opt_mut().while_parsing_child(NAME, BoxType::ILST)
+    }
+
+    pub fn keys_mut(&mut self) -> Result<Option<&mut KeysBox>, ParseError> {
+        self.children.get_opt_mut().while_parsing_child(NAME, BoxType::KEYS)
+    }
+
+    /// Parse the items of the `ilst` box, if any, resolving their keys from the `keys` box, if any.
+    pub fn items(&mut self) -> Result<Vec<MetadataItem>, ParseError> {
+        let keys = match self.keys_mut()? {
+            Some(keys) => Some(
+                keys.keys()?
+                    .into_iter()
+                    .map(|(_namespace, key)| key.to_vec())
+                    .collect::<Vec<_>>(),
+            ),
+            None => None,
+        };
+        let Some(ilst) = self.ilst_mut()? else {
+            return Ok(Vec::new());
+        };
+        let mut items = Vec::new();
+        for item in ilst.items() {
+            let (box_type, values) = item?;
+            let key = match (&keys, box_type) {
+                (None, BoxType::FourCC(fourcc)) => fourcc.value.to_vec(),
+                (None, BoxType::Uuid(uuid)) => uuid.value.to_vec(),
+                (Some(keys), BoxType::FourCC(fourcc)) => {
+                    let index = u32::from_be_bytes(fourcc.value);
+                    let key = usize::try_from(index)
+                        .ok()
+                        .and_then(|index| keys.get(index.checked_sub(1)?));
+                    ensure_matches_attach!(
+                        key,
+                        Some(key),
+                        ParseError::InvalidInput,
+                        MissingKey(index),
+                        WhileParsingChild(NAME, BoxType::ILST),
+                    );
+                    key.clone()
+                }
+                (Some(_), BoxType::Uuid(_)) => bail_attach!(
+                    ParseError::InvalidInput,
+                    "uuid item with keys",
+                    WhileParsingChild(NAME, BoxType::ILST),
+                ),
+            };
+            items.push(MetadataItem { key, values });
+        }
+        Ok(items)
+    }
+
+    /// Remove the `ilst` box and its `keys` box, if any, returning whether any were present.
+    pub fn remove_items(&mut self) -> Result<bool, ParseError> {
+        let len = self.children.box_types().len();
+        self.children.retain_mut(|_: &mut IlstBox| Ok(false))?;
+        self.children.retain_mut(|_: &mut KeysBox| Ok(false))?;
+        Ok(self.children.box_types().len() != len)
+    }
+}
+
+/// Remove the iTunes-style metadata items from the `meta` box among the `children` of a `parent` box, and from the one
+/// in their `udta` box, if any, returning whether any were removed.
+pub(super) fn strip_child_metadata_items<V>(parent: BoxType, children: &mut Boxes<V>) -> Result<bool, ParseError> {
+    let mut removed = false;
+    if let Some(meta) = children.get_opt_mut::<MetaBox>().while_parsing_child(parent, NAME)? {
+        removed |= meta.remove_items().while_parsing_child(parent, NAME)?;
+    }
+    if let Some(udta) = children
+        .get_opt_mut::<UdtaBox>()
+        .while_parsing_child(parent, BoxType::UDTA)?
+    {
+        removed |= udta.strip_metadata_items().while_parsing_child(parent, BoxType::UDTA)?;
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use bytes::BytesMut;
+
+    use crate::parse::{BoxType, Mp4Value, ParseBox, ParseError, ParsedBox};
+    use crate::util::test::{test_hdlr, write_test_ilst_meta_data, write_test_meta_data, MDIR};
+
+    use super::MetaBox;
+
+    fn keys_meta_data() -> BytesMut {
+        let mut buf = BytesMut::from(&[0; 4][..]);
+        test_hdlr(MDIR).put_buf(&mut buf);
+        buf.extend_from_slice(b"\0\0\0\x1dkeys\0\0\0\0\0\0\0\x01\0\0\0\x0dmdtatitle");
+        buf.extend_from_slice(b"\0\0\0\x24ilst\0\0\0\x1c\0\0\0\x01\0\0\0\x14data\0\0\0\x01\0\0\0\0test");
+        buf
+    }
+
+    #[test]
+    fn roundtrip() {
+        let mut buf = BytesMut::new();
+        write_test_meta_data(&mut buf);
+        let meta = MetaBox::parse(&mut buf.clone()).unwrap();
+        assert_eq!(meta.encoded_len(), buf.len() as u64);
+
+        let mut output = BytesMut::new();
+        meta.put_buf(&mut output);
+        assert_eq!(output, buf);
+    }
+
+    #[test]
+    fn ilst() {
+        let mut buf = BytesMut::new();
+        write_test_ilst_meta_data(&mut buf);
+        let mut meta = MetaBox::parse(&mut buf).unwrap();
+        assert_eq!(meta.child_types().collect::<Vec<_>>(), [BoxType::HDLR, BoxType::ILST]);
+        assert_eq!(meta.hdlr_mut().unwrap().handler_type(), MDIR);
+    }
+
+    #[test]
+    fn items() {
+        let mut buf = BytesMut::new();
+        write_test_ilst_meta_data(&mut buf);
+        let mut meta = MetaBox::parse(&mut buf).unwrap();
+        let items = meta.items().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].key, b"\xa9nam");
+        assert_eq!(items[0].values[0].value(), b"test");
+    }
+
+    #[test]
+    fn items_with_keys() {
+        let mut meta = MetaBox::parse(&mut keys_meta_data()).unwrap();
+        let items = meta.items().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].key, b"title");
+        assert_eq!(items[0].values[0].value(), b"test");
+    }
+
+    #[test]
+    fn items_missing_key() {
+        let mut buf = keys_meta_data();
+        let len = buf.len();
+        buf[len - 21] = 2;
+        let mut meta = MetaBox::parse(&mut buf).unwrap();
+        assert_matches!(meta.items().unwrap_err().into_inner(), ParseError::InvalidInput);
+    }
+
+    #[test]
+    fn remove_items() {
+        let mut meta = MetaBox::parse(&mut keys_meta_data()).unwrap();
+        assert!(meta.remove_items().unwrap());
+        assert_eq!(meta.child_types().collect::<Vec<_>>(), [BoxType::HDLR]);
+        assert_eq!(meta.items().unwrap(), []);
+        assert!(!meta.remove_items().unwrap());
+    }
+
+    #[test]
+    fn no_hdlr() {
+        let mut buf = BytesMut::from(&[0; 4][..]);
+        let mut meta = MetaBox::parse(&mut buf).unwrap();
+        assert_matches!(
+            meta.hdlr_mut().unwrap_err().into_inner(),
+            ParseError::MissingRequiredBox(BoxType::HDLR)
+        );
+    }
+}
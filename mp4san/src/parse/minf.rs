@@ -2,9 +2,9 @@
 
 use crate::error::Result;
 
-use super::error::ParseResultExt;
+use super::error::{ParseResultExt, WhereEq, WhileParsingBox};
 use super::mp4box::Boxes;
-use super::{BoxType, ParseBox, ParseError, ParsedBox, StblBox};
+use super::{BoxType, FourCC, NmhdBox, ParseBox, ParseError, ParsedBox, SmhdBox, StblBox, VmhdBox};
 
 #[derive(Clone, Debug, ParseBox, ParsedBox)]
 #[box_type = "minf"]
@@ -14,12 +14,85 @@ pub struct MinfBox {
 
 const NAME: BoxType = BoxType::MINF;
 
+/// The media header boxes whose presence is checked against a track's handler type.
+const MEDIA_HEADERS: [BoxType; 4] = [BoxType::VMHD, BoxType::SMHD, BoxType::NMHD, BoxType::GMHD];
+
 impl MinfBox {
     #[cfg(test)]
     pub(crate) fn with_children<C: Into<Boxes>>(children: C) -> Self {
         Self { children: children.into() }
     }
 
+    pub fn remove_padding(&mut self) -> Result<(), ParseError> {
+        self.children.remove_padding();
+        self.stbl_mut()?.remove_padding();
+        Ok(())
+    }
+
+    /// Replace the header of each box within this box and its `stbl` and `stsd` descendants, including the sample
+    /// entries, with the minimal encoding of its size.
+    pub fn canonicalize_box_sizes(&mut self) -> Result<(), ParseError> {
+        self.children.canonicalize_headers()?;
+        self.stbl_mut()?.canonicalize_box_sizes()
+    }
+
+    /// Check that the media header box matches the track's `handler_type`, and parse it.
+    ///
+    /// Video tracks must have a `vmhd` box and audio tracks an `smhd` box. Timed metadata and text tracks may have a
+    /// null media header, i.e. an `nmhd` box or its QuickTime equivalent `gmhd`, but no other media header. Tracks with
+    /// other handler types are not checked.
+    pub fn validate_media_header(&mut self, handler_type: FourCC) -> Result<(), ParseError> {
+        let (allowed_media_headers, required): (&[BoxType], bool) = match &handler_type.value {
+            b"vide" => (&[BoxType::VMHD], true),
+            b"soun" => (&[BoxType::SMHD], true),
+            b"meta" | b"text" | b"sbtl" => (&[BoxType::NMHD, BoxType::GMHD], false),
+            _ => return Ok(()),
+        };
+        let mut present_media_headers = Vec::new();
+        for media_header in MEDIA_HEADERS {
+            if self.children.box_types().any(|box_type| box_type == media_header) {
+                ensure_attach!(
+                    allowed_media_headers.contains(&media_header),
+                    ParseError::InvalidBoxLayout,
+                    "media header does not match handler type",
+                    WhereEq("handler type", handler_type),
+                    WhereEq("media header", media_header),
+                    WhileParsingBox(NAME),
+                );
+                present_media_headers.push(media_header);
+            }
+        }
+        ensure_attach!(
+            !required || !present_media_headers.is_empty(),
+            ParseError::InvalidBoxLayout,
+            "media header does not match handler type",
+            WhereEq("handler type", handler_type),
+            WhereEq("media header", allowed_media_headers[0]),
+            WhileParsingBox(NAME),
+        );
+        for media_header in present_media_headers {
+            match media_header {
+                BoxType::VMHD => self.vmhd_mut().map(drop)?,
+                BoxType::SMHD => self.smhd_mut().map(drop)?,
+                BoxType::NMHD => self.nmhd_mut().map(drop)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub fn nmhd_mut(&mut self) -> Result<&mut NmhdBox, ParseError> {
+        self.children.get_one_mut().while_parsing_child(NAME, BoxType::NMHD)
+    }
+
+    pub fn smhd_mut(&mut self) -> Result<&mut SmhdBox, ParseError> {
+        self.children.get_one_mut().while_parsing_child(NAME, BoxType::SMHD)
+    }
+
+    pub fn vmhd_mut(&mut self) -> Result<&mut VmhdBox, ParseError> {
+        self.children.get_one_mut().while_parsing_child(NAME, BoxType::VMHD)
+    }
+
     pub fn stbl_mut(&mut self) -> Result<&mut StblBox, ParseError> {
         self.children.get_one_mut().while_parsing_child(NAME, BoxType::STBL)
     }
@@ -1,9 +1,12 @@
 #![allow(missing_docs)]
 
+use std::collections::HashSet;
+
 use crate::error::Result;
 
 use super::error::{ParseResultExt, WhileParsingField};
-use super::{BoxType, Boxes, BoxesValidator, ParseBox, ParseError, ParsedBox, TrakBox};
+use super::meta::strip_child_metadata_items;
+use super::{BoxType, Boxes, BoxesValidator, MvexBox, MvhdBox, ParseBox, ParseError, ParsedBox, TrakBox, UdtaBox};
 
 #[derive(Clone, Debug, ParseBox, ParsedBox)]
 #[box_type = "moov"]
@@ -21,6 +24,150 @@ impl MoovBox {
         Self { children: children.into() }
     }
 
+    /// Remove all `free` and `skip` padding boxes from this box and its `trak` descendants.
+    pub fn remove_padding(&mut self) -> Result<(), ParseError> {
+        self.children.remove_padding();
+        for trak in self.traks() {
+            trak?.remove_padding()?;
+        }
+        Ok(())
+    }
+
+    /// Replace the header of each box within this box, its `mvex` box, and each `trak` box's descendants with the
+    /// minimal encoding of its size.
+    ///
+    /// See [`TrakBox::canonicalize_box_sizes`] for the descendants of a `trak` box which are canonicalized. Boxes
+    /// within other boxes, e.g. `udta`, are left as they are.
+    pub fn canonicalize_box_sizes(&mut self) -> Result<(), ParseError> {
+        self.children.canonicalize_headers()?;
+        if let Some(mvex) = self.mvex_mut()? {
+            mvex.canonicalize_box_sizes().while_parsing_child(NAME, BoxType::MVEX)?;
+        }
+        for trak in self.traks() {
+            trak?.canonicalize_box_sizes()?;
+        }
+        Ok(())
+    }
+
+    /// Set the creation and modification times in the `mvhd` box and each track's `tkhd` and `mdhd` boxes, if present,
+    /// to zero.
+    pub fn zero_timestamps(&mut self) -> Result<(), ParseError> {
+        if let Some(mvhd) = self.mvhd_mut()? {
+            mvhd.times_mut().zero_timestamps();
+        }
+        for trak in self.traks() {
+            trak?.zero_timestamps()?;
+        }
+        Ok(())
+    }
+
+    /// Whether the movie uses Common Encryption, as indicated by a `pssh` box or by any of its tracks.
+    ///
+    /// See [`TrakBox::is_encrypted`].
+    pub fn is_encrypted(&mut self) -> Result<bool, ParseError> {
+        if self.children.box_types().any(|box_type| box_type == BoxType::PSSH) {
+            return Ok(true);
+        }
+        for trak in self.traks() {
+            if trak?.is_encrypted()? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    #[cfg(feature = "fingerprint")]
+    pub(crate) fn children(&self) -> impl ExactSizeIterator<Item = &super::AnyMp4Box> + '_ {
+        self.children.iter()
+    }
+
+    pub fn mvex_mut(&mut self) -> Result<Option<&mut MvexBox>, ParseError> {
+        self.children.get_opt_mut().while_parsing_child(NAME, BoxType::MVEX)
+    }
+
+    /// Whether the movie is fragmented, as indicated by an `mvex` box, so that the `moov` is an initialization segment.
+    ///
+    /// The `mvex` box and its `mehd` box, if any, are parsed and validated.
+    pub fn is_fragmented_init(&mut self) -> Result<bool, ParseError> {
+        let Some(mvex) = self.mvex_mut()? else {
+            return Ok(false);
+        };
+        mvex.mehd_mut().while_parsing_child(NAME, BoxType::MVEX)?;
+        Ok(true)
+    }
+
+    pub fn udta_mut(&mut self) -> Result<Option<&mut UdtaBox>, ParseError> {
+        self.children.get_opt_mut().while_parsing_child(NAME, BoxType::UDTA)
+    }
+
+    /// Remove the iTunes-style metadata items, i.e. the `ilst` and `keys` boxes, from the `meta` boxes within this box
+    /// and each `trak` box, whether directly or within a `udta` box, returning whether any were removed.
+    pub fn strip_metadata_items(&mut self) -> Result<bool, ParseError> {
+        let mut removed = strip_child_metadata_items(NAME, &mut self.children)?;
+        for trak in self.traks() {
+            removed |= trak?.strip_metadata_items()?;
+        }
+        Ok(removed)
+    }
+
+    pub fn mvhd_mut(&mut self) -> Result<Option<&mut MvhdBox>, ParseError> {
+        self.children.get_opt_mut().while_parsing_child(NAME, BoxType::MVHD)
+    }
+
+    /// Remove every `trak` box except the one whose `tkhd` box has the given `track_id`, returning whether it was
+    /// found.
+    ///
+    /// See [`retain_tracks`](Self::retain_tracks).
+    pub fn retain_track(&mut self, track_id: u32) -> Result<bool, ParseError> {
+        self.retain_tracks(&HashSet::from([track_id]))?;
+        Ok(self.children.box_types().any(|box_type| box_type == BoxType::TRAK))
+    }
+
+    /// Remove every `trak` box whose `tkhd` box's `track_id` is not in `track_ids`, along with any references to
+    /// removed tracks from the remaining tracks' `tref` boxes, returning the number of tracks removed.
+    pub fn retain_tracks(&mut self, track_ids: &HashSet<u32>) -> Result<usize, ParseError> {
+        let mut removed = 0;
+        self.children
+            .retain_mut(|trak: &mut TrakBox| {
+                let keep = trak
+                    .tkhd_mut()?
+                    .is_some_and(|tkhd| track_ids.contains(&tkhd.track_id()));
+                removed += usize::from(!keep);
+                Ok(keep)
+            })
+            .while_parsing_child(NAME, BoxType::TRAK)?;
+        for trak in self.traks() {
+            if let Some(tref) = trak?.tref_mut()? {
+                tref.retain_track_ids(track_ids)?;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Check that each track ID referenced by a track's `tref` box is the ID in the `tkhd` box of a track in the movie.
+    pub fn validate_track_references(&mut self) -> Result<(), ParseError> {
+        let mut track_ids = HashSet::new();
+        for trak in self.traks() {
+            if let Some(tkhd) = trak?.tkhd_mut()? {
+                track_ids.insert(tkhd.track_id());
+            }
+        }
+        for trak in self.traks() {
+            if let Some(tref) = trak?.tref_mut()? {
+                tref.validate_track_ids(&track_ids)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The number of `trak` boxes in the movie, counted without parsing them.
+    pub fn trak_count(&self) -> usize {
+        self.children
+            .box_types()
+            .filter(|&box_type| box_type == BoxType::TRAK)
+            .count()
+    }
+
     pub fn traks(&mut self) -> impl Iterator<Item = Result<&mut TrakBox, ParseError>> + '_ {
         self.children
             .get_mut()
@@ -28,6 +175,22 @@ impl MoovBox {
     }
 }
 
+/// Add `delta` to each chunk offset of each track in `moov`, e.g. to account for its `mdat` being relocated.
+///
+/// Any `stco` box with a displaced offset which no longer fits in 32 bits is replaced with an equivalent `co64` box,
+/// which changes the encoded length of `moov`.
+///
+/// # Errors
+///
+/// If a track's sample table cannot be parsed, or a displaced offset would be negative or overflow, an error is
+/// returned.
+pub fn displace_chunk_offsets(moov: &mut MoovBox, delta: i64) -> Result<(), ParseError> {
+    for trak in moov.traks() {
+        trak?.displace_chunk_offsets(delta)?;
+    }
+    Ok(())
+}
+
 impl BoxesValidator for MoovChildrenValidator {
     fn validate<V>(children: &Boxes<V>) -> Result<(), ParseError> {
         ensure_attach!(
@@ -43,10 +206,30 @@ impl BoxesValidator for MoovChildrenValidator {
 mod test {
     use bytes::BytesMut;
 
-    use crate::parse::Mp4Box;
+    use assert_matches::assert_matches;
+
+    use crate::parse::fourcc::{AVC1, METT};
+    use crate::parse::{Mp4Box, Mp4Value, StblBox, StblCoMut};
+    use crate::util::test::{test_moov, test_stsz, test_stz2, HDR10_NCLX_COLR};
 
     use super::*;
 
+    fn co_entries(moov: &mut MoovBox) -> Vec<(BoxType, u64)> {
+        let mut entries = vec![];
+        for trak in moov.traks() {
+            match trak.unwrap().co_mut().unwrap() {
+                StblCoMut::Stco(stco) => entries.extend(
+                    stco.entries_mut()
+                        .map(|entry| (BoxType::STCO, entry.get().unwrap().into())),
+                ),
+                StblCoMut::Co64(co64) => {
+                    entries.extend(co64.entries_mut().map(|entry| (BoxType::CO64, entry.get().unwrap())))
+                }
+            }
+        }
+        entries
+    }
+
     fn test_trak() -> Mp4Box<TrakBox> {
         Mp4Box::with_data(TrakBox::with_children(vec![]).into()).unwrap()
     }
@@ -58,6 +241,146 @@ mod test {
         MoovBox::parse(&mut data).unwrap();
     }
 
+    #[test]
+    fn sample_count() {
+        let mut moov = test_moov().build();
+        let trak = moov.data.parse().unwrap().traks().next().unwrap().unwrap();
+        assert_eq!(trak.sample_count().unwrap(), 0);
+
+        let mut moov = test_moov().co_entries(vec![0, 100, 200]).build();
+        let trak = moov.data.parse().unwrap().traks().next().unwrap().unwrap();
+        assert_eq!(trak.sample_count().unwrap(), 3);
+    }
+
+    #[test]
+    fn sample_count_stz2() {
+        let mut moov = test_moov().co_entries(vec![0, 100, 200]).stz2_field_size(4).build();
+        let trak = moov.data.parse().unwrap().traks().next().unwrap().unwrap();
+        assert_eq!(trak.sample_count().unwrap(), 3);
+    }
+
+    #[test]
+    fn sample_sizes() {
+        let mut moov = test_moov().co_entries(vec![0, 100, 200]).build();
+        let trak = moov.data.parse().unwrap().traks().next().unwrap().unwrap();
+        assert_eq!(trak.sample_sizes().unwrap().collect::<Vec<_>>(), [1, 1, 1]);
+
+        for field_size in [4, 8, 16] {
+            let mut moov = test_moov()
+                .co_entries(vec![0, 100, 200])
+                .stz2_field_size(field_size)
+                .build();
+            let trak = moov.data.parse().unwrap().traks().next().unwrap().unwrap();
+            assert_eq!(trak.sample_sizes().unwrap().collect::<Vec<_>>(), [0, 0, 0]);
+        }
+    }
+
+    #[test]
+    fn sample_entry_types() {
+        let mut moov = test_moov().build();
+        let trak = moov.data.parse().unwrap().traks().next().unwrap().unwrap();
+        assert_eq!(trak.sample_entry_types().unwrap(), [METT]);
+
+        let mut moov = test_moov().visual_sample_entry_children(HDR10_NCLX_COLR).build();
+        let trak = moov.data.parse().unwrap().traks().next().unwrap().unwrap();
+        assert_eq!(trak.sample_entry_types().unwrap(), [AVC1]);
+    }
+
+    #[test]
+    fn chunk_offsets() {
+        let offsets = vec![0, 100, u32::MAX.into()];
+        let mut stco_moov = test_moov().co_entries(offsets.clone()).build();
+        let mut co64_moov = test_moov().stco(false).co64(true).co_entries(offsets.clone()).build();
+        for moov in [&mut stco_moov, &mut co64_moov] {
+            let trak = moov.data.parse().unwrap().traks().next().unwrap().unwrap();
+            assert_eq!(
+                trak.chunk_offsets().unwrap().collect::<Result<Vec<_>, _>>().unwrap(),
+                offsets
+            );
+        }
+    }
+
+    #[test]
+    fn language() {
+        let mut moov = test_moov().build();
+        let trak = moov.data.parse().unwrap().traks().next().unwrap().unwrap();
+        assert_eq!(trak.language().unwrap(), Some(*b"und"));
+    }
+
+    #[test]
+    fn sample_count_stsz_and_stz2() {
+        let mut stbl = StblBox::with_children(vec![test_stsz(3), test_stz2(8, 3)]);
+        assert_matches!(
+            stbl.sample_count().unwrap_err().into_inner(),
+            ParseError::InvalidBoxLayout
+        );
+    }
+
+    #[test]
+    fn displace_stco() {
+        let mut moov = test_moov().co_entries(vec![0, 100]).build();
+        displace_chunk_offsets(moov.data.parse().unwrap(), 100).unwrap();
+        assert_eq!(
+            co_entries(moov.data.parse().unwrap()),
+            [(BoxType::STCO, 100), (BoxType::STCO, 200)]
+        );
+        displace_chunk_offsets(moov.data.parse().unwrap(), -100).unwrap();
+        assert_eq!(
+            co_entries(moov.data.parse().unwrap()),
+            [(BoxType::STCO, 0), (BoxType::STCO, 100)]
+        );
+    }
+
+    #[test]
+    fn displace_stco_upgrade() {
+        let max = u32::MAX as u64;
+        let mut moov = test_moov().co_entries(vec![0, max - 10]).build();
+        let len = moov.encoded_len();
+        displace_chunk_offsets(moov.data.parse().unwrap(), 100).unwrap();
+        assert_eq!(
+            co_entries(moov.data.parse().unwrap()),
+            [(BoxType::CO64, 100), (BoxType::CO64, max + 90)]
+        );
+        assert_eq!(moov.encoded_len(), len + 2 * 4);
+
+        let mut data = BytesMut::new();
+        moov.put_buf(&mut data);
+        let mut moov = Mp4Box::<MoovBox>::parse(&mut data).unwrap();
+        assert_eq!(
+            co_entries(moov.data.parse().unwrap()),
+            [(BoxType::CO64, 100), (BoxType::CO64, max + 90)]
+        );
+    }
+
+    #[test]
+    fn displace_co64() {
+        let mut moov = test_moov()
+            .stco(false)
+            .co64(true)
+            .co_entries(vec![100, u64::MAX - 10])
+            .build();
+        displace_chunk_offsets(moov.data.parse().unwrap(), -100).unwrap();
+        assert_eq!(
+            co_entries(moov.data.parse().unwrap()),
+            [(BoxType::CO64, 0), (BoxType::CO64, u64::MAX - 110)]
+        );
+    }
+
+    #[test]
+    fn displace_out_of_range() {
+        let mut moov = test_moov().co_entries(vec![10]).build();
+        let err = displace_chunk_offsets(moov.data.parse().unwrap(), -11).unwrap_err();
+        assert_matches!(err.into_inner(), ParseError::InvalidInput);
+
+        let mut moov = test_moov()
+            .stco(false)
+            .co64(true)
+            .co_entries(vec![u64::MAX - 10])
+            .build();
+        let err = displace_chunk_offsets(moov.data.parse().unwrap(), 11).unwrap_err();
+        assert_matches!(err.into_inner(), ParseError::InvalidInput);
+    }
+
     #[test]
     fn no_traks() {
         let mut data = BytesMut::new();
@@ -14,11 +14,10 @@ use dyn_clonable::clonable;
 use futures_util::io::BufReader;
 use futures_util::{AsyncRead, AsyncReadExt};
 use mediasan_common::error::WhileParsingType;
-use mediasan_common::{AsyncSkipExt, ResultExt};
+use mediasan_common::ResultExt;
 
 use crate::error::Result;
-use crate::util::IoResultExt;
-use crate::{AsyncSkip, BoxDataTooLarge, Error};
+use crate::{remaining_input_len, AsyncSkip, BoxDataTooLarge, Error, TruncatedBoxData};
 
 use super::error::{MultipleBoxes, WhileParsingBox};
 use super::{BoxHeader, BoxType, Mp4Value, ParseError};
@@ -76,7 +75,7 @@ impl<T: ParsedBox + ?Sized> Mp4Box<T> {
 
     /// Read and parse a box's data assuming its header has already been read.
     pub(crate) async fn read_data<R>(
-        mut reader: Pin<&mut BufReader<R>>,
+        reader: Pin<&mut BufReader<R>>,
         header: BoxHeader,
         max_size: u64,
     ) -> StdResult<Self, Error>
@@ -84,26 +83,133 @@ impl<T: ParsedBox + ?Sized> Mp4Box<T> {
         R: AsyncRead + AsyncSkip,
         T: ParseBox,
     {
-        let box_data_size = match header.box_data_size()? {
-            Some(box_data_size) => box_data_size,
-            None => reader.as_mut().stream_len().await? - reader.as_mut().stream_position().await?,
-        };
+        Self::read_data_into(reader, header, max_size, &mut BytesMut::new()).await
+    }
 
+    /// Read and parse a box's data assuming its header has already been read, reusing the memory of `buf`.
+    ///
+    /// `buf` is cleared, and the box's data is read into it and then split off of it. Once the returned box and
+    /// anything parsed from it are dropped, the memory is reclaimed by `buf` for the next read.
+    pub(crate) async fn read_data_into<R>(
+        reader: Pin<&mut BufReader<R>>,
+        header: BoxHeader,
+        max_size: u64,
+        buf: &mut BytesMut,
+    ) -> StdResult<Self, Error>
+    where
+        R: AsyncRead + AsyncSkip,
+        T: ParseBox,
+    {
+        let box_data_size = Self::read_available_data_into(reader, &header, max_size, buf).await?;
         ensure_attach!(
-            box_data_size <= max_size,
-            ParseError::InvalidInput,
-            BoxDataTooLarge(box_data_size, max_size),
+            buf.len() as u64 == box_data_size,
+            ParseError::TruncatedBox,
+            TruncatedBoxData(box_data_size, buf.len() as u64),
             WhileParsingBox(header.box_type()),
         );
+        Ok(Self { parsed_header: header, data: BoxData::Bytes(buf.split()) })
+    }
 
-        let mut buf = BytesMut::zeroed(box_data_size as usize);
-        reader.read_exact(&mut buf).await.map_eof(|_| {
-            Error::Parse(report_attach!(
-                ParseError::TruncatedBox,
-                WhileParsingBox(header.box_type())
-            ))
-        })?;
-        Ok(Self { parsed_header: header, data: BoxData::Bytes(buf) })
+    /// Read a container box's data assuming its header has already been read, like
+    /// [`read_data_into`](Self::read_data_into), but tolerating the input ending before the end of the box.
+    ///
+    /// If the input ends early, the data read is cut back to the end of the last complete child box, and the box's
+    /// header is rewritten to match. Returns the box along with whether it was truncated.
+    pub(crate) async fn read_truncated_data_into<R>(
+        reader: Pin<&mut BufReader<R>>,
+        header: BoxHeader,
+        max_size: u64,
+        buf: &mut BytesMut,
+    ) -> StdResult<(Self, bool), Error>
+    where
+        R: AsyncRead + AsyncSkip,
+        T: ParseBox,
+    {
+        let box_data_size = Self::read_available_data_into(reader, &header, max_size, buf).await?;
+        if buf.len() as u64 == box_data_size {
+            return Ok((Self { parsed_header: header, data: BoxData::Bytes(buf.split()) }, false));
+        }
+
+        let read_len = buf.len();
+        let mut children_len = 0;
+        while let Ok(child_header) = BoxHeader::parse(&buf[children_len..]) {
+            let Some(child_size) = child_header.box_size() else {
+                break;
+            };
+            match usize::try_from(child_size)
+                .ok()
+                .and_then(|size| children_len.checked_add(size))
+            {
+                Some(child_end) if child_end <= read_len => children_len = child_end,
+                _ => break,
+            }
+        }
+        log::warn!(
+            "{} truncated: read {read_len} of {box_data_size} bytes, keeping {children_len} bytes of complete child boxes",
+            header.box_type(),
+        );
+        buf.truncate(children_len);
+        let parsed_header = BoxHeader::with_data_size(header.box_type(), children_len as u64)?;
+        Ok((Self { parsed_header, data: BoxData::Bytes(buf.split()) }, true))
+    }
+
+    /// Read as much of a box's data as is available, up to its declared size, into `buf`, returning its declared size.
+    async fn read_available_data_into<R>(
+        mut reader: Pin<&mut BufReader<R>>,
+        header: &BoxHeader,
+        max_size: u64,
+        buf: &mut BytesMut,
+    ) -> StdResult<u64, Error>
+    where
+        R: AsyncRead + AsyncSkip,
+    {
+        let box_data_size = match header.box_data_size()? {
+            Some(box_data_size) => box_data_size,
+            None => match remaining_input_len(reader.as_mut()).await? {
+                Some(remaining_len) => remaining_len,
+                None => {
+                    // The input's length is unknown, so the box's data is read to the end of the input, stopping once
+                    // it exceeds the maximum size.
+                    let mut data = Vec::new();
+                    let limit = max_size.saturating_add(1);
+                    reader.as_mut().take(limit).read_to_end(&mut data).await?;
+                    Self::check_data_size(header, data.len() as u64, max_size)?;
+                    buf.clear();
+                    buf.extend_from_slice(&data);
+                    return Ok(data.len() as u64);
+                }
+            },
+        };
+        Self::check_data_size(header, box_data_size, max_size)?;
+
+        buf.clear();
+        buf.resize(box_data_size as usize, 0);
+        let mut read_len = 0;
+        while read_len < buf.len() {
+            match reader.read(&mut buf[read_len..]).await? {
+                0 => break,
+                amount_read => read_len += amount_read,
+            }
+        }
+        buf.truncate(read_len);
+        Ok(box_data_size)
+    }
+
+    fn check_data_size(header: &BoxHeader, box_data_size: u64, max_size: u64) -> Result<(), ParseError> {
+        if box_data_size > max_size {
+            if matches!(header.box_type(), BoxType::MOOV | BoxType::META) {
+                bail_attach!(
+                    ParseError::MetadataTooLarge { size: box_data_size, limit: max_size },
+                    WhileParsingBox(header.box_type()),
+                );
+            }
+            bail_attach!(
+                ParseError::InvalidInput,
+                BoxDataTooLarge(box_data_size, max_size),
+                WhileParsingBox(header.box_type()),
+            );
+        }
+        Ok(())
     }
 
     pub fn calculated_header(&self) -> BoxHeader {
@@ -117,6 +223,13 @@ impl<T: ParsedBox + ?Sized> Mp4Box<T> {
         }
     }
 
+    /// Replace the parsed header with the minimal encoding of the box's current size, e.g. a 32-bit size in place of an
+    /// extended 64-bit size where it fits.
+    pub fn canonicalize_header(&mut self) -> Result<(), ParseError> {
+        self.parsed_header = BoxHeader::with_data_size(self.parsed_header.box_type(), self.data.encoded_len())?;
+        Ok(())
+    }
+
     pub fn parse_data_as<U: ParseBox + ParsedBox + Into<Box<T>>>(&mut self) -> Result<Option<&mut U>, ParseError> {
         if self.parsed_header.box_type() != U::box_type() {
             return Ok(None);
@@ -269,10 +382,18 @@ impl<'a, T: ParsedBox + 'a> From<T> for Box<dyn ParsedBox + 'a> {
 //
 
 impl<V> Boxes<V> {
-    pub fn box_types(&self) -> impl Iterator<Item = BoxType> + ExactSizeIterator + '_ {
+    pub fn box_types(&self) -> impl ExactSizeIterator<Item = BoxType> + '_ {
         self.boxes.iter().map(|mp4box| mp4box.parsed_header.box_type())
     }
 
+    pub(crate) fn iter(&self) -> impl ExactSizeIterator<Item = &AnyMp4Box> + '_ {
+        self.boxes.iter()
+    }
+
+    pub(crate) fn iter_mut(&mut self) -> impl ExactSizeIterator<Item = &mut AnyMp4Box> + '_ {
+        self.boxes.iter_mut()
+    }
+
     pub fn get_mut<T: ParseBox + ParsedBox>(&mut self) -> impl Iterator<Item = Result<&mut T, ParseError>> {
         self.boxes
             .iter_mut()
@@ -280,14 +401,82 @@ impl<V> Boxes<V> {
     }
 
     pub fn get_one_mut<T: ParseBox + ParsedBox>(&mut self) -> Result<&mut T, ParseError> {
+        Ok(self
+            .get_opt_mut()?
+            .ok_or(ParseError::MissingRequiredBox(T::box_type()))?)
+    }
+
+    /// Replace the only box of type `T` with `replacement`, keeping its position.
+    pub fn replace_one<T: ParseBox + ParsedBox, U: ParseBox + ParsedBox>(
+        &mut self,
+        replacement: U,
+    ) -> Result<(), ParseError> {
+        let mut boxes = self
+            .boxes
+            .iter_mut()
+            .filter(|mp4box| mp4box.parsed_header.box_type() == T::box_type());
+        let mp4box = boxes.next().ok_or(ParseError::MissingRequiredBox(T::box_type()))?;
+        ensure_attach!(
+            boxes.next().is_none(),
+            ParseError::InvalidBoxLayout,
+            MultipleBoxes(T::box_type()),
+        );
+        *mp4box = Mp4Box::with_data(replacement.into())?.into();
+        Ok(())
+    }
+
+    /// Parse and take each box of type `T`, discarding boxes of other types.
+    pub fn into_parsed<T: ParseBox + ParsedBox>(self) -> impl Iterator<Item = Result<T, ParseError>> {
+        self.boxes
+            .into_iter()
+            .filter(|mp4box| mp4box.parsed_header.box_type() == T::box_type())
+            .map(|mut mp4box| {
+                mp4box.data.parse_as::<T>()?;
+                match mp4box.data {
+                    BoxData::Parsed(parsed) => Ok(*parsed.downcast().unwrap_or_else(|_| unreachable!())),
+                    BoxData::Bytes(_) => unreachable!(),
+                }
+            })
+    }
+
+    /// Remove each box of type `T` for which `keep` returns `false`, keeping boxes of other types.
+    pub fn retain_mut<T: ParseBox + ParsedBox>(
+        &mut self,
+        mut keep: impl FnMut(&mut T) -> Result<bool, ParseError>,
+    ) -> Result<(), ParseError> {
+        let mut kept = Vec::with_capacity(self.boxes.len());
+        for mp4box in &mut self.boxes {
+            kept.push(match mp4box.parse_data_as()? {
+                Some(data) => keep(data)?,
+                None => true,
+            });
+        }
+        let mut kept = kept.into_iter();
+        self.boxes.retain(|_| kept.next().unwrap_or_else(|| unreachable!()));
+        Ok(())
+    }
+
+    /// Remove all `free` and `skip` padding boxes.
+    pub fn remove_padding(&mut self) {
+        self.boxes
+            .retain(|mp4box| !matches!(mp4box.parsed_header.box_type(), BoxType::FREE | BoxType::SKIP));
+    }
+
+    /// Replace the header of each box with the minimal encoding of its size.
+    pub fn canonicalize_headers(&mut self) -> Result<(), ParseError> {
+        for mp4box in &mut self.boxes {
+            mp4box.canonicalize_header()?;
+        }
+        Ok(())
+    }
+
+    pub fn get_opt_mut<T: ParseBox + ParsedBox>(&mut self) -> Result<Option<&mut T>, ParseError> {
         ensure_attach!(
             self.box_types().filter(|box_type| *box_type == T::box_type()).count() <= 1,
             ParseError::InvalidBoxLayout,
             MultipleBoxes(T::box_type()),
         );
-        self.get_mut()
-            .next()
-            .ok_or_else(|| ParseError::MissingRequiredBox(T::box_type()))?
+        self.get_mut().next().transpose()
     }
 }
 
@@ -0,0 +1,33 @@
+#![allow(missing_docs)]
+
+use crate::error::Result;
+
+use super::error::ParseResultExt;
+use super::mp4box::Boxes;
+use super::{BoxType, MehdBox, ParseBox, ParseError, ParsedBox};
+
+/// A movie extends box, whose presence in a `moov` box indicates that the movie is fragmented, i.e. that the `moov` is
+/// an initialization segment for media in subsequent movie fragments.
+#[derive(Clone, Debug, ParseBox, ParsedBox)]
+#[box_type = "mvex"]
+pub struct MvexBox {
+    children: Boxes,
+}
+
+const NAME: BoxType = BoxType::MVEX;
+
+impl MvexBox {
+    #[cfg(test)]
+    pub(crate) fn with_children<C: Into<Boxes>>(children: C) -> Self {
+        Self { children: children.into() }
+    }
+
+    /// Replace the header of each child box with the minimal encoding of its size.
+    pub fn canonicalize_box_sizes(&mut self) -> Result<(), ParseError> {
+        self.children.canonicalize_headers()
+    }
+
+    pub fn mehd_mut(&mut self) -> Result<Option<&mut MehdBox>, ParseError> {
+        self.children.get_opt_mut().while_parsing_child(NAME, BoxType::MEHD)
+    }
+}
@@ -0,0 +1,69 @@
+#![allow(missing_docs)]
+
+use super::{MediaTimes, ParseBox, ParsedBox};
+
+#[derive(Clone, Debug, ParseBox, ParsedBox)]
+#[box_type = "mvhd"]
+pub struct MvhdBox {
+    times: MediaTimes,
+    pub rate: i32,
+    pub volume: i16,
+    reserved: u16,
+    reserved_2: [u32; 2],
+    pub matrix: [i32; 9],
+    pre_defined: [u32; 6],
+    pub next_track_id: u32,
+}
+
+impl MvhdBox {
+    pub fn times(&self) -> &MediaTimes {
+        &self.times
+    }
+
+    pub fn times_mut(&mut self) -> &mut MediaTimes {
+        &mut self.times
+    }
+
+    /// The duration of the movie in seconds, or [`None`] if it is unknown or the timescale is zero.
+    pub fn duration_secs(&self) -> Option<f64> {
+        self.times.duration_secs()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::BytesMut;
+
+    use crate::parse::{ParseBox, ParsedBox};
+    use crate::util::test::write_test_mvhd_data;
+
+    use super::MvhdBox;
+
+    #[test]
+    fn roundtrip() {
+        let mut buf = BytesMut::new();
+        write_test_mvhd_data(&mut buf);
+        let mvhd = MvhdBox::parse(&mut buf.clone()).unwrap();
+        assert_eq!(mvhd.encoded_len(), buf.len() as u64);
+
+        let mut output = BytesMut::new();
+        mvhd.put_buf(&mut output);
+        assert_eq!(output, buf);
+    }
+
+    #[test]
+    fn duration_secs() {
+        let mut buf = BytesMut::new();
+        write_test_mvhd_data(&mut buf);
+        buf[12..20].copy_from_slice(&[0, 0, 0x03, 0xe8, 0, 0, 0x0b, 0xb8]); // timescale 1000, duration 3000
+        assert_eq!(MvhdBox::parse(&mut buf).unwrap().duration_secs(), Some(3.0));
+    }
+
+    #[test]
+    fn duration_secs_zero_timescale() {
+        let mut buf = BytesMut::new();
+        write_test_mvhd_data(&mut buf);
+        buf[12..20].copy_from_slice(&[0, 0, 0, 0, 0, 0, 0x0b, 0xb8]); // timescale 0, duration 3000
+        assert_eq!(MvhdBox::parse(&mut buf).unwrap().duration_secs(), None);
+    }
+}
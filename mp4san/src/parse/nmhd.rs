@@ -0,0 +1,33 @@
+#![allow(missing_docs)]
+
+use super::{ConstFullBoxHeader, ParseBox, ParsedBox};
+
+/// The null media header box, found in the `minf` box of tracks which are neither video nor audio, e.g. timed metadata
+/// tracks.
+#[derive(Clone, Debug, ParseBox, ParsedBox)]
+#[box_type = "nmhd"]
+pub struct NmhdBox {
+    header: ConstFullBoxHeader,
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::BytesMut;
+
+    use crate::parse::{ParseBox, ParsedBox};
+    use crate::util::test::write_test_nmhd_data;
+
+    use super::NmhdBox;
+
+    #[test]
+    fn roundtrip() {
+        let mut buf = BytesMut::new();
+        write_test_nmhd_data(&mut buf);
+        let nmhd = NmhdBox::parse(&mut buf.clone()).unwrap();
+        assert_eq!(nmhd.encoded_len(), buf.len() as u64);
+
+        let mut output = BytesMut::new();
+        nmhd.put_buf(&mut output);
+        assert_eq!(output, buf);
+    }
+}
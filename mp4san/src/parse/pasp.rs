@@ -0,0 +1,78 @@
+#![allow(missing_docs)]
+
+use derive_more::Display;
+
+use crate::error::Result;
+
+use super::error::WhileParsingBox;
+use super::{BoxType, ParseBox, ParseError, ParsedBox};
+
+/// A pixel aspect ratio box, found in a visual sample entry, giving the relative width and height of a pixel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ParseBox, ParsedBox)]
+#[box_type = "pasp"]
+pub struct PaspBox {
+    h_spacing: u32,
+    v_spacing: u32,
+}
+
+#[derive(Clone, Copy, Debug, Display)]
+#[display(fmt = "zero pixel spacing {}:{}", _0, _1)]
+struct ZeroSpacing(u32, u32);
+
+impl PaspBox {
+    /// The relative width of a pixel.
+    pub fn h_spacing(&self) -> u32 {
+        self.h_spacing
+    }
+
+    /// The relative height of a pixel.
+    pub fn v_spacing(&self) -> u32 {
+        self.v_spacing
+    }
+
+    /// Check that neither spacing is zero, which would make the display aspect ratio undefined.
+    pub fn validate(&self) -> Result<(), ParseError> {
+        ensure_attach!(
+            self.h_spacing != 0 && self.v_spacing != 0,
+            ParseError::InvalidInput,
+            ZeroSpacing(self.h_spacing, self.v_spacing),
+            WhileParsingBox(BoxType::PASP),
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use bytes::BytesMut;
+
+    use crate::parse::{Mp4Box, Mp4Value};
+    use crate::util::test::TEST_PASP;
+
+    use super::*;
+
+    fn parse_pasp(data: &[u8]) -> Result<PaspBox, ParseError> {
+        let mut mp4box = Mp4Box::<PaspBox>::parse(&mut BytesMut::from(data))?;
+        mp4box.data.parse().cloned()
+    }
+
+    #[test]
+    fn pasp() {
+        let pasp = parse_pasp(TEST_PASP).unwrap();
+        assert_eq!((pasp.h_spacing(), pasp.v_spacing()), (4, 3));
+        pasp.validate().unwrap();
+
+        let mut encoded = BytesMut::new();
+        Mp4Box::with_data(pasp.into()).unwrap().put_buf(&mut encoded);
+        assert_eq!(&encoded[..], TEST_PASP);
+    }
+
+    #[test]
+    fn zero_spacing() {
+        let mut data = TEST_PASP.to_vec();
+        data[11] = 0;
+        let pasp = parse_pasp(&data).unwrap();
+        assert_matches!(pasp.validate().unwrap_err().into_inner(), ParseError::InvalidInput);
+    }
+}
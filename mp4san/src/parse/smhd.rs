@@ -0,0 +1,42 @@
+#![allow(missing_docs)]
+
+use super::{ConstFullBoxHeader, ParseBox, ParsedBox};
+
+/// The sound media header box, found in the `minf` box of audio tracks.
+#[derive(Clone, Debug, ParseBox, ParsedBox)]
+#[box_type = "smhd"]
+pub struct SmhdBox {
+    header: ConstFullBoxHeader,
+    balance: u16,
+    reserved: u16,
+}
+
+impl SmhdBox {
+    /// The stereo balance, as a signed 8.8 fixed-point number where `-1.0` is full left and `1.0` is full right.
+    pub fn balance(&self) -> i16 {
+        self.balance as i16
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::BytesMut;
+
+    use crate::parse::{ParseBox, ParsedBox};
+    use crate::util::test::write_test_smhd_data;
+
+    use super::SmhdBox;
+
+    #[test]
+    fn roundtrip() {
+        let mut buf = BytesMut::new();
+        write_test_smhd_data(&mut buf);
+        let smhd = SmhdBox::parse(&mut buf.clone()).unwrap();
+        assert_eq!(smhd.encoded_len(), buf.len() as u64);
+        assert_eq!(smhd.balance(), 0);
+
+        let mut output = BytesMut::new();
+        smhd.put_buf(&mut output);
+        assert_eq!(output, buf);
+    }
+}
@@ -1,9 +1,16 @@
 #![allow(missing_docs)]
 
+use derive_more::Display;
+use mediasan_common::util::checked_add_signed;
+use mediasan_common::InputSpan;
+
 use crate::error::Result;
 
 use super::error::{ParseResultExt, WhileParsingChild};
-use super::{BoxType, Boxes, Co64Box, ParseBox, ParseError, ParsedBox, StcoBox};
+use super::{
+    BoxData, BoxType, Boxes, Co64Box, CttsBox, ParseBox, ParseError, ParsedBox, StcoBox, StscBox, StsdBox, StssBox,
+    StszBox, Stz2Box,
+};
 
 #[derive(Clone, Debug, ParseBox, ParsedBox)]
 #[box_type = "stbl"]
@@ -17,6 +24,15 @@ pub enum StblCoMut<'a> {
     Co64(&'a mut Co64Box),
 }
 
+enum StblSampleSizeMut<'a> {
+    Stsz(&'a mut StszBox),
+    Stz2(&'a mut Stz2Box),
+}
+
+#[derive(Clone, Copy, Debug, Display)]
+#[display(fmt = "chunk offset {} does not fit in the target type", _0)]
+struct ChunkOffsetOverflow(u64);
+
 const NAME: BoxType = BoxType::STBL;
 const STCO: BoxType = BoxType::STCO;
 const CO64: BoxType = BoxType::CO64;
@@ -48,6 +64,284 @@ impl StblBox {
                 .map(StblCoMut::Co64)
         }
     }
+
+    /// Returns an iterator over the offset of each chunk in the track, from either its `stco` box or its `co64` box.
+    ///
+    /// An entry which can't be read is returned as an error rather than being skipped.
+    pub fn chunk_offsets(&mut self) -> Result<Box<dyn Iterator<Item = Result<u64, ParseError>> + '_>, ParseError> {
+        match self.co_mut()? {
+            StblCoMut::Stco(stco) => Ok(Box::new(stco.entries_mut().map(|entry| Ok(entry.get()?.into())))),
+            StblCoMut::Co64(co64) => Ok(Box::new(co64.entries_mut().map(|entry| entry.get()))),
+        }
+    }
+
+    /// Returns an iterator over the offset of each chunk in the track as a `usize`, from either its `stco` box or its
+    /// `co64` box.
+    ///
+    /// A `co64` offset which does not fit in a `usize`, as is possible on 32-bit targets, is returned as an error
+    /// rather than being truncated.
+    pub fn usize_chunk_offsets(&mut self) -> Result<impl Iterator<Item = Result<usize, ParseError>> + '_, ParseError> {
+        Ok(self.chunk_offsets()?.map(|offset| checked_chunk_offset(offset?)))
+    }
+
+    /// Returns the size in bytes of each chunk in the track, from the number of samples in each chunk given by `stsc`
+    /// and the size of each sample.
+    pub fn chunk_sizes(&mut self) -> Result<Vec<u64>, ParseError> {
+        let chunk_count = self.co_mut()?.entry_count();
+        let runs = self
+            .stsc_mut()?
+            .entries()
+            .map(|entry| entry.while_parsing_child(NAME, BoxType::STSC))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut sample_sizes = self.sample_sizes()?;
+        let mut chunk_sizes = Vec::with_capacity(chunk_count as usize);
+        let mut runs = runs.iter().peekable();
+        let mut samples_per_chunk = 0;
+        for chunk in 1..=chunk_count {
+            while let Some(run) = runs.next_if(|run| run.first_chunk <= chunk) {
+                samples_per_chunk = run.samples_per_chunk;
+            }
+            let mut chunk_size = 0;
+            for _ in 0..samples_per_chunk {
+                let Some(sample_size) = sample_sizes.next() else {
+                    bail_attach!(
+                        ParseError::InvalidInput,
+                        "fewer samples than chunks require",
+                        WhileParsingChild(NAME, BoxType::STSZ),
+                    );
+                };
+                chunk_size += u64::from(sample_size);
+            }
+            chunk_sizes.push(chunk_size);
+        }
+        Ok(chunk_sizes)
+    }
+
+    /// Returns the span of the input containing the sample numbered `sample_number`, counting from `1`, as located by
+    /// the chunk offsets, the chunk runs in `stsc`, and the sample sizes.
+    ///
+    /// Returns `None` if the tables don't describe a sample with that number.
+    pub fn sample_span(&mut self, sample_number: u32) -> Result<Option<InputSpan>, ParseError> {
+        let Some(sample_index) = sample_number.checked_sub(1) else {
+            return Ok(None);
+        };
+        let chunk_offsets = self.chunk_offsets()?.collect::<Result<Vec<_>, _>>()?;
+        let runs = self
+            .stsc_mut()?
+            .entries()
+            .map(|entry| entry.while_parsing_child(NAME, BoxType::STSC))
+            .collect::<Result<Vec<_>, _>>()?;
+        let constant_sample_size = match self.sample_size_mut()? {
+            StblSampleSizeMut::Stsz(stsz) if stsz.sample_size() != 0 => Some(stsz.sample_size()),
+            _ => None,
+        };
+        let mut sample_sizes = self.sample_sizes()?;
+        let mut runs = runs.iter().peekable();
+        let mut samples_per_chunk = 0;
+        let mut chunk_first_sample_index = 0u32;
+        for (chunk, chunk_offset) in (1..).zip(chunk_offsets) {
+            while let Some(run) = runs.next_if(|run| run.first_chunk <= chunk) {
+                samples_per_chunk = run.samples_per_chunk;
+            }
+            let index_in_chunk = sample_index - chunk_first_sample_index;
+            if index_in_chunk >= samples_per_chunk {
+                if samples_per_chunk != 0 && sample_sizes.nth(samples_per_chunk as usize - 1).is_none() {
+                    return Ok(None);
+                }
+                chunk_first_sample_index += samples_per_chunk;
+                continue;
+            }
+
+            // Samples of a constant size are located directly, rather than by summing the preceding sample sizes.
+            let (preceding_len, sample_size) = match constant_sample_size {
+                Some(sample_size) => (u64::from(sample_size) * u64::from(index_in_chunk), sample_size),
+                None => {
+                    let mut preceding_len = 0;
+                    for _ in 0..index_in_chunk {
+                        let Some(sample_size) = sample_sizes.next() else {
+                            return Ok(None);
+                        };
+                        preceding_len += u64::from(sample_size);
+                    }
+                    let Some(sample_size) = sample_sizes.next() else {
+                        return Ok(None);
+                    };
+                    (preceding_len, sample_size)
+                }
+            };
+            let span = chunk_offset
+                .checked_add(preceding_len)
+                .map(|offset| InputSpan { offset, len: sample_size.into() });
+            return Ok(span);
+        }
+        Ok(None)
+    }
+
+    /// Replace the chunk offsets in `stco` or `co64` with `offsets`, which must have the same number of entries.
+    ///
+    /// If any offset doesn't fit in an `stco` box, it is replaced with an equivalent `co64` box.
+    pub fn set_chunk_offsets(&mut self, offsets: &[u64]) -> Result<(), ParseError> {
+        let co = self.co_mut()?;
+        ensure_attach!(
+            co.entry_count() as usize == offsets.len(),
+            ParseError::InvalidInput,
+            "chunk offset count mismatch",
+            WhileParsingChild(NAME, STCO),
+        );
+        match co {
+            StblCoMut::Stco(stco) if offsets.iter().all(|&offset| offset <= u32::MAX.into()) => {
+                for (mut entry, &offset) in stco.entries_mut().zip(offsets) {
+                    entry.set(offset as u32);
+                }
+            }
+            StblCoMut::Stco(_) => {
+                log::info!("upgrading stco to co64 for chunk offsets");
+                self.children
+                    .replace_one::<StcoBox, _>(Co64Box::from_iter(offsets.iter().copied()))
+                    .while_parsing_child(NAME, STCO)?;
+            }
+            StblCoMut::Co64(co64) => {
+                for (mut entry, &offset) in co64.entries_mut().zip(offsets) {
+                    entry.set(offset);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Add `delta` to each chunk offset in `stco` or `co64`.
+    ///
+    /// If any displaced offset no longer fits in an `stco` box, it is replaced with an equivalent `co64` box.
+    pub fn displace_chunk_offsets(&mut self, delta: i64) -> Result<(), ParseError> {
+        let displace = |offset: u64| {
+            checked_add_signed(offset, delta)
+                .ok_or_else(|| report_attach!(ParseError::InvalidInput, "chunk offset not within mdat"))
+        };
+        let offsets = match self.co_mut()? {
+            StblCoMut::Stco(stco) => {
+                let offsets = stco
+                    .entries_mut()
+                    .map(|entry| displace(entry.get()?.into()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if offsets.iter().any(|&offset| offset > u32::MAX.into()) {
+                    offsets
+                } else {
+                    for (mut entry, offset) in stco.entries_mut().zip(offsets) {
+                        entry.set(offset as u32);
+                    }
+                    return Ok(());
+                }
+            }
+            StblCoMut::Co64(co64) => {
+                for mut entry in co64.entries_mut() {
+                    let offset = displace(entry.get()?)?;
+                    entry.set(offset);
+                }
+                return Ok(());
+            }
+        };
+        log::info!("upgrading stco to co64 for displaced chunk offsets");
+        self.children
+            .replace_one::<StcoBox, _>(Co64Box::from_iter(offsets))
+            .while_parsing_child(NAME, STCO)
+    }
+
+    pub fn remove_padding(&mut self) {
+        self.children.remove_padding();
+    }
+
+    /// Replace the header of each box within this box and its `stsd` box's sample entries with the minimal encoding of
+    /// its size.
+    pub fn canonicalize_box_sizes(&mut self) -> Result<(), ParseError> {
+        self.children.canonicalize_headers()?;
+        if let Some(stsd) = self.stsd_mut()? {
+            stsd.canonicalize_box_sizes().while_parsing_child(NAME, BoxType::STSD)?;
+        }
+        Ok(())
+    }
+
+    pub fn ctts_mut(&mut self) -> Result<Option<&mut CttsBox>, ParseError> {
+        self.children.get_opt_mut().while_parsing_child(NAME, BoxType::CTTS)
+    }
+
+    pub fn stsc_mut(&mut self) -> Result<&mut StscBox, ParseError> {
+        self.children.get_one_mut().while_parsing_child(NAME, BoxType::STSC)
+    }
+
+    /// Returns the number of entries declared by the `stsc` box, reading only its entry count if it hasn't yet been
+    /// parsed, so that the count can be bounded before the entries are read.
+    pub fn stsc_entry_count(&mut self) -> Result<u32, ParseError> {
+        let unparsed_stsc = self.children.iter().find_map(|mp4box| match &mp4box.data {
+            BoxData::Bytes(data) if mp4box.calculated_header().box_type() == BoxType::STSC => Some(data),
+            _ => None,
+        });
+        match unparsed_stsc {
+            Some(data) => StscBox::parse_entry_count(data).while_parsing_child(NAME, BoxType::STSC),
+            None => Ok(self.stsc_mut()?.entry_count()),
+        }
+    }
+
+    /// Whether the samples are encrypted with Common Encryption, as indicated by `saiz`, `saio`, or `senc` sample
+    /// auxiliary information boxes, or by protected `encv` or `enca` sample entries, which carry the `tenc` track
+    /// encryption box.
+    pub fn is_encrypted(&mut self) -> Result<bool, ParseError> {
+        let has_aux_info = self
+            .children
+            .box_types()
+            .any(|box_type| matches!(box_type, BoxType::SAIZ | BoxType::SAIO | BoxType::SENC));
+        if has_aux_info {
+            return Ok(true);
+        }
+        Ok(self.stsd_mut()?.is_some_and(|stsd| stsd.has_protected_entries()))
+    }
+
+    pub fn stsd_mut(&mut self) -> Result<Option<&mut StsdBox>, ParseError> {
+        self.children.get_opt_mut().while_parsing_child(NAME, BoxType::STSD)
+    }
+
+    pub fn stss_mut(&mut self) -> Result<Option<&mut StssBox>, ParseError> {
+        self.children.get_opt_mut().while_parsing_child(NAME, BoxType::STSS)
+    }
+
+    pub fn stsz_mut(&mut self) -> Result<&mut StszBox, ParseError> {
+        self.children.get_one_mut().while_parsing_child(NAME, BoxType::STSZ)
+    }
+
+    pub fn stz2_mut(&mut self) -> Result<&mut Stz2Box, ParseError> {
+        self.children.get_one_mut().while_parsing_child(NAME, BoxType::STZ2)
+    }
+
+    /// Returns the number of samples, from either the `stsz` box or the compact `stz2` box.
+    pub fn sample_count(&mut self) -> Result<u32, ParseError> {
+        match self.sample_size_mut()? {
+            StblSampleSizeMut::Stsz(stsz) => Ok(stsz.sample_count()),
+            StblSampleSizeMut::Stz2(stz2) => Ok(stz2.sample_count()),
+        }
+    }
+
+    /// Returns an iterator over the size of each sample, from either the `stsz` box or the compact `stz2` box.
+    pub fn sample_sizes(&mut self) -> Result<Box<dyn Iterator<Item = u32> + '_>, ParseError> {
+        match self.sample_size_mut()? {
+            StblSampleSizeMut::Stsz(stsz) => stsz.sample_sizes(),
+            StblSampleSizeMut::Stz2(stz2) => stz2.sample_sizes(),
+        }
+    }
+
+    fn sample_size_mut(&mut self) -> Result<StblSampleSizeMut<'_>, ParseError> {
+        let have_stsz = self.children.box_types().any(|box_type| box_type == BoxType::STSZ);
+        let have_stz2 = self.children.box_types().any(|box_type| box_type == BoxType::STZ2);
+        ensure_attach!(
+            !(have_stsz && have_stz2),
+            ParseError::InvalidBoxLayout,
+            "more than one stsz and stz2 present",
+            WhileParsingChild(NAME, BoxType::STSZ),
+        );
+        if have_stz2 {
+            self.stz2_mut().map(StblSampleSizeMut::Stz2)
+        } else {
+            self.stsz_mut().map(StblSampleSizeMut::Stsz)
+        }
+    }
 }
 
 //
@@ -62,3 +356,29 @@ impl StblCoMut<'_> {
         }
     }
 }
+
+/// Convert a chunk offset to a `T`, returning an error rather than truncating if it does not fit.
+fn checked_chunk_offset<T: TryFrom<u64>>(offset: u64) -> Result<T, ParseError> {
+    T::try_from(offset).map_err(|_| {
+        report_attach!(
+            ParseError::InvalidInput,
+            ChunkOffsetOverflow(offset),
+            WhileParsingChild(NAME, CO64),
+        )
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+
+    use super::*;
+
+    #[test]
+    fn checked_chunk_offset_overflow() {
+        let offset = u64::from(u32::MAX);
+        assert_eq!(checked_chunk_offset::<u32>(offset).unwrap(), u32::MAX);
+        let err = checked_chunk_offset::<u32>(offset + 1).unwrap_err();
+        assert_matches!(err.get_ref(), ParseError::InvalidInput);
+    }
+}
@@ -0,0 +1,96 @@
+#![allow(missing_docs)]
+
+use bytes::BytesMut;
+
+use crate::error::Result;
+
+use super::{BoundedArray, ConstFullBoxHeader, Mp4Value, ParseBox, ParseError, ParsedBox};
+
+#[derive(Clone, Debug, Default, ParseBox, ParsedBox)]
+#[box_type = "stsc"]
+pub struct StscBox {
+    header: ConstFullBoxHeader,
+    entries: BoundedArray<u32, [u32; 3]>,
+}
+
+/// An entry in a [`StscBox`], describing a run of chunks with the same number of samples.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StscEntry {
+    /// The 1-based index of the first chunk in this run.
+    pub first_chunk: u32,
+    pub samples_per_chunk: u32,
+    pub sample_description_index: u32,
+}
+
+impl StscBox {
+    pub fn entries(&self) -> impl ExactSizeIterator<Item = Result<StscEntry, ParseError>> + '_ {
+        self.entries.entries().map(|entry| {
+            let [first_chunk, samples_per_chunk, sample_description_index] = entry.get()?;
+            Ok(StscEntry { first_chunk, samples_per_chunk, sample_description_index })
+        })
+    }
+
+    pub fn entry_count(&self) -> u32 {
+        self.entries.entry_count()
+    }
+
+    /// Read the number of entries declared by the unparsed data of an `stsc` box, without reading the entries.
+    pub fn parse_entry_count(data: &[u8]) -> Result<u32, ParseError> {
+        let mut buf = BytesMut::from(&data[..data.len().min(8)]);
+        <ConstFullBoxHeader>::parse(&mut buf)?;
+        u32::parse(&mut buf)
+    }
+}
+
+impl FromIterator<StscEntry> for StscBox {
+    fn from_iter<I: IntoIterator<Item = StscEntry>>(entries: I) -> Self {
+        let entries = entries.into_iter().map(|entry| {
+            let StscEntry { first_chunk, samples_per_chunk, sample_description_index } = entry;
+            [first_chunk, samples_per_chunk, sample_description_index]
+        });
+        Self { header: Default::default(), entries: entries.collect() }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use bytes::BytesMut;
+
+    use crate::parse::{ParseBox, ParsedBox};
+    use crate::util::test::write_test_stsc_data;
+
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let mut buf = BytesMut::new();
+        write_test_stsc_data(&mut buf, &[1, 3]);
+        let stsc = StscBox::parse(&mut buf.clone()).unwrap();
+        let entries: Vec<_> = stsc.entries().map(Result::unwrap).collect();
+        assert_eq!(
+            entries,
+            [
+                StscEntry { first_chunk: 1, samples_per_chunk: 1, sample_description_index: 1 },
+                StscEntry { first_chunk: 3, samples_per_chunk: 1, sample_description_index: 1 },
+            ],
+        );
+
+        let mut output = BytesMut::new();
+        StscBox::from_iter(entries).put_buf(&mut output);
+        assert_eq!(output, buf);
+    }
+
+    #[test]
+    fn inflated_entry_count() {
+        let mut buf = BytesMut::new();
+        write_test_stsc_data(&mut buf, &[1, 3]);
+        // Declare far more entries than the box contains.
+        buf[4..8].copy_from_slice(&0x1000_0000_u32.to_be_bytes());
+        assert_matches!(
+            StscBox::parse(&mut buf.clone()).unwrap_err().into_inner(),
+            ParseError::TruncatedBox
+        );
+        assert_eq!(StscBox::parse_entry_count(&buf).unwrap(), 0x1000_0000);
+    }
+}
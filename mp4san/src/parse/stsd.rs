@@ -0,0 +1,528 @@
+#![allow(missing_docs)]
+
+use bytes::{BufMut, BytesMut};
+
+use crate::error::Result;
+
+use super::error::{ParseResultExt, WhileParsingBox, WhileParsingChild};
+use super::{
+    BoxData, BoxType, Boxes, ColrBox, EsdsBox, FourCC, FullBoxHeader, HvccBox, Mp4Box, Mp4Prim, Mp4Value, ParseBox,
+    ParseError, ParsedBox, PaspBox,
+};
+
+#[derive(Clone, Debug, ParseBox, ParsedBox)]
+#[box_type = "stsd"]
+pub struct StsdBox {
+    /// The version and flags are not validated, and are re-encoded as they were parsed.
+    header: FullBoxHeader,
+    entry_count: u32,
+    entries: Boxes,
+}
+
+/// The fields common to all visual sample entries in a [`StsdBox`], e.g. `avc1` or `hvc1`, followed by their child
+/// boxes.
+#[derive(Clone, Debug)]
+pub struct VisualSampleEntry {
+    fields: [u8; VISUAL_SAMPLE_ENTRY_FIELDS_LEN],
+    children: Boxes,
+    padding: BytesMut,
+}
+
+/// The fields common to all audio sample entries in a [`StsdBox`], e.g. `mp4a`, followed by their child boxes.
+#[derive(Clone, Debug)]
+pub struct AudioSampleEntry {
+    fields: [u8; AUDIO_SAMPLE_ENTRY_FIELDS_LEN],
+    /// The additional fields of a QuickTime version 1 or 2 sound sample description, or, for an unknown version, all of
+    /// the remaining data, which is kept as it is without parsing any child boxes.
+    extension: BytesMut,
+    children: Boxes,
+    padding: BytesMut,
+}
+
+const NAME: BoxType = BoxType::STSD;
+
+/// The minimum size of a box header.
+const MIN_BOX_SIZE: usize = 8;
+
+/// The length of the fields preceding the child boxes in a visual sample entry.
+const VISUAL_SAMPLE_ENTRY_FIELDS_LEN: usize = 78;
+
+/// The length of the fields preceding the child boxes in a version 0 audio sample entry.
+const AUDIO_SAMPLE_ENTRY_FIELDS_LEN: usize = 28;
+
+/// The length of the additional fields of a QuickTime version 1 sound sample description.
+const QUICKTIME_V1_SOUND_EXTENSION_LEN: usize = 16;
+
+/// The length of the additional fields of a QuickTime version 2 sound sample description.
+const QUICKTIME_V2_SOUND_EXTENSION_LEN: usize = 36;
+
+/// The sample entry types known to be audio sample entries.
+const AUDIO_SAMPLE_ENTRY_TYPES: [FourCC; 2] = [FourCC::from_str("enca"), FourCC::from_str("mp4a")];
+
+/// The sample entry types known to be visual sample entries.
+const VISUAL_SAMPLE_ENTRY_TYPES: [FourCC; 12] = [
+    FourCC::from_str("av01"),
+    FourCC::from_str("avc1"),
+    FourCC::from_str("avc3"),
+    FourCC::from_str("dvh1"),
+    FourCC::from_str("dvhe"),
+    FourCC::from_str("encv"),
+    FourCC::from_str("hev1"),
+    FourCC::from_str("hvc1"),
+    FourCC::from_str("mp4v"),
+    FourCC::from_str("s263"),
+    FourCC::from_str("vp08"),
+    FourCC::from_str("vp09"),
+];
+
+impl StsdBox {
+    pub fn entry_count(&self) -> u32 {
+        self.entry_count
+    }
+
+    pub fn entry_types(&self) -> impl ExactSizeIterator<Item = BoxType> + '_ {
+        self.entries.box_types()
+    }
+
+    /// Whether any sample entry is a protected `encv` or `enca` sample entry, as used by Common Encryption.
+    pub fn has_protected_entries(&self) -> bool {
+        self.entry_types()
+            .any(|box_type| matches!(box_type, BoxType::ENCV | BoxType::ENCA))
+    }
+
+    /// Parse the sample entries which are known to be visual sample entries, returning their types and contents.
+    pub fn visual_sample_entries(&self) -> impl Iterator<Item = Result<(BoxType, VisualSampleEntry), ParseError>> + '_ {
+        self.sample_entries(&VISUAL_SAMPLE_ENTRY_TYPES)
+    }
+
+    /// Parse the sample entries which are known to be audio sample entries, returning their types and contents.
+    pub fn audio_sample_entries(&self) -> impl Iterator<Item = Result<(BoxType, AudioSampleEntry), ParseError>> + '_ {
+        self.sample_entries(&AUDIO_SAMPLE_ENTRY_TYPES)
+    }
+
+    fn sample_entries<'a, T: Mp4Value>(
+        &'a self,
+        types: &'a [FourCC],
+    ) -> impl Iterator<Item = Result<(BoxType, T), ParseError>> + 'a {
+        self.entries.iter().filter_map(|entry| {
+            let box_type = entry.calculated_header().box_type();
+            let BoxType::FourCC(fourcc) = box_type else {
+                return None;
+            };
+            if !types.contains(&fourcc) {
+                return None;
+            }
+            let BoxData::Bytes(data) = &entry.data else {
+                return None;
+            };
+            let entry = T::parse(&mut data.clone())
+                .while_parsing_child(NAME, box_type)
+                .map(|entry| (box_type, entry));
+            Some(entry)
+        })
+    }
+
+    /// Replace the header of each sample entry, and of each child box of the sample entries known to be visual or audio
+    /// sample entries, with the minimal encoding of its size.
+    pub fn canonicalize_box_sizes(&mut self) -> Result<(), ParseError> {
+        for entry in self.entries.iter_mut() {
+            let box_type = entry.calculated_header().box_type();
+            if let (BoxType::FourCC(fourcc), BoxData::Bytes(data)) = (box_type, &mut entry.data) {
+                if VISUAL_SAMPLE_ENTRY_TYPES.contains(&fourcc) {
+                    let mut sample_entry =
+                        VisualSampleEntry::parse(&mut data.clone()).while_parsing_child(NAME, box_type)?;
+                    sample_entry.children.canonicalize_headers()?;
+                    *data = encode(&sample_entry);
+                } else if AUDIO_SAMPLE_ENTRY_TYPES.contains(&fourcc) {
+                    let mut sample_entry =
+                        AudioSampleEntry::parse(&mut data.clone()).while_parsing_child(NAME, box_type)?;
+                    sample_entry.children.canonicalize_headers()?;
+                    *data = encode(&sample_entry);
+                }
+            }
+            entry.canonicalize_header()?;
+        }
+        Ok(())
+    }
+
+    /// Parse all `colr` boxes found in visual sample entries.
+    pub fn colr_boxes(&self) -> Result<Vec<ColrBox>, ParseError> {
+        let mut colr_boxes = Vec::new();
+        for entry in self.visual_sample_entries() {
+            let (box_type, entry) = entry?;
+            for colr in entry.children.into_parsed::<ColrBox>() {
+                colr_boxes.push(colr.while_parsing_child(box_type, BoxType::COLR)?);
+            }
+        }
+        Ok(colr_boxes)
+    }
+
+    /// Parse and validate all `pasp` boxes found in visual sample entries.
+    pub fn pasp_boxes(&self) -> Result<Vec<PaspBox>, ParseError> {
+        let mut pasp_boxes = Vec::new();
+        for entry in self.visual_sample_entries() {
+            let (box_type, mut entry) = entry?;
+            if let Some(pasp) = entry.pasp().while_parsing_child(NAME, box_type)? {
+                pasp_boxes.push(pasp);
+            }
+        }
+        Ok(pasp_boxes)
+    }
+
+    /// Parse all `esds` boxes found in audio sample entries, validating their descriptors.
+    pub fn esds_boxes(&self) -> Result<Vec<EsdsBox>, ParseError> {
+        let mut esds_boxes = Vec::new();
+        for entry in self.audio_sample_entries() {
+            let (box_type, entry) = entry?;
+            for esds in entry.children.into_parsed::<EsdsBox>() {
+                esds_boxes.push(esds.while_parsing_child(box_type, BoxType::ESDS)?);
+            }
+        }
+        Ok(esds_boxes)
+    }
+
+    /// Parse and validate the `hvcC` boxes of HEVC visual sample entries.
+    ///
+    /// An `hvc1` sample entry stores the stream's parameter sets only in its `hvcC` box, so it must have an `hvcC` box
+    /// containing at least one each of a video, sequence, and picture parameter set. An `hev1` sample entry may carry
+    /// its parameter sets in-band instead, so its `hvcC` box need not contain any.
+    pub fn validate_hevc_parameter_sets(&self) -> Result<(), ParseError> {
+        for entry in self.visual_sample_entries() {
+            let (box_type, mut entry) = entry?;
+            if !matches!(box_type, BoxType::HVC1 | BoxType::HEV1) {
+                continue;
+            }
+            let mut has_parameter_sets = false;
+            for hvcc in entry.children.get_mut::<HvccBox>() {
+                let hvcc = hvcc.while_parsing_child(box_type, HvccBox::box_type())?;
+                has_parameter_sets |= hvcc.record.has_parameter_sets();
+            }
+            ensure_attach!(
+                box_type != BoxType::HVC1 || has_parameter_sets,
+                ParseError::InvalidInput,
+                "hvc1 sample entry without parameter sets in hvcC",
+                WhileParsingChild(NAME, box_type),
+            );
+        }
+        Ok(())
+    }
+}
+
+fn encode<T: Mp4Value>(value: &T) -> BytesMut {
+    let mut buf = BytesMut::with_capacity(value.encoded_len() as usize);
+    value.put_buf(&mut buf);
+    buf
+}
+
+impl AudioSampleEntry {
+    /// The version of a QuickTime sound sample description, which is always `0` in ISO base media files.
+    pub fn version(&self) -> u16 {
+        u16::from_be_bytes([self.fields[8], self.fields[9]])
+    }
+
+    pub fn channel_count(&self) -> u16 {
+        u16::from_be_bytes([self.fields[16], self.fields[17]])
+    }
+
+    pub fn sample_size(&self) -> u16 {
+        u16::from_be_bytes([self.fields[18], self.fields[19]])
+    }
+
+    /// The integer part of the 16.16 fixed-point sample rate.
+    pub fn sample_rate(&self) -> u16 {
+        u16::from_be_bytes([self.fields[24], self.fields[25]])
+    }
+
+    pub fn children_mut(&mut self) -> &mut Boxes {
+        &mut self.children
+    }
+}
+
+impl VisualSampleEntry {
+    pub fn width(&self) -> u16 {
+        u16::from_be_bytes([self.fields[24], self.fields[25]])
+    }
+
+    pub fn height(&self) -> u16 {
+        u16::from_be_bytes([self.fields[26], self.fields[27]])
+    }
+
+    pub fn children_mut(&mut self) -> &mut Boxes {
+        &mut self.children
+    }
+
+    /// Returns the entry's `pasp` box, if any, after checking that its spacings are non-zero.
+    pub fn pasp(&mut self) -> Result<Option<PaspBox>, ParseError> {
+        let Some(pasp) = self.children.get_opt_mut::<PaspBox>()? else {
+            return Ok(None);
+        };
+        pasp.validate()?;
+        Ok(Some(*pasp))
+    }
+}
+
+impl Mp4Value for VisualSampleEntry {
+    fn parse(buf: &mut BytesMut) -> Result<Self, ParseError> {
+        let fields = Mp4Prim::parse(&mut *buf)?;
+        let mut children = Vec::new();
+        // Some writers terminate sample entries with a few zero bytes too short to be a box.
+        while buf.len() >= MIN_BOX_SIZE || buf.iter().any(|&byte| byte != 0) {
+            children.push(Mp4Box::parse(&mut *buf)?);
+        }
+        Ok(Self { fields, children: children.into(), padding: buf.split() })
+    }
+
+    fn encoded_len(&self) -> u64 {
+        VISUAL_SAMPLE_ENTRY_FIELDS_LEN as u64 + self.children.encoded_len() + self.padding.len() as u64
+    }
+
+    fn put_buf<B: BufMut>(&self, mut buf: B) {
+        Mp4Prim::put_buf(&self.fields, &mut buf);
+        self.children.put_buf(&mut buf);
+        buf.put_slice(&self.padding);
+    }
+}
+
+impl Mp4Value for AudioSampleEntry {
+    fn parse(buf: &mut BytesMut) -> Result<Self, ParseError> {
+        let fields: [u8; AUDIO_SAMPLE_ENTRY_FIELDS_LEN] = Mp4Prim::parse(&mut *buf)?;
+        let extension_len = match u16::from_be_bytes([fields[8], fields[9]]) {
+            0 => 0,
+            1 => QUICKTIME_V1_SOUND_EXTENSION_LEN,
+            2 => QUICKTIME_V2_SOUND_EXTENSION_LEN,
+            _ => buf.len(),
+        };
+        ensure_attach!(
+            buf.len() >= extension_len,
+            ParseError::TruncatedBox,
+            WhileParsingBox(NAME)
+        );
+        let extension = buf.split_to(extension_len);
+        let mut children = Vec::new();
+        // Some writers terminate sample entries with a few zero bytes too short to be a box.
+        while buf.len() >= MIN_BOX_SIZE || buf.iter().any(|&byte| byte != 0) {
+            children.push(Mp4Box::parse(&mut *buf)?);
+        }
+        Ok(Self { fields, extension, children: children.into(), padding: buf.split() })
+    }
+
+    fn encoded_len(&self) -> u64 {
+        AUDIO_SAMPLE_ENTRY_FIELDS_LEN as u64
+            + self.extension.len() as u64
+            + self.children.encoded_len()
+            + self.padding.len() as u64
+    }
+
+    fn put_buf<B: BufMut>(&self, mut buf: B) {
+        Mp4Prim::put_buf(&self.fields, &mut buf);
+        buf.put_slice(&self.extension);
+        self.children.put_buf(&mut buf);
+        buf.put_slice(&self.padding);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+
+    use crate::util::test::{
+        write_test_audio_stsd_data, write_test_stsd_data, write_test_visual_stsd_data,
+        write_test_visual_stsd_data_with_type, HDR10_NCLX_COLR, TEST_ESDS, TEST_HVCC, TEST_PASP,
+    };
+
+    use crate::parse::ColourInformation;
+
+    use super::*;
+
+    fn parse_stsd(data: BytesMut) -> StsdBox {
+        let mut mp4box = Mp4Box::<StsdBox>::with_data(BoxData::Bytes(data)).unwrap();
+        mp4box.data.parse().unwrap().clone()
+    }
+
+    #[test]
+    fn visual_sample_entry() {
+        let mut data = BytesMut::new();
+        write_test_visual_stsd_data(&mut data, HDR10_NCLX_COLR);
+        let stsd = parse_stsd(data);
+        let (box_type, entry) = stsd.visual_sample_entries().next().unwrap().unwrap();
+        assert_eq!(box_type, BoxType::AVC1);
+        assert_eq!((entry.width(), entry.height()), (1920, 1080));
+        assert_eq!(
+            entry.encoded_len(),
+            VISUAL_SAMPLE_ENTRY_FIELDS_LEN as u64 + HDR10_NCLX_COLR.len() as u64
+        );
+
+        let colr_boxes = stsd.colr_boxes().unwrap();
+        assert_eq!(colr_boxes.len(), 1);
+        assert_matches!(
+            colr_boxes[0].info,
+            ColourInformation::Nclx { colour_primaries: 9, transfer_characteristics: 16, matrix_coefficients: 9, .. }
+        );
+    }
+
+    #[test]
+    fn visual_sample_entry_zero_padding() {
+        let children = [HDR10_NCLX_COLR, &[0; 4]].concat();
+        let mut data = BytesMut::new();
+        write_test_visual_stsd_data(&mut data, &children);
+        let stsd = parse_stsd(data);
+        assert_eq!(stsd.colr_boxes().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn visual_sample_entry_pasp() {
+        let children = [HDR10_NCLX_COLR, TEST_PASP].concat();
+        let mut data = BytesMut::new();
+        write_test_visual_stsd_data(&mut data, &children);
+        let stsd = parse_stsd(data);
+        let (_, mut entry) = stsd.visual_sample_entries().next().unwrap().unwrap();
+        let pasp = entry.pasp().unwrap().unwrap();
+        assert_eq!((pasp.h_spacing(), pasp.v_spacing()), (4, 3));
+        assert_eq!(stsd.pasp_boxes().unwrap(), [pasp]);
+
+        let mut children = children.clone();
+        *children.last_mut().unwrap() = 0;
+        let mut data = BytesMut::new();
+        write_test_visual_stsd_data(&mut data, &children);
+        let stsd = parse_stsd(data);
+        assert_matches!(stsd.pasp_boxes().unwrap_err().into_inner(), ParseError::InvalidInput);
+    }
+
+    #[test]
+    fn audio_sample_entry() {
+        let mut data = BytesMut::new();
+        write_test_audio_stsd_data(&mut data, TEST_ESDS);
+        let stsd = parse_stsd(data);
+        assert_eq!(stsd.visual_sample_entries().count(), 0);
+        let (box_type, entry) = stsd.audio_sample_entries().next().unwrap().unwrap();
+        assert_eq!(box_type, BoxType::MP4A);
+        assert_eq!(entry.version(), 0);
+        assert_eq!((entry.channel_count(), entry.sample_size()), (2, 16));
+        assert_eq!(entry.sample_rate(), 44100);
+        assert_eq!(
+            entry.encoded_len(),
+            AUDIO_SAMPLE_ENTRY_FIELDS_LEN as u64 + TEST_ESDS.len() as u64
+        );
+
+        let esds_boxes = stsd.esds_boxes().unwrap();
+        assert_eq!(esds_boxes.len(), 1);
+        assert_eq!(esds_boxes[0].descriptor.object_type_indication(), 0x40);
+    }
+
+    #[test]
+    fn audio_sample_entry_versions() {
+        for (version, extension_len, esds_count) in [
+            (1, QUICKTIME_V1_SOUND_EXTENSION_LEN, 1),
+            (2, QUICKTIME_V2_SOUND_EXTENSION_LEN, 1),
+            (3, 0, 0),
+        ] {
+            let children = [&vec![0; extension_len][..], TEST_ESDS].concat();
+            let mut data = BytesMut::new();
+            write_test_audio_stsd_data(&mut data, &children);
+            data[24..26].copy_from_slice(&u16::to_be_bytes(version));
+            let stsd = parse_stsd(data);
+            let (_, entry) = stsd.audio_sample_entries().next().unwrap().unwrap();
+            assert_eq!(entry.version(), version);
+            assert_eq!(
+                entry.encoded_len(),
+                AUDIO_SAMPLE_ENTRY_FIELDS_LEN as u64 + children.len() as u64
+            );
+            assert_eq!(stsd.esds_boxes().unwrap().len(), esds_count, "version {version}");
+        }
+    }
+
+    #[test]
+    fn truncated_esds() {
+        let mut children = TEST_ESDS.to_vec();
+        children.truncate(children.len() - 3);
+        children[3] -= 3;
+        let mut data = BytesMut::new();
+        write_test_audio_stsd_data(&mut data, &children);
+        let stsd = parse_stsd(data);
+        assert_matches!(stsd.esds_boxes().unwrap_err().into_inner(), ParseError::TruncatedBox);
+    }
+
+    #[test]
+    fn version_and_flags() {
+        let mut data = BytesMut::new();
+        write_test_visual_stsd_data(&mut data, HDR10_NCLX_COLR);
+        data[..4].copy_from_slice(&[1, 0, 0, 1]);
+        let stsd = parse_stsd(data.clone());
+        assert_eq!(stsd.colr_boxes().unwrap().len(), 1);
+
+        let mut encoded = BytesMut::new();
+        stsd.put_buf(&mut encoded);
+        assert_eq!(encoded, data);
+    }
+
+    #[test]
+    fn non_visual_sample_entry() {
+        let mut data = BytesMut::new();
+        write_test_stsd_data(&mut data);
+        let stsd = parse_stsd(data);
+        assert_eq!(stsd.visual_sample_entries().count(), 0);
+        assert!(stsd.colr_boxes().unwrap().is_empty());
+    }
+
+    #[test]
+    fn hevc_parameter_sets() {
+        let no_parameter_sets = [&TEST_HVCC[..30], &[0]].concat();
+        let no_parameter_sets = [&[0, 0, 0, no_parameter_sets.len() as u8], &no_parameter_sets[4..]].concat();
+        for (entry_type, children, valid) in [
+            (BoxType::HVC1, TEST_HVCC, true),
+            (BoxType::HVC1, &no_parameter_sets[..], false),
+            (BoxType::HVC1, &[][..], false),
+            (BoxType::HEV1, TEST_HVCC, true),
+            (BoxType::HEV1, &no_parameter_sets[..], true),
+            (BoxType::HEV1, &[][..], true),
+        ] {
+            let mut data = BytesMut::new();
+            write_test_visual_stsd_data_with_type(&mut data, entry_type, children);
+            let result = parse_stsd(data).validate_hevc_parameter_sets();
+            if valid {
+                result.unwrap_or_else(|err| panic!("{entry_type} {children:?}: {err:?}"));
+            } else {
+                assert_matches!(
+                    result.unwrap_err().into_inner(),
+                    ParseError::InvalidInput,
+                    "{entry_type} {children:?}"
+                );
+            }
+        }
+    }
+
+    /// Re-encode `child`, a box with a 32-bit size, with an extended 64-bit size.
+    fn with_extended_size(child: &[u8]) -> Vec<u8> {
+        let size = child.len() as u64 + 8;
+        [&1u32.to_be_bytes()[..], &child[4..8], &size.to_be_bytes(), &child[8..]].concat()
+    }
+
+    #[test]
+    fn canonicalize_box_sizes() {
+        let write_visual: fn(&mut BytesMut, &[u8]) = |out, children| write_test_visual_stsd_data(out, children);
+        let write_audio: fn(&mut BytesMut, &[u8]) = |out, children| write_test_audio_stsd_data(out, children);
+        for (write_stsd_data, child) in [(write_visual, HDR10_NCLX_COLR), (write_audio, TEST_ESDS)] {
+            let mut data = BytesMut::new();
+            write_stsd_data(&mut data, &with_extended_size(child));
+            let mut stsd = parse_stsd(data);
+            stsd.canonicalize_box_sizes().unwrap();
+
+            let mut canonical = BytesMut::new();
+            write_stsd_data(&mut canonical, child);
+            let mut encoded = BytesMut::new();
+            stsd.put_buf(&mut encoded);
+            assert_eq!(encoded, canonical);
+        }
+    }
+
+    #[test]
+    fn truncated_colr() {
+        let mut children = HDR10_NCLX_COLR.to_vec();
+        children.pop();
+        children[3] -= 1;
+        let mut data = BytesMut::new();
+        write_test_visual_stsd_data(&mut data, &children);
+        let stsd = parse_stsd(data);
+        assert_matches!(stsd.colr_boxes().unwrap_err().into_inner(), ParseError::TruncatedBox);
+    }
+}
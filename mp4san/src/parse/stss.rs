@@ -0,0 +1,76 @@
+#![allow(missing_docs)]
+
+use crate::error::Result;
+
+use super::{BoundedArray, ConstFullBoxHeader, ParseBox, ParseError, ParsedBox};
+
+/// A sync sample box, listing the 1-based numbers of the track's sync samples, e.g. key frames.
+#[derive(Clone, Debug, Default, ParseBox, ParsedBox)]
+#[box_type = "stss"]
+pub struct StssBox {
+    header: ConstFullBoxHeader,
+    entries: BoundedArray<u32, u32>,
+}
+
+impl StssBox {
+    pub fn entry_count(&self) -> u32 {
+        self.entries.entry_count()
+    }
+
+    /// Returns an iterator over the 1-based number of each sync sample.
+    pub fn sample_numbers(&self) -> impl ExactSizeIterator<Item = Result<u32, ParseError>> + '_ {
+        self.entries.entries().map(|entry| entry.get())
+    }
+}
+
+impl FromIterator<u32> for StssBox {
+    fn from_iter<I: IntoIterator<Item = u32>>(sample_numbers: I) -> Self {
+        Self { header: Default::default(), entries: sample_numbers.into_iter().collect() }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use bytes::BytesMut;
+
+    use crate::parse::TrakBox;
+    use crate::util::test::test_moov;
+
+    use super::*;
+
+    fn validate_trak_stss(sample_count: usize, stss: StssBox) -> Result<(), ParseError> {
+        let mut moov = test_moov().co_entries(vec![0; sample_count]).stss(stss).build();
+        let trak: &mut TrakBox = moov.data.parse()?.traks().next().unwrap()?;
+        trak.validate_sync_samples()
+    }
+
+    #[test]
+    fn roundtrip() {
+        let stss = StssBox::from_iter([1, 3]);
+        let mut buf = BytesMut::new();
+        stss.put_buf(&mut buf);
+        let parsed = StssBox::parse(&mut buf.clone()).unwrap();
+        assert_eq!(parsed.encoded_len(), buf.len() as u64);
+        let sample_numbers = parsed.sample_numbers().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(sample_numbers, [1, 3]);
+    }
+
+    #[test]
+    fn valid_sample_numbers() {
+        validate_trak_stss(3, StssBox::from_iter([1, 3])).unwrap();
+        validate_trak_stss(3, StssBox::default()).unwrap();
+    }
+
+    #[test]
+    fn sample_number_beyond_sample_count() {
+        let err = validate_trak_stss(3, StssBox::from_iter([1, 4])).unwrap_err();
+        assert_matches!(err.into_inner(), ParseError::InvalidInput);
+    }
+
+    #[test]
+    fn zero_sample_number() {
+        let err = validate_trak_stss(3, StssBox::from_iter([0])).unwrap_err();
+        assert_matches!(err.into_inner(), ParseError::InvalidInput);
+    }
+}
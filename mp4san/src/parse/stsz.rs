@@ -0,0 +1,66 @@
+#![allow(missing_docs)]
+
+use std::iter;
+
+use crate::error::Result;
+
+use super::error::{WhereEq, WhileParsingBox};
+use super::{BoxType, ConstFullBoxHeader, ParseBox, ParseError, ParsedBox, UnboundedArray};
+
+#[derive(Clone, Debug, Default, ParseBox, ParsedBox)]
+#[box_type = "stsz"]
+pub struct StszBox {
+    header: ConstFullBoxHeader,
+    sample_size: u32,
+    sample_count: u32,
+    entries: UnboundedArray<u32>,
+}
+
+impl StszBox {
+    pub fn sample_size(&self) -> u32 {
+        self.sample_size
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// Returns an iterator over the size of each sample, after checking the number of entries.
+    pub fn sample_sizes(&self) -> Result<Box<dyn Iterator<Item = u32> + '_>, ParseError> {
+        if self.sample_size != 0 {
+            return Ok(Box::new(
+                iter::repeat(self.sample_size).take(self.sample_count as usize),
+            ));
+        }
+        ensure_attach!(
+            self.entries.entry_count() as u64 == u64::from(self.sample_count),
+            ParseError::InvalidInput,
+            "stsz entries do not match sample count",
+            WhereEq("sample count", self.sample_count),
+            WhileParsingBox(BoxType::STSZ),
+        );
+        Ok(Box::new(self.entries.entries().flat_map(|entry| entry.get().ok())))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::BytesMut;
+
+    use crate::parse::{ParseBox, ParsedBox};
+    use crate::util::test::write_test_stsz_data;
+
+    use super::StszBox;
+
+    #[test]
+    fn roundtrip() {
+        let mut buf = BytesMut::new();
+        write_test_stsz_data(&mut buf, 3);
+        let stsz = StszBox::parse(&mut buf.clone()).unwrap();
+        assert_eq!(stsz.sample_count(), 3);
+
+        let mut output = BytesMut::new();
+        stsz.put_buf(&mut output);
+        assert_eq!(output, buf);
+    }
+}
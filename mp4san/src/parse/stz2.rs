@@ -0,0 +1,168 @@
+#![allow(missing_docs)]
+
+use derive_more::Display;
+
+use crate::error::Result;
+
+use super::error::{WhereEq, WhileParsingBox};
+use super::{BoxType, ConstFullBoxHeader, ParseBox, ParseError, ParsedBox, UnboundedArray};
+
+/// The compact sample size box, an alternative to [`StszBox`](super::StszBox) with 4, 8, or 16-bit entries.
+#[derive(Clone, Debug, Default, ParseBox, ParsedBox)]
+#[box_type = "stz2"]
+pub struct Stz2Box {
+    header: ConstFullBoxHeader,
+    reserved: [u8; 3],
+    field_size: u8,
+    sample_count: u32,
+    entries: UnboundedArray<u8>,
+}
+
+#[derive(Clone, Copy, Debug, Display)]
+#[display(fmt = "invalid stz2 field size `{}`", _0)]
+struct InvalidFieldSize(u8);
+
+const NAME: BoxType = BoxType::STZ2;
+
+impl Stz2Box {
+    /// Construct an [`Stz2Box`] from sample sizes, packed into entries of `field_size` bits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `field_size` is not 4, 8, or 16, or if a sample size does not fit in `field_size` bits.
+    pub fn from_sample_sizes<I: IntoIterator<Item = u16>>(field_size: u8, sample_sizes: I) -> Self {
+        let sample_sizes: Vec<u16> = sample_sizes.into_iter().collect();
+        assert!(matches!(field_size, 4 | 8 | 16), "invalid stz2 field size {field_size}");
+        assert!(sample_sizes.iter().all(|&size| u32::from(size) < 1 << field_size));
+        let entries = match field_size {
+            4 => sample_sizes
+                .chunks(2)
+                .map(|pair| (pair[0] << 4 | pair.get(1).copied().unwrap_or(0)) as u8)
+                .collect(),
+            8 => sample_sizes.iter().map(|&size| size as u8).collect(),
+            _ => sample_sizes.iter().flat_map(|size| size.to_be_bytes()).collect(),
+        };
+        Self {
+            header: Default::default(),
+            reserved: Default::default(),
+            field_size,
+            sample_count: sample_sizes.len() as u32,
+            entries,
+        }
+    }
+
+    pub fn field_size(&self) -> u8 {
+        self.field_size
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// Returns an iterator over the size of each sample, after checking the field size and the length of the entries.
+    pub fn sample_sizes(&self) -> Result<Box<dyn Iterator<Item = u32> + '_>, ParseError> {
+        ensure_attach!(
+            matches!(self.field_size, 4 | 8 | 16),
+            ParseError::InvalidInput,
+            InvalidFieldSize(self.field_size),
+            WhileParsingBox(NAME),
+        );
+        let entries = self.entries.as_bytes();
+        let expected_entries_len = (u64::from(self.field_size) * u64::from(self.sample_count) + 7) / 8;
+        ensure_attach!(
+            entries.len() as u64 == expected_entries_len,
+            ParseError::InvalidInput,
+            "stz2 entries do not match sample count",
+            WhereEq("sample count", self.sample_count),
+            WhileParsingBox(NAME),
+        );
+
+        let sample_sizes: Box<dyn Iterator<Item = u32> + '_> = match self.field_size {
+            4 => Box::new(entries.iter().flat_map(|&byte| [byte >> 4, byte & 0xf]).map(u32::from)),
+            8 => Box::new(entries.iter().copied().map(u32::from)),
+            _ => Box::new(
+                entries
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_be_bytes([pair[0], pair[1]]).into()),
+            ),
+        };
+        Ok(Box::new(sample_sizes.take(self.sample_count as usize)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use bytes::BytesMut;
+
+    use crate::parse::{ParseBox, ParsedBox};
+    use crate::util::test::write_test_stz2_data;
+
+    use super::*;
+
+    fn roundtrip_sample_sizes(field_size: u8, sample_sizes: &[u16]) {
+        let stz2 = Stz2Box::from_sample_sizes(field_size, sample_sizes.iter().copied());
+        let mut buf = BytesMut::new();
+        stz2.put_buf(&mut buf);
+        let stz2 = Stz2Box::parse(&mut buf).unwrap();
+        assert_eq!(stz2.field_size(), field_size);
+        assert_eq!(stz2.sample_count(), sample_sizes.len() as u32);
+        let expected: Vec<u32> = sample_sizes.iter().copied().map(u32::from).collect();
+        assert_eq!(stz2.sample_sizes().unwrap().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn roundtrip() {
+        let mut buf = BytesMut::new();
+        write_test_stz2_data(&mut buf, 16, 3);
+        let stz2 = Stz2Box::parse(&mut buf.clone()).unwrap();
+        assert_eq!(stz2.field_size(), 16);
+        assert_eq!(stz2.sample_count(), 3);
+
+        let mut output = BytesMut::new();
+        stz2.put_buf(&mut output);
+        assert_eq!(output, buf);
+    }
+
+    #[test]
+    fn field_size_4() {
+        roundtrip_sample_sizes(4, &[1, 15, 0, 7, 9]);
+        roundtrip_sample_sizes(4, &[1, 15, 0, 7]);
+
+        let stz2 = Stz2Box::from_sample_sizes(4, [0xa, 0xb, 0xc]);
+        assert_eq!(stz2.entries.as_bytes(), [0xab, 0xc0]);
+    }
+
+    #[test]
+    fn field_size_8() {
+        roundtrip_sample_sizes(8, &[1, 255, 0, 128]);
+    }
+
+    #[test]
+    fn field_size_16() {
+        roundtrip_sample_sizes(16, &[1, 65535, 0, 256]);
+    }
+
+    #[test]
+    fn invalid_field_size() {
+        let mut buf = BytesMut::new();
+        write_test_stz2_data(&mut buf, 12, 2);
+        let stz2 = Stz2Box::parse(&mut buf).unwrap();
+        assert_matches!(
+            stz2.sample_sizes().err().unwrap().into_inner(),
+            ParseError::InvalidInput
+        );
+    }
+
+    #[test]
+    fn truncated_entries() {
+        let mut buf = BytesMut::new();
+        write_test_stz2_data(&mut buf, 4, 3);
+        buf.truncate(buf.len() - 1);
+        let stz2 = Stz2Box::parse(&mut buf).unwrap();
+        assert_matches!(
+            stz2.sample_sizes().err().unwrap().into_inner(),
+            ParseError::InvalidInput
+        );
+    }
+}
@@ -0,0 +1,207 @@
+#![allow(missing_docs)]
+
+use bytes::{BufMut, BytesMut};
+
+use crate::error::Result;
+
+use super::error::UnsupportedVersion;
+use super::{FullBoxHeader, Mp4Value, ParseError};
+
+/// The version-dependent full box header and timing fields shared by the `mvhd` and `mdhd` boxes.
+///
+/// Version 0 boxes encode the creation time, modification time, and duration as 32-bit values, and version 1 boxes as
+/// 64-bit values.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MediaTimes {
+    header: FullBoxHeader,
+    creation_time: u64,
+    modification_time: u64,
+    timescale: u32,
+    duration: u64,
+}
+
+impl MediaTimes {
+    /// Construct the timing fields of a box with the given version.
+    ///
+    /// Returns [`None`] if `version` is neither 0 nor 1, or if it is 0 and any of the times or the duration doesn't fit
+    /// in 32 bits.
+    pub fn new(version: u8, creation_time: u64, modification_time: u64, timescale: u32, duration: u64) -> Option<Self> {
+        let fits_v0 = |value: u64| u32::try_from(value).is_ok();
+        match version {
+            0 if fits_v0(creation_time) && fits_v0(modification_time) && fits_v0(duration) => {}
+            1 => {}
+            _ => return None,
+        }
+        let header = FullBoxHeader { version, flags: 0 };
+        Some(Self { header, creation_time, modification_time, timescale, duration })
+    }
+
+    pub fn version(&self) -> u8 {
+        self.header.version
+    }
+
+    pub fn creation_time(&self) -> u64 {
+        self.creation_time
+    }
+
+    pub fn modification_time(&self) -> u64 {
+        self.modification_time
+    }
+
+    /// Set the creation and modification times to zero, e.g. to avoid revealing when the media was created.
+    pub fn zero_timestamps(&mut self) {
+        self.creation_time = 0;
+        self.modification_time = 0;
+    }
+
+    pub fn timescale(&self) -> u32 {
+        self.timescale
+    }
+
+    /// The duration, in units of the [timescale](Self::timescale), or [`None`] if the duration is unknown.
+    pub fn duration(&self) -> Option<u64> {
+        let unknown = match self.header.version {
+            0 => u32::MAX.into(),
+            _ => u64::MAX,
+        };
+        (self.duration != unknown).then_some(self.duration)
+    }
+
+    /// The duration in seconds, or [`None`] if the duration is unknown or the timescale is zero.
+    pub fn duration_secs(&self) -> Option<f64> {
+        let duration = self.duration()?;
+        if self.timescale == 0 {
+            return None;
+        }
+        Some(duration as f64 / f64::from(self.timescale))
+    }
+}
+
+impl Mp4Value for MediaTimes {
+    fn parse(buf: &mut BytesMut) -> Result<Self, ParseError> {
+        let header = FullBoxHeader::parse(&mut *buf)?;
+        let (creation_time, modification_time, timescale, duration) = match header.version {
+            0 => (
+                u32::parse(&mut *buf)?.into(),
+                u32::parse(&mut *buf)?.into(),
+                u32::parse(&mut *buf)?,
+                u32::parse(&mut *buf)?.into(),
+            ),
+            1 => (
+                u64::parse(&mut *buf)?,
+                u64::parse(&mut *buf)?,
+                u32::parse(&mut *buf)?,
+                u64::parse(&mut *buf)?,
+            ),
+            version => bail_attach!(ParseError::InvalidInput, UnsupportedVersion(version)),
+        };
+        Ok(Self { header, creation_time, modification_time, timescale, duration })
+    }
+
+    fn encoded_len(&self) -> u64 {
+        match self.header.version {
+            0 => 4 + 4 * 4,
+            _ => 4 + 3 * 8 + 4,
+        }
+    }
+
+    fn put_buf<B: BufMut>(&self, mut buf: B) {
+        self.header.put_buf(&mut buf);
+        match self.header.version {
+            0 => {
+                buf.put_u32(self.creation_time as u32);
+                buf.put_u32(self.modification_time as u32);
+                buf.put_u32(self.timescale);
+                buf.put_u32(self.duration as u32);
+            }
+            _ => {
+                buf.put_u64(self.creation_time);
+                buf.put_u64(self.modification_time);
+                buf.put_u32(self.timescale);
+                buf.put_u64(self.duration);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn roundtrip_v0() {
+        let times = MediaTimes::new(0, 1, 2, 3, 4).unwrap();
+        let mut buf = BytesMut::new();
+        times.put_buf(&mut buf);
+        assert_eq!(buf.len() as u64, times.encoded_len());
+        assert_eq!(MediaTimes::parse(&mut buf).unwrap(), times);
+    }
+
+    #[test]
+    fn roundtrip_v1() {
+        let times = MediaTimes::new(1, u64::MAX - 1, u64::MAX - 2, 3, u64::MAX - 4).unwrap();
+        let mut buf = BytesMut::new();
+        times.put_buf(&mut buf);
+        assert_eq!(buf.len() as u64, times.encoded_len());
+        assert_eq!(MediaTimes::parse(&mut buf).unwrap(), times);
+    }
+
+    #[test]
+    fn v0_overflow() {
+        let times = MediaTimes::new(0, u32::MAX.into(), u32::MAX.into(), 1, u32::MAX.into()).unwrap();
+        assert_eq!(times.version(), 0);
+        assert_eq!(MediaTimes::new(0, u64::from(u32::MAX) + 1, 0, 1, 0), None);
+        assert_eq!(MediaTimes::new(0, 0, u64::from(u32::MAX) + 1, 1, 0), None);
+        assert_eq!(MediaTimes::new(0, 0, 0, 1, u64::from(u32::MAX) + 1), None);
+    }
+
+    #[test]
+    fn invalid_version() {
+        assert_eq!(MediaTimes::new(2, 0, 0, 1, 0), None);
+
+        let mut buf = BytesMut::new();
+        MediaTimes::new(1, 0, 0, 1, 0).unwrap().put_buf(&mut buf);
+        buf[0] = 2;
+        assert_matches::assert_matches!(
+            MediaTimes::parse(&mut buf).unwrap_err().into_inner(),
+            ParseError::InvalidInput
+        );
+    }
+
+    #[test]
+    fn duration_secs() {
+        assert_eq!(MediaTimes::new(0, 0, 0, 1000, 1500).unwrap().duration_secs(), Some(1.5));
+        assert_eq!(
+            MediaTimes::new(1, 0, 0, 90000, 90000 * 3600).unwrap().duration_secs(),
+            Some(3600.0)
+        );
+    }
+
+    #[test]
+    fn duration_secs_v1_large() {
+        let duration_secs = MediaTimes::new(1, 0, 0, 1, u64::MAX - 1)
+            .unwrap()
+            .duration_secs()
+            .unwrap();
+        assert_eq!(duration_secs, (u64::MAX - 1) as f64);
+    }
+
+    #[test]
+    fn duration_secs_zero_timescale() {
+        assert_eq!(MediaTimes::new(0, 0, 0, 0, 1500).unwrap().duration_secs(), None);
+        assert_eq!(MediaTimes::new(1, 0, 0, 0, 1500).unwrap().duration_secs(), None);
+    }
+
+    #[test]
+    fn duration_unknown() {
+        assert_eq!(
+            MediaTimes::new(0, 0, 0, 1000, u32::MAX.into()).unwrap().duration(),
+            None
+        );
+        assert_eq!(MediaTimes::new(1, 0, 0, 1000, u64::MAX).unwrap().duration(), None);
+        assert_eq!(
+            MediaTimes::new(1, 0, 0, 1000, u32::MAX.into()).unwrap().duration(),
+            Some(u32::MAX.into())
+        );
+    }
+}
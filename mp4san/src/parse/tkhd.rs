@@ -0,0 +1,170 @@
+#![allow(missing_docs)]
+
+use bytes::{BufMut, BytesMut};
+
+use crate::error::Result;
+
+use super::error::UnsupportedVersion;
+use super::{FullBoxHeader, Mp4Value, ParseBox, ParseError, ParsedBox};
+
+#[derive(Clone, Debug, ParseBox, ParsedBox)]
+#[box_type = "tkhd"]
+pub struct TkhdBox {
+    times: TrackTimes,
+    reserved: [u32; 2],
+    pub layer: i16,
+    pub alternate_group: i16,
+    pub volume: i16,
+    reserved_2: u16,
+    pub matrix: [i32; 9],
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The version-dependent full box header and timing fields of the `tkhd` box.
+///
+/// Version 0 boxes encode the creation time, modification time, and duration as 32-bit values, and version 1 boxes as
+/// 64-bit values.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct TrackTimes {
+    header: FullBoxHeader,
+    creation_time: u64,
+    modification_time: u64,
+    track_id: u32,
+    reserved: u32,
+    duration: u64,
+}
+
+impl TkhdBox {
+    pub fn version(&self) -> u8 {
+        self.times.header.version
+    }
+
+    pub fn flags(&self) -> u32 {
+        self.times.header.flags
+    }
+
+    pub fn creation_time(&self) -> u64 {
+        self.times.creation_time
+    }
+
+    pub fn modification_time(&self) -> u64 {
+        self.times.modification_time
+    }
+
+    /// Set the creation and modification times to zero, e.g. to avoid revealing when the media was created.
+    pub fn zero_timestamps(&mut self) {
+        self.times.creation_time = 0;
+        self.times.modification_time = 0;
+    }
+
+    pub fn track_id(&self) -> u32 {
+        self.times.track_id
+    }
+
+    /// The duration, in units of the movie timescale.
+    pub fn duration(&self) -> u64 {
+        self.times.duration
+    }
+}
+
+impl Mp4Value for TrackTimes {
+    fn parse(buf: &mut BytesMut) -> Result<Self, ParseError> {
+        let header = FullBoxHeader::parse(&mut *buf)?;
+        let (creation_time, modification_time, track_id, reserved, duration) = match header.version {
+            0 => (
+                u32::parse(&mut *buf)?.into(),
+                u32::parse(&mut *buf)?.into(),
+                u32::parse(&mut *buf)?,
+                u32::parse(&mut *buf)?,
+                u32::parse(&mut *buf)?.into(),
+            ),
+            1 => (
+                u64::parse(&mut *buf)?,
+                u64::parse(&mut *buf)?,
+                u32::parse(&mut *buf)?,
+                u32::parse(&mut *buf)?,
+                u64::parse(&mut *buf)?,
+            ),
+            version => bail_attach!(ParseError::InvalidInput, UnsupportedVersion(version)),
+        };
+        Ok(Self { header, creation_time, modification_time, track_id, reserved, duration })
+    }
+
+    fn encoded_len(&self) -> u64 {
+        match self.header.version {
+            0 => 4 + 5 * 4,
+            _ => 4 + 3 * 8 + 2 * 4,
+        }
+    }
+
+    fn put_buf<B: BufMut>(&self, mut buf: B) {
+        self.header.put_buf(&mut buf);
+        match self.header.version {
+            0 => {
+                buf.put_u32(self.creation_time as u32);
+                buf.put_u32(self.modification_time as u32);
+                buf.put_u32(self.track_id);
+                buf.put_u32(self.reserved);
+                buf.put_u32(self.duration as u32);
+            }
+            _ => {
+                buf.put_u64(self.creation_time);
+                buf.put_u64(self.modification_time);
+                buf.put_u32(self.track_id);
+                buf.put_u32(self.reserved);
+                buf.put_u64(self.duration);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::BytesMut;
+
+    use crate::parse::{ParseBox, ParsedBox};
+    use crate::util::test::{write_test_tkhd_data, TEST_CREATION_TIME, TEST_MODIFICATION_TIME};
+
+    use super::TkhdBox;
+
+    #[test]
+    fn roundtrip() {
+        let mut buf = BytesMut::new();
+        write_test_tkhd_data(&mut buf, 7);
+        let tkhd = TkhdBox::parse(&mut buf.clone()).unwrap();
+        assert_eq!(tkhd.encoded_len(), buf.len() as u64);
+        assert_eq!(tkhd.track_id(), 7);
+        assert_eq!(tkhd.creation_time(), u64::from(TEST_CREATION_TIME));
+        assert_eq!(tkhd.modification_time(), u64::from(TEST_MODIFICATION_TIME));
+
+        let mut output = BytesMut::new();
+        tkhd.put_buf(&mut output);
+        assert_eq!(output, buf);
+    }
+
+    #[test]
+    fn version_1() {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(&[1, 0, 0, 3]); // version, flags
+        buf.extend_from_slice(&u64::MAX.to_be_bytes()); // creation time
+        buf.extend_from_slice(&(u64::MAX - 1).to_be_bytes()); // modification time
+        buf.extend_from_slice(&2u32.to_be_bytes()); // track id
+        buf.extend_from_slice(&[0; 4]); // reserved
+        buf.extend_from_slice(&(1u64 << 32).to_be_bytes()); // duration
+        buf.extend_from_slice(&[0; 60]); // reserved, layer, alternate group, volume, reserved, matrix, width, height
+        let mut tkhd = TkhdBox::parse(&mut buf.clone()).unwrap();
+        assert_eq!((tkhd.version(), tkhd.flags()), (1, 3));
+        assert_eq!(tkhd.creation_time(), u64::MAX);
+        assert_eq!(tkhd.modification_time(), u64::MAX - 1);
+        assert_eq!(tkhd.track_id(), 2);
+        assert_eq!(tkhd.duration(), 1 << 32);
+        assert_eq!(tkhd.encoded_len(), buf.len() as u64);
+
+        tkhd.zero_timestamps();
+        let mut output = BytesMut::new();
+        tkhd.put_buf(&mut output);
+        assert_eq!(output[4..20], [0; 16]);
+        assert_eq!(output[20..], buf[20..]);
+    }
+}
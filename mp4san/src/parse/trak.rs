@@ -1,10 +1,16 @@
 #![allow(missing_docs)]
 
+use mediasan_common::InputSpan;
+
 use crate::error::Result;
 
-use super::error::ParseResultExt;
+use super::error::{ParseResultExt, WhereEq, WhileParsingChild};
+use super::meta::strip_child_metadata_items;
 use super::mp4box::Boxes;
-use super::{BoxType, MdiaBox, ParseBox, ParseError, ParsedBox, StblCoMut};
+use super::{
+    fourcc, BoxType, ColrBox, CttsBox, EdtsBox, ElstBox, EsdsBox, FourCC, MdiaBox, ParseBox, ParseError, ParsedBox,
+    PaspBox, StblBox, StblCoMut, TkhdBox, TrefBox,
+};
 
 #[derive(Clone, Debug, ParseBox, ParsedBox)]
 #[box_type = "trak"]
@@ -21,7 +27,310 @@ impl TrakBox {
     }
 
     pub fn co_mut(&mut self) -> Result<StblCoMut<'_>, ParseError> {
-        self.mdia_mut()?.minf_mut()?.stbl_mut()?.co_mut()
+        self.stbl_mut()?.co_mut()
+    }
+
+    /// Returns an iterator over the offset of each chunk in the track, from either its `stco` box or its `co64` box.
+    pub fn chunk_offsets(&mut self) -> Result<Box<dyn Iterator<Item = Result<u64, ParseError>> + '_>, ParseError> {
+        self.stbl_mut()?.chunk_offsets()
+    }
+
+    /// Returns an iterator over the offset of each chunk in the track as a `usize`, returning an error for any offset
+    /// which does not fit.
+    pub fn usize_chunk_offsets(&mut self) -> Result<impl Iterator<Item = Result<usize, ParseError>> + '_, ParseError> {
+        self.stbl_mut()?.usize_chunk_offsets()
+    }
+
+    /// Returns the size in bytes of each chunk in the track.
+    pub fn chunk_sizes(&mut self) -> Result<Vec<u64>, ParseError> {
+        self.stbl_mut()?.chunk_sizes()
+    }
+
+    /// Replace the track's chunk offsets with `offsets`, upgrading `stco` to `co64` if necessary.
+    pub fn set_chunk_offsets(&mut self, offsets: &[u64]) -> Result<(), ParseError> {
+        self.stbl_mut()?.set_chunk_offsets(offsets)
+    }
+
+    /// Returns the number of samples in the track, from either its `stsz` box or its compact `stz2` box.
+    pub fn sample_count(&mut self) -> Result<u32, ParseError> {
+        self.stbl_mut()?.sample_count()
+    }
+
+    /// Returns an iterator over the size of each sample in the track, from either its `stsz` box or its compact `stz2`
+    /// box.
+    pub fn sample_sizes(&mut self) -> Result<Box<dyn Iterator<Item = u32> + '_>, ParseError> {
+        self.stbl_mut()?.sample_sizes()
+    }
+
+    /// Returns the track's `ctts` box, if any, after checking that it describes the same number of samples as `stsz`.
+    pub fn ctts_mut(&mut self) -> Result<Option<&mut CttsBox>, ParseError> {
+        let stbl = self.stbl_mut()?;
+        if stbl.ctts_mut()?.is_none() {
+            return Ok(None);
+        }
+        let sample_count = stbl.sample_count()?;
+        let ctts = stbl.ctts_mut()?.unwrap_or_else(|| unreachable!());
+        let ctts_sample_count = ctts.sample_count()?;
+        ensure_attach!(
+            ctts_sample_count == u64::from(sample_count),
+            ParseError::InvalidInput,
+            "ctts sample count does not match stsz",
+            WhereEq("ctts sample count", ctts_sample_count),
+            WhereEq("stsz sample count", sample_count),
+            WhileParsingChild(BoxType::STBL, BoxType::CTTS),
+        );
+        Ok(Some(ctts))
+    }
+
+    /// Check that each sample number in the track's `stss` box, if any, refers to a sample in `stsz`.
+    pub fn validate_sync_samples(&mut self) -> Result<(), ParseError> {
+        let stbl = self.stbl_mut()?;
+        let Some(stss) = stbl.stss_mut()? else {
+            return Ok(());
+        };
+        let mut max_sample_number = 0;
+        for sample_number in stss.sample_numbers() {
+            let sample_number = sample_number.while_parsing_child(BoxType::STBL, BoxType::STSS)?;
+            ensure_attach!(
+                sample_number != 0,
+                ParseError::InvalidInput,
+                "zero stss sample number",
+                WhileParsingChild(BoxType::STBL, BoxType::STSS),
+            );
+            max_sample_number = max_sample_number.max(sample_number);
+        }
+        let sample_count = stbl.sample_count()?;
+        ensure_attach!(
+            max_sample_number <= sample_count,
+            ParseError::InvalidInput,
+            "stss sample number not within stsz sample count",
+            WhereEq("sample number", max_sample_number),
+            WhereEq("stsz sample count", sample_count),
+            WhileParsingChild(BoxType::STBL, BoxType::STSS),
+        );
+        Ok(())
+    }
+
+    /// Returns the span of the input containing the track's first sync sample, as listed in its `stss` box.
+    ///
+    /// Returns `None` if the track has no `stss` box, or if its first sync sample isn't described by the track's
+    /// sample tables.
+    pub fn first_sync_sample_span(&mut self) -> Result<Option<InputSpan>, ParseError> {
+        let stbl = self.stbl_mut()?;
+        let Some(stss) = stbl.stss_mut()? else {
+            return Ok(None);
+        };
+        let Some(sample_number) = stss.sample_numbers().next() else {
+            return Ok(None);
+        };
+        let sample_number = sample_number.while_parsing_child(BoxType::STBL, BoxType::STSS)?;
+        stbl.sample_span(sample_number)
+    }
+
+    /// Whether the track's handler type is [`vide`](crate::VIDEO_HANDLER_TYPE). A track without an `hdlr` box is not
+    /// considered a video track.
+    pub fn is_video(&mut self) -> Result<bool, ParseError> {
+        let mdia = self.mdia_mut()?;
+        if !mdia.children.box_types().any(|box_type| box_type == BoxType::HDLR) {
+            return Ok(false);
+        }
+        Ok(mdia.handler_type()? == crate::VIDEO_HANDLER_TYPE)
+    }
+
+    pub fn displace_chunk_offsets(&mut self, delta: i64) -> Result<(), ParseError> {
+        self.stbl_mut()?.displace_chunk_offsets(delta)
+    }
+
+    /// Remove all `free` and `skip` padding boxes from this box and its `mdia`, `minf`, and `stbl` descendants.
+    pub fn remove_padding(&mut self) -> Result<(), ParseError> {
+        self.children.remove_padding();
+        self.mdia_mut()?.remove_padding()
+    }
+
+    /// Replace the header of each box within this box and its `edts`, `mdia`, `minf`, `stbl`, and `stsd` descendants,
+    /// including the sample entries, with the minimal encoding of its size.
+    pub fn canonicalize_box_sizes(&mut self) -> Result<(), ParseError> {
+        self.children.canonicalize_headers()?;
+        if let Some(edts) = self.edts_mut()? {
+            edts.canonicalize_box_sizes()?;
+        }
+        self.mdia_mut()?.canonicalize_box_sizes()
+    }
+
+    /// Returns the number of entries declared by the track's `stsc` box, each describing a run of chunks, without
+    /// reading them.
+    pub fn stsc_entry_count(&mut self) -> Result<u32, ParseError> {
+        self.stbl_mut()?.stsc_entry_count()
+    }
+
+    /// Check that the number of chunks in `stco` or `co64` is consistent with the chunk runs described by `stsc`.
+    pub fn validate_chunk_count(&mut self) -> Result<(), ParseError> {
+        let stbl = self.stbl_mut()?;
+        let chunk_count = stbl.co_mut()?.entry_count();
+        let mut last_first_chunk = 0;
+        for entry in stbl.stsc_mut()?.entries() {
+            let first_chunk = entry.while_parsing_child(BoxType::STBL, BoxType::STSC)?.first_chunk;
+            ensure_attach!(
+                first_chunk > last_first_chunk && (last_first_chunk != 0 || first_chunk == 1),
+                ParseError::InvalidInput,
+                "invalid stsc first chunk index",
+                WhereEq("first chunk", first_chunk),
+                WhileParsingChild(BoxType::STBL, BoxType::STSC),
+            );
+            last_first_chunk = first_chunk;
+        }
+        ensure_attach!(
+            last_first_chunk <= chunk_count && (last_first_chunk == 0) == (chunk_count == 0),
+            ParseError::InvalidInput,
+            "chunk count does not match stsc",
+            WhereEq("chunk count", chunk_count),
+            WhereEq("last stsc first chunk", last_first_chunk),
+            WhileParsingChild(NAME, BoxType::STBL),
+        );
+        Ok(())
+    }
+
+    /// Parse and validate the `colr` boxes of the track's visual sample entries, if any.
+    pub fn colr_boxes(&mut self) -> Result<Vec<ColrBox>, ParseError> {
+        match self.stbl_mut()?.stsd_mut()? {
+            Some(stsd) => stsd.colr_boxes(),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Parse and validate the `pasp` boxes of the track's visual sample entries, if any.
+    pub fn pasp_boxes(&mut self) -> Result<Vec<PaspBox>, ParseError> {
+        match self.stbl_mut()?.stsd_mut()? {
+            Some(stsd) => stsd.pasp_boxes(),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Parse and validate the `esds` boxes of the track's audio sample entries, if any.
+    pub fn esds_boxes(&mut self) -> Result<Vec<EsdsBox>, ParseError> {
+        match self.stbl_mut()?.stsd_mut()? {
+            Some(stsd) => stsd.esds_boxes(),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Validate the parameter sets of the track's HEVC sample entries, if any.
+    ///
+    /// See [`StsdBox::validate_hevc_parameter_sets`](super::StsdBox::validate_hevc_parameter_sets).
+    pub fn validate_hevc_parameter_sets(&mut self) -> Result<(), ParseError> {
+        match self.stbl_mut()?.stsd_mut()? {
+            Some(stsd) => stsd.validate_hevc_parameter_sets(),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether the track's samples are encrypted with Common Encryption.
+    ///
+    /// See [`StblBox::is_encrypted`].
+    pub fn is_encrypted(&mut self) -> Result<bool, ParseError> {
+        self.stbl_mut()?.is_encrypted()
+    }
+
+    /// Returns the ISO 639-2/T language code of the track's media from its `mdhd` box, if it has one.
+    ///
+    /// See [`MdhdBox::language`](super::MdhdBox::language).
+    pub fn language(&mut self) -> Result<Option<[u8; 3]>, ParseError> {
+        Ok(self.mdia_mut()?.mdhd_mut()?.map(|mdhd| mdhd.language()))
+    }
+
+    /// Returns the handler type declaring the type of the media in the track, e.g. `vide` or `soun`.
+    pub fn handler_type(&mut self) -> Result<FourCC, ParseError> {
+        self.mdia_mut()?.handler_type()
+    }
+
+    /// Check that the track's media header box matches its handler type, if it has one.
+    pub fn validate_media_header(&mut self) -> Result<(), ParseError> {
+        let mdia = self.mdia_mut()?;
+        // Though the `hdlr` box is required by the spec, tracks without one have been accepted historically.
+        if !mdia.children.box_types().any(|box_type| box_type == BoxType::HDLR) {
+            return Ok(());
+        }
+        let handler_type = mdia.handler_type()?;
+        mdia.minf_mut()?.validate_media_header(handler_type)
+    }
+
+    /// Returns the types of the track's sample entries, e.g. `avc1` or `mp4a`, without parsing the entries themselves.
+    ///
+    /// Sample entries with a UUID type are returned as `uuid`.
+    pub fn sample_entry_types(&mut self) -> Result<Vec<FourCC>, ParseError> {
+        let Some(stsd) = self.stbl_mut()?.stsd_mut()? else {
+            return Ok(Vec::new());
+        };
+        let entry_types = stsd.entry_types().map(|entry_type| match entry_type {
+            BoxType::FourCC(fourcc) => fourcc,
+            BoxType::Uuid(_) => fourcc::UUID,
+        });
+        Ok(entry_types.collect())
+    }
+
+    /// Returns the track's edit list, if any.
+    pub fn elst_mut(&mut self) -> Result<Option<&mut ElstBox>, ParseError> {
+        match self.edts_mut()? {
+            Some(edts) => edts.elst_mut(),
+            None => Ok(None),
+        }
+    }
+
+    /// Check that the track has no edit list, or only a trivial one which presents its media unmodified from its
+    /// start, i.e. a single entry with a media time of zero and a media rate of one.
+    pub fn validate_no_edits(&mut self) -> Result<(), ParseError> {
+        let Some(elst) = self.elst_mut()? else {
+            return Ok(());
+        };
+        let entry_count = elst.entry_count();
+        for entry in elst.entries()? {
+            let entry = entry.while_parsing_child(BoxType::EDTS, BoxType::ELST)?;
+            let trivial = entry_count == 1
+                && entry.media_time == 0
+                && (entry.media_rate_integer, entry.media_rate_fraction) == (1, 0);
+            ensure_attach!(
+                trivial,
+                ParseError::UnsupportedBox(BoxType::ELST),
+                "non-trivial edit list",
+                WhereEq("entry count", entry_count),
+                WhereEq("media time", entry.media_time),
+                WhileParsingChild(BoxType::EDTS, BoxType::ELST),
+            );
+        }
+        Ok(())
+    }
+
+    /// Set the creation and modification times in the track's `tkhd` and `mdhd` boxes, if present, to zero.
+    pub fn zero_timestamps(&mut self) -> Result<(), ParseError> {
+        if let Some(tkhd) = self.tkhd_mut()? {
+            tkhd.zero_timestamps();
+        }
+        if let Some(mdhd) = self.mdia_mut()?.mdhd_mut()? {
+            mdhd.times_mut().zero_timestamps();
+        }
+        Ok(())
+    }
+
+    /// Remove the iTunes-style metadata items, i.e. the `ilst` and `keys` boxes, from the `meta` box within this box,
+    /// whether directly or within a `udta` box, returning whether any were removed.
+    pub fn strip_metadata_items(&mut self) -> Result<bool, ParseError> {
+        strip_child_metadata_items(NAME, &mut self.children)
+    }
+
+    pub fn tkhd_mut(&mut self) -> Result<Option<&mut TkhdBox>, ParseError> {
+        self.children.get_opt_mut().while_parsing_child(NAME, BoxType::TKHD)
+    }
+
+    pub fn edts_mut(&mut self) -> Result<Option<&mut EdtsBox>, ParseError> {
+        self.children.get_opt_mut().while_parsing_child(NAME, BoxType::EDTS)
+    }
+
+    pub fn tref_mut(&mut self) -> Result<Option<&mut TrefBox>, ParseError> {
+        self.children.get_opt_mut().while_parsing_child(NAME, BoxType::TREF)
+    }
+
+    pub fn stbl_mut(&mut self) -> Result<&mut StblBox, ParseError> {
+        self.mdia_mut()?.minf_mut()?.stbl_mut()
     }
 
     pub fn mdia_mut(&mut self) -> Result<&mut MdiaBox, ParseError> {
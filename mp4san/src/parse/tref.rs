@@ -0,0 +1,146 @@
+#![allow(missing_docs)]
+
+use std::collections::HashSet;
+
+use bytes::BytesMut;
+use derive_more::Display;
+
+use crate::error::Result;
+
+use super::error::WhileParsingChild;
+use super::mp4box::{BoxData, Boxes};
+use super::{AnyMp4Box, BoxType, ParseBox, ParseError, ParsedBox};
+
+/// A track reference box, containing a box for each type of reference from its track to other tracks, e.g. `chap` or
+/// `hint`, each listing the IDs of the referenced tracks.
+#[derive(Clone, Debug, ParseBox, ParsedBox)]
+#[box_type = "tref"]
+pub struct TrefBox {
+    children: Boxes,
+}
+
+#[derive(Clone, Copy, Debug, Display)]
+#[display(fmt = "{} reference to nonexistent track ID {}", _0, _1)]
+struct DanglingTrackReference(BoxType, u32);
+
+const NAME: BoxType = BoxType::TREF;
+
+impl TrefBox {
+    #[cfg(test)]
+    pub(crate) fn with_children<C: Into<Boxes>>(children: C) -> Self {
+        Self { children: children.into() }
+    }
+
+    /// Returns an iterator over the type of each reference and the IDs of the tracks it refers to.
+    pub fn references(&self) -> impl Iterator<Item = Result<(BoxType, Vec<u32>), ParseError>> + '_ {
+        self.children.iter().filter_map(|reference| {
+            let box_type = reference.calculated_header().box_type();
+            let BoxData::Bytes(data) = &reference.data else {
+                return None;
+            };
+            if data.len() % 4 != 0 {
+                return Some(Err(report_attach!(
+                    ParseError::InvalidInput,
+                    "partial track ID",
+                    WhileParsingChild(NAME, box_type),
+                )));
+            }
+            let track_ids = data
+                .chunks_exact(4)
+                .map(|track_id| u32::from_be_bytes(track_id.try_into().unwrap_or_else(|_| unreachable!())))
+                .collect();
+            Some(Ok((box_type, track_ids)))
+        })
+    }
+
+    /// Remove each reference to a track ID not in `track_ids`, and each type of reference left without any.
+    pub fn retain_track_ids(&mut self, track_ids: &HashSet<u32>) -> Result<(), ParseError> {
+        let mut children = Vec::with_capacity(self.children.iter().len());
+        for reference in self.references() {
+            let (box_type, referenced_track_ids) = reference?;
+            let data: BytesMut = referenced_track_ids
+                .into_iter()
+                .filter(|track_id| track_ids.contains(track_id))
+                .flat_map(u32::to_be_bytes)
+                .collect();
+            if !data.is_empty() {
+                children.push(AnyMp4Box::with_bytes(box_type, data));
+            }
+        }
+        self.children = children.into();
+        Ok(())
+    }
+
+    /// Check that each referenced track ID is one of `track_ids`.
+    pub fn validate_track_ids(&self, track_ids: &HashSet<u32>) -> Result<(), ParseError> {
+        for reference in self.references() {
+            let (box_type, referenced_track_ids) = reference?;
+            for track_id in referenced_track_ids {
+                ensure_attach!(
+                    track_ids.contains(&track_id),
+                    ParseError::InvalidInput,
+                    DanglingTrackReference(box_type, track_id),
+                    WhileParsingChild(NAME, box_type),
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+
+    use crate::parse::{FourCC, Mp4Box, Mp4Value};
+    use crate::util::test::test_tref;
+
+    use super::*;
+
+    const CHAP: BoxType = BoxType::FourCC(FourCC::from_str("chap"));
+
+    #[test]
+    fn references() {
+        let tref = test_tref(CHAP, &[2, 3]);
+        let references: Vec<_> = tref.references().collect::<Result<_, _>>().unwrap();
+        assert_eq!(references, [(CHAP, vec![2, 3])]);
+    }
+
+    #[test]
+    fn roundtrip() {
+        let tref = test_tref(CHAP, &[2]);
+        let mut buf = BytesMut::new();
+        Mp4Box::with_data(tref.into()).unwrap().put_buf(&mut buf);
+        let mut mp4box = Mp4Box::<TrefBox>::parse(&mut buf).unwrap();
+        let tref = mp4box.data.parse().unwrap();
+        let references: Vec<_> = tref.references().collect::<Result<_, _>>().unwrap();
+        assert_eq!(references, [(CHAP, vec![2])]);
+    }
+
+    #[test]
+    fn partial_track_id() {
+        let reference = Mp4Box::with_bytes(CHAP, BytesMut::from(&[0, 0, 0, 2, 0][..]));
+        let tref = TrefBox::with_children(vec![reference]);
+        let err = tref.references().next().unwrap().unwrap_err();
+        assert_matches!(err.into_inner(), ParseError::InvalidInput);
+    }
+
+    #[test]
+    fn retain_track_ids() {
+        let mut tref = test_tref(CHAP, &[1, 2, 3]);
+        tref.retain_track_ids(&HashSet::from([1, 3])).unwrap();
+        let references: Vec<_> = tref.references().collect::<Result<_, _>>().unwrap();
+        assert_eq!(references, [(CHAP, vec![1, 3])]);
+
+        tref.retain_track_ids(&HashSet::from([2])).unwrap();
+        assert_eq!(tref.references().count(), 0);
+    }
+
+    #[test]
+    fn validate_track_ids() {
+        let tref = test_tref(CHAP, &[1, 2]);
+        tref.validate_track_ids(&HashSet::from([1, 2])).unwrap();
+        let err = tref.validate_track_ids(&HashSet::from([1])).unwrap_err();
+        assert_matches!(err.into_inner(), ParseError::InvalidInput);
+    }
+}
@@ -0,0 +1,35 @@
+#![allow(missing_docs)]
+
+use crate::error::Result;
+
+use super::error::ParseResultExt;
+use super::mp4box::Boxes;
+use super::{BoxType, MetaBox, ParseBox, ParseError, ParsedBox};
+
+/// A user data box, holding user-defined information about the movie, such as a [`MetaBox`] of iTunes-style tags.
+#[derive(Clone, Debug, ParseBox, ParsedBox)]
+#[box_type = "udta"]
+pub struct UdtaBox {
+    children: Boxes,
+}
+
+const NAME: BoxType = BoxType::UDTA;
+
+impl UdtaBox {
+    #[cfg(test)]
+    pub(crate) fn with_children<C: Into<Boxes>>(children: C) -> Self {
+        Self { children: children.into() }
+    }
+
+    /// Remove the iTunes-style metadata items from the `meta` box, if any, returning whether any were removed.
+    pub fn strip_metadata_items(&mut self) -> Result<bool, ParseError> {
+        match self.meta_mut()? {
+            Some(meta) => meta.remove_items().while_parsing_child(NAME, BoxType::META),
+            None => Ok(false),
+        }
+    }
+
+    pub fn meta_mut(&mut self) -> Result<Option<&mut MetaBox>, ParseError> {
+        self.children.get_opt_mut().while_parsing_child(NAME, BoxType::META)
+    }
+}
@@ -0,0 +1,41 @@
+#![allow(missing_docs)]
+
+use super::{FullBoxHeader, ParseBox, ParsedBox};
+
+/// The video media header box, found in the `minf` box of video tracks.
+#[derive(Clone, Debug, ParseBox, ParsedBox)]
+#[box_type = "vmhd"]
+pub struct VmhdBox {
+    header: FullBoxHeader,
+    graphics_mode: u16,
+    opcolor: [u16; 3],
+}
+
+impl VmhdBox {
+    pub fn graphics_mode(&self) -> u16 {
+        self.graphics_mode
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::BytesMut;
+
+    use crate::parse::{ParseBox, ParsedBox};
+    use crate::util::test::write_test_vmhd_data;
+
+    use super::VmhdBox;
+
+    #[test]
+    fn roundtrip() {
+        let mut buf = BytesMut::new();
+        write_test_vmhd_data(&mut buf);
+        let vmhd = VmhdBox::parse(&mut buf.clone()).unwrap();
+        assert_eq!(vmhd.encoded_len(), buf.len() as u64);
+        assert_eq!(vmhd.graphics_mode(), 0);
+
+        let mut output = BytesMut::new();
+        vmhd.put_buf(&mut output);
+        assert_eq!(output, buf);
+    }
+}
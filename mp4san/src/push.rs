@@ -0,0 +1,357 @@
+//! A push-style driver for the sanitizer, for input which arrives incrementally.
+
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::task::{Context, Poll};
+
+use bytes::{Buf, BytesMut};
+use futures_util::future::LocalBoxFuture;
+use futures_util::task::noop_waker_ref;
+use futures_util::AsyncRead;
+
+use crate::parse::ParseError;
+use crate::{sanitize_async_with_config, AsyncSkip, Config, Error, InputTooLarge, SanitizedMetadata};
+
+//
+// public types
+//
+
+/// A sanitizer which is fed its input incrementally, for callers which receive the input in arbitrary chunks and cannot
+/// provide a [`Read`](std::io::Read) + [`Skip`](crate::Skip) input.
+///
+/// Input is passed to [`push`](Self::push) as it arrives, and the sanitizer runs as far as it can each time, until it
+/// needs more input. Input is only buffered for as long as the sanitizer needs it, so media data is discarded as soon
+/// as it is pushed. Once all input has been pushed, [`finish`](Self::finish) returns the [`SanitizedMetadata`], with
+/// the same semantics as [`sanitize_with_config`](crate::sanitize_with_config).
+///
+/// A `Sanitizer` is [`Send`], so it can be held across an `.await` on a multi-threaded executor.
+///
+/// # Examples
+///
+/// ```
+/// # use mp4san::{Config, Sanitizer};
+/// # use mp4san_test::{example_ftyp, example_mdat, example_moov};
+/// #
+/// let example_input = [example_ftyp(), example_mdat(), example_moov()].concat();
+///
+/// let mut sanitizer = Sanitizer::new(Config::default());
+/// for chunk in example_input.chunks(7) {
+///     sanitizer.push(chunk)?;
+/// }
+/// let sanitized = sanitizer.finish()?;
+///
+/// assert_eq!(sanitized.metadata, Some([example_ftyp(), example_moov()].concat()));
+/// # Ok::<(), mp4san::Error>(())
+/// ```
+pub struct Sanitizer {
+    input: Arc<Mutex<PushInputState>>,
+    future: Option<SanitizeFuture>,
+    sanitized: Option<SanitizedMetadata>,
+    max_input_len: Option<u64>,
+}
+
+//
+// private types
+//
+
+/// The future driven by a [`Sanitizer`].
+struct SanitizeFuture(LocalBoxFuture<'static, Result<SanitizedMetadata, Error>>);
+
+/// The input to a [`Sanitizer`], which is pending until enough input has been pushed.
+///
+/// The state is shared with the [`Sanitizer`] through a [`Mutex`], rather than a `RefCell`, so that the [`Sanitizer`]
+/// is [`Send`]. It is never contended, as the sanitizer only runs while the [`Sanitizer`] is being pushed to.
+struct PushInput(Arc<Mutex<PushInputState>>);
+
+#[derive(Default)]
+struct PushInputState {
+    /// Input which has been pushed but not yet read.
+    buf: BytesMut,
+    /// The total length of the input pushed so far.
+    len: u64,
+    /// The stream position of the sanitizer in the input.
+    position: u64,
+    /// The amount of the currently pending skip which has already been performed.
+    skipped: u64,
+    /// Whether all input has been pushed.
+    finished: bool,
+}
+
+//
+// Sanitizer impls
+//
+
+impl Sanitizer {
+    /// Construct a new [`Sanitizer`] with the given [`Config`].
+    pub fn new(config: Config) -> Self {
+        let input = Arc::new(Mutex::new(PushInputState::default()));
+        let max_input_len = config.max_input_len;
+        let future = Box::pin(sanitize_async_with_config(PushInput(Arc::clone(&input)), config));
+        Self { input, future: Some(SanitizeFuture(future)), sanitized: None, max_input_len }
+    }
+
+    /// Push the next chunk of input to the sanitizer, running it until it needs more input.
+    ///
+    /// # Errors
+    ///
+    /// If the input pushed so far cannot be parsed, or is longer than [`Config::max_input_len`], an [`Error`] is
+    /// returned, and the sanitizer must not be used any further.
+    pub fn push(&mut self, input: &[u8]) -> Result<(), Error> {
+        {
+            let mut state = lock_state(&self.input);
+            let input_len = state.len.saturating_add(input.len() as u64);
+            if let Some(max_input_len) = self.max_input_len {
+                if input_len > max_input_len {
+                    self.future = None;
+                    bail_attach!(ParseError::InvalidInput, InputTooLarge(input_len, max_input_len));
+                }
+            }
+            state.buf.extend_from_slice(input);
+            state.len = input_len;
+        }
+        self.poll()
+    }
+
+    /// Signal the end of the input, returning the sanitized metadata.
+    ///
+    /// # Errors
+    ///
+    /// If the input cannot be parsed, an [`Error`] is returned.
+    pub fn finish(mut self) -> Result<SanitizedMetadata, Error> {
+        lock_state(&self.input).finished = true;
+        self.poll()?;
+
+        // All IO on the input completes immediately once it has finished, so the sanitizer must have completed.
+        Ok(self.sanitized.take().unwrap_or_else(|| unreachable!()))
+    }
+
+    fn poll(&mut self) -> Result<(), Error> {
+        let Some(future) = &mut self.future else {
+            if self.sanitized.is_some() {
+                return Ok(());
+            }
+            return Err(io::Error::new(io::ErrorKind::Other, "sanitizer used after returning an error").into());
+        };
+        let Poll::Ready(sanitized) = future.0.as_mut().poll(&mut Context::from_waker(noop_waker_ref())) else {
+            return Ok(());
+        };
+        self.future = None;
+        self.sanitized = Some(sanitized?);
+        Ok(())
+    }
+}
+
+//
+// SanitizeFuture impls
+//
+
+// SAFETY: The sanitizer's future is only not provably `Send` because it holds boxes containing `Box<dyn ParsedBox>`
+// children, which the `ParsedBox` trait doesn't require to be `Send`, across await points. The only values the
+// sanitizer parses into those children are this crate's own box types, which are either containers of further such
+// children or leaf boxes that are `Send`, as asserted by `test::box_types_are_send`. The rest of its state, including
+// the `PushInput` shared through an `Arc<Mutex>`, is `Send`.
+unsafe impl Send for SanitizeFuture {}
+
+//
+// PushInput impls
+//
+
+impl PushInput {
+    fn state(&self) -> MutexGuard<'_, PushInputState> {
+        lock_state(&self.0)
+    }
+}
+
+//
+// private functions
+//
+
+/// Lock the state of a [`PushInput`]. A panic while the lock is held leaves the state consistent, so poisoning is
+/// ignored.
+fn lock_state(state: &Mutex<PushInputState>) -> MutexGuard<'_, PushInputState> {
+    state.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl AsyncRead for PushInput {
+    fn poll_read(self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        let mut state = self.state();
+        if state.buf.is_empty() && !state.finished {
+            return Poll::Pending;
+        }
+        let read_len = buf.len().min(state.buf.len());
+        state.buf.copy_to_slice(&mut buf[..read_len]);
+        state.position += read_len as u64;
+        Poll::Ready(Ok(read_len))
+    }
+}
+
+impl AsyncSkip for PushInput {
+    fn poll_skip(self: Pin<&mut Self>, _cx: &mut Context<'_>, amount: u64) -> Poll<io::Result<()>> {
+        let mut state = self.state();
+        let skip_len = (amount - state.skipped).min(state.buf.len() as u64);
+        state.buf.advance(skip_len as usize);
+        state.position += skip_len;
+        state.skipped += skip_len;
+        if state.skipped != amount {
+            if !state.finished {
+                return Poll::Pending;
+            }
+            // Like a seek, skipping past the end of the input is allowed.
+            state.position += amount - state.skipped;
+        }
+        state.skipped = 0;
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_stream_position(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(self.state().position))
+    }
+
+    /// The input's length is unknown until all of it has been pushed, so it is unsupported until then, rather than
+    /// pending, which would leave all of the input buffered until [`Sanitizer::finish`].
+    fn poll_stream_len(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        let state = self.state();
+        match state.finished {
+            true => Poll::Ready(Ok(state.len)),
+            false => Poll::Ready(Err(io::ErrorKind::Unsupported.into())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+
+    use crate::parse::box_type::{FTYP, MDAT, MOOV};
+    use crate::parse::{
+        Co64Box, ColrBox, CttsBox, DataBox, ElstBox, EsdsBox, FtypBox, HdlrBox, HvccBox, KeysBox, MdhdBox, MehdBox,
+        MvhdBox, NmhdBox, PaspBox, SmhdBox, StcoBox, StscBox, StssBox, StszBox, Stz2Box, TkhdBox, VmhdBox,
+    };
+    use crate::sanitize;
+    use crate::util::test::test_mp4;
+
+    use super::*;
+
+    fn push_sanitize(input: &[u8], chunk_len: usize) -> Result<SanitizedMetadata, Error> {
+        let mut sanitizer = Sanitizer::new(Config::default());
+        for chunk in input.chunks(chunk_len) {
+            sanitizer.push(chunk)?;
+        }
+        sanitizer.finish()
+    }
+
+    fn assert_send<T: Send>() {}
+
+    #[test]
+    fn sanitizer_is_send() {
+        assert_send::<Sanitizer>();
+    }
+
+    #[test]
+    fn box_types_are_send() {
+        assert_send::<Co64Box>();
+        assert_send::<ColrBox>();
+        assert_send::<CttsBox>();
+        assert_send::<DataBox>();
+        assert_send::<ElstBox>();
+        assert_send::<EsdsBox>();
+        assert_send::<FtypBox>();
+        assert_send::<HdlrBox>();
+        assert_send::<HvccBox>();
+        assert_send::<KeysBox>();
+        assert_send::<MdhdBox>();
+        assert_send::<MehdBox>();
+        assert_send::<MvhdBox>();
+        assert_send::<NmhdBox>();
+        assert_send::<PaspBox>();
+        assert_send::<SmhdBox>();
+        assert_send::<StcoBox>();
+        assert_send::<StscBox>();
+        assert_send::<StssBox>();
+        assert_send::<StszBox>();
+        assert_send::<Stz2Box>();
+        assert_send::<TkhdBox>();
+        assert_send::<VmhdBox>();
+    }
+
+    #[test]
+    fn one_byte_at_a_time() {
+        let test = test_mp4().build();
+        let sanitized = push_sanitize(&test.data, 1).unwrap();
+        assert_eq!(sanitized, sanitize(test.clone()).unwrap());
+    }
+
+    #[test]
+    fn whole_input() {
+        let test = test_mp4().build();
+        let sanitized = push_sanitize(&test.data, test.data.len()).unwrap();
+        assert_eq!(sanitized, sanitize(test.clone()).unwrap());
+    }
+
+    #[test]
+    fn until_eof_sized_mdat() {
+        let test = test_mp4()
+            .boxes(&[FTYP, MOOV, MDAT][..])
+            .mdat_data(&b"abcdefg"[..])
+            .mdat_data_until_eof()
+            .build();
+        let sanitized = push_sanitize(&test.data, 1).unwrap();
+        assert_eq!(sanitized, sanitize(test.clone()).unwrap());
+    }
+
+    #[test]
+    fn empty() {
+        let sanitizer = Sanitizer::new(Config::default());
+        assert_matches!(sanitizer.finish().unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::MissingRequiredBox(_));
+        });
+    }
+
+    #[test]
+    fn truncated() {
+        let test = test_mp4().build();
+        let data = &test.data[..test.data.len() - 1];
+        assert_matches!(push_sanitize(data, 1).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::TruncatedBox);
+        });
+    }
+
+    #[test]
+    fn push_after_error() {
+        let mut sanitizer = Sanitizer::new(Config::default());
+        assert_matches!(sanitizer.push(b"\0\0\0\x04ftyp").unwrap_err(), Error::Parse(_));
+        assert_matches!(sanitizer.push(b"\0").unwrap_err(), Error::Io(_));
+    }
+
+    #[test]
+    fn max_input_len() {
+        let test = test_mp4().build();
+        let config = Config::builder().max_input_len(test.data_len).build();
+        let mut sanitizer = Sanitizer::new(config);
+        for chunk in test.data.chunks(7) {
+            sanitizer.push(chunk).unwrap();
+        }
+        assert_eq!(sanitizer.finish().unwrap(), sanitize(test.clone()).unwrap());
+
+        let config = Config::builder().max_input_len(test.data_len - 1).build();
+        let mut sanitizer = Sanitizer::new(config);
+        let (head, tail) = test.data.split_at(test.data.len() - 1);
+        sanitizer.push(head).unwrap();
+        assert_matches!(sanitizer.push(tail).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.into_inner(), ParseError::InvalidInput);
+        });
+    }
+
+    #[test]
+    fn max_input_len_discards_media_data() {
+        let test = test_mp4().build();
+        let config = Config::builder().max_input_len(test.data_len).build();
+        let mut sanitizer = Sanitizer::new(config);
+        for chunk in test.data.chunks(7) {
+            sanitizer.push(chunk).unwrap();
+            assert!(lock_state(&sanitizer.input).buf.len() < 7 + 8);
+        }
+        sanitizer.finish().unwrap();
+    }
+}
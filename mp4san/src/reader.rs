@@ -0,0 +1,198 @@
+//! Readers bounded to a region of the input.
+
+use std::io;
+use std::io::Read;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use futures_util::AsyncRead;
+use mediasan_common::{AsyncSkip, Skip};
+
+use crate::parse::BoxType;
+
+//
+// public types
+//
+
+/// An [`AsyncRead`] + [`AsyncSkip`] type over a box's data, assuming its header has already been read.
+///
+/// Reads and skips are bounded to the box's data, so that a parser can't overrun into the boxes following it: reading
+/// past the end of the data returns EOF, and skipping past it returns an [`io::ErrorKind::UnexpectedEof`] error.
+pub struct BoxDataReader<R> {
+    inner: R,
+    box_type: BoxType,
+    remaining: u64,
+}
+
+/// A [`Read`] + [`Skip`] type over the remainder of an input from a starting offset, whose stream positions are
+/// relative to that offset, so that the input appears to begin there.
+pub struct OffsetReader<R> {
+    inner: R,
+    start: u64,
+}
+
+//
+// BoxDataReader impls
+//
+
+impl<R> BoxDataReader<R> {
+    /// Construct a new [`BoxDataReader`] over the next `data_len` bytes of `inner`, which are the data of a box of type
+    /// `box_type`.
+    pub fn new(inner: R, box_type: BoxType, data_len: u64) -> Self {
+        Self { inner, box_type, remaining: data_len }
+    }
+
+    /// The type of the box being read.
+    pub fn box_type(&self) -> BoxType {
+        self.box_type
+    }
+
+    /// The number of bytes of the box's data remaining to be read.
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for BoxDataReader<R> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        if self.remaining == 0 {
+            return Poll::Ready(Ok(0));
+        }
+        let read_len = buf.len().min(self.remaining.try_into().unwrap_or(usize::MAX));
+        let amount_read = ready!(Pin::new(&mut self.inner).poll_read(cx, &mut buf[..read_len]))?;
+        self.remaining -= amount_read as u64;
+        Poll::Ready(Ok(amount_read))
+    }
+}
+
+impl<R: AsyncSkip + Unpin> AsyncSkip for BoxDataReader<R> {
+    fn poll_skip(mut self: Pin<&mut Self>, cx: &mut Context<'_>, amount: u64) -> Poll<io::Result<()>> {
+        if amount > self.remaining {
+            return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+        }
+        ready!(Pin::new(&mut self.inner).poll_skip(cx, amount))?;
+        self.remaining -= amount;
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_stream_position(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Pin::new(&mut self.inner).poll_stream_position(cx)
+    }
+
+    fn poll_stream_len(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Pin::new(&mut self.inner).poll_stream_len(cx)
+    }
+}
+
+//
+// OffsetReader impls
+//
+
+impl<R: Skip> OffsetReader<R> {
+    /// Construct a new [`OffsetReader`] over `inner` from the offset `start`, skipping forward to it.
+    ///
+    /// If `inner` has already been read past `start`, an [`io::ErrorKind::InvalidInput`] error is returned.
+    pub fn new(mut inner: R, start: u64) -> io::Result<Self> {
+        let position = inner.stream_position()?;
+        let skip_amount = start.checked_sub(position).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("input position {position} is past start position {start}"),
+            )
+        })?;
+        inner.skip(skip_amount)?;
+        Ok(Self { inner, start })
+    }
+}
+
+impl<R: Read> Read for OffsetReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl<R: Skip> Skip for OffsetReader<R> {
+    fn skip(&mut self, amount: u64) -> io::Result<()> {
+        self.inner.skip(amount)
+    }
+
+    fn stream_position(&mut self) -> io::Result<u64> {
+        Ok(self.inner.stream_position()? - self.start)
+    }
+
+    fn stream_len(&mut self) -> io::Result<u64> {
+        Ok(self.inner.stream_len()?.saturating_sub(self.start))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures_util::io::Cursor;
+    use futures_util::{AsyncReadExt, FutureExt};
+    use mediasan_common::AsyncSkipExt;
+
+    use super::*;
+
+    fn test_reader(input: &mut Cursor<Vec<u8>>, data_len: u64) -> BoxDataReader<&mut Cursor<Vec<u8>>> {
+        BoxDataReader::new(input, BoxType::FREE, data_len)
+    }
+
+    #[test]
+    fn read_bounded() {
+        let mut input = Cursor::new(b"abcdefgh".to_vec());
+        let mut reader = test_reader(&mut input, 4);
+        let mut data = vec![];
+        reader.read_to_end(&mut data).now_or_never().unwrap().unwrap();
+        assert_eq!(data, b"abcd");
+        assert_eq!(reader.remaining(), 0);
+        assert_eq!(reader.read(&mut [0; 4]).now_or_never().unwrap().unwrap(), 0);
+        assert_eq!(input.position(), 4);
+    }
+
+    #[test]
+    fn skip_bounded() {
+        let mut input = Cursor::new(b"abcdefgh".to_vec());
+        let mut reader = test_reader(&mut input, 4);
+        reader.skip(3).now_or_never().unwrap().unwrap();
+        assert_eq!(reader.stream_position().now_or_never().unwrap().unwrap(), 3);
+        let err = reader.skip(2).now_or_never().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        reader.skip(1).now_or_never().unwrap().unwrap();
+        reader.skip(0).now_or_never().unwrap().unwrap();
+        assert_eq!(
+            reader.skip(1).now_or_never().unwrap().unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+        assert_eq!(input.position(), 4);
+    }
+
+    #[test]
+    fn offset_reader() {
+        let mut reader = OffsetReader::new(io::Cursor::new(b"abcdefgh"), 3).unwrap();
+        assert_eq!(Skip::stream_position(&mut reader).unwrap(), 0);
+        assert_eq!(Skip::stream_len(&mut reader).unwrap(), 5);
+        let mut data = [0; 2];
+        Read::read_exact(&mut reader, &mut data).unwrap();
+        assert_eq!(&data, b"de");
+        Skip::skip(&mut reader, 1).unwrap();
+        assert_eq!(Skip::stream_position(&mut reader).unwrap(), 3);
+    }
+
+    #[test]
+    fn offset_reader_past_start() {
+        let mut input = io::Cursor::new(b"abcdefgh");
+        input.set_position(4);
+        let err = OffsetReader::new(input, 3).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn read_truncated() {
+        let mut input = Cursor::new(b"ab".to_vec());
+        let mut reader = test_reader(&mut input, 4);
+        let mut data = vec![];
+        reader.read_to_end(&mut data).now_or_never().unwrap().unwrap();
+        assert_eq!(data, b"ab");
+        assert_eq!(reader.remaining(), 2);
+    }
+}
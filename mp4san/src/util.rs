@@ -1,4 +1,2 @@
 #[cfg(test)]
 pub mod test;
-
-pub use mediasan_common::util::IoResultExt;
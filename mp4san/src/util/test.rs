@@ -6,14 +6,44 @@ use std::iter;
 
 use bytes::{BufMut, BytesMut};
 
-use crate::parse::box_type::{DINF, DREF, HDLR, MDAT, MDHD, MECO, META, METT, MVHD, STSC, STSD, STSZ, STTS, TKHD, URL};
-use crate::parse::{fourcc, AnyMp4Box, BoxHeader, BoxType, BoxUuid, FourCC, FullBoxHeader, Mp4Box, Mp4Value};
+use crate::parse::box_type::{
+    AVC1, DINF, DREF, HDLR, ILST, MDAT, MDHD, MECO, META, METT, MP4A, MVHD, NMHD, SMHD, STSC, STSD, STSZ, STTS, STZ2,
+    TKHD, URL, VMHD,
+};
+use crate::parse::{
+    fourcc, AnyMp4Box, BoxHeader, BoxType, BoxUuid, FourCC, FullBoxHeader, Mp4Box, Mp4Value, TrefBox, UdtaBox,
+};
 use crate::{InputSpan, SanitizedMetadata};
 
 pub const TEST_UUID: BoxType = BoxType::Uuid(BoxUuid { value: *b"thisisatestuuid!" });
+/// A pseudo box type standing in for a region of corrupted data, which is not a valid box.
+pub const TEST_JUNK: BoxType = BoxType::FourCC(FourCC { value: *b"JUNK" });
 pub const MP42: FourCC = FourCC { value: *b"mp42" };
 pub const MP41: FourCC = FourCC { value: *b"mp41" };
 pub const ISOM: FourCC = FourCC { value: *b"isom" };
+pub const MDIR: FourCC = FourCC { value: *b"mdir" };
+pub const SOUN: FourCC = FourCC { value: *b"soun" };
+
+/// The creation time written to test `mvhd`, `tkhd`, and `mdhd` boxes, in seconds since 1904.
+pub const TEST_CREATION_TIME: u32 = 3_700_000_000;
+/// The modification time written to test `mvhd`, `tkhd`, and `mdhd` boxes, in seconds since 1904.
+pub const TEST_MODIFICATION_TIME: u32 = 3_700_000_001;
+
+/// A hand-written `nclx` colr box with the colour parameters of HDR10 video: BT.2020 primaries, SMPTE ST 2084 (PQ)
+/// transfer, BT.2020 non-constant luminance matrix, limited range.
+pub const HDR10_NCLX_COLR: &[u8] = b"\0\0\0\x13colrnclx\0\x09\0\x10\0\x09\0";
+
+/// An `esds` box for AAC-LC audio, with a `DecoderConfigDescriptor` containing a two-byte `AudioSpecificConfig` and an
+/// `SLConfigDescriptor`.
+pub const TEST_ESDS: &[u8] =
+    b"\0\0\0\x27esds\0\0\0\0\x03\x19\0\x01\0\x04\x11\x40\x15\0\0\0\0\0\0\0\0\0\0\0\x05\x02\x12\x10\x06\x01\x02";
+
+/// A `pasp` box for 4:3 pixels, e.g. for 1440x1080 video displayed at 16:9.
+pub const TEST_PASP: &[u8] = b"\0\0\0\x10pasp\0\0\0\x04\0\0\0\x03";
+
+/// An `hvcC` box containing one each of a dummy video, sequence, and picture parameter set.
+pub const TEST_HVCC: &[u8] = b"\0\0\0\x34hvcC\x01\x01\x60\0\0\0\x90\0\0\0\0\0\x5d\xf0\0\xfc\xfd\xf8\xf8\0\0\x0f\x03\
+    \xa0\0\x01\0\x02\x40\x01\xa1\0\x01\0\x02\x42\x01\xa2\0\x01\0\x02\x44\x01";
 
 pub use ftyp::TestFtypBuilder;
 pub use moov::TestMoovBuilder;
@@ -85,9 +115,21 @@ pub fn test_mvhd() -> AnyMp4Box {
     Mp4Box::with_bytes(MVHD, data)
 }
 
-pub fn test_stsc() -> AnyMp4Box {
+pub fn test_nmhd() -> AnyMp4Box {
+    let mut data = BytesMut::new();
+    write_test_nmhd_data(&mut data);
+    Mp4Box::with_bytes(NMHD, data)
+}
+
+pub fn test_smhd() -> AnyMp4Box {
+    let mut data = BytesMut::new();
+    write_test_smhd_data(&mut data);
+    Mp4Box::with_bytes(SMHD, data)
+}
+
+pub fn test_stsc(first_chunks: &[u32]) -> AnyMp4Box {
     let mut data = BytesMut::new();
-    write_test_stsc_data(&mut data);
+    write_test_stsc_data(&mut data, first_chunks);
     Mp4Box::with_bytes(STSC, data)
 }
 
@@ -97,24 +139,73 @@ pub fn test_stsd() -> AnyMp4Box {
     Mp4Box::with_bytes(STSD, data)
 }
 
+pub fn test_audio_stsd(children: &[u8]) -> AnyMp4Box {
+    let mut data = BytesMut::new();
+    write_test_audio_stsd_data(&mut data, children);
+    Mp4Box::with_bytes(STSD, data)
+}
+
+pub fn test_visual_stsd(children: &[u8]) -> AnyMp4Box {
+    let mut data = BytesMut::new();
+    write_test_visual_stsd_data(&mut data, children);
+    Mp4Box::with_bytes(STSD, data)
+}
+
 pub fn test_stsz(chunk_count: u32) -> AnyMp4Box {
     let mut data = BytesMut::new();
     write_test_stsz_data(&mut data, chunk_count);
     Mp4Box::with_bytes(STSZ, data)
 }
 
+pub fn test_stz2(field_size: u8, chunk_count: u32) -> AnyMp4Box {
+    let mut data = BytesMut::new();
+    write_test_stz2_data(&mut data, field_size, chunk_count);
+    Mp4Box::with_bytes(STZ2, data)
+}
+
 pub fn test_stts(chunk_count: u32) -> AnyMp4Box {
     let mut data = BytesMut::new();
     write_test_stts_data(&mut data, chunk_count);
     Mp4Box::with_bytes(STTS, data)
 }
 
+/// A `tref` box containing one reference of type `reference_type` to each of `track_ids`.
+pub fn test_tref(reference_type: BoxType, track_ids: &[u32]) -> TrefBox {
+    let mut data = BytesMut::new();
+    for &track_id in track_ids {
+        data.put_u32(track_id);
+    }
+    TrefBox::with_children(vec![Mp4Box::with_bytes(reference_type, data)])
+}
+
+/// A `udta` box containing a `meta` box with an `ilst` box, as written by [`write_test_ilst_meta_data`].
+pub fn test_udta() -> UdtaBox {
+    let mut data = BytesMut::new();
+    write_test_ilst_meta_data(&mut data);
+    UdtaBox::with_children(vec![Mp4Box::with_bytes(META, data)])
+}
+
+/// Re-encode `mp4box` with an extended 64-bit size in its header, even though its size fits in 32 bits.
+pub fn with_ext_size(mp4box: AnyMp4Box) -> AnyMp4Box {
+    let header = mp4box.calculated_header();
+    let mut buf = BytesMut::new();
+    BoxHeader::with_ext_data_size(header.box_type(), header.box_data_size().unwrap().unwrap()).put_buf(&mut buf);
+    mp4box.data.put_buf(&mut buf);
+    AnyMp4Box::parse(&mut buf).unwrap()
+}
+
 pub fn test_tkhd(track_id: u32) -> AnyMp4Box {
     let mut data = BytesMut::new();
     write_test_tkhd_data(&mut data, track_id);
     Mp4Box::with_bytes(TKHD, data)
 }
 
+pub fn test_vmhd() -> AnyMp4Box {
+    let mut data = BytesMut::new();
+    write_test_vmhd_data(&mut data);
+    Mp4Box::with_bytes(VMHD, data)
+}
+
 pub fn write_hdlr_data<B: BufMut>(mut out: B, handler_type: FourCC) {
     FullBoxHeader::default().put_buf(&mut out);
     out.put_u32(0); // pre-defined
@@ -159,20 +250,32 @@ pub fn write_test_meta_data<B: BufMut>(mut out: B) {
     test_hdlr(fourcc::META).put_buf(&mut out);
 }
 
+/// Write the data of a `meta` box holding an iTunes-style `ilst` item list with a single title item.
+pub fn write_test_ilst_meta_data<B: BufMut>(mut out: B) {
+    FullBoxHeader::default().put_buf(&mut out);
+    test_hdlr(MDIR).put_buf(&mut out);
+    BoxHeader::with_u32_data_size(ILST, 28).put_buf(&mut out); // ilst header
+    BoxHeader::with_u32_data_size(BoxType::FourCC(FourCC { value: *b"\xa9nam" }), 20).put_buf(&mut out); // item header
+    BoxHeader::with_u32_data_size(BoxType::FourCC(FourCC { value: *b"data" }), 12).put_buf(&mut out); // data header
+    out.put_u32(1); // type indicator: UTF-8
+    out.put_u32(0); // locale
+    out.put_slice(b"test"); // value
+}
+
 pub fn write_test_mdhd_data<B: BufMut>(mut out: B) {
     FullBoxHeader::default().put_buf(&mut out);
-    out.put_u32(0); // creation time
-    out.put_u32(0); // modification time
+    out.put_u32(TEST_CREATION_TIME); // creation time
+    out.put_u32(TEST_MODIFICATION_TIME); // modification time
     out.put_u32(1); // timescale
     out.put_u32(0); // duration
-    out.put_u16(u16::from_be_bytes(*b"US")); // language
+    out.put_u16(0x55c4); // language: "und"
     out.put_u16(0); // pre-defined
 }
 
 pub fn write_test_mvhd_data<B: BufMut>(mut out: B) {
     FullBoxHeader::default().put_buf(&mut out);
-    out.put_u32(0); // creation time
-    out.put_u32(0); // modification time
+    out.put_u32(TEST_CREATION_TIME); // creation time
+    out.put_u32(TEST_MODIFICATION_TIME); // modification time
     out.put_u32(1); // timescale
     out.put_u32(0); // duration
     out.put_u32(0x00010000); // rate
@@ -190,12 +293,24 @@ pub fn write_test_mvhd_data<B: BufMut>(mut out: B) {
     out.put_u32(u32::MAX); // next track id
 }
 
-pub fn write_test_stsc_data<B: BufMut>(mut out: B) {
+pub fn write_test_nmhd_data<B: BufMut>(mut out: B) {
     FullBoxHeader::default().put_buf(&mut out);
-    out.put_u32(1); // entry count
-    out.put_u32(1); // first chunk
-    out.put_u32(1); // samples per chunk
-    out.put_u32(1); // sample description index
+}
+
+pub fn write_test_smhd_data<B: BufMut>(mut out: B) {
+    FullBoxHeader::default().put_buf(&mut out);
+    out.put_u16(0); // balance
+    out.put_u16(0); // reserved
+}
+
+pub fn write_test_stsc_data<B: BufMut>(mut out: B, first_chunks: &[u32]) {
+    FullBoxHeader::default().put_buf(&mut out);
+    out.put_u32(first_chunks.len() as u32); // entry count
+    for &first_chunk in first_chunks {
+        out.put_u32(first_chunk); // first chunk
+        out.put_u32(1); // samples per chunk
+        out.put_u32(1); // sample description index
+    }
 }
 
 pub fn write_test_stsd_data<B: BufMut>(mut out: B) {
@@ -209,12 +324,71 @@ pub fn write_test_stsd_data<B: BufMut>(mut out: B) {
     out.put_u8(0); // mime format
 }
 
+pub fn write_test_audio_stsd_data<B: BufMut>(mut out: B, children: &[u8]) {
+    FullBoxHeader::default().put_buf(&mut out);
+    out.put_u32(1); // entry count
+    BoxHeader::with_u32_data_size(MP4A, 28 + children.len() as u32).put_buf(&mut out); // mp4a header
+    for _ in 0..6 {
+        out.put_u8(0); // reserved
+    }
+    out.put_u16(1); // data reference index
+    for _ in 0..8 {
+        out.put_u8(0); // reserved
+    }
+    out.put_u16(2); // channel count
+    out.put_u16(16); // sample size
+    out.put_u16(0); // pre-defined
+    out.put_u16(0); // reserved
+    out.put_u32(44100 << 16); // sample rate
+    out.put_slice(children);
+}
+
+pub fn write_test_visual_stsd_data<B: BufMut>(out: B, children: &[u8]) {
+    write_test_visual_stsd_data_with_type(out, AVC1, children)
+}
+
+pub fn write_test_visual_stsd_data_with_type<B: BufMut>(mut out: B, entry_type: BoxType, children: &[u8]) {
+    FullBoxHeader::default().put_buf(&mut out);
+    out.put_u32(1); // entry count
+    BoxHeader::with_u32_data_size(entry_type, 78 + children.len() as u32).put_buf(&mut out); // sample entry header
+    for _ in 0..6 {
+        out.put_u8(0); // reserved
+    }
+    out.put_u16(1); // data reference index
+    for _ in 0..16 {
+        out.put_u8(0); // pre-defined and reserved
+    }
+    out.put_u16(1920); // width
+    out.put_u16(1080); // height
+    out.put_u32(0x00480000); // horizontal resolution
+    out.put_u32(0x00480000); // vertical resolution
+    out.put_u32(0); // reserved
+    out.put_u16(1); // frame count
+    for _ in 0..32 {
+        out.put_u8(0); // compressor name
+    }
+    out.put_u16(0x0018); // depth
+    out.put_i16(-1); // pre-defined
+    out.put_slice(children);
+}
+
 pub fn write_test_stsz_data<B: BufMut>(mut out: B, chunk_count: u32) {
     FullBoxHeader::default().put_buf(&mut out);
     out.put_u32(1); // sample size
     out.put_u32(chunk_count); // sample count
 }
 
+pub fn write_test_stz2_data<B: BufMut>(mut out: B, field_size: u8, sample_count: u32) {
+    FullBoxHeader::default().put_buf(&mut out);
+    out.put_slice(&[0; 3]); // reserved
+    out.put_u8(field_size); // field size
+    out.put_u32(sample_count); // sample count
+    let entries_len = (u64::from(field_size) * u64::from(sample_count) + 7) / 8;
+    for _ in 0..entries_len {
+        out.put_u8(0); // packed entry sizes
+    }
+}
+
 pub fn write_test_stts_data<B: BufMut>(mut out: B, chunk_count: u32) {
     FullBoxHeader::default().put_buf(&mut out);
     out.put_u32(1); // entry count
@@ -224,8 +398,8 @@ pub fn write_test_stts_data<B: BufMut>(mut out: B, chunk_count: u32) {
 
 pub fn write_test_tkhd_data<B: BufMut>(mut out: B, track_id: u32) {
     FullBoxHeader::default().put_buf(&mut out);
-    out.put_u32(0); // creation time
-    out.put_u32(0); // modification time
+    out.put_u32(TEST_CREATION_TIME); // creation time
+    out.put_u32(TEST_MODIFICATION_TIME); // modification time
     out.put_u32(track_id); // track id
     out.put_u32(0); // reserved
     out.put_u32(0); // duration
@@ -243,6 +417,18 @@ pub fn write_test_tkhd_data<B: BufMut>(mut out: B, track_id: u32) {
     out.put_u32(0); // height
 }
 
+pub fn write_test_junk(out: &mut Vec<u8>) {
+    out.extend_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF].repeat(4));
+}
+
 pub fn write_test_uuid(out: &mut Vec<u8>) {
     BoxHeader::with_u32_data_size(TEST_UUID, 0).put_buf(out);
 }
+
+pub fn write_test_vmhd_data<B: BufMut>(mut out: B) {
+    FullBoxHeader { version: 0, flags: 1 }.put_buf(&mut out);
+    out.put_u16(0); // graphics mode
+    for _ in 0..3 {
+        out.put_u16(0); // opcolor
+    }
+}
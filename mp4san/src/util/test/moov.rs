@@ -1,8 +1,17 @@
+use std::iter;
+
 use derive_builder::Builder;
 
-use crate::parse::{fourcc, Co64Box, MdiaBox, MinfBox, MoovBox, Mp4Box, StblBox, StcoBox, TrakBox};
+use crate::parse::box_type::{FREE, GMHD, NMHD, SKIP, SMHD, VMHD};
+use crate::parse::{
+    fourcc, BoxType, Co64Box, CttsBox, EdtsBox, ElstBox, FourCC, MdiaBox, MinfBox, MoovBox, Mp4Box, MvexBox, StblBox,
+    StcoBox, StssBox, TrakBox, TrefBox, UdtaBox,
+};
 
-use super::{test_dinf, test_hdlr, test_mdhd, test_mvhd, test_stsc, test_stsd, test_stsz, test_stts, test_tkhd};
+use super::{
+    test_audio_stsd, test_dinf, test_free, test_hdlr, test_mdhd, test_mvhd, test_nmhd, test_smhd, test_stsc, test_stsd,
+    test_stsz, test_stts, test_stz2, test_tkhd, test_visual_stsd, test_vmhd, with_ext_size,
+};
 
 #[derive(Builder)]
 #[builder(name = "TestMoovBuilder", build_fn(name = "build_spec"))]
@@ -11,15 +20,82 @@ pub struct TestMoovSpec {
     #[builder(setter(into, each(name = "add_co_entry")))]
     pub co_entries: Vec<u64>,
 
+    #[builder(default = "vec![1]")]
+    pub stsc_first_chunks: Vec<u32>,
+
     #[builder(default = "true")]
     pub stco: bool,
 
     #[builder(default)]
     pub co64: bool,
 
+    #[builder(default)]
+    pub ctts: Option<CttsBox>,
+
+    /// An `stss` box to add to the track, if any.
+    #[builder(default, setter(strip_option))]
+    pub stss: Option<StssBox>,
+
+    /// A `tref` box to add to the track, if any.
+    #[builder(default, setter(strip_option))]
+    pub tref: Option<TrefBox>,
+
+    /// An `mvex` box to add to `moov`, if any.
+    #[builder(default, setter(strip_option))]
+    pub mvex: Option<MvexBox>,
+
+    /// A `udta` box to add to `moov`, if any.
+    #[builder(default, setter(strip_option))]
+    pub udta: Option<UdtaBox>,
+
+    /// A `udta` box to add to the track, if any.
+    #[builder(default, setter(strip_option))]
+    pub trak_udta: Option<UdtaBox>,
+
+    /// An `elst` box to add to the track in an `edts` box, if any.
+    #[builder(default, setter(strip_option))]
+    pub elst: Option<ElstBox>,
+
+    /// The field size of a compact `stz2` box to use in place of `stsz`, if any.
+    #[builder(default, setter(strip_option))]
+    pub stz2_field_size: Option<u8>,
+
+    /// The child boxes of an `avc1` sample entry to use in place of the default `mett` sample entry.
+    #[builder(default, setter(into, strip_option))]
+    pub visual_sample_entry_children: Option<Vec<u8>>,
+
+    /// The child boxes of an `mp4a` sample entry to use in place of the default `mett` sample entry.
+    #[builder(default, setter(into, strip_option))]
+    pub audio_sample_entry_children: Option<Vec<u8>>,
+
+    /// The handler type of the track's `hdlr` box.
+    #[builder(default = "fourcc::META")]
+    pub handler_type: FourCC,
+
+    /// The media header box, if any, to add to `minf` in place of the one matching the handler type, i.e. `vmhd` for
+    /// `vide` tracks and `smhd` for `soun` tracks.
+    #[builder(default, setter(strip_option))]
+    pub media_header: Option<Option<BoxType>>,
+
+    /// Whether to encode the header of the `tkhd` box with an extended 64-bit size, although its size fits in 32 bits.
+    #[builder(default)]
+    pub ext_tkhd_size: bool,
+
+    /// The types of opaque boxes to add to `stbl`, e.g. sample auxiliary information boxes.
+    #[builder(default, setter(into, each(name = "add_stbl_box")))]
+    pub extra_stbl_boxes: Vec<BoxType>,
+
+    /// The types of opaque boxes to add to `moov`, e.g. `pssh`.
+    #[builder(default, setter(into, each(name = "add_moov_box")))]
+    pub extra_moov_boxes: Vec<BoxType>,
+
     #[builder(default = "true")]
     pub stbl: bool,
 
+    /// Whether to add padding boxes to `moov` and each of its descendants.
+    #[builder(default)]
+    pub padding: bool,
+
     #[builder(default = "true")]
     pub minf: bool,
 
@@ -28,6 +104,10 @@ pub struct TestMoovSpec {
 
     #[builder(default = "true")]
     pub trak: bool,
+
+    /// The number of `trak` boxes to add to `moov`, if `trak` is set, identical except for their track IDs `1..`.
+    #[builder(default = "1")]
+    pub trak_count: usize,
 }
 
 impl TestMoovBuilder {
@@ -35,7 +115,20 @@ impl TestMoovBuilder {
         let spec = self.build_spec().unwrap();
         let chunk_count = spec.co_entries.len() as u32;
 
-        let mut stbl = vec![test_stsd(), test_stts(chunk_count), test_stsc(), test_stsz(chunk_count)];
+        let stsd = match (&spec.visual_sample_entry_children, &spec.audio_sample_entry_children) {
+            (Some(children), _) => test_visual_stsd(children),
+            (None, Some(children)) => test_audio_stsd(children),
+            (None, None) => test_stsd(),
+        };
+        let mut stbl = vec![
+            stsd,
+            test_stts(chunk_count),
+            test_stsc(&spec.stsc_first_chunks),
+            match spec.stz2_field_size {
+                Some(field_size) => test_stz2(field_size, chunk_count),
+                None => test_stsz(chunk_count),
+            },
+        ];
         if spec.co64 {
             let entries = spec.co_entries.iter().cloned();
             stbl.push(Mp4Box::with_data(Co64Box::from_iter(entries).into()).unwrap().into());
@@ -45,24 +138,95 @@ impl TestMoovBuilder {
             stbl.push(Mp4Box::with_data(StcoBox::from_iter(entries).into()).unwrap().into());
         }
 
+        if let Some(ctts) = spec.ctts {
+            stbl.push(Mp4Box::with_data(ctts.into()).unwrap().into());
+        }
+
+        if let Some(stss) = spec.stss {
+            stbl.push(Mp4Box::with_data(stss.into()).unwrap().into());
+        }
+
+        for &box_type in &spec.extra_stbl_boxes {
+            stbl.push(test_free(box_type, 16));
+        }
+
+        if spec.padding {
+            stbl.push(test_free(FREE, 8));
+        }
+
         let mut minf = vec![test_dinf()];
+        let media_header = spec.media_header.unwrap_or(match &spec.handler_type.value {
+            b"vide" => Some(VMHD),
+            b"soun" => Some(SMHD),
+            _ => None,
+        });
+        match media_header {
+            Some(VMHD) => minf.insert(0, test_vmhd()),
+            Some(SMHD) => minf.insert(0, test_smhd()),
+            Some(NMHD) => minf.insert(0, test_nmhd()),
+            Some(GMHD) => minf.insert(0, test_free(GMHD, 16)),
+            Some(media_header) => panic!("invalid media header for test {media_header}"),
+            None => {}
+        }
         if spec.stbl {
             minf.push(Mp4Box::with_data(StblBox::with_children(stbl).into()).unwrap().into());
         }
 
-        let mut mdia = vec![test_mdhd(), test_hdlr(fourcc::META)];
+        if spec.padding {
+            minf.insert(0, test_free(SKIP, 16));
+        }
+
+        let mut mdia = vec![test_mdhd(), test_hdlr(spec.handler_type)];
         if spec.minf {
             mdia.push(Mp4Box::with_data(MinfBox::with_children(minf).into()).unwrap().into());
         }
 
-        let mut trak = vec![test_tkhd(1)];
+        if spec.padding {
+            mdia.push(test_free(FREE, 8));
+        }
+
+        let mut trak = Vec::new();
+        if let Some(tref) = spec.tref {
+            trak.push(Mp4Box::with_data(tref.into()).unwrap().into());
+        }
+        if let Some(elst) = spec.elst {
+            let edts = EdtsBox::with_children(vec![Mp4Box::with_data(elst.into()).unwrap().into()]);
+            trak.push(Mp4Box::with_data(edts.into()).unwrap().into());
+        }
         if spec.mdia {
             trak.push(Mp4Box::with_data(MdiaBox::with_children(mdia).into()).unwrap().into());
         }
+        if let Some(udta) = spec.trak_udta {
+            trak.push(Mp4Box::with_data(udta.into()).unwrap().into());
+        }
+
+        if spec.padding {
+            trak.push(test_free(SKIP, 8));
+        }
 
         let mut moov = vec![test_mvhd()];
         if spec.trak {
-            moov.push(Mp4Box::with_data(TrakBox::with_children(trak).into()).unwrap().into());
+            for track_id in (1..).take(spec.trak_count) {
+                let tkhd = match spec.ext_tkhd_size {
+                    true => with_ext_size(test_tkhd(track_id)),
+                    false => test_tkhd(track_id),
+                };
+                let trak_children = iter::once(tkhd).chain(trak.iter().cloned()).collect::<Vec<_>>();
+                let trak = TrakBox::with_children(trak_children);
+                moov.push(Mp4Box::with_data(trak.into()).unwrap().into());
+            }
+        }
+        if let Some(mvex) = spec.mvex {
+            moov.push(Mp4Box::with_data(mvex.into()).unwrap().into());
+        }
+        if let Some(udta) = spec.udta {
+            moov.push(Mp4Box::with_data(udta.into()).unwrap().into());
+        }
+        for &box_type in &spec.extra_moov_boxes {
+            moov.push(test_free(box_type, 16));
+        }
+        if spec.padding {
+            moov.insert(1, test_free(FREE, 32));
         }
         Mp4Box::with_data(MoovBox::with_children(moov).into()).unwrap()
     }
@@ -7,12 +7,12 @@ use derive_builder::Builder;
 use mp4san_test::{ffmpeg_assert_eq, gpac_assert_eq};
 
 use crate::parse::box_type::{FREE, FTYP, MDAT, MECO, META, MOOV, SKIP};
-use crate::parse::{BoxType, Mp4Value};
+use crate::parse::{BoxHeader, BoxType, Mp4Value};
 use crate::{sanitize, sanitize_with_config, Config, InputSpan, SanitizedMetadata, Skip};
 
 use super::{
-    init_logger, sanitized_data, test_free, test_meco, test_meta, write_mdat_header, write_test_uuid, TestFtypBuilder,
-    TestMoovBuilder, TEST_UUID,
+    init_logger, sanitized_data, test_free, test_meco, test_meta, write_mdat_header, write_test_junk, write_test_uuid,
+    TestFtypBuilder, TestMoovBuilder, TEST_JUNK, TEST_UUID,
 };
 
 #[derive(Builder)]
@@ -37,6 +37,10 @@ pub struct TestMp4Spec {
     #[builder(default = "vec![FTYP, MDAT, MOOV]")]
     #[builder(setter(into, each(name = "add_box")))]
     boxes: Vec<BoxType>,
+
+    /// Boxes with arbitrary data, written in order in place of each box in `boxes` not otherwise known to the builder.
+    #[builder(default, setter(custom))]
+    raw_boxes: Vec<(BoxType, Vec<u8>)>,
 }
 
 #[derive(Clone)]
@@ -60,6 +64,14 @@ impl TestMp4Builder {
     pub fn build(&self) -> TestMp4 {
         self.build_spec().unwrap().build()
     }
+
+    /// Append a box of type `box_type` with the raw data `data` to the sequence of boxes.
+    pub fn raw_box(&mut self, box_type: BoxType, data: impl Into<Vec<u8>>) -> &mut Self {
+        self.raw_boxes
+            .get_or_insert_with(Vec::new)
+            .push((box_type, data.into()));
+        self.add_box(box_type)
+    }
 }
 
 impl TestMp4Spec {
@@ -72,6 +84,7 @@ impl TestMp4Spec {
         let mut mdat: Option<InputSpan> = None;
         let mut mdat_header_len = None;
         let mut moov_offsets = Vec::new();
+        let mut raw_boxes = self.raw_boxes.iter();
         for box_type in &self.boxes {
             match *box_type {
                 FTYP => {
@@ -110,7 +123,18 @@ impl TestMp4Spec {
                 TEST_UUID => {
                     write_test_uuid(&mut data);
                 }
-                _ => panic!("invalid box type for test {box_type}"),
+                TEST_JUNK => {
+                    write_test_junk(&mut data);
+                }
+                _ => match raw_boxes.next() {
+                    Some((raw_box_type, raw_data)) if raw_box_type == box_type => {
+                        BoxHeader::with_data_size(*box_type, raw_data.len() as u64)
+                            .unwrap()
+                            .put_buf(&mut data);
+                        data.extend_from_slice(raw_data);
+                    }
+                    _ => panic!("invalid box type for test {box_type}"),
+                },
             }
         }
 
@@ -175,6 +199,7 @@ impl TestMp4 {
     pub fn sanitize_ok_with_config(&self, config: Config) -> SanitizedMetadata {
         let sanitized = sanitize_with_config(self.clone(), config).unwrap();
         assert_eq!(sanitized.data, self.mdat);
+        assert!(!sanitized.was_already_faststart);
         assert_matches!(sanitized.metadata.as_deref(), Some(metadata) => {
             assert_eq!(metadata, self.expected_metadata(metadata.len()));
         });
@@ -189,6 +214,7 @@ impl TestMp4 {
         let sanitized = sanitize(self.clone()).unwrap();
         assert_eq!(sanitized.data, self.mdat);
         assert_eq!(sanitized.metadata, None);
+        assert!(sanitized.was_already_faststart);
         ffmpeg_assert_eq(&self.data, &self.mdat_data);
         gpac_assert_eq(&self.data, &self.mdat_data);
         sanitized
@@ -1,28 +1,74 @@
 use std::io;
-use std::io::Read;
+use std::io::{Cursor, Read};
 
-use bitstream_io::LE;
+use bitstream_io::{BitWrite, BitWriter, LittleEndian, LE};
 use criterion::measurement::Measurement;
-use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkGroup, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkGroup, Criterion, Throughput};
 use webpsan::parse::{BitBufReader, CanonicalHuffmanTree};
-use webpsan::Error;
+use webpsan::{Error, Skip};
 
 criterion_group!(
     benches,
     read_huffman_one_symbol,
     read_huffman_two_symbols,
-    read_huffman_256_symbols
+    read_huffman_256_symbols,
+    fill_slow_input,
+    sanitize_large_vp8l
 );
 criterion_main!(benches);
 
 struct BlackBoxZeroesInput;
 
+/// An input which returns at most [`SLOW_INPUT_READ_LEN`] bytes per read, as a slow network input might, counting its
+/// reads.
+struct SlowInput {
+    inner: Cursor<Vec<u8>>,
+    read_count: usize,
+}
+
+/// The maximum number of bytes returned by each read of a [`SlowInput`].
+const SLOW_INPUT_READ_LEN: usize = 64;
+
+/// The width of the image generated by [`large_vp8l`].
+const LARGE_VP8L_WIDTH: u32 = 4096;
+
+/// The height of the image generated by [`large_vp8l`].
+const LARGE_VP8L_HEIGHT: u32 = 2048;
+
 impl Read for BlackBoxZeroesInput {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         black_box(Ok(buf.len()))
     }
 }
 
+impl SlowInput {
+    fn new(data: Vec<u8>) -> Self {
+        Self { inner: Cursor::new(data), read_count: 0 }
+    }
+}
+
+impl Read for SlowInput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_count += 1;
+        let read_len = buf.len().min(SLOW_INPUT_READ_LEN);
+        self.inner.read(&mut buf[..read_len])
+    }
+}
+
+impl Skip for SlowInput {
+    fn skip(&mut self, amount: u64) -> io::Result<()> {
+        self.inner.skip(amount)
+    }
+
+    fn stream_position(&mut self) -> io::Result<u64> {
+        self.inner.stream_position()
+    }
+
+    fn stream_len(&mut self) -> io::Result<u64> {
+        self.inner.stream_len()
+    }
+}
+
 pub fn read_huffman_one_symbol(c: &mut Criterion) {
     let group = c.benchmark_group("one symbol");
     let code = CanonicalHuffmanTree::<LE, ()>::default();
@@ -74,3 +120,164 @@ fn read_huffman<M: Measurement, S: Clone>(mut group: BenchmarkGroup<'_, M>, code
     });
     group.finish();
 }
+
+/// Compare filling the whole buffer before decoding with filling only as much as needed, from a slow input.
+pub fn fill_slow_input(c: &mut Criterion) {
+    let buf_len = 4096;
+    let code = CanonicalHuffmanTree::<LE, ()>::new(&mut [((), 8); 256]).unwrap();
+    let input = vec![0; buf_len * 16];
+
+    let decode_greedy = |reader: &mut BitBufReader<SlowInput, LE>| -> Result<(), Error> {
+        loop {
+            if reader.buf_bits() < code.longest_code_len().into() {
+                reader.fill_buf()?;
+            }
+            if reader.buf_bits() == 0 {
+                return Ok(());
+            }
+            black_box(reader.buf_read_huffman(&code))?;
+        }
+    };
+    let decode_fill_to = |reader: &mut BitBufReader<SlowInput, LE>| -> Result<(), Error> {
+        loop {
+            reader.fill_buf_to(code.longest_code_len().into())?;
+            if reader.buf_bits() == 0 {
+                return Ok(());
+            }
+            black_box(reader.buf_read_huffman(&code))?;
+        }
+    };
+
+    let mut slow_input = SlowInput::new(input.clone());
+    BitBufReader::<_, LE>::with_capacity(&mut slow_input, buf_len)
+        .fill_buf()
+        .unwrap();
+    let greedy_first_reads = slow_input.read_count;
+    let mut slow_input = SlowInput::new(input.clone());
+    BitBufReader::<_, LE>::with_capacity(&mut slow_input, buf_len)
+        .fill_buf_to(code.longest_code_len().into())
+        .unwrap();
+    let fill_to_first_reads = slow_input.read_count;
+    eprintln!("reads of a slow input before decoding the first symbol:");
+    eprintln!("  fill_buf: {greedy_first_reads}");
+    eprintln!("  fill_buf_to: {fill_to_first_reads}");
+
+    let mut group = c.benchmark_group("slow input");
+    group.throughput(Throughput::Bytes(input.len() as u64));
+    let setup = || BitBufReader::<_, LE>::with_capacity(SlowInput::new(input.clone()), buf_len);
+    group.bench_function("fill_buf", |bencher| {
+        bencher.iter_batched(setup, |mut reader| decode_greedy(&mut reader), BatchSize::SmallInput)
+    });
+    group.bench_function("fill_buf_to", |bencher| {
+        bencher.iter_batched(setup, |mut reader| decode_fill_to(&mut reader), BatchSize::SmallInput)
+    });
+    group.finish();
+}
+
+/// Sanitize a large lossless image from a slow input.
+pub fn sanitize_large_vp8l(c: &mut Criterion) {
+    let input = large_vp8l();
+
+    let mut slow_input = SlowInput::new(input.clone());
+    webpsan::sanitize(&mut slow_input).unwrap();
+    eprintln!(
+        "reads of a slow input sanitizing a {LARGE_VP8L_WIDTH}x{LARGE_VP8L_HEIGHT} lossless image of {} bytes: {}",
+        input.len(),
+        slow_input.read_count
+    );
+
+    let mut group = c.benchmark_group("large vp8l");
+    group.throughput(Throughput::Bytes(input.len() as u64));
+    group.bench_function("sanitize", |bencher| {
+        bencher.iter_batched(
+            || Cursor::new(&input[..]),
+            |input| webpsan::sanitize(input).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("sanitize_without_bitstream", |bencher| {
+        let config = webpsan::Config::builder().validate_image_bitstream(false).build();
+        bencher.iter_batched(
+            || Cursor::new(&input[..]),
+            |input| webpsan::sanitize_with_config(input, config.clone()).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("sanitize_slow_input", |bencher| {
+        bencher.iter_batched(
+            || SlowInput::new(input.clone()),
+            |input| webpsan::sanitize(input).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+/// A lossless image using a two-color palette, so that its pixels are decoded to be checked against the palette, with
+/// each bundle of pixels coded with a single bit using a two-symbol green prefix code.
+fn large_vp8l() -> Vec<u8> {
+    let mut data = vec![];
+    let mut writer = BitWriter::endian(&mut data, LittleEndian);
+    writer.write(8, 0x2f_u8).unwrap(); // signature
+    writer.write(14, LARGE_VP8L_WIDTH - 1).unwrap(); // width
+    writer.write(14, LARGE_VP8L_HEIGHT - 1).unwrap(); // height
+    writer.write(1, 0_u8).unwrap(); // alpha is used
+    writer.write(3, 0_u8).unwrap(); // version
+
+    writer.write_bit(true).unwrap(); // transform present
+    writer.write(2, 3_u8).unwrap(); // color indexing transform
+    writer.write(8, 1_u8).unwrap(); // color table size - 1
+    writer.write_bit(false).unwrap(); // no color cache
+    for _ in 0..5 {
+        write_single_symbol_code(&mut writer);
+    }
+    writer.write_bit(false).unwrap(); // no more transforms
+
+    writer.write_bit(false).unwrap(); // no color cache
+    writer.write_bit(false).unwrap(); // no meta prefix codes
+
+    // Green: a simple code with the symbols `0` and `1`, i.e. bundles of eight pixels of color index `0`, or of color
+    // index `1` followed by seven of color index `0`.
+    writer.write_bit(true).unwrap();
+    writer.write_bit(true).unwrap();
+    writer.write_bit(false).unwrap();
+    writer.write(1, 0_u8).unwrap();
+    writer.write(8, 1_u8).unwrap();
+    // Red, blue, alpha, and distance.
+    for _ in 0..4 {
+        write_single_symbol_code(&mut writer);
+    }
+
+    // Eight one-bit color indices are bundled into each pixel.
+    let mut pixel_bits = 0x2545_f491_4f6c_dd1d_u64;
+    for _ in 0..LARGE_VP8L_WIDTH / 8 * LARGE_VP8L_HEIGHT {
+        pixel_bits ^= pixel_bits << 13;
+        pixel_bits ^= pixel_bits >> 7;
+        pixel_bits ^= pixel_bits << 17;
+        writer.write_bit(pixel_bits & 1 != 0).unwrap();
+    }
+    writer.byte_align().unwrap();
+    let vp8l_len = u32::try_from(data.len()).unwrap();
+    if data.len() % 2 != 0 {
+        data.push(0); // padding
+    }
+
+    let riff_len = u32::try_from(4 + 8 + data.len()).unwrap();
+    [
+        b"RIFF",
+        &riff_len.to_le_bytes()[..],
+        b"WEBP",
+        b"VP8L",
+        &vp8l_len.to_le_bytes(),
+        &data,
+    ]
+    .concat()
+}
+
+/// Write a simple prefix code with the single symbol `0`, coded with zero bits.
+fn write_single_symbol_code(writer: &mut BitWriter<&mut Vec<u8>, LittleEndian>) {
+    writer.write_bit(true).unwrap(); // simple code
+    writer.write_bit(false).unwrap(); // one symbol
+    writer.write_bit(false).unwrap(); // one-bit symbol
+    writer.write(1, 0_u8).unwrap();
+}
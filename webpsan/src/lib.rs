@@ -26,18 +26,23 @@ pub mod parse;
 mod reader;
 mod util;
 
-use std::io::Read;
+use std::collections::HashSet;
+use std::io::{Cursor, Read};
 use std::num::{NonZeroU16, NonZeroU32};
 
 use derive_builder::Builder;
 use derive_more::Display;
 use mediasan_common::error::{ExtraUnparsedInput, WhileParsingType};
+use mediasan_common::parse::FourCC;
 use mediasan_common::{bail_attach, ensure_attach, ensure_matches_attach, InputSpan, ResultExt};
 use parse::error::WhileParsingChunk;
 
 use crate::parse::chunk_type::{ALPH, ANIM, ANMF, EXIF, ICCP, RIFF, VP8, VP8L, VP8X, XMP};
 use crate::parse::error::MultipleChunks;
-use crate::parse::{AlphChunk, AnimChunk, AnmfChunk, ParseError, Vp8lChunk, Vp8xChunk, Vp8xFlags, WebpChunk};
+use crate::parse::{
+    AlphChunk, AnimChunk, AnmfChunk, ChunkHeader, ParseError, Vp8Chunk, Vp8lChunk, Vp8xChunk, Vp8xFlags, WebmPrim,
+    WebpChunk,
+};
 use crate::reader::ChunkReader;
 
 //
@@ -56,12 +61,158 @@ pub struct Config {
     /// The default is `false`.
     #[builder(default)]
     pub allow_unknown_chunks: bool,
+
+    /// Chunk types to allow at allowed positions for unknown chunks, even if
+    /// [`allow_unknown_chunks`](Self::allow_unknown_chunks) is not set.
+    ///
+    /// This permits specific known-safe chunk types, e.g. vendor extensions, without allowing all unknown chunks. It
+    /// has no effect on chunk types known to the sanitizer.
+    ///
+    /// The default is empty.
+    #[builder(default, setter(into, each(name = "allow_extra_chunk")))]
+    pub allowed_extra_chunks: HashSet<FourCC>,
+
+    /// The maximum number of pixels, `width * height`, of a lossless-compressed image to decode.
+    ///
+    /// Decoding lossless image data takes time proportional to its pixel count, which is bounded only by the image
+    /// dimensions rather than by the input length. Images exceeding this limit are rejected before being decoded.
+    ///
+    /// The default is `None`, for no limit.
+    #[builder(default, setter(strip_option))]
+    pub max_lossless_pixels: Option<u64>,
+
+    /// The maximum length of the image data in a `VP8` or `VP8L` chunk.
+    ///
+    /// Image data chunks declaring a longer length are rejected before any of their data is read. This protects
+    /// decoders which subsequently allocate memory based on the declared length.
+    ///
+    /// The default is `None`, for no limit.
+    #[builder(default, setter(strip_option))]
+    pub max_image_data_len: Option<u64>,
+
+    /// The maximum number of transforms a lossless-compressed image may apply.
+    ///
+    /// Each transform carries its own entropy-coded sub-image, so limiting the number of transforms bounds the work
+    /// done before the main image data is reached.
+    ///
+    /// The default is `None`, for no limit.
+    #[builder(default, setter(strip_option))]
+    pub max_vp8l_transforms: Option<u8>,
+
+    /// The maximum number of prefix code groups a lossless-compressed image may use.
+    ///
+    /// An image with a meta prefix code image may declare up to 65536 prefix code groups, each of which is read and
+    /// built into prefix code trees before any of its pixels, so limiting the number of groups bounds the memory and
+    /// time spent on them. An image without a meta prefix code image uses a single group.
+    ///
+    /// The default is `None`, for no limit.
+    #[builder(default, setter(strip_option))]
+    pub max_vp8l_code_groups: Option<u16>,
+
+    /// Whether to reject lossless-compressed images using a color indexing transform whose pixels index past the end of
+    /// its palette.
+    ///
+    /// The WebP specification decodes such indices as transparent black, as does libwebp, so such images are valid, but
+    /// usually indicate a broken encoder. Checking the indices requires decoding the image's meta prefix code image and
+    /// keeping each of its prefix code groups in memory.
+    ///
+    /// The default is `false`.
+    #[builder(default)]
+    pub validate_palette_indices: bool,
+
+    /// Whether to reject inputs with non-zero reserved bits where the WebP specification requires them to be zero.
+    ///
+    /// Currently this covers the reserved bits of the `VP8X` chunk's flags and the reserved bytes following them, which
+    /// indicate either corruption or a newer version of the format that decoders may reject. When disabled, such bits
+    /// are ignored.
+    ///
+    /// The default is `true`.
+    #[builder(default = "true")]
+    pub strict: bool,
+
+    /// Whether to accept a non-zero padding byte following an odd-length chunk.
+    ///
+    /// The WebP specification requires the padding byte to be zero, but some encoders write arbitrary values there,
+    /// which lenient decoders ignore. When enabled, the value of each padding byte is ignored.
+    ///
+    /// The default is `false`.
+    #[builder(default)]
+    pub allow_nonzero_padding: bool,
+
+    /// Whether to decode and validate the compressed bitstreams of lossless `VP8L` image data and `ALPH` alpha data.
+    ///
+    /// Decoding a lossless bitstream takes time proportional to the image's pixel count. When disabled, only the chunk
+    /// structure and the image dimensions are validated, and the image data is skipped, trading thoroughness for speed
+    /// on inputs from trusted sources. Limits which apply to the decoded image, such as
+    /// [`max_lossless_pixels`](Self::max_lossless_pixels), are then not enforced.
+    ///
+    /// The default is `true`.
+    #[builder(default = "true")]
+    pub validate_image_bitstream: bool,
+}
+
+/// Information about a sanitized WebP input, as returned by [`sanitize_with_info`].
+///
+/// All dimensions are [`NonZeroU32`]: the sanitizer rejects any input declaring a zero canvas or frame dimension, so
+/// callers need not check for zero themselves.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct WebpInfo {
+    /// The width of the canvas, in pixels.
+    pub canvas_width: NonZeroU32,
+
+    /// The height of the canvas, in pixels.
+    pub canvas_height: NonZeroU32,
+
+    /// The frames of the input, in order.
+    ///
+    /// A still image has a single frame covering the whole canvas.
+    pub frames: Vec<FrameInfo>,
+}
+
+/// Information about a single frame of a sanitized WebP input, as part of a [`WebpInfo`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FrameInfo {
+    /// The horizontal offset of the frame on the canvas, in pixels.
+    pub x: u32,
+
+    /// The vertical offset of the frame on the canvas, in pixels.
+    pub y: u32,
+
+    /// The width of the frame, in pixels.
+    pub width: NonZeroU32,
+
+    /// The height of the frame, in pixels.
+    pub height: NonZeroU32,
+}
+
+bitflags::bitflags! {
+    /// A set of the known chunk types present in a WebP file, as returned by [`present_chunks`].
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+    pub struct ChunkKinds: u8 {
+        /// The file uses the extended format, beginning with a `VP8X` chunk.
+        const VP8X = 0b0000_0001;
+        /// An `ICCP` color profile chunk is present.
+        const ICCP = 0b0000_0010;
+        /// The file is animated, containing an `ANIM` chunk followed by `ANMF` frame chunks.
+        const ANIM = 0b0000_0100;
+        /// `ALPH` alpha channel chunks are present.
+        const ALPH = 0b0000_1000;
+        /// An `EXIF` metadata chunk is present.
+        const EXIF = 0b0001_0000;
+        /// An `XMP ` metadata chunk is present.
+        const XMP = 0b0010_0000;
+    }
 }
 
 pub use mediasan_common::{Report, SeekSkipAdapter, Skip};
 
 /// Maximum file length as permitted by WebP.
-pub const MAX_FILE_LEN: u32 = u32::MAX - 2;
+///
+/// The WebP specification limits the RIFF file size field to 2<sup>32</sup> - 10, so the whole file, including its
+/// RIFF header, is at most 4 GiB minus 2 bytes.
+pub const MAX_FILE_LEN: u32 = u32::MAX - 1;
 
 //
 // private types
@@ -75,6 +226,10 @@ type DynChunkReader<'a> = ChunkReader<dyn ReadSkip + 'a>;
 #[display(fmt = "frame dimensions `{_0}`x`{_1}` do not match canvas dimensions `{_2}`x`{_3}`")]
 struct FrameDimensionsMismatch(NonZeroU16, NonZeroU16, NonZeroU32, NonZeroU32);
 
+#[derive(Clone, Copy, Debug, Display)]
+#[display(fmt = "image data too large: {_0} > {_1}")]
+struct ImageDataTooLarge(u64, u64);
+
 //
 // public functions
 //
@@ -105,40 +260,79 @@ pub fn sanitize<R: Read + Skip>(input: R) -> Result<(), Error> {
 /// If the input cannot be parsed, or an IO error occurs, an [`Error`] is returned.
 ///
 /// [`Seek`]: std::io::Seek
-pub fn sanitize_with_config<R: Read + Skip>(mut input: R, config: Config) -> Result<(), Error> {
-    let file_reader: &mut DynChunkReader<'_> = &mut ChunkReader::new(&mut input, RIFF);
+pub fn sanitize_with_config<R: Read + Skip>(input: R, config: Config) -> Result<(), Error> {
+    sanitize_with_info(input, config)?;
+    Ok(())
+}
+
+/// Sanitize a WebP input, with the given [`Config`], returning [`WebpInfo`] describing it.
+///
+/// The `input` must implement [`Read`] + [`Skip`], where [`Skip`] represents a subset of the [`Seek`] trait; an input
+/// stream which can be skipped forward, but not necessarily seeked to arbitrary positions.
+///
+/// # Errors
+///
+/// If the input cannot be parsed, or an IO error occurs, an [`Error`] is returned.
+///
+/// [`Seek`]: std::io::Seek
+pub fn sanitize_with_info<R: Read + Skip>(mut input: R, config: Config) -> Result<WebpInfo, Error> {
+    let file_reader: &mut DynChunkReader<'_> =
+        &mut ChunkReader::new(&mut input, RIFF).allow_nonzero_padding(config.allow_nonzero_padding);
     let InputSpan { offset, len } = file_reader.read_header(RIFF)?;
     let WebpChunk = file_reader.parse_data()?;
 
+    // An odd-length RIFF chunk is followed by a padding byte, which still counts towards the file length.
+    let padded_len = len + len % 2;
     ensure_attach!(
-        len <= MAX_FILE_LEN.into(),
+        padded_len <= MAX_FILE_LEN.into(),
         ParseError::InvalidInput,
         WhileParsingChunk(RIFF)
     );
 
+    if let Some(input_len) = file_reader.stream_len()? {
+        let riff_end = offset + len;
+        ensure_attach!(
+            riff_end <= input_len,
+            ParseError::RiffLenExceedsInput { len: riff_end, input_len },
+            WhileParsingChunk(RIFF),
+        );
+    }
+
     let reader: &mut DynChunkReader<'_> = &mut file_reader.child_reader();
 
     log::info!("{name} @ 0x{offset:08x}: {len} bytes", name = RIFF);
 
     let (name, InputSpan { offset, len }) = reader.read_any_header().attach_printable("while parsing first chunk")?;
-    match name {
+    let info = match name {
         VP8 => {
+            check_image_data_len(name, len, &config)?;
+            let vp8 @ Vp8Chunk { .. } = reader.parse_data()?;
+            let (width, height) = (vp8.width(), vp8.height());
             reader.skip_data()?;
-            log::info!("{name} @ 0x{offset:08x}: {len} bytes");
+            log::info!("{name} @ 0x{offset:08x}: {len} bytes, {width}x{height}");
+            WebpInfo::simple(width, height)
         }
         VP8L => {
+            check_image_data_len(name, len, &config)?;
             let vp8l @ Vp8lChunk { .. } = reader.parse_data()?;
             let (width, height) = (vp8l.width(), vp8l.height());
-            vp8l.sanitize_image_data(reader.data_reader())?;
+            if config.validate_image_bitstream {
+                vp8l.sanitize_image_data(reader.data_reader(), &config)?;
+            }
             reader.skip_data()?;
             log::info!("{name} @ 0x{offset:08x}: {len} bytes, {width}x{height}");
+            WebpInfo::simple(width, height)
         }
         VP8X => {
             let vp8x @ Vp8xChunk { flags, .. } = reader.parse_data()?;
             let (width, height) = (vp8x.canvas_width(), vp8x.canvas_height());
             log::info!("{name} @ 0x{offset:08x}: {width}x{height}, flags {flags:08b}");
+            if config.strict {
+                vp8x.validate_reserved()?;
+            }
 
-            sanitize_extended(reader, &vp8x, &config)?
+            let frames = sanitize_extended(reader, &vp8x, &config)?;
+            WebpInfo { canvas_width: width, canvas_height: height, frames }
         }
         _ => {
             log::info!("{name} @ 0x{offset:08x}: {len} bytes");
@@ -148,7 +342,7 @@ pub fn sanitize_with_config<R: Read + Skip>(mut input: R, config: Config) -> Res
                 WhileParsingChunk(name),
             );
         }
-    }
+    };
 
     // It's not clear whether the WebP spec accepts unknown chunks at the end of simple format files, but many of the
     // WebP test vectors contain non-standard trailing informational chunks.
@@ -161,7 +355,7 @@ pub fn sanitize_with_config<R: Read + Skip>(mut input: R, config: Config) -> Res
                 bail_attach!(ParseError::InvalidChunkLayout, MultipleChunks(name))
             }
             ANMF => bail_attach!(ParseError::InvalidChunkLayout, "non-contiguous ANMF chunk"),
-            _ => ensure_attach!(config.allow_unknown_chunks, ParseError::UnsupportedChunk(name)),
+            _ => ensure_attach!(config.allows_unknown_chunk(name), ParseError::UnsupportedChunk(name)),
         }
         reader.skip_data()?;
         log::info!("{name} @ 0x{offset:08x}: {len} bytes");
@@ -173,21 +367,178 @@ pub fn sanitize_with_config<R: Read + Skip>(mut input: R, config: Config) -> Res
         ExtraUnparsedInput,
     );
 
-    Ok(())
+    Ok(info)
+}
+
+/// Return the set of known chunk types present in a WebP input.
+///
+/// This reads only as far as is needed to determine the set, which for an extended format file is just the flags in
+/// its `VP8X` chunk, so it is much cheaper than [`sanitize`]. It does not validate the rest of the input, and the
+/// presence of chunks is reported as declared by those flags; callers which need to know that the file is valid must
+/// still [`sanitize`] it.
+///
+/// The image data chunks, `VP8` and `VP8L`, are not reported.
+///
+/// # Errors
+///
+/// If the input cannot be parsed up to the point the set is determined, or an IO error occurs, an [`Error`] is
+/// returned.
+pub fn present_chunks<R: Read + Skip>(mut input: R) -> Result<ChunkKinds, Error> {
+    let file_reader: &mut DynChunkReader<'_> = &mut ChunkReader::new(&mut input, RIFF);
+    file_reader.read_header(RIFF)?;
+    let WebpChunk = file_reader.parse_data()?;
+
+    let reader: &mut DynChunkReader<'_> = &mut file_reader.child_reader();
+
+    let (name, _) = reader.read_any_header().attach_printable("while parsing first chunk")?;
+    match name {
+        VP8 | VP8L => Ok(ChunkKinds::empty()),
+        VP8X => {
+            let Vp8xChunk { flags, .. } = reader.parse_data()?;
+            let mut kinds = ChunkKinds::VP8X;
+            kinds.set(ChunkKinds::ICCP, flags.contains(Vp8xFlags::HAS_ICCP_CHUNK));
+            kinds.set(ChunkKinds::ANIM, flags.contains(Vp8xFlags::IS_ANIMATED));
+            kinds.set(ChunkKinds::ALPH, flags.contains(Vp8xFlags::HAS_ALPH_CHUNK));
+            kinds.set(ChunkKinds::EXIF, flags.contains(Vp8xFlags::HAS_EXIF_CHUNK));
+            kinds.set(ChunkKinds::XMP, flags.contains(Vp8xFlags::HAS_XMP_CHUNK));
+            Ok(kinds)
+        }
+        _ => bail_attach!(
+            ParseError::InvalidChunkLayout,
+            "expected image data or VP8X",
+            WhileParsingChunk(name),
+        ),
+    }
+}
+
+/// Extract the ICC color profile from the `ICCP` chunk of a WebP input, if it has one.
+///
+/// Like [`present_chunks`], this reads only as far as is needed, which is up to the end of the `ICCP` chunk, since it
+/// must immediately follow the `VP8X` chunk. It does not validate the rest of the input or the profile itself, so
+/// callers which need to know that the file is valid must still [`sanitize`] it.
+///
+/// Simple format inputs, and extended format inputs whose `VP8X` flags declare no `ICCP` chunk, have no profile.
+///
+/// # Errors
+///
+/// If the input cannot be parsed up to the end of the `ICCP` chunk, or an IO error occurs, an [`Error`] is returned.
+pub fn extract_iccp<R: Read + Skip>(mut input: R) -> Result<Option<Vec<u8>>, Error> {
+    let file_reader: &mut DynChunkReader<'_> = &mut ChunkReader::new(&mut input, RIFF);
+    file_reader.read_header(RIFF)?;
+    let WebpChunk = file_reader.parse_data()?;
+
+    let reader: &mut DynChunkReader<'_> = &mut file_reader.child_reader();
+
+    let (name, _) = reader.read_any_header().attach_printable("while parsing first chunk")?;
+    match name {
+        VP8 | VP8L => return Ok(None),
+        VP8X => (),
+        _ => bail_attach!(
+            ParseError::InvalidChunkLayout,
+            "expected image data or VP8X",
+            WhileParsingChunk(name),
+        ),
+    }
+    let Vp8xChunk { flags, .. } = reader.parse_data()?;
+    if !flags.contains(Vp8xFlags::HAS_ICCP_CHUNK) {
+        return Ok(None);
+    }
+
+    let InputSpan { len, .. } = reader.read_header(ICCP)?;
+    let profile_len = len - u64::from(ChunkHeader::ENCODED_LEN);
+    // The profile is read incrementally rather than into a buffer of its declared length, which may be far larger than
+    // the input.
+    let mut profile = Vec::new();
+    reader.data_reader().read_to_end(&mut profile)?;
+    ensure_attach!(
+        profile.len() as u64 == profile_len,
+        ParseError::TruncatedChunk,
+        WhileParsingChunk(ICCP),
+    );
+    Ok(Some(profile))
 }
 
-fn sanitize_extended(reader: &mut DynChunkReader<'_>, vp8x: &Vp8xChunk, config: &Config) -> Result<(), Error> {
+/// Rewrite the flags of a WebP input's `VP8X` chunk to match the chunks actually present.
+///
+/// Files are sometimes written with `VP8X` flags inconsistent with their chunks, e.g. declaring an `EXIF` chunk which
+/// is absent, which [`sanitize`] rejects. This recomputes the flags from the chunks present in `input`, including
+/// `ALPH` chunks within animation frames, and returns a copy of `input` with its `VP8X` flags rewritten. Reserved flag
+/// bits are cleared. Simple format inputs, which have no `VP8X` chunk, are returned unchanged.
+///
+/// Only the chunk structure of the input is read, so the result should still be passed to [`sanitize`].
+///
+/// # Errors
+///
+/// If the chunk structure of the input cannot be parsed, an [`Error`] is returned.
+pub fn repair_vp8x_flags(input: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut cursor = Cursor::new(input);
+    let file_reader: &mut DynChunkReader<'_> = &mut ChunkReader::new(&mut cursor, RIFF);
+    file_reader.read_header(RIFF)?;
+    let WebpChunk = file_reader.parse_data()?;
+
+    let reader: &mut DynChunkReader<'_> = &mut file_reader.child_reader();
+
+    let (name, InputSpan { offset: vp8x_offset, .. }) =
+        reader.read_any_header().attach_printable("while parsing first chunk")?;
+    match name {
+        VP8 | VP8L => return Ok(input.to_vec()),
+        VP8X => (),
+        _ => bail_attach!(
+            ParseError::InvalidChunkLayout,
+            "expected image data or VP8X",
+            WhileParsingChunk(name),
+        ),
+    }
+    let Vp8xChunk { .. } = reader.parse_data()?;
+
+    let mut flags = Vp8xFlags::empty();
+    while reader.has_remaining()? {
+        let (name, _) = reader.read_any_header()?;
+        match name {
+            ICCP => flags.insert(Vp8xFlags::HAS_ICCP_CHUNK),
+            ALPH => flags.insert(Vp8xFlags::HAS_ALPH_CHUNK),
+            ANIM => flags.insert(Vp8xFlags::IS_ANIMATED),
+            ANMF => {
+                flags.insert(Vp8xFlags::IS_ANIMATED);
+                let AnmfChunk { .. } = reader.parse_data()?;
+                let anmf_reader: &mut DynChunkReader<'_> = &mut reader.child_reader();
+                while anmf_reader.has_remaining()? {
+                    let (name, _) = anmf_reader.read_any_header()?;
+                    if name == ALPH {
+                        flags.insert(Vp8xFlags::HAS_ALPH_CHUNK);
+                    }
+                    anmf_reader.skip_data()?;
+                }
+            }
+            EXIF => flags.insert(Vp8xFlags::HAS_EXIF_CHUNK),
+            XMP => flags.insert(Vp8xFlags::HAS_XMP_CHUNK),
+            _ => (),
+        }
+        reader.skip_data()?;
+    }
+
+    let mut output = input.to_vec();
+    let flags_offset = vp8x_offset + u64::from(ChunkHeader::ENCODED_LEN);
+    output[flags_offset as usize] = flags.bits();
+    Ok(output)
+}
+
+fn sanitize_extended(
+    reader: &mut DynChunkReader<'_>,
+    vp8x: &Vp8xChunk,
+    config: &Config,
+) -> Result<Vec<FrameInfo>, Error> {
     if vp8x.flags.contains(Vp8xFlags::HAS_ICCP_CHUNK) {
         let InputSpan { offset, len } = reader.read_header(ICCP)?;
         reader.skip_data()?;
         log::info!("{name} @ 0x{offset:08x}: {len} bytes", name = ICCP);
     }
 
-    if vp8x.flags.contains(Vp8xFlags::IS_ANIMATED) {
-        sanitize_animated(reader, vp8x, config)?;
+    let frames = if vp8x.flags.contains(Vp8xFlags::IS_ANIMATED) {
+        sanitize_animated(reader, vp8x, config)?
     } else {
-        sanitize_still(reader, vp8x).attach_printable("while parsing still image data")?;
-    }
+        vec![sanitize_still(reader, vp8x, config).attach_printable("while parsing still image data")?]
+    };
 
     if vp8x.flags.contains(Vp8xFlags::HAS_EXIF_CHUNK) {
         let InputSpan { offset, len } = reader.read_header(EXIF)?;
@@ -201,15 +552,17 @@ fn sanitize_extended(reader: &mut DynChunkReader<'_>, vp8x: &Vp8xChunk, config:
         log::info!("{name} @ 0x{offset:08x}: {len} bytes", name = XMP);
     }
 
-    Ok(())
+    Ok(frames)
 }
 
-fn sanitize_still(reader: &mut DynChunkReader<'_>, vp8x: &Vp8xChunk) -> Result<(), Error> {
+fn sanitize_still(reader: &mut DynChunkReader<'_>, vp8x: &Vp8xChunk, config: &Config) -> Result<FrameInfo, Error> {
     let mut alph = None;
     if vp8x.flags.contains(Vp8xFlags::HAS_ALPH_CHUNK) {
         let InputSpan { offset, len } = reader.read_header(ALPH)?;
         let read_alph @ AlphChunk { flags } = reader.parse_data()?;
-        read_alph.sanitize_image_data(reader.data_reader(), vp8x)?;
+        if config.validate_image_bitstream {
+            read_alph.sanitize_image_data(reader.data_reader(), vp8x.canvas_width(), vp8x.canvas_height(), config)?;
+        }
         reader.skip_data()?;
         log::info!("{name} @ 0x{offset:08x}: {len} bytes, flags {flags:08b}", name = ALPH);
         alph = Some(read_alph);
@@ -219,11 +572,22 @@ fn sanitize_still(reader: &mut DynChunkReader<'_>, vp8x: &Vp8xChunk) -> Result<(
     let (name, InputSpan { offset, len }) = reader.read_any_header()?;
     match name {
         VP8 => {
+            check_image_data_len(name, len, config)?;
+
+            let vp8 @ Vp8Chunk { .. } = reader.parse_data()?;
+            let (width, height) = (vp8.width(), vp8.height());
+            ensure_attach!(
+                (width.into(), height.into()) == (vp8x.canvas_width(), vp8x.canvas_height()),
+                ParseError::InvalidInput,
+                FrameDimensionsMismatch(width, height, vp8x.canvas_width(), vp8x.canvas_height()),
+                WhileParsingType::new::<Vp8Chunk>(),
+            );
             reader.skip_data()?;
-            log::info!("{name} @ 0x{offset:08x}: {len} bytes");
+            log::info!("{name} @ 0x{offset:08x}: {len} bytes, {width}x{height}");
         }
         VP8L => {
             ensure_matches_attach!(alph, None, ParseError::InvalidChunkLayout, WhileParsingChunk(VP8L));
+            check_image_data_len(name, len, config)?;
 
             let vp8l @ Vp8lChunk { .. } = reader.parse_data()?;
             let (width, height) = (vp8l.width(), vp8l.height());
@@ -233,7 +597,9 @@ fn sanitize_still(reader: &mut DynChunkReader<'_>, vp8x: &Vp8xChunk) -> Result<(
                 FrameDimensionsMismatch(width, height, vp8x.canvas_width(), vp8x.canvas_height()),
                 WhileParsingType::new::<Vp8lChunk>(),
             );
-            vp8l.sanitize_image_data(reader.data_reader())?;
+            if config.validate_image_bitstream {
+                vp8l.sanitize_image_data(reader.data_reader(), config)?;
+            }
             reader.skip_data()?;
             log::info!("{name} @ 0x{offset:08x}: {len} bytes, {width}x{height}");
         }
@@ -243,10 +609,15 @@ fn sanitize_still(reader: &mut DynChunkReader<'_>, vp8x: &Vp8xChunk) -> Result<(
             WhileParsingChunk(name),
         ),
     }
-    Ok(())
+    Ok(FrameInfo { x: 0, y: 0, width: vp8x.canvas_width(), height: vp8x.canvas_height() })
 }
 
-fn sanitize_animated(reader: &mut DynChunkReader<'_>, vp8x: &Vp8xChunk, config: &Config) -> Result<(), Error> {
+fn sanitize_animated(
+    reader: &mut DynChunkReader<'_>,
+    vp8x: &Vp8xChunk,
+    config: &Config,
+) -> Result<Vec<FrameInfo>, Error> {
+    let mut frames = vec![];
     let InputSpan { offset, len } = reader.read_header(ANIM)?;
     let AnimChunk { .. } = reader.parse_data()?;
     log::info!("{name} @ 0x{offset:08x}: {len} bytes", name = ANIM);
@@ -261,6 +632,8 @@ fn sanitize_animated(reader: &mut DynChunkReader<'_>, vp8x: &Vp8xChunk, config:
         let InputSpan { offset, len } = reader.read_header(ANMF)?;
         let anmf @ AnmfChunk { flags, .. } = reader.parse_data()?;
         let (x, y, width, height) = (anmf.x(), anmf.y(), anmf.width(), anmf.height());
+        // The frame offset is stored divided by two.
+        frames.push(FrameInfo { x: 2 * x, y: 2 * y, width, height });
         log::info!(
             "{name} @ 0x{offset:08x}: {len} bytes, {width}x{height} @ ({x}, {y}), flags {flags:08b}",
             name = ANMF
@@ -273,7 +646,9 @@ fn sanitize_animated(reader: &mut DynChunkReader<'_>, vp8x: &Vp8xChunk, config:
             if let Some(ALPH) = anmf_reader.peek_header()? {
                 let InputSpan { offset, len } = anmf_reader.read_header(ALPH)?;
                 let read_alph @ AlphChunk { flags } = anmf_reader.parse_data()?;
-                read_alph.sanitize_image_data(anmf_reader.data_reader(), vp8x)?;
+                if config.validate_image_bitstream {
+                    read_alph.sanitize_image_data(anmf_reader.data_reader(), anmf.width(), anmf.height(), config)?;
+                }
                 anmf_reader.skip_data()?;
                 log::info!("{name} @ 0x{offset:08x}: {len} bytes, flags {flags:08b}", name = ALPH);
                 alph = Some(read_alph);
@@ -285,11 +660,15 @@ fn sanitize_animated(reader: &mut DynChunkReader<'_>, vp8x: &Vp8xChunk, config:
             .attach_printable("while parsing animated image frame")?;
         match name {
             VP8 => {
+                check_image_data_len(name, len, config)?;
+                let vp8 @ Vp8Chunk { .. } = anmf_reader.parse_data()?;
+                let (width, height) = (vp8.width(), vp8.height());
                 anmf_reader.skip_data()?;
-                log::info!("{name} @ 0x{offset:08x}: {len} bytes");
+                log::info!("{name} @ 0x{offset:08x}: {len} bytes, {width}x{height}");
             }
             VP8L => {
                 ensure_matches_attach!(alph, None, ParseError::InvalidChunkLayout, WhileParsingChunk(VP8L));
+                check_image_data_len(name, len, config)?;
 
                 let vp8l @ Vp8lChunk { .. } = anmf_reader.parse_data()?;
                 let (width, height) = (vp8l.width(), vp8l.height());
@@ -299,7 +678,9 @@ fn sanitize_animated(reader: &mut DynChunkReader<'_>, vp8x: &Vp8xChunk, config:
                     FrameDimensionsMismatch(vp8l.width(), vp8l.height(), vp8x.canvas_width(), vp8x.canvas_height()),
                     WhileParsingType::new::<Vp8lChunk>(),
                 );
-                vp8l.sanitize_image_data(anmf_reader.data_reader())?;
+                if config.validate_image_bitstream {
+                    vp8l.sanitize_image_data(anmf_reader.data_reader(), config)?;
+                }
                 anmf_reader.skip_data()?;
                 log::info!("{name} @ 0x{offset:08x}: {len} bytes, {width}x{height}");
             }
@@ -321,7 +702,7 @@ fn sanitize_animated(reader: &mut DynChunkReader<'_>, vp8x: &Vp8xChunk, config:
                     WhileParsingChunk(ANMF),
                 ),
                 _ => ensure_attach!(
-                    config.allow_unknown_chunks,
+                    config.allows_unknown_chunk(name),
                     ParseError::UnsupportedChunk(name),
                     WhileParsingChunk(ANMF)
                 ),
@@ -330,9 +711,35 @@ fn sanitize_animated(reader: &mut DynChunkReader<'_>, vp8x: &Vp8xChunk, config:
             log::info!("{name} @ 0x{offset:08x}: {len} bytes");
         }
     }
+    Ok(frames)
+}
+
+/// Check the data length of an image data chunk, given the length of the whole chunk, against the configured limit.
+fn check_image_data_len(name: FourCC, chunk_len: u64, config: &Config) -> Result<(), Error> {
+    if let Some(max_image_data_len) = config.max_image_data_len {
+        let data_len = chunk_len - u64::from(ChunkHeader::ENCODED_LEN);
+        ensure_attach!(
+            data_len <= max_image_data_len,
+            ParseError::InvalidInput,
+            ImageDataTooLarge(data_len, max_image_data_len),
+            WhileParsingChunk(name),
+        );
+    }
     Ok(())
 }
 
+//
+// WebpInfo impls
+//
+
+impl WebpInfo {
+    /// Construct the info for a simple format file, with a single frame covering the whole canvas.
+    fn simple(width: NonZeroU16, height: NonZeroU16) -> Self {
+        let (width, height) = (width.into(), height.into());
+        Self { canvas_width: width, canvas_height: height, frames: vec![FrameInfo { x: 0, y: 0, width, height }] }
+    }
+}
+
 //
 // Config impls
 //
@@ -344,6 +751,12 @@ impl Config {
     pub fn builder() -> ConfigBuilder {
         ConfigBuilder::default()
     }
+
+    /// Whether an unknown chunk of type `name` is allowed, either by
+    /// [`allowed_extra_chunks`](Self::allowed_extra_chunks) or by [`allow_unknown_chunks`](Self::allow_unknown_chunks).
+    fn allows_unknown_chunk(&self, name: FourCC) -> bool {
+        self.allowed_extra_chunks.contains(&name) || self.allow_unknown_chunks
+    }
 }
 
 impl Default for Config {
@@ -378,10 +791,10 @@ mod test {
     use super::*;
 
     use assert_matches::assert_matches;
-    use mediasan_common::parse::FourCC;
+    use bytes::BufMut;
 
     use crate::parse::AlphFlags;
-    use crate::util::test::{test_alph, test_anmf, test_header, test_vp8x, test_webp};
+    use crate::util::test::{test_alph, test_anmf, test_header, test_vp8x, test_webp, TEST_ICCP_PROFILE};
 
     const TEST: FourCC = FourCC { value: *b"TeSt" };
 
@@ -421,6 +834,20 @@ mod test {
         });
     }
 
+    #[test]
+    pub fn file_len_exceeds_input() {
+        let header = test_header().len(Some(1000)).clone();
+        let test = test_webp().header(Some(header)).build();
+        let input_len = test.data_len;
+        assert_matches!(test.sanitize_invalid(), Error::Parse(err) => {
+            assert_matches!(
+                err.get_ref(),
+                &ParseError::RiffLenExceedsInput { len: 1008, input_len: actual_len } if actual_len == input_len,
+                "{err:?}"
+            );
+        });
+    }
+
     #[test]
     pub fn file_len_invalid() {
         let header = test_header().len(Some(MAX_FILE_LEN + 1)).clone();
@@ -441,6 +868,40 @@ mod test {
         });
     }
 
+    #[test]
+    pub fn unknown_chunk() {
+        let test = test_webp().chunks([VP8L, TEST]).build();
+        assert_matches!(test.sanitize_non_compliant(), Error::Parse(err) => {
+            assert_matches!(err.get_ref(), ParseError::UnsupportedChunk(TEST), "{err:?}");
+        });
+        test.sanitize_ok_with_config(Config::builder().allow_unknown_chunks(true).build());
+    }
+
+    #[test]
+    pub fn allowed_extra_chunk() {
+        let config = Config::builder().allow_extra_chunk(TEST).build();
+        test_webp()
+            .chunks([VP8L, TEST])
+            .build()
+            .sanitize_ok_with_config(config.clone());
+
+        let other = FourCC { value: *b"OtHr" };
+        let test = test_webp().chunks([VP8L, TEST, other]).build();
+        assert_matches!(test.sanitize_non_compliant_with_config(config), Error::Parse(err) => {
+            assert_matches!(err.get_ref(), ParseError::UnsupportedChunk(name) if *name == other, "{err:?}");
+        });
+    }
+
+    #[test]
+    pub fn allowed_extra_chunk_in_anmf() {
+        let anmf = test_anmf().chunks([VP8L, TEST]).clone();
+        let test = test_webp().chunks([VP8X, ANIM, ANMF]).add_anmf(anmf).build();
+        assert_matches!(test.sanitize_non_compliant(), Error::Parse(err) => {
+            assert_matches!(err.get_ref(), ParseError::UnsupportedChunk(TEST), "{err:?}");
+        });
+        test.sanitize_ok_with_config(Config::builder().allow_extra_chunk(TEST).build());
+    }
+
     #[test]
     pub fn image_data_missing() {
         let test = test_webp().chunks([]).build();
@@ -464,6 +925,206 @@ mod test {
         test_webp().chunks([VP8X, VP8]).build().sanitize_ok();
     }
 
+    #[test]
+    pub fn lossy_not_key_frame() {
+        let mut test = test_webp().chunks([VP8]).build();
+        let mut data = test.data.to_vec();
+        data[20] |= 1;
+        test.data = data.into();
+        assert_matches!(test.sanitize_invalid(), Error::Parse(err) => {
+            assert_matches!(err.get_ref(), ParseError::InvalidInput, "{err:?}");
+        });
+    }
+
+    #[test]
+    pub fn lossy_invalid_start_code() {
+        let mut test = test_webp().chunks([VP8]).build();
+        let mut data = test.data.to_vec();
+        data[23] = 0;
+        test.data = data.into();
+        assert_matches!(test.sanitize_invalid(), Error::Parse(err) => {
+            assert_matches!(err.get_ref(), ParseError::InvalidInput, "{err:?}");
+        });
+    }
+
+    #[test]
+    pub fn vp8x_lossy_dimensions_mismatch() {
+        let vp8x = test_vp8x().width(1).clone();
+        let test = test_webp().chunks([VP8X, VP8]).vp8x(vp8x).build();
+        assert_matches!(test.sanitize_invalid(), Error::Parse(err) => {
+            assert_matches!(err.get_ref(), ParseError::InvalidInput, "{err:?}");
+        });
+
+        let vp8x = test_vp8x().height(1).clone();
+        let test = test_webp().chunks([VP8X, VP8]).vp8x(vp8x).build();
+        assert_matches!(test.sanitize_invalid(), Error::Parse(err) => {
+            assert_matches!(err.get_ref(), ParseError::InvalidInput, "{err:?}");
+        });
+    }
+
+    #[test]
+    pub fn vp8x_lossless_dimensions_mismatch() {
+        let vp8x = test_vp8x().width(1).clone();
+        let test = test_webp().chunks([VP8X, VP8L]).vp8x(vp8x).build();
+        assert_matches!(test.sanitize_invalid(), Error::Parse(err) => {
+            assert_matches!(err.get_ref(), ParseError::InvalidInput, "{err:?}");
+        });
+    }
+
+    #[test]
+    pub fn lossless_corrupt_bitstream_not_validated() {
+        let vp8l_data = b"\x2f\0\0\0\0\xff\xff\xff";
+        let config = Config::builder().validate_image_bitstream(false).build();
+        let test = test_webp().vp8l_data(&vp8l_data[..]).build();
+        assert_matches!(test.sanitize_invalid(), Error::Parse(err) => {
+            assert_matches!(err.get_ref(), ParseError::InvalidInput, "{err:?}");
+        });
+        sanitize_with_config(test, config.clone()).unwrap();
+
+        let test = test_webp().chunks([VP8X, VP8L]).vp8l_data(&vp8l_data[..]).build();
+        sanitize_with_config(test, config.clone()).unwrap();
+    }
+
+    #[test]
+    pub fn lossless_structure_validated_without_bitstream() {
+        let config = Config::builder().validate_image_bitstream(false).build();
+
+        let vp8x = test_vp8x().width(1).clone();
+        let test = test_webp().chunks([VP8X, VP8L]).vp8x(vp8x).build();
+        assert_matches!(test.sanitize_invalid_with_config(config.clone()), Error::Parse(err) => {
+            assert_matches!(err.get_ref(), ParseError::InvalidInput, "{err:?}");
+        });
+
+        let test = test_webp().chunks([VP8L, VP8X]).build();
+        assert_matches!(test.sanitize_non_compliant_with_config(config), Error::Parse(err) => {
+            assert_matches!(err.get_ref(), ParseError::InvalidChunkLayout, "{err:?}");
+        });
+    }
+
+    #[test]
+    pub fn repair_vp8x_flags_missing_chunk() {
+        let vp8x = test_vp8x().flags(Some(Vp8xFlags::HAS_EXIF_CHUNK)).clone();
+        let mut test = test_webp().chunks([VP8X, VP8L]).vp8x(vp8x).build();
+        test.sanitize_non_compliant();
+        test.data = repair_vp8x_flags(&test.data).unwrap().into();
+        test.sanitize_ok();
+    }
+
+    #[test]
+    pub fn repair_vp8x_flags_undeclared_chunks() {
+        let vp8x = test_vp8x().flags(Some(Vp8xFlags::empty())).clone();
+        let mut test = test_webp()
+            .chunks([VP8X, ICCP, ALPH, VP8, EXIF, XMP])
+            .vp8x(vp8x)
+            .build();
+        test.sanitize_invalid();
+        test.data = repair_vp8x_flags(&test.data).unwrap().into();
+        test.sanitize_ok();
+    }
+
+    #[test]
+    pub fn repair_vp8x_flags_animated() {
+        let vp8x = test_vp8x()
+            .flags(Some(Vp8xFlags::from_bits_retain(0b1000_0000)))
+            .clone();
+        let anmfs = [
+            test_anmf().chunks([VP8]).clone(),
+            test_anmf().chunks([ALPH, VP8]).clone(),
+        ];
+        let mut test = test_webp()
+            .chunks([VP8X, ANIM, ANMF, ANMF])
+            .vp8x(vp8x)
+            .anmfs(anmfs)
+            .build();
+        test.sanitize_invalid();
+        test.data = repair_vp8x_flags(&test.data).unwrap().into();
+        test.sanitize_ok();
+    }
+
+    #[test]
+    pub fn repair_vp8x_flags_simple() {
+        let test = test_webp().build();
+        assert_eq!(repair_vp8x_flags(&test.data).unwrap(), test.data);
+    }
+
+    #[test]
+    pub fn info_simple() {
+        let test = test_webp().chunks([VP8]).build();
+        let info = sanitize_with_info(test, Config::default()).unwrap();
+        let one = NonZeroU32::MIN;
+        assert_eq!((info.canvas_width, info.canvas_height), (one, one));
+        assert_eq!(info.frames, [FrameInfo { x: 0, y: 0, width: one, height: one }]);
+    }
+
+    #[test]
+    pub fn info_extended() {
+        let test = test_webp().chunks([VP8X, VP8L]).build();
+        let info = sanitize_with_info(test, Config::default()).unwrap();
+        let one = NonZeroU32::MIN;
+        assert_eq!((info.canvas_width, info.canvas_height), (one, one));
+        assert_eq!(info.frames, [FrameInfo { x: 0, y: 0, width: one, height: one }]);
+    }
+
+    #[test]
+    pub fn info_animated() {
+        let vp8x = test_vp8x().width(3).height(3).clone();
+        let anmfs = [
+            test_anmf().chunks([VP8]).clone(),
+            test_anmf().x(1).y(1).chunks([VP8]).clone(),
+        ];
+        let test = test_webp()
+            .chunks([VP8X, ANIM, ANMF, ANMF])
+            .vp8x(vp8x)
+            .anmfs(anmfs)
+            .build();
+        let info = sanitize_with_info(test, Config::default()).unwrap();
+        let (one, four) = (NonZeroU32::MIN, NonZeroU32::new(4).unwrap());
+        assert_eq!((info.canvas_width, info.canvas_height), (four, four));
+        assert_eq!(
+            info.frames,
+            [
+                FrameInfo { x: 0, y: 0, width: one, height: one },
+                FrameInfo { x: 2, y: 2, width: one, height: one },
+            ]
+        );
+    }
+
+    #[test]
+    pub fn info_zero_dimensions() {
+        let vp8_data = [
+            18, 1, 0, 157, 1, 42, 0, 0, 1, 0, 18, 0, 52, 0, 0, 13, 192, 0, 254, 251, 253, 80, 0, 0,
+        ];
+        let test = test_webp().chunks([VP8]).vp8_data(vp8_data).build();
+        assert_matches!(sanitize_with_info(test.clone(), Config::default()).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.get_ref(), ParseError::InvalidInput, "{err:?}");
+        });
+        test.sanitize_invalid();
+    }
+
+    #[test]
+    pub fn vp8x_reserved_flag_bit() {
+        let vp8x = test_vp8x()
+            .flags(Some(Vp8xFlags::from_bits_retain(0b1000_0000)))
+            .clone();
+        let test = test_webp().chunks([VP8X, VP8]).vp8x(vp8x).build();
+        assert_matches!(test.sanitize_invalid(), Error::Parse(err) => {
+            assert_matches!(err.get_ref(), ParseError::InvalidInput, "{err:?}");
+        });
+        test.sanitize_ok_with_config(Config::builder().strict(false).build());
+    }
+
+    #[test]
+    pub fn vp8x_reserved_bytes() {
+        let mut test = test_webp().chunks([VP8X, VP8]).build();
+        let mut data = test.data.to_vec();
+        data[12 + 8 + 1] = 1; // The first reserved byte following the VP8X flags.
+        test.data = data.into();
+        assert_matches!(test.sanitize_invalid(), Error::Parse(err) => {
+            assert_matches!(err.get_ref(), ParseError::InvalidInput, "{err:?}");
+        });
+        test.sanitize_ok_with_config(Config::builder().strict(false).build());
+    }
+
     #[test]
     pub fn vp8x_lossy_alpha_lossless() {
         let alph = test_alph().flags(AlphFlags::COMPRESS_LOSSLESS).clone();
@@ -472,10 +1133,46 @@ mod test {
 
     #[test]
     pub fn vp8x_lossy_alpha_uncompressed() {
-        let alph = test_alph().flags(AlphFlags::empty()).clone();
+        let alph = test_alph().flags(AlphFlags::empty()).image_data([0xff]).clone();
         test_webp().chunks([VP8X, ALPH, VP8]).alph(alph).build().sanitize_ok();
     }
 
+    #[test]
+    pub fn vp8x_lossy_alpha_uncompressed_len_mismatch() {
+        for image_data in [&[][..], &[0xff; 2][..]] {
+            let alph = test_alph().flags(AlphFlags::empty()).image_data(image_data).clone();
+            let test = test_webp().chunks([VP8X, ALPH, VP8]).alph(alph).build();
+            assert_matches!(test.sanitize_invalid(), Error::Parse(err) => {
+                assert_matches!(err.get_ref(), ParseError::InvalidInput, "{err:?}");
+            });
+        }
+    }
+
+    #[test]
+    pub fn vp8x_lossy_alpha_lossless_len_not_checked() {
+        let vp8x = test_vp8x().width(1).height(1).clone();
+        let alph = test_alph().flags(AlphFlags::COMPRESS_LOSSLESS).clone();
+        let vp8_data = [
+            18, 1, 0, 157, 1, 42, 2, 0, 2, 0, 18, 0, 52, 0, 0, 13, 192, 0, 254, 251, 253, 80, 0, 0,
+        ];
+        let test = test_webp()
+            .chunks([VP8X, ALPH, VP8])
+            .vp8x(vp8x)
+            .alph(alph)
+            .vp8_data(vp8_data)
+            .build();
+        test.sanitize_ok();
+    }
+
+    #[test]
+    pub fn vp8x_animated_lossy_alpha_uncompressed() {
+        let vp8x = test_vp8x().width(3).height(3).clone();
+        let alph = test_alph().flags(AlphFlags::empty()).image_data([0xff; 2]).clone();
+        let anmf = test_anmf().width(1).height(0).alph(alph).chunks([ALPH, VP8]).clone();
+        let test = test_webp().chunks([VP8X, ANIM, ANMF]).vp8x(vp8x).anmfs([anmf]).build();
+        test.sanitize_ok();
+    }
+
     #[test]
     pub fn vp8x_lossless() {
         test_webp().chunks([VP8X, VP8L]).build().sanitize_ok();
@@ -673,6 +1370,132 @@ mod test {
         test.sanitize_ok();
     }
 
+    #[test]
+    pub fn present_chunks_all_meta() {
+        let test = test_webp().chunks([VP8X, ICCP, VP8L, EXIF, XMP]).build();
+        let kinds = present_chunks(test).unwrap();
+        assert_eq!(
+            kinds,
+            ChunkKinds::VP8X | ChunkKinds::ICCP | ChunkKinds::EXIF | ChunkKinds::XMP
+        );
+    }
+
+    #[test]
+    pub fn extract_iccp_profile() {
+        let test = test_webp().chunks([VP8X, ICCP, VP8L, EXIF, XMP]).build();
+        assert_eq!(extract_iccp(test).unwrap().as_deref(), Some(TEST_ICCP_PROFILE));
+    }
+
+    #[test]
+    pub fn extract_iccp_absent() {
+        let test = test_webp().chunks([VP8X, VP8L, EXIF]).build();
+        assert_eq!(extract_iccp(test).unwrap(), None);
+        assert_eq!(extract_iccp(test_webp().build()).unwrap(), None);
+    }
+
+    #[test]
+    pub fn extract_iccp_truncated() {
+        let test = test_webp().chunks([VP8X, ICCP, VP8L]).build();
+        let iccp_end = test.data.windows(4).position(|name| name == b"ICCP").unwrap() + 8 + TEST_ICCP_PROFILE.len();
+        let input = Cursor::new(&test.data[..iccp_end - 1]);
+        assert_matches!(extract_iccp(input).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.get_ref(), ParseError::TruncatedChunk, "{err:?}");
+        });
+    }
+
+    #[test]
+    pub fn present_chunks_animated() {
+        let test = test_webp().chunks([VP8X, ANIM, ANMF]).build();
+        assert_eq!(present_chunks(test).unwrap(), ChunkKinds::VP8X | ChunkKinds::ANIM);
+    }
+
+    #[test]
+    pub fn present_chunks_simple() {
+        let test = test_webp().build();
+        assert_eq!(present_chunks(test).unwrap(), ChunkKinds::empty());
+    }
+
+    #[test]
+    pub fn vp8x_exif_odd_len() {
+        let test = test_webp()
+            .chunks([VP8X, VP8L, EXIF])
+            .exif_data(&b"odd length EXIF"[..])
+            .build();
+        test.sanitize_ok();
+    }
+
+    #[test]
+    pub fn vp8x_xmp_odd_len() {
+        let test = test_webp()
+            .chunks([VP8X, VP8L, XMP])
+            .xmp_data(&b"odd length XMP"[..])
+            .build();
+        test.sanitize_ok();
+    }
+
+    #[test]
+    pub fn vp8x_exif_xmp_odd_len() {
+        let test = test_webp()
+            .chunks([VP8X, VP8L, EXIF, XMP])
+            .exif_data(&b"odd length EXIF"[..])
+            .xmp_data(&b"odd length XMP"[..])
+            .build();
+        test.sanitize_ok();
+    }
+
+    #[test]
+    pub fn vp8x_xmp_odd_len_pad_missing() {
+        let mut test = test_webp()
+            .chunks([VP8X, VP8L, XMP])
+            .xmp_data(&b"odd length XMP"[..])
+            .build();
+        test.sanitize_ok();
+
+        let mut data = test.data[..test.data.len() - 1].to_vec();
+        let riff_len = data.len() as u32 - 8;
+        (&mut data[4..]).put_u32_le(riff_len);
+        test.data = data.into();
+        test.data_len = test.data.len() as u64;
+        assert_matches!(test.sanitize_non_compliant(), Error::Parse(err) => {
+            assert_matches!(err.get_ref(), ParseError::TruncatedChunk, "{err:?}");
+        });
+    }
+
+    #[test]
+    pub fn vp8x_exif_odd_len_pad_nonzero() {
+        let exif_data = &b"odd length EXIF"[..];
+        let mut test = test_webp().chunks([VP8X, VP8L, EXIF, XMP]).exif_data(exif_data).build();
+        let config = Config::builder().allow_nonzero_padding(true).build();
+        test.sanitize_ok();
+        test.sanitize_ok_with_config(config.clone());
+
+        let mut data = test.data.to_vec();
+        let exif_offset = data.windows(4).position(|name| name == b"EXIF").unwrap();
+        data[exif_offset + 8 + exif_data.len()] = 0xff;
+        test.data = data.into();
+        assert_matches!(test.sanitize_non_compliant(), Error::Parse(err) => {
+            assert_matches!(err.get_ref(), ParseError::InvalidInput, "{err:?}");
+        });
+        sanitize_with_config(test, config).unwrap();
+    }
+
+    #[test]
+    pub fn vp8x_xmp_odd_len_pad_outside_file() {
+        let mut test = test_webp()
+            .chunks([VP8X, VP8L, XMP])
+            .xmp_data(&b"odd length XMP"[..])
+            .build();
+        test.sanitize_ok();
+
+        let mut data = test.data.to_vec();
+        let riff_len = data.len() as u32 - 8 - 1;
+        (&mut data[4..]).put_u32_le(riff_len);
+        test.data = data.into();
+        assert_matches!(test.sanitize_non_compliant(), Error::Parse(err) => {
+            assert_matches!(err.get_ref(), ParseError::TruncatedChunk, "{err:?}");
+        });
+    }
+
     #[test]
     pub fn vp8x_wrong_order() {
         let test = test_webp().chunks([VP8L, VP8X]).build();
@@ -776,4 +1599,142 @@ mod test {
         let data = b"\x2f\xff\xff\xff\x0f\x81\x88\x88\x18\x44\x44\xc4\xff\x45\x44\x04\x21\x22\x22\x22\x22\x02";
         test_webp().vp8l_data(&data[..]).build().sanitize_ok();
     }
+
+    #[test]
+    pub fn lossless_max_pixels() {
+        let data = b"\x2f\xff\xff\xff\x0f\x81\x88\x88\x18\x44\x44\xc4\xff\x45\x44\x04\x21\x22\x22\x22\x22\x02";
+        let config = Config::builder().max_lossless_pixels(16384 * 16384).build();
+        test_webp().vp8l_data(&data[..]).build().sanitize_ok_with_config(config);
+    }
+
+    #[test]
+    pub fn lossless_max_pixels_exceeded() {
+        let data = b"\x2f\xff\xff\xff\x0f\x81\x88\x88\x18\x44\x44\xc4\xff\x45\x44\x04\x21\x22\x22\x22\x22\x02";
+        let config = Config::builder().max_lossless_pixels(16384 * 16384 - 1).build();
+        let test = test_webp().vp8l_data(&data[..]).build();
+        assert_matches!(sanitize_with_config(test, config).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.get_ref(), ParseError::InvalidInput, "{err:?}");
+        });
+    }
+
+    #[test]
+    pub fn vp8x_lossless_alpha_max_pixels_exceeded() {
+        let alph = test_alph().flags(AlphFlags::COMPRESS_LOSSLESS).clone();
+        let config = Config::builder().max_lossless_pixels(0).build();
+        let test = test_webp().chunks([VP8X, ALPH, VP8]).alph(alph).build();
+        assert_matches!(sanitize_with_config(test, config).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.get_ref(), ParseError::InvalidInput, "{err:?}");
+        });
+    }
+
+    #[test]
+    pub fn lossless_all_transforms() {
+        let data = b"\x2f\x00\x00\x00\x00\x81\x88\x88\x18\x44\x44\x44\x0f\x20\x22\x22\x10\x11\x11";
+        test_webp().vp8l_data(&data[..]).build().sanitize_ok();
+    }
+
+    #[test]
+    pub fn lossless_max_transforms() {
+        let data = b"\x2f\x00\x00\x00\x00\x81\x88\x88\x18\x44\x44\x44\x0f\x20\x22\x22\x10\x11\x11";
+        let config = Config::builder().max_vp8l_transforms(4).build();
+        test_webp().vp8l_data(&data[..]).build().sanitize_ok_with_config(config);
+    }
+
+    #[test]
+    pub fn lossless_max_transforms_exceeded() {
+        let data = b"\x2f\x00\x00\x00\x00\x81\x88\x88\x18\x44\x44\x44\x0f\x20\x22\x22\x10\x11\x11";
+        let config = Config::builder().max_vp8l_transforms(2).build();
+        let test = test_webp().vp8l_data(&data[..]).build();
+        assert_matches!(sanitize_with_config(test, config).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.get_ref(), ParseError::InvalidInput, "{err:?}");
+        });
+    }
+
+    #[test]
+    pub fn lossless_max_code_groups() {
+        // A 1x1 image whose meta prefix code image maps its only block to prefix code group 5.
+        let data =
+            b"\x2f\x00\x00\x00\x00\x84\x16\x44\x44\x44\x44\x44\x44\x44\x44\x44\x44\x44\x44\x44\x44\x44\x44\x44\x00";
+        let test = test_webp().vp8l_data(&data[..]).build();
+        test.sanitize_ok();
+        let config = Config::builder().max_vp8l_code_groups(6).build();
+        test.sanitize_ok_with_config(config);
+    }
+
+    #[test]
+    pub fn lossless_max_code_groups_exceeded() {
+        let data =
+            b"\x2f\x00\x00\x00\x00\x84\x16\x44\x44\x44\x44\x44\x44\x44\x44\x44\x44\x44\x44\x44\x44\x44\x44\x44\x00";
+        let config = Config::builder().max_vp8l_code_groups(5).build();
+        let test = test_webp().vp8l_data(&data[..]).build();
+        assert_matches!(sanitize_with_config(test, config).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.get_ref(), ParseError::InvalidInput, "{err:?}");
+        });
+    }
+
+    #[test]
+    pub fn lossless_max_image_data_len() {
+        let data = b"\x2f\x00\x00\x00\x00\x87\x10\x11\x11\x28\x44\x44\x04";
+        let config = Config::builder().max_image_data_len(data.len() as u64).build();
+        test_webp().vp8l_data(&data[..]).build().sanitize_ok_with_config(config);
+    }
+
+    #[test]
+    pub fn lossless_max_image_data_len_exceeded() {
+        let data = b"\x2f\x00\x00\x00\x00\x87\x10\x11\x11\x28\x44\x44\x04";
+        let config = Config::builder().max_image_data_len(data.len() as u64 - 1).build();
+        let test = test_webp().vp8l_data(&data[..]).build();
+        assert_matches!(sanitize_with_config(test, config).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.get_ref(), ParseError::InvalidInput, "{err:?}");
+        });
+    }
+
+    #[test]
+    pub fn lossy_max_image_data_len_exceeded() {
+        let config = Config::builder().max_image_data_len(0).build();
+        let test = test_webp().chunks([VP8]).build();
+        assert_matches!(sanitize_with_config(test, config).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.get_ref(), ParseError::InvalidInput, "{err:?}");
+        });
+    }
+
+    #[test]
+    pub fn lossless_palette() {
+        let data = b"\x2f\x00\x00\x00\x00\x87\x10\x11\x11\x28\x44\x44\x04";
+        let test = test_webp().vp8l_data(&data[..]).build();
+        test.sanitize_ok();
+        test.sanitize_ok_with_config(Config::builder().validate_palette_indices(true).build());
+    }
+
+    #[test]
+    pub fn lossless_palette_bundled() {
+        let data = b"\x2f\x00\x00\x00\x00\x17\x10\x11\x11\xa8\x40\x44\x04";
+        let test = test_webp().vp8l_data(&data[..]).build();
+        test.sanitize_ok();
+        test.sanitize_ok_with_config(Config::builder().validate_palette_indices(true).build());
+    }
+
+    #[test]
+    pub fn lossless_palette_index_out_of_bounds() {
+        let data = b"\x2f\x00\x00\x00\x00\x87\x10\x11\x11\x68\x44\x44\x04";
+        let test = test_webp().vp8l_data(&data[..]).build();
+        test.sanitize_ok();
+
+        let config = Config::builder().validate_palette_indices(true).build();
+        assert_matches!(test.sanitize_non_compliant_with_config(config), Error::Parse(err) => {
+            assert_matches!(err.get_ref(), ParseError::InvalidInput, "{err:?}");
+        });
+    }
+
+    #[test]
+    pub fn lossless_palette_bundled_index_out_of_bounds() {
+        let data = b"\x2f\x00\x00\x00\x00\x17\x10\x11\x11\xe8\x40\x44\x04";
+        let test = test_webp().vp8l_data(&data[..]).build();
+        test.sanitize_ok();
+
+        let config = Config::builder().validate_palette_indices(true).build();
+        assert_matches!(test.sanitize_non_compliant_with_config(config), Error::Parse(err) => {
+            assert_matches!(err.get_ref(), ParseError::InvalidInput, "{err:?}");
+        });
+    }
 }
@@ -8,6 +8,7 @@ pub mod error;
 mod header;
 mod integers;
 mod lossless;
+mod vp8;
 mod vp8l;
 mod vp8x;
 
@@ -28,14 +29,16 @@ pub trait ParsedChunk {
     fn put_buf(&self, buf: &mut dyn BufMut);
 }
 
-pub use alph::{AlphChunk, AlphFlags};
+pub use alph::{AlphChunk, AlphCompression, AlphFlags};
 pub use anim::AnimChunk;
 pub use anmf::{AnmfChunk, AnmfFlags};
 pub use bitstream::{BitBufReader, CanonicalHuffmanTree};
-pub use error::ParseError;
+pub use bitstream_io::{BigEndian, Endianness, LittleEndian};
+pub use error::{ParseError, ParseErrorCode};
 pub use header::{chunk_type, ChunkHeader, WebpChunk};
 pub use integers::{OneBasedU24, Reserved, WebmFlags, WebmPrim, U24};
 pub use lossless::LosslessImage;
+pub use vp8::Vp8Chunk;
 pub use vp8l::Vp8lChunk;
 pub use vp8x::{Vp8xChunk, Vp8xFlags};
 
@@ -1,20 +1,23 @@
 #![allow(missing_docs)]
 
+use std::io;
 use std::io::Read;
+use std::num::NonZeroU32;
 use std::result::Result as StdResult;
 
 use bitstream_io::LE;
 use bytes::{BufMut, BytesMut};
+use derive_more::Display;
 use mediasan_common::parse::FourCC;
-use mediasan_common::Result;
+use mediasan_common::{ensure_attach, Result};
 
-use crate::Error;
+use crate::{Config, Error};
 
 use super::bitstream::BitBufReader;
 use super::chunk_type::ALPH;
-use super::error::ParseResultExt;
+use super::error::{ParseResultExt, WhileParsingChunk};
 use super::lossless::LosslessImage;
-use super::{ParseChunk, ParseError, ParsedChunk, Vp8xChunk, WebmFlags, WebmPrim};
+use super::{ParseChunk, ParseError, ParsedChunk, WebmFlags, WebmPrim};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct AlphChunk {
@@ -31,16 +34,54 @@ bitflags::bitflags! {
     }
 }
 
+/// The compression method of the alpha data in an [`AlphChunk`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AlphCompression {
+    /// The alpha data is stored uncompressed, as one byte per pixel.
+    None,
+    /// The alpha data is compressed as the green channel of a lossless image without a header.
+    Lossless,
+}
+
+#[derive(Clone, Copy, Debug, Display)]
+#[display(fmt = "uncompressed alpha data length `{_0}` does not match frame dimensions `{_1}`x`{_2}`")]
+struct UncompressedAlphLenMismatch(u64, NonZeroU32, NonZeroU32);
+
 //
 // AlphChunk impls
 //
 
 impl AlphChunk {
-    pub fn sanitize_image_data<R: Read>(&self, input: R, vp8x: &Vp8xChunk) -> StdResult<(), Error> {
-        let (width, height) = (vp8x.canvas_width(), vp8x.canvas_height());
+    pub fn compression(&self) -> AlphCompression {
         if self.flags.contains(AlphFlags::COMPRESS_LOSSLESS) {
-            let mut reader = BitBufReader::<_, LE>::with_capacity(input, 4096);
-            let _image = LosslessImage::read(&mut reader, width, height)?;
+            AlphCompression::Lossless
+        } else {
+            AlphCompression::None
+        }
+    }
+
+    /// Sanitize the alpha data for a frame with dimensions `width`x`height`.
+    pub fn sanitize_image_data<R: Read>(
+        &self,
+        mut input: R,
+        width: NonZeroU32,
+        height: NonZeroU32,
+        config: &Config,
+    ) -> StdResult<(), Error> {
+        match self.compression() {
+            AlphCompression::None => {
+                let len = io::copy(&mut input, &mut io::sink())?;
+                ensure_attach!(
+                    len == u64::from(width.get()) * u64::from(height.get()),
+                    ParseError::InvalidInput,
+                    UncompressedAlphLenMismatch(len, width, height),
+                    WhileParsingChunk(ALPH),
+                );
+            }
+            AlphCompression::Lossless => {
+                let mut reader = BitBufReader::<_, LE>::with_capacity(input, 4096);
+                let _image = LosslessImage::read(&mut reader, width, height, config)?;
+            }
         }
         Ok(())
     }
@@ -1,6 +1,7 @@
-#![allow(missing_docs)]
+//! Bit-level readers for entropy-coded WebP lossless bitstreams.
 
 use std::fmt::Debug;
+use std::io;
 use std::io::{Cursor, Read};
 use std::mem::replace;
 use std::num::NonZeroU32;
@@ -14,12 +15,37 @@ use mediasan_common::{bail_attach, report_attach};
 use crate::parse::ParseError;
 use crate::Error;
 
+/// A buffered bit reader.
+///
+/// Methods prefixed with `buf_` read only from the already-buffered input, returning
+/// [`ParseError::TruncatedChunk`] if not enough bits are buffered, while the remaining methods first refill the buffer
+/// from the underlying input as needed.
 pub struct BitBufReader<R, E: Endianness> {
     input: Option<R>,
     reader: BitReader<Cursor<Vec<u8>>, E>,
     buf_len: usize,
 }
 
+/// A canonical Huffman code, compiled for reading symbols of type `S` with a [`BitBufReader`].
+///
+/// This is used internally to parse VP8L image data, but is general enough to be reused to parse other formats which
+/// are also coded using canonical Huffman codes.
+///
+/// # Examples
+///
+/// ```
+/// use webpsan::parse::{BitBufReader, CanonicalHuffmanTree, LittleEndian};
+///
+/// // Codes `a` = `0`, `b` = `10`, `c` = `110`, `d` = `111`.
+/// let tree = CanonicalHuffmanTree::<LittleEndian, char>::new(&mut [('a', 1), ('b', 2), ('c', 3), ('d', 3)])?;
+///
+/// // Bits are read starting from the least significant bit of each byte.
+/// let input: &[u8] = &[0b1110_1101, 0b0000_0000];
+/// let mut reader = BitBufReader::<_, LittleEndian>::with_capacity(input, 16);
+/// let symbols = (0..4).map(|_| reader.read_huffman(&tree)).collect::<Result<String, _>>()?;
+/// assert_eq!(symbols, "bcda");
+/// # Ok::<(), webpsan::Error>(())
+/// ```
 pub struct CanonicalHuffmanTree<E: Endianness, S: Clone> {
     read_tree: Box<[ReadHuffmanTree<E, S>]>,
     longest_code_len: u32,
@@ -29,6 +55,7 @@ pub struct CanonicalHuffmanTree<E: Endianness, S: Clone> {
 #[display(fmt = "invalid lz77 prefix code `{_0}`")]
 struct InvalidLz77PrefixCode(u16);
 
+/// The maximum number of extra bits read for an LZ77 prefix-coded length or distance.
 pub const LZ77_MAX_LEN: u16 = (LZ77_MAX_SYMBOL - 2) >> 1;
 
 const LZ77_MAX_SYMBOL: u16 = 39;
@@ -38,55 +65,59 @@ const LZ77_MAX_SYMBOL: u16 = 39;
 //
 
 impl<R: Read, E: Endianness> BitBufReader<R, E> {
+    /// Construct a new reader over `input`, buffering up to `capacity` bytes of it at a time.
     pub fn with_capacity(input: R, capacity: usize) -> Self {
         Self { input: Some(input), reader: BitReader::new(Cursor::new(Vec::with_capacity(capacity))), buf_len: 0 }
     }
 
+    /// Refill the buffer from the underlying input, keeping any bits not yet read.
+    ///
+    /// The input is read until the buffer is full or the input ends.
     pub fn fill_buf(&mut self) -> Result<(), Error> {
-        let bit_pos = self.buf_bit_pos();
-        let byte_pos = (bit_pos / 8) as usize;
+        self.refill(None)
+    }
 
-        let Some(input) = self.input.as_mut() else {
+    /// Refill the buffer from the underlying input until at least `bits` bits are buffered, keeping any bits not yet
+    /// read.
+    ///
+    /// Unlike [`fill_buf`](Self::fill_buf), this returns as soon as enough bits are buffered, rather than waiting for
+    /// the buffer to be filled, which matters for inputs which are slow to produce data. Each read of the input is
+    /// still offered the whole free space in the buffer, so inputs which can produce data immediately fill the buffer
+    /// at once. Fewer bits are buffered if the input ends first, or if `bits` exceeds the capacity of the buffer.
+    pub fn fill_buf_to(&mut self, bits: u64) -> Result<(), Error> {
+        if self.buf_bits() >= bits {
             return Ok(());
-        };
-
-        let reader = replace(&mut self.reader, BitReader::new(Cursor::new(Vec::new())));
-        let mut buf = reader.into_reader().into_inner();
-
-        buf.drain(..byte_pos);
-        input.take((buf.capacity() - buf.len()) as u64).read_to_end(&mut buf)?;
-        if self.buf_len - byte_pos == buf.len() {
-            self.input = None;
         }
-        self.buf_len = buf.len();
-
-        self.reader = BitReader::new(Cursor::new(buf));
-        self.reader.skip((bit_pos % 8) as u32)?;
-        Ok(())
+        self.refill(Some(bits))
     }
 
+    /// Return the number of bits buffered but not yet read.
     pub fn buf_bits(&mut self) -> u64 {
         self.buf_len as u64 * 8 - self.buf_bit_pos()
     }
 
+    /// Read a `bits`-wide value from the buffer.
     pub fn buf_read<T: Numeric>(&mut self, bits: u32) -> Result<T, Error> {
         self.reader
             .read(bits)
             .map_eof(|_| Error::Parse(report_attach!(ParseError::TruncatedChunk)))
     }
 
+    /// Read a single bit from the buffer.
     pub fn buf_read_bit(&mut self) -> Result<bool, Error> {
         self.reader
             .read_bit()
             .map_eof(|_| Error::Parse(report_attach!(ParseError::TruncatedChunk)))
     }
 
+    /// Read a symbol coded with `tree` from the buffer.
     pub fn buf_read_huffman<T: Clone>(&mut self, tree: &CanonicalHuffmanTree<E, T>) -> Result<T, Error> {
         self.reader
             .read_huffman(&tree.read_tree)
             .map_eof(|_| Error::Parse(report_attach!(ParseError::TruncatedChunk)))
     }
 
+    /// Read an LZ77-coded length or distance with prefix code `prefix_code` from the buffer.
     pub fn buf_read_lz77(&mut self, prefix_code: u16) -> Result<NonZeroU32, Error> {
         match prefix_code {
             0..=3 => Ok(NonZeroU32::MIN.saturating_add(prefix_code.into())),
@@ -99,30 +130,75 @@ impl<R: Read, E: Endianness> BitBufReader<R, E> {
         }
     }
 
+    /// Read a `bits`-wide value, refilling the buffer if necessary.
     pub fn read<T: Numeric>(&mut self, bits: u32) -> Result<T, Error> {
-        if self.buf_bits() < bits.into() {
-            self.fill_buf()?;
-        }
+        self.fill_buf_to(bits.into())?;
         self.buf_read(bits)
     }
 
+    /// Read a single bit, refilling the buffer if necessary.
     pub fn read_bit(&mut self) -> Result<bool, Error> {
-        if self.buf_bits() < 1 {
-            self.fill_buf()?;
-        }
+        self.fill_buf_to(1)?;
         self.buf_read_bit()
     }
 
+    /// Read a symbol coded with `tree`, refilling the buffer if necessary.
     pub fn read_huffman<T: Clone>(&mut self, tree: &CanonicalHuffmanTree<E, T>) -> Result<T, Error> {
-        if self.buf_bits() < tree.longest_code_len.into() {
-            self.fill_buf()?;
-        }
+        self.fill_buf_to(tree.longest_code_len.into())?;
         self.buf_read_huffman(tree)
     }
 
     fn buf_bit_pos(&mut self) -> u64 {
         self.reader.position_in_bits().unwrap_or_else(|_| unreachable!())
     }
+
+    /// Refill the buffer, reading until at least `min_bits` bits are buffered if given, or otherwise until it is full.
+    fn refill(&mut self, min_bits: Option<u64>) -> Result<(), Error> {
+        let bit_pos = self.buf_bit_pos();
+        let byte_pos = (bit_pos / 8) as usize;
+
+        let Some(input) = self.input.as_mut() else {
+            return Ok(());
+        };
+
+        let reader = replace(&mut self.reader, BitReader::new(Cursor::new(Vec::new())));
+        let mut buf = reader.into_reader().into_inner();
+
+        buf.drain(..byte_pos);
+        let mut eof = false;
+        match min_bits {
+            None => {
+                input.take((buf.capacity() - buf.len()) as u64).read_to_end(&mut buf)?;
+                eof = self.buf_len - byte_pos == buf.len();
+            }
+            Some(min_bits) => {
+                let min_len = (bit_pos % 8).saturating_add(min_bits).saturating_add(7) / 8;
+                while (buf.len() as u64) < min_len && buf.len() < buf.capacity() {
+                    let read_pos = buf.len();
+                    buf.resize(buf.capacity(), 0);
+                    let read_result = input.read(&mut buf[read_pos..]);
+                    buf.truncate(read_pos + read_result.as_ref().map_or(0, |&read_len| read_len));
+                    match read_result {
+                        Ok(0) => {
+                            eof = true;
+                            break;
+                        }
+                        Ok(_) => {}
+                        Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+            }
+        }
+        if eof {
+            self.input = None;
+        }
+        self.buf_len = buf.len();
+
+        self.reader = BitReader::new(Cursor::new(buf));
+        self.reader.skip((bit_pos % 8) as u32)?;
+        Ok(())
+    }
 }
 
 //
@@ -130,6 +206,10 @@ impl<R: Read, E: Endianness> BitBufReader<R, E> {
 //
 
 impl<E: Endianness, S: Clone> CanonicalHuffmanTree<E, S> {
+    /// Construct a canonical Huffman code from the code length of each symbol.
+    ///
+    /// Symbols with a code length of zero are unused. Codes are assigned to the used symbols in order of code length,
+    /// then symbol.
     pub fn new(code_lengths: &mut [(S, u8)]) -> Result<Self, Error>
     where
         S: Copy + Debug + Ord + 'static,
@@ -139,6 +219,20 @@ impl<E: Endianness, S: Clone> CanonicalHuffmanTree<E, S> {
         Self::from_symbols(symbols)
     }
 
+    /// Construct a Huffman code from an explicit list of symbols and their codes.
+    ///
+    /// Each code is a list of bits in the order they are read. A single symbol with an empty code is read using zero
+    /// bits.
+    ///
+    /// ```
+    /// use webpsan::parse::{BitBufReader, CanonicalHuffmanTree, LittleEndian};
+    ///
+    /// let tree = CanonicalHuffmanTree::<LittleEndian, u8>::from_symbols(vec![(7, vec![0]), (42, vec![1])])?;
+    /// let mut reader = BitBufReader::<_, LittleEndian>::with_capacity(&[0b10][..], 1);
+    /// assert_eq!(reader.read_huffman(&tree)?, 7);
+    /// assert_eq!(reader.read_huffman(&tree)?, 42);
+    /// # Ok::<(), webpsan::Error>(())
+    /// ```
     pub fn from_symbols(symbols: Vec<(S, Vec<u8>)>) -> Result<Self, Error> {
         let longest_code_len = match &symbols[..] {
             [_symbol] => 0,
@@ -149,10 +243,12 @@ impl<E: Endianness, S: Clone> CanonicalHuffmanTree<E, S> {
         Ok(Self { read_tree, longest_code_len })
     }
 
+    /// Return the compiled tree used to read symbols.
     pub fn read_tree(&self) -> &[ReadHuffmanTree<E, S>] {
         &self.read_tree
     }
 
+    /// Return the length of the longest code in bits.
     pub fn longest_code_len(&self) -> u32 {
         self.longest_code_len
     }
@@ -195,3 +291,84 @@ impl<E: Endianness, S: Clone + Default> Default for CanonicalHuffmanTree<E, S> {
         Self::from_symbols(vec![(S::default(), vec![])]).unwrap_or_else(|_| unreachable!())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use bitstream_io::LittleEndian;
+
+    use super::*;
+
+    /// An input returning at most one byte per read, after first failing with [`io::ErrorKind::Interrupted`].
+    struct ShortReads<'a> {
+        data: &'a [u8],
+        read_count: usize,
+    }
+
+    impl Read for ShortReads<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.read_count += 1;
+            if self.read_count == 1 {
+                return Err(io::ErrorKind::Interrupted.into());
+            }
+            let len = buf.len().min(self.data.len()).min(1);
+            buf[..len].copy_from_slice(&self.data[..len]);
+            self.data = &self.data[len..];
+            Ok(len)
+        }
+    }
+
+    fn short_reads(data: &[u8]) -> ShortReads<'_> {
+        ShortReads { data, read_count: 0 }
+    }
+
+    #[test]
+    fn fill_buf_to_short_reads() {
+        let mut reader = BitBufReader::<_, LittleEndian>::with_capacity(short_reads(&[0x21, 0x43, 0x65, 0x87]), 4);
+        reader.fill_buf_to(12).unwrap();
+        assert_eq!(reader.buf_bits(), 16);
+        assert_eq!(reader.buf_read::<u16>(12).unwrap(), 0x321);
+
+        // The unread bits are kept across a refill.
+        reader.fill_buf_to(12).unwrap();
+        assert_eq!(reader.buf_bits(), 12);
+        assert_eq!(reader.buf_read::<u16>(12).unwrap(), 0x654);
+        assert_eq!(reader.read::<u8>(8).unwrap(), 0x87);
+    }
+
+    #[test]
+    fn fill_buf_short_reads() {
+        let mut reader = BitBufReader::<_, LittleEndian>::with_capacity(short_reads(&[0x21, 0x43, 0x65, 0x87]), 4);
+        reader.fill_buf().unwrap();
+        assert_eq!(reader.buf_bits(), 32);
+        assert_eq!(reader.buf_read::<u32>(32).unwrap(), 0x87654321);
+    }
+
+    #[test]
+    fn fill_buf_to_eof() {
+        let mut reader = BitBufReader::<_, LittleEndian>::with_capacity(short_reads(&[0x21, 0x43]), 4);
+        assert_matches!(reader.buf_read_bit().unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.get_ref(), ParseError::TruncatedChunk);
+        });
+
+        // The input ending mid-refill is not an error, and it isn't read again.
+        reader.fill_buf_to(24).unwrap();
+        assert_eq!(reader.buf_bits(), 16);
+        assert!(reader.input.is_none());
+        reader.fill_buf_to(24).unwrap();
+        assert_eq!(reader.buf_bits(), 16);
+
+        assert_matches!(reader.read::<u32>(24).unwrap_err(), Error::Parse(err) => {
+            assert_matches!(err.get_ref(), ParseError::TruncatedChunk);
+        });
+    }
+
+    #[test]
+    fn fill_buf_to_capacity() {
+        let mut reader = BitBufReader::<_, LittleEndian>::with_capacity(short_reads(&[0x21, 0x43, 0x65]), 2);
+        reader.fill_buf_to(24).unwrap();
+        assert_eq!(reader.buf_bits(), 16);
+        assert_eq!(reader.read::<u16>(12).unwrap(), 0x321);
+        assert_eq!(reader.read::<u16>(12).unwrap(), 0x654);
+    }
+}
@@ -29,6 +29,18 @@ pub enum ParseError {
     #[error("Missing required `{_0}` chunk")]
     MissingRequiredChunk(FourCC),
 
+    /// The input is invalid because the `RIFF` chunk declares a length longer than the input.
+    ///
+    /// This is only detected up front when the length of the input is known; otherwise
+    /// [`TruncatedChunk`](Self::TruncatedChunk) is returned once the input ends.
+    #[error("RIFF length `{len}` exceeds input length `{input_len}`")]
+    RiffLenExceedsInput {
+        /// The end of the `RIFF` chunk, as declared by its header.
+        len: u64,
+        /// The length of the input.
+        input_len: u64,
+    },
+
     /// The input is invalid because the input ended before the end of a valid RIFF chunk.
     ///
     /// This can occur either when the entire input is truncated or when a chunk size is incorrect.
@@ -44,6 +56,37 @@ pub enum ParseError {
     UnsupportedVp8lVersion(u8),
 }
 
+/// A machine-readable code identifying the kind of a [`ParseError`], as returned by [`ParseError::code`].
+///
+/// Unlike [`ParseError`], the codes carry no context, and are suitable e.g. as labels for metrics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ParseErrorCode {
+    /// See [`ParseError::InvalidChunkLayout`].
+    InvalidChunkLayout,
+
+    /// See [`ParseError::InvalidInput`].
+    InvalidInput,
+
+    /// See [`ParseError::InvalidVp8lPrefixCode`].
+    InvalidVp8lPrefixCode,
+
+    /// See [`ParseError::MissingRequiredChunk`].
+    MissingRequiredChunk,
+
+    /// See [`ParseError::RiffLenExceedsInput`].
+    RiffLenExceedsInput,
+
+    /// See [`ParseError::TruncatedChunk`].
+    TruncatedChunk,
+
+    /// See [`ParseError::UnsupportedChunk`].
+    UnsupportedChunk,
+
+    /// See [`ParseError::UnsupportedVp8lVersion`].
+    UnsupportedVp8lVersion,
+}
+
 pub(crate) trait ParseResultExt: ResultExt + Sized {
     fn while_parsing_chunk(self, chunk_type: FourCC) -> Self {
         self.attach_printable(WhileParsingChunk(chunk_type))
@@ -73,6 +116,22 @@ pub(crate) struct WhileParsingChunk(pub(crate) FourCC);
 #[display(fmt = "while parsing `{}` chunk field `{}`", _0, _1)]
 pub(crate) struct WhileParsingField<T>(pub(crate) FourCC, pub(crate) T);
 
+impl ParseError {
+    /// Returns the [`ParseErrorCode`] identifying the kind of this error.
+    pub fn code(&self) -> ParseErrorCode {
+        match self {
+            Self::InvalidChunkLayout => ParseErrorCode::InvalidChunkLayout,
+            Self::InvalidInput => ParseErrorCode::InvalidInput,
+            Self::InvalidVp8lPrefixCode => ParseErrorCode::InvalidVp8lPrefixCode,
+            Self::MissingRequiredChunk(_) => ParseErrorCode::MissingRequiredChunk,
+            Self::RiffLenExceedsInput { .. } => ParseErrorCode::RiffLenExceedsInput,
+            Self::TruncatedChunk => ParseErrorCode::TruncatedChunk,
+            Self::UnsupportedChunk(_) => ParseErrorCode::UnsupportedChunk,
+            Self::UnsupportedVp8lVersion(_) => ParseErrorCode::UnsupportedVp8lVersion,
+        }
+    }
+}
+
 impl ReportableError for ParseError {
     #[cfg(feature = "error-detail")]
     type Stack = mediasan_common::error::ReportStack;
@@ -81,3 +140,26 @@ impl ReportableError for ParseError {
 }
 
 impl<T> ParseResultExt for Result<T, ParseError> {}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn distinct_codes() {
+        let errors = [
+            ParseError::InvalidChunkLayout,
+            ParseError::InvalidInput,
+            ParseError::InvalidVp8lPrefixCode,
+            ParseError::MissingRequiredChunk(FourCC { value: *b"VP8 " }),
+            ParseError::RiffLenExceedsInput { len: 2, input_len: 1 },
+            ParseError::TruncatedChunk,
+            ParseError::UnsupportedChunk(FourCC { value: *b"VP8 " }),
+            ParseError::UnsupportedVp8lVersion(1),
+        ];
+        let codes = errors.iter().map(ParseError::code).collect::<HashSet<_>>();
+        assert_eq!(codes.len(), errors.len());
+    }
+}
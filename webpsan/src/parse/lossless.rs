@@ -10,7 +10,7 @@ use mediasan_common::{ensure_attach, ensure_matches_attach};
 use num_integer::div_ceil;
 use num_traits::AsPrimitive;
 
-use crate::{Error, ResultExt};
+use crate::{Config, Error, ResultExt};
 
 use super::bitstream::{BitBufReader, CanonicalHuffmanTree, LZ77_MAX_LEN};
 use super::ParseError;
@@ -58,6 +58,14 @@ struct EntropyCodedImage {
 #[derive(Clone, PartialEq, Eq)]
 struct SpatiallyCodedImage;
 
+/// The bounds of the palette indices encoded by a spatially-coded image whose last transform is color indexing.
+#[derive(Clone, Copy)]
+struct PaletteBounds {
+    len: NonZeroU32,
+    width: NonZeroU32,
+    bundle_bits: u32,
+}
+
 #[derive(Clone, Copy, Debug, Display, PartialEq, Eq)]
 #[display(fmt = "distance {dist} length {len}")]
 struct BackReference {
@@ -79,6 +87,11 @@ struct ColorCache {
     order: Option<NonZeroU8>,
 }
 
+struct EntropyCodes {
+    color_cache: ColorCache,
+    groups: Vec<PrefixCodeGroup>,
+}
+
 #[derive(Clone, Display, PartialEq, Eq)]
 enum MetaPrefixCodes {
     #[display(fmt = "single meta prefix code")]
@@ -87,6 +100,9 @@ enum MetaPrefixCodes {
     Multiple {
         block_size: u16,
         max_code_group: u16,
+        /// The decoded meta prefix code image, whose red and green components give the code group of each block, if it
+        /// was decoded.
+        code_group_image: Option<Vec<Color>>,
         _image: EntropyCodedImage,
     },
 }
@@ -155,6 +171,22 @@ struct InvalidPredictor(u8);
 #[display(fmt = "invalid symbol count `{_0}` >= `{_1}`")]
 struct InvalidSymbolCount(u16, u16);
 
+#[derive(Clone, Copy, Debug, Display)]
+#[display(fmt = "out-of-bounds palette index `{_0}` >= `{_1}` at pixel `{_2}`")]
+struct PaletteIndexOutOfBounds(u32, NonZeroU32, u32);
+
+#[derive(Clone, Copy, Debug, Display)]
+#[display(fmt = "image pixel count `{_0}` exceeds configured maximum `{_1}`")]
+struct TooManyPixels(u64, u64);
+
+#[derive(Clone, Copy, Debug, Display)]
+#[display(fmt = "transform count exceeds configured maximum `{_0}`")]
+struct TooManyTransforms(u8);
+
+#[derive(Clone, Copy, Debug, Display)]
+#[display(fmt = "prefix code group count `{_0}` exceeds configured maximum `{_1}`")]
+struct TooManyCodeGroups(u32, u16);
+
 #[derive(Clone, Copy, Debug, Display)]
 #[display(fmt = "while parsing {_0} transform")]
 struct WhileParsingTransform(TransformType);
@@ -168,12 +200,36 @@ impl LosslessImage {
         reader: &mut BitBufReader<R, LE>,
         width: NonZeroU32,
         height: NonZeroU32,
+        config: &Config,
     ) -> Result<Self, Error> {
+        if let Some(max_pixels) = config.max_lossless_pixels {
+            let pixels = u64::from(width.get()) * u64::from(height.get());
+            ensure_attach!(
+                pixels <= max_pixels,
+                ParseError::InvalidInput,
+                TooManyPixels(pixels, max_pixels),
+            );
+        }
+
         let mut transformed_width = width;
         let mut transforms = [false; TransformType::COUNT];
+        let mut palette = None;
+        let mut transform_count = 0u8;
         while reader.read_bit()? {
+            if let Some(max_transforms) = config.max_vp8l_transforms {
+                ensure_attach!(
+                    transform_count < max_transforms,
+                    ParseError::InvalidInput,
+                    TooManyTransforms(max_transforms),
+                );
+            }
+            transform_count += 1;
+
             let transform = Transform::read(reader, transformed_width, height).while_parsing_type()?;
 
+            palette = transform
+                .palette_bounds(transformed_width)
+                .filter(|_| config.validate_palette_indices);
             transformed_width = transform.transformed_width(transformed_width);
 
             ensure_attach!(
@@ -186,7 +242,8 @@ impl LosslessImage {
             log::info!("{transform}");
         }
 
-        let _image = SpatiallyCodedImage::read(reader, transformed_width, height).while_parsing_type()?;
+        let _image =
+            SpatiallyCodedImage::read(reader, transformed_width, height, palette, config).while_parsing_type()?;
 
         Ok(Self { _image })
     }
@@ -253,17 +310,24 @@ impl Transform {
     }
 
     fn transformed_width(&self, width: NonZeroU32) -> NonZeroU32 {
+        match self.palette_bounds(width) {
+            Some(palette) => len_in_blocks(width, 1 << palette.bundle_bits),
+            None => width,
+        }
+    }
+
+    fn palette_bounds(&self, width: NonZeroU32) -> Option<PaletteBounds> {
         match self {
             Transform::ColorIndexing { image } => {
-                let block_size = match image.width.get() {
-                    0..=2 => 8,
-                    3..=4 => 4,
-                    5..=16 => 2,
-                    17.. => 1,
+                let bundle_bits = match image.width.get() {
+                    0..=2 => 3,
+                    3..=4 => 2,
+                    5..=16 => 1,
+                    17.. => 0,
                 };
-                len_in_blocks(width, block_size)
+                Some(PaletteBounds { len: image.width, width, bundle_bits })
             }
-            _ => width,
+            _ => None,
         }
     }
 }
@@ -298,63 +362,29 @@ impl EntropyCodedImage {
         height: NonZeroU32,
         mut fun: F,
     ) -> Result<Self, Error> {
-        let color_cache = ColorCache::read(reader).while_parsing_type()?;
-        let codes = PrefixCodeGroup::read(reader, &color_cache).while_parsing_type()?;
-        let green_readahead_bits = codes.green_readahead_bits();
-        let arb_readahead_bits = codes.arb_readahead_bits();
-        let backref_readahead_bits = codes.backref_readahead_bits();
-        let readahead_bits = green_readahead_bits + arb_readahead_bits.max(backref_readahead_bits);
+        let codes = EntropyCodes::read_single(reader)?;
+        codes.read_pixels(reader, width, height, |_| 0, |_, color| fun(color), None)?;
+        Ok(Self { width, height })
+    }
 
-        let len = width.saturating_mul(height);
-        let mut pixel_idx = 0;
-        while pixel_idx < len.get() {
-            if reader.buf_bits() < u64::from(readahead_bits) {
-                reader.fill_buf()?;
-            }
-            match reader.buf_read_huffman(&codes.green.tree)? {
-                symbol @ 0..=255 => {
-                    let color = Color::buf_read(reader, symbol as u8, &codes).while_parsing_type()?;
-                    log::debug!("color: {color}");
-                    fun(color)?;
-                    if green_readahead_bits + arb_readahead_bits == 0 {
-                        pixel_idx = len.get();
-                    } else {
-                        pixel_idx += 1;
-                    }
-                }
-                symbol @ 256..=279 => {
-                    let back_ref = BackReference::buf_read(reader, symbol - 256, &codes, width).while_parsing_type()?;
-                    log::debug!("backref: {back_ref}");
-                    ensure_matches_attach!(
-                        pixel_idx.checked_sub(back_ref.dist.get()),
-                        Some(_),
-                        ParseError::InvalidInput,
-                        InvalidBackRefDistance(back_ref.dist, pixel_idx),
-                    );
-                    ensure_attach!(
-                        back_ref.len.get() <= len.get() - pixel_idx,
-                        ParseError::InvalidInput,
-                        InvalidBackRefLength(back_ref.len, pixel_idx, len.get()),
-                    );
-                    pixel_idx += back_ref.len.get();
-                }
-                symbol @ 280.. => {
-                    let color_cache_index = symbol - 280;
-                    log::debug!("cached: {color_cache_index}");
-                    ensure_attach!(
-                        color_cache_index < color_cache.len(),
-                        ParseError::InvalidInput,
-                        ColorCacheIndexOutOfBounds(color_cache_index, color_cache.len()),
-                    );
-                    if green_readahead_bits == 0 {
-                        pixel_idx = len.get();
-                    } else {
-                        pixel_idx += 1;
-                    }
-                }
-            }
+    fn decode<R: Read>(
+        reader: &mut BitBufReader<R, LE>,
+        width: NonZeroU32,
+        height: NonZeroU32,
+        config: &Config,
+    ) -> Result<(Self, Vec<Color>), Error> {
+        if let Some(max_pixels) = config.max_lossless_pixels {
+            let pixels = u64::from(width.get()) * u64::from(height.get());
+            ensure_attach!(
+                pixels <= max_pixels,
+                ParseError::InvalidInput,
+                TooManyPixels(pixels, max_pixels),
+            );
         }
-        Ok(Self { width, height })
+        let codes = EntropyCodes::read_single(reader)?;
+        let mut pixels = Vec::new();
+        codes.read_pixels(reader, width, height, |_| 0, |_, _| Ok(()), Some(&mut pixels))?;
+        Ok((Self { width, height }, pixels))
     }
 }
 
@@ -363,18 +393,73 @@ impl EntropyCodedImage {
 //
 
 impl SpatiallyCodedImage {
-    fn read<R: Read>(reader: &mut BitBufReader<R, LE>, width: NonZeroU32, height: NonZeroU32) -> Result<Self, Error> {
+    fn read<R: Read>(
+        reader: &mut BitBufReader<R, LE>,
+        width: NonZeroU32,
+        height: NonZeroU32,
+        palette: Option<PaletteBounds>,
+        config: &Config,
+    ) -> Result<Self, Error> {
         let color_cache = ColorCache::read(reader).while_parsing_type()?;
-        let meta = MetaPrefixCodes::read(reader, width, height).while_parsing_type()?;
+        let meta = MetaPrefixCodes::read(reader, width, height, palette.is_some(), config).while_parsing_type()?;
         log::info!("{meta}");
 
-        for _ in 0..=meta.max_code_group() {
-            let _codes = PrefixCodeGroup::read(reader, &color_cache).while_parsing_type()?;
+        if let Some(max_code_groups) = config.max_vp8l_code_groups {
+            let code_group_count = u32::from(meta.max_code_group()) + 1;
+            ensure_attach!(
+                code_group_count <= u32::from(max_code_groups),
+                ParseError::InvalidInput,
+                TooManyCodeGroups(code_group_count, max_code_groups),
+            );
         }
+
+        let Some(palette) = palette else {
+            for _ in 0..=meta.max_code_group() {
+                let _codes = PrefixCodeGroup::read(reader, &color_cache).while_parsing_type()?;
+            }
+            return Ok(Self);
+        };
+
+        // The pixels are only decoded when they are color indices, which must be checked against the palette. Only
+        // literal pixels need to be checked, as back-references and color cache entries repeat earlier pixels.
+        let groups = (0..=meta.max_code_group())
+            .map(|_| PrefixCodeGroup::read(reader, &color_cache).while_parsing_type())
+            .collect::<Result<Vec<_>, _>>()?;
+        let codes = EntropyCodes { color_cache, groups };
+        codes.read_pixels(
+            reader,
+            width,
+            height,
+            |pixel_idx| meta.code_group(width, pixel_idx),
+            |pixel_idx, color| palette.check(pixel_idx % width, color),
+            None,
+        )?;
         Ok(Self)
     }
 }
 
+//
+// PaletteBounds impls
+//
+
+impl PaletteBounds {
+    fn check(&self, x: u32, color: Color) -> Result<(), Error> {
+        let indices_per_pixel = 1 << self.bundle_bits;
+        let index_bits = 8 >> self.bundle_bits;
+        let unbundled_x = x << self.bundle_bits;
+        let index_count = indices_per_pixel.min(self.width.get() - unbundled_x);
+        for sub_x in 0..index_count {
+            let index = (u32::from(color.green) >> (sub_x * index_bits)) & ((1 << index_bits) - 1);
+            ensure_attach!(
+                index < self.len.get(),
+                ParseError::InvalidInput,
+                PaletteIndexOutOfBounds(index, self.len, unbundled_x + sub_x),
+            );
+        }
+        Ok(())
+    }
+}
+
 //
 // BackReference impls
 //
@@ -477,6 +562,123 @@ impl ColorCache {
     fn len(&self) -> u16 {
         self.order.map(|order| 2u16.pow(order.get().into())).unwrap_or_default()
     }
+
+    fn insert(&self, entries: &mut [Color], color: Color) {
+        if let Some(order) = self.order {
+            let index = 0x1e35a7bd_u32.wrapping_mul(color.into()) >> (32 - u32::from(order.get()));
+            entries[index as usize] = color;
+        }
+    }
+}
+
+//
+// EntropyCodes impls
+//
+
+impl EntropyCodes {
+    fn read_single<R: Read>(reader: &mut BitBufReader<R, LE>) -> Result<Self, Error> {
+        let color_cache = ColorCache::read(reader).while_parsing_type()?;
+        let group = PrefixCodeGroup::read(reader, &color_cache).while_parsing_type()?;
+        Ok(Self { color_cache, groups: vec![group] })
+    }
+
+    /// Read the entropy-coded pixels of an image, calling `fun` with the index of each literal pixel read.
+    ///
+    /// If `decoded` is given, all pixels of the image, including back-references and color cache entries, are
+    /// decoded into it.
+    fn read_pixels<R, G, F>(
+        &self,
+        reader: &mut BitBufReader<R, LE>,
+        width: NonZeroU32,
+        height: NonZeroU32,
+        mut code_group: G,
+        mut fun: F,
+        mut decoded: Option<&mut Vec<Color>>,
+    ) -> Result<(), Error>
+    where
+        R: Read,
+        G: FnMut(u32) -> u16,
+        F: FnMut(u32, Color) -> Result<(), Error>,
+    {
+        let readahead_bits = self
+            .groups
+            .iter()
+            .map(PrefixCodeGroup::readahead_bits)
+            .max()
+            .unwrap_or_default();
+        let single_group = self.groups.len() == 1;
+        let mut color_cache = vec![Color::default(); self.color_cache.len().into()];
+
+        let len = width.saturating_mul(height);
+        let mut pixel_idx = 0;
+        while pixel_idx < len.get() {
+            let codes = &self.groups[usize::from(code_group(pixel_idx))];
+            let green_readahead_bits = codes.green_readahead_bits();
+            let arb_readahead_bits = codes.arb_readahead_bits();
+
+            reader.fill_buf_to(readahead_bits.into())?;
+            match reader.buf_read_huffman(&codes.green.tree)? {
+                symbol @ 0..=255 => {
+                    let color = Color::buf_read(reader, symbol as u8, codes).while_parsing_type()?;
+                    log::debug!("color: {color}");
+                    fun(pixel_idx, color)?;
+                    let run_len = if single_group && green_readahead_bits + arb_readahead_bits == 0 {
+                        len.get() - pixel_idx
+                    } else {
+                        1
+                    };
+                    if let Some(decoded) = decoded.as_deref_mut() {
+                        decoded.resize(decoded.len() + run_len as usize, color);
+                        self.color_cache.insert(&mut color_cache, color);
+                    }
+                    pixel_idx += run_len;
+                }
+                symbol @ 256..=279 => {
+                    let back_ref = BackReference::buf_read(reader, symbol - 256, codes, width).while_parsing_type()?;
+                    log::debug!("backref: {back_ref}");
+                    ensure_matches_attach!(
+                        pixel_idx.checked_sub(back_ref.dist.get()),
+                        Some(_),
+                        ParseError::InvalidInput,
+                        InvalidBackRefDistance(back_ref.dist, pixel_idx),
+                    );
+                    ensure_attach!(
+                        back_ref.len.get() <= len.get() - pixel_idx,
+                        ParseError::InvalidInput,
+                        InvalidBackRefLength(back_ref.len, pixel_idx, len.get()),
+                    );
+                    if let Some(decoded) = decoded.as_deref_mut() {
+                        for _ in 0..back_ref.len.get() {
+                            let color = decoded[decoded.len() - back_ref.dist.get() as usize];
+                            decoded.push(color);
+                            self.color_cache.insert(&mut color_cache, color);
+                        }
+                    }
+                    pixel_idx += back_ref.len.get();
+                }
+                symbol @ 280.. => {
+                    let color_cache_index = symbol - 280;
+                    log::debug!("cached: {color_cache_index}");
+                    ensure_attach!(
+                        color_cache_index < self.color_cache.len(),
+                        ParseError::InvalidInput,
+                        ColorCacheIndexOutOfBounds(color_cache_index, self.color_cache.len()),
+                    );
+                    if let Some(decoded) = decoded.as_deref_mut() {
+                        let color = color_cache[usize::from(color_cache_index)];
+                        decoded.push(color);
+                        self.color_cache.insert(&mut color_cache, color);
+                        pixel_idx += 1;
+                    } else if single_group && green_readahead_bits == 0 {
+                        pixel_idx = len.get();
+                    } else {
+                        pixel_idx += 1;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 //
@@ -484,31 +686,70 @@ impl ColorCache {
 //
 
 impl MetaPrefixCodes {
-    fn read<R: Read>(reader: &mut BitBufReader<R, LE>, width: NonZeroU32, height: NonZeroU32) -> Result<Self, Error> {
+    /// Read the meta prefix codes of an image, decoding and keeping the code group of each block only if `decode` is
+    /// set, as needed by [`code_group`](Self::code_group).
+    fn read<R: Read>(
+        reader: &mut BitBufReader<R, LE>,
+        width: NonZeroU32,
+        height: NonZeroU32,
+        decode: bool,
+        config: &Config,
+    ) -> Result<Self, Error> {
         let has_meta = reader.read_bit()?;
         if has_meta {
             let block_order = 2 + reader.read::<u32>(3)?;
             let block_size = 2u16.pow(block_order);
             let width_in_blocks = len_in_blocks(width, block_size);
             let height_in_blocks = len_in_blocks(height, block_size);
-            let mut max_code_group = 0;
-            let _image = EntropyCodedImage::read(reader, width_in_blocks, height_in_blocks, |color| {
-                max_code_group = max_code_group.max(u16::from(color.red) << 8 | u16::from(color.green));
-                Ok(())
-            })
-            .while_parsing_type()?;
-            Ok(Self::Multiple { block_size, max_code_group, _image })
+            if decode {
+                let (_image, pixels) = EntropyCodedImage::decode(reader, width_in_blocks, height_in_blocks, config)
+                    .while_parsing_type()?;
+                let max_code_group = pixels
+                    .iter()
+                    .copied()
+                    .map(Self::code_group_of)
+                    .max()
+                    .unwrap_or_default();
+                Ok(Self::Multiple { block_size, max_code_group, code_group_image: Some(pixels), _image })
+            } else {
+                let mut max_code_group = 0;
+                let _image = EntropyCodedImage::read(reader, width_in_blocks, height_in_blocks, |color| {
+                    max_code_group = max_code_group.max(Self::code_group_of(color));
+                    Ok(())
+                })
+                .while_parsing_type()?;
+                Ok(Self::Multiple { block_size, max_code_group, code_group_image: None, _image })
+            }
         } else {
             Ok(Self::Single)
         }
     }
 
+    fn code_group_of(color: Color) -> u16 {
+        u16::from(color.red) << 8 | u16::from(color.green)
+    }
+
     fn max_code_group(&self) -> u16 {
         match self {
             MetaPrefixCodes::Single => 0,
             &MetaPrefixCodes::Multiple { max_code_group, .. } => max_code_group,
         }
     }
+
+    fn code_group(&self, width: NonZeroU32, pixel_idx: u32) -> u16 {
+        match self {
+            MetaPrefixCodes::Single => 0,
+            MetaPrefixCodes::Multiple { block_size, code_group_image, .. } => {
+                let Some(code_group_image) = code_group_image else {
+                    unreachable!();
+                };
+                let width_in_blocks = len_in_blocks(width, *block_size).get();
+                let block_x = pixel_idx % width.get() / u32::from(*block_size);
+                let block_y = pixel_idx / width.get() / u32::from(*block_size);
+                Self::code_group_of(code_group_image[(block_y * width_in_blocks + block_x) as usize])
+            }
+        }
+    }
 }
 
 //
@@ -617,6 +858,10 @@ impl PrefixCodeGroup {
     pub fn backref_readahead_bits(&self) -> u32 {
         self.green.tree.longest_code_len() + BackReference::readahead_bits(self)
     }
+
+    fn readahead_bits(&self) -> u32 {
+        self.green_readahead_bits() + self.arb_readahead_bits().max(self.backref_readahead_bits())
+    }
 }
 
 //
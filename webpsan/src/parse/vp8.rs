@@ -0,0 +1,80 @@
+#![allow(missing_docs)]
+
+use std::num::NonZeroU16;
+
+use bytes::{Buf, BytesMut};
+use derive_more::Display;
+use mediasan_common::parse::FourCC;
+use mediasan_common::Result;
+use mediasan_common::{bail_attach, ensure_attach};
+
+use super::chunk_type::VP8;
+use super::{ParseChunk, ParseError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Vp8Chunk {
+    width: NonZeroU16,
+    height: NonZeroU16,
+}
+
+//
+// private types
+//
+
+#[derive(Clone, Copy, Debug, Display)]
+#[display(fmt = "VP8 frame is not a key frame")]
+struct NotKeyFrame;
+
+#[derive(Clone, Copy, Debug, Display)]
+#[display(fmt = "invalid VP8 start code `{_0:02x?}` != `{:02x?}`", Vp8Chunk::START_CODE)]
+struct InvalidStartCode([u8; 3]);
+
+#[derive(Clone, Copy, Debug, Display)]
+#[display(fmt = "zero VP8 frame dimensions `{_0}`x`{_1}`")]
+struct ZeroDimensions(u16, u16);
+
+//
+// Vp8Chunk impls
+//
+
+impl Vp8Chunk {
+    const START_CODE: [u8; 3] = [0x9d, 0x01, 0x2a];
+
+    const DIMENSION_MASK: u16 = 0x3fff;
+
+    pub fn width(&self) -> NonZeroU16 {
+        self.width
+    }
+
+    pub fn height(&self) -> NonZeroU16 {
+        self.height
+    }
+}
+
+impl ParseChunk for Vp8Chunk {
+    const NAME: FourCC = VP8;
+
+    const ENCODED_LEN: u32 = 10;
+
+    fn parse(buf: &mut BytesMut) -> Result<Self, ParseError> {
+        let frame_tag = buf.get_uint_le(3);
+        ensure_attach!(frame_tag & 1 == 0, ParseError::InvalidInput, NotKeyFrame);
+
+        let mut start_code = [0; 3];
+        buf.copy_to_slice(&mut start_code);
+        ensure_attach!(
+            start_code == Self::START_CODE,
+            ParseError::InvalidInput,
+            InvalidStartCode(start_code)
+        );
+
+        // The upper two bits of each dimension are an upscaling factor, which doesn't affect the frame dimensions.
+        let width = buf.get_u16_le() & Self::DIMENSION_MASK;
+        let height = buf.get_u16_le() & Self::DIMENSION_MASK;
+        let (Some(width), Some(height)) = (NonZeroU16::new(width), NonZeroU16::new(height)) else {
+            bail_attach!(ParseError::InvalidInput, ZeroDimensions(width, height));
+        };
+
+        Ok(Self { width, height })
+    }
+}
@@ -12,7 +12,7 @@ use mediasan_common::ensure_attach;
 use mediasan_common::parse::FourCC;
 use mediasan_common::Result;
 
-use crate::Error;
+use crate::{Config, Error};
 
 use super::bitstream::BitBufReader;
 use super::chunk_type::VP8L;
@@ -49,9 +49,9 @@ impl Vp8lChunk {
         self.height
     }
 
-    pub fn sanitize_image_data<R: Read>(&self, input: R) -> StdResult<(), Error> {
+    pub fn sanitize_image_data<R: Read>(&self, input: R, config: &Config) -> StdResult<(), Error> {
         let mut reader = BitBufReader::<_, LE>::with_capacity(input, 4096);
-        let _image = LosslessImage::read(&mut reader, self.width.into(), self.height.into())?;
+        let _image = LosslessImage::read(&mut reader, self.width.into(), self.height.into(), config)?;
         Ok(())
     }
 }
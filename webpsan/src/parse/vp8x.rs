@@ -7,13 +7,13 @@ use mediasan_common::parse::FourCC;
 use mediasan_common::{ensure_attach, Result};
 
 use super::chunk_type::VP8X;
-use super::error::ParseResultExt;
-use super::{OneBasedU24, ParseChunk, ParseError, ParsedChunk, Reserved, WebmFlags, WebmPrim};
+use super::error::{ParseResultExt, WhileParsingChunk};
+use super::{OneBasedU24, ParseChunk, ParseError, ParsedChunk, WebmFlags, WebmPrim, U24};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Vp8xChunk {
     pub flags: Vp8xFlags,
-    reserved: Reserved<3>,
+    reserved: U24,
     canvas_width: OneBasedU24,
     canvas_height: OneBasedU24,
 }
@@ -41,17 +41,31 @@ impl Vp8xChunk {
     pub fn canvas_height(&self) -> NonZeroU32 {
         self.canvas_height.get()
     }
+
+    /// Check that the reserved bits of the flags, and the reserved bytes following them, are zero.
+    ///
+    /// Non-zero reserved bits indicate either corruption or a newer version of the format, which decoders may reject.
+    pub fn validate_reserved(&self) -> Result<(), ParseError> {
+        ensure_attach!(
+            Vp8xFlags::from_bits(self.flags.bits()).is_some() && self.reserved.get() == 0,
+            ParseError::InvalidInput,
+            "non-zero reserved bits",
+            WhileParsingChunk(Self::NAME),
+        );
+        Ok(())
+    }
 }
 
 impl ParseChunk for Vp8xChunk {
     const NAME: FourCC = VP8X;
 
-    const ENCODED_LEN: u32 =
-        Vp8xFlags::ENCODED_LEN + Reserved::<3>::ENCODED_LEN + OneBasedU24::ENCODED_LEN + OneBasedU24::ENCODED_LEN;
+    const ENCODED_LEN: u32 = u8::ENCODED_LEN + U24::ENCODED_LEN + OneBasedU24::ENCODED_LEN + OneBasedU24::ENCODED_LEN;
 
     fn parse(mut buf: &mut BytesMut) -> Result<Self, ParseError> {
-        let flags = Vp8xFlags::parse(&mut buf).while_parsing_field(Self::NAME, "flags")?;
-        let reserved = Reserved::parse(&mut buf).while_parsing_field(Self::NAME, "reserved")?;
+        // Reserved bits are retained here, and checked separately by `validate_reserved`.
+        let flags = u8::parse(&mut buf).while_parsing_field(Self::NAME, "flags")?;
+        let flags = Vp8xFlags::from_bits_retain(flags);
+        let reserved = U24::parse(&mut buf).while_parsing_field(Self::NAME, "reserved")?;
         let canvas_width = OneBasedU24::parse(&mut buf).while_parsing_field(Self::NAME, "canvas_width")?;
         let canvas_height = OneBasedU24::parse(&mut buf).while_parsing_field(Self::NAME, "canvas_height")?;
         ensure_attach!(
@@ -66,7 +80,7 @@ impl ParseChunk for Vp8xChunk {
 impl ParsedChunk for Vp8xChunk {
     fn put_buf(&self, mut buf: &mut dyn BufMut) {
         let Self { flags, reserved, canvas_width, canvas_height } = self;
-        flags.put_buf(&mut buf);
+        flags.bits().put_buf(&mut buf);
         reserved.put_buf(&mut buf);
         canvas_width.put_buf(&mut buf);
         canvas_height.put_buf(&mut buf);
@@ -15,6 +15,7 @@ use crate::Error;
 
 pub struct ChunkReader<R: ?Sized> {
     state: State,
+    allow_nonzero_padding: bool,
     inner: BufReader<R>,
 }
 
@@ -38,7 +39,14 @@ enum PaddingReadToken {}
 impl<R: Read + Skip> ChunkReader<R> {
     pub fn new(input: R, chunk_name: FourCC) -> Self {
         let inner = BufReader::with_capacity(ChunkHeader::ENCODED_LEN as usize, input);
-        Self { state: State::Idle { last: chunk_name }, inner }
+        Self { state: State::Idle { last: chunk_name }, allow_nonzero_padding: false, inner }
+    }
+
+    /// Set whether to accept a non-zero padding byte following an odd-length chunk, in this reader and any
+    /// [child readers](Self::child_reader).
+    pub fn allow_nonzero_padding(mut self, allow_nonzero_padding: bool) -> Self {
+        self.allow_nonzero_padding = allow_nonzero_padding;
+        self
     }
 }
 
@@ -53,6 +61,15 @@ impl<R: Read + Skip + ?Sized> ChunkReader<R> {
         Ok(!self.inner.fill_buf()?.is_empty())
     }
 
+    /// Return the length of the underlying input, or `None` if it is unknown.
+    pub fn stream_len(&mut self) -> Result<Option<u64>, Error> {
+        match self.inner.stream_len() {
+            Ok(len) => Ok(Some(len)),
+            Err(err) if err.kind() == io::ErrorKind::Unsupported => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
     /// Read a chunk header, also saving it to be returned by [`read_header`](Self::read_header) later.
     pub fn peek_header(&mut self) -> Result<Option<FourCC>, Error> {
         let header = match self.read_padding()? {
@@ -201,7 +218,8 @@ impl<R: Read + Skip + ?Sized> ChunkReader<R> {
     /// Return a [`ChunkReader`] type over a chunk's data, assuming its header has already been read.
     pub fn child_reader(&mut self) -> ChunkReader<ChunkDataReader<'_, R>> {
         let name = self.current_chunk_name();
-        ChunkReader::new(self.data_reader(), name)
+        let allow_nonzero_padding = self.allow_nonzero_padding;
+        ChunkReader::new(self.data_reader(), name).allow_nonzero_padding(allow_nonzero_padding)
     }
 
     fn current_chunk_name(&self) -> FourCC {
@@ -229,7 +247,9 @@ impl<R: Read + Skip + ?Sized> ChunkReader<R> {
                     WhileParsingChunk(header.name),
                 ))
             })?;
-            ensure_matches_attach!(pad, 0, ParseError::InvalidInput, WhileParsingChunk(header.name));
+            if !self.allow_nonzero_padding {
+                ensure_matches_attach!(pad, 0, ParseError::InvalidInput, WhileParsingChunk(header.name));
+            }
         }
 
         self.state = State::Idle { last: header.name };
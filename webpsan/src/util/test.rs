@@ -5,6 +5,9 @@ use webp::{TestFileHeaderSpecBuilder, TestWebpBuilder};
 
 use self::webp::{TestAlphSpecBuilder, TestAnmfSpecBuilder, TestVp8xSpecBuilder};
 
+/// The color profile written to test `ICCP` chunks.
+pub const TEST_ICCP_PROFILE: &[u8] = b"dummy ICCP profile";
+
 pub fn test_alph() -> TestAlphSpecBuilder {
     Default::default()
 }
@@ -63,12 +66,12 @@ pub fn write_test_anmf(out: &mut Vec<u8>, x: u32, y: u32, width: u32, height: u3
     out.extend_from_slice(data);
 }
 
-pub fn write_test_exif(out: &mut Vec<u8>) {
-    write_test_chunk(out, b"EXIF", b"dummy EXIF data");
+pub fn write_test_exif(out: &mut Vec<u8>, data: &[u8]) {
+    write_test_chunk(out, b"EXIF", data);
 }
 
 pub fn write_test_iccp(out: &mut Vec<u8>) {
-    write_test_chunk(out, b"ICCP", b"dummy ICCP profile");
+    write_test_chunk(out, b"ICCP", TEST_ICCP_PROFILE);
 }
 
 pub fn write_test_vp8x(out: &mut Vec<u8>, flags: u8, width: u32, height: u32) {
@@ -82,6 +85,6 @@ pub fn write_test_vp8x(out: &mut Vec<u8>, flags: u8, width: u32, height: u32) {
     out.put_int_le(height as i64, 3);
 }
 
-pub fn write_test_xmp(out: &mut Vec<u8>) {
-    write_test_chunk(out, b"XMP ", b"dummy XMP data");
+pub fn write_test_xmp(out: &mut Vec<u8>, data: &[u8]) {
+    write_test_chunk(out, b"XMP ", data);
 }
@@ -39,6 +39,14 @@ pub struct TestWebpSpec {
     #[builder(setter(into, each(name = "add_vp8_data", into)))]
     vp8_data: Vec<u8>,
 
+    #[builder(default = "DEFAULT_EXIF_DATA.to_vec()")]
+    #[builder(setter(into))]
+    exif_data: Vec<u8>,
+
+    #[builder(default = "DEFAULT_XMP_DATA.to_vec()")]
+    #[builder(setter(into))]
+    xmp_data: Vec<u8>,
+
     #[builder(default = "vec![VP8L]")]
     #[builder(setter(into, each(name = "add_chunk")))]
     chunks: Vec<FourCC>,
@@ -118,6 +126,13 @@ const DEFAULT_VP8_DATA: &[u8] = &[
     18, 1, 0, 157, 1, 42, 1, 0, 1, 0, 18, 0, 52, 0, 0, 13, 192, 0, 254, 251, 253, 80, 0, 0,
 ];
 
+const DEFAULT_EXIF_DATA: &[u8] = b"dummy EXIF data";
+
+const DEFAULT_XMP_DATA: &[u8] = b"dummy XMP data";
+
+/// The data written for chunk types unknown to the sanitizer.
+const UNKNOWN_CHUNK_DATA: &[u8] = b"dummy unknown chunk data";
+
 const DEFAULT_VP8L_DATA: &[u8] = &[
     // image-header: signature image-size alpha-is-used version
     0x2f,
@@ -155,7 +170,7 @@ impl TestWebpSpec {
             let len = file_header.len.unwrap_or(0xDEADBEEF);
 
             data.extend_from_slice(&file_header.chunk_type.value);
-            data.put_u32_le(len as u32);
+            data.put_u32_le(len);
             data.extend_from_slice(&file_header.name.value);
         }
 
@@ -197,7 +212,11 @@ impl TestWebpSpec {
                 }
                 ANIM => write_test_anim(&mut data),
                 ANMF => {
-                    let anmf = (!anmfs.is_empty()).then(|| anmfs.remove(0)).unwrap_or_default();
+                    let anmf = if anmfs.is_empty() {
+                        Default::default()
+                    } else {
+                        anmfs.remove(0)
+                    };
                     let TestAnmfSpec { x, y, width, height, alph, vp8l_data, vp8_data, chunks } = anmf.build().unwrap();
                     let alph = alph.build().unwrap();
 
@@ -207,15 +226,18 @@ impl TestWebpSpec {
                             ALPH => write_test_alph(&mut anmf_data, alph.flags.bits(), &alph.image_data),
                             VP8L => write_test_chunk(&mut anmf_data, &chunk_type.value, &vp8l_data),
                             VP8 => write_test_chunk(&mut anmf_data, &chunk_type.value, &vp8_data),
-                            _ => panic!("invalid chunk type in ANMF for test {chunk_type}"),
+                            ANIM | ANMF | EXIF | ICCP | VP8X | XMP => {
+                                panic!("invalid chunk type in ANMF for test {chunk_type}")
+                            }
+                            _ => write_test_chunk(&mut anmf_data, &chunk_type.value, UNKNOWN_CHUNK_DATA),
                         }
                     }
                     write_test_anmf(&mut data, x, y, width, height, &anmf_data);
                 }
                 ICCP => write_test_iccp(&mut data),
-                EXIF => write_test_exif(&mut data),
-                XMP => write_test_xmp(&mut data),
-                _ => panic!("invalid chunk type for test {chunk_type}"),
+                EXIF => write_test_exif(&mut data, &self.exif_data),
+                XMP => write_test_xmp(&mut data, &self.xmp_data),
+                _ => write_test_chunk(&mut data, &chunk_type.value, UNKNOWN_CHUNK_DATA),
             }
         }
 
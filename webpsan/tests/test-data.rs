@@ -3,20 +3,33 @@ use std::io::Cursor;
 use mediasan_common_test::{init_logger, TestType};
 use webpsan::{sanitize_with_config, Config};
 
-const CONFIG: Config = Config { allow_unknown_chunks: true };
+fn config() -> Config {
+    Config {
+        allow_unknown_chunks: true,
+        allowed_extra_chunks: Default::default(),
+        max_lossless_pixels: None,
+        max_image_data_len: None,
+        max_vp8l_transforms: None,
+        max_vp8l_code_groups: None,
+        validate_palette_indices: false,
+        strict: true,
+        allow_nonzero_padding: false,
+        validate_image_bitstream: true,
+    }
+}
 
 #[test]
 fn test_data() {
     init_logger();
     mediasan_common_test::test_data(".webp", |test_type, data| match test_type {
         TestType::Valid => {
-            sanitize_with_config(Cursor::new(data), CONFIG).unwrap();
+            sanitize_with_config(Cursor::new(data), config()).unwrap();
         }
         TestType::InvalidPass => {
-            sanitize_with_config(Cursor::new(data), CONFIG).unwrap();
+            sanitize_with_config(Cursor::new(data), config()).unwrap();
         }
         TestType::InvalidFail => {
-            dbg!(sanitize_with_config(Cursor::new(data), CONFIG).unwrap_err());
+            dbg!(sanitize_with_config(Cursor::new(data), config()).unwrap_err());
         }
     });
 }